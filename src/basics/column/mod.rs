@@ -7,7 +7,7 @@ pub use r#type::*;
 pub use validate::Validate;
 pub use transform::Transform;
 
-use crate::syntax::ast::Node;
+use crate::{syntax::ast::Node, basics::{Value, value::{ToBytes, format_json}}};
 
 #[derive(Debug, Clone)]
 pub struct Column {
@@ -44,4 +44,20 @@ impl Column {
     pub fn set_length(&mut self, length: u32) {
         self.length = length;
     }
+
+    /// Converts 'value' to the bytes stored for this column, json columns store JSON text and
+    /// vector columns prefix the components with a flag byte, 1 for vectors and 0 for NULL
+    pub fn value_to_bytes(&self, value: &Value) -> Vec<u8> {
+        match (&self.data_type, value) {
+            (ColumnType::Vector(_), Value::Null) => value.to_bytes(self.length),
+            (ColumnType::Vector(_), value) => {
+                let mut bytes = vec![1];
+                bytes.extend(value.to_bytes(self.length - 1));
+                bytes
+            },
+            (ColumnType::Json(_), Value::Null) => value.to_bytes(self.length),
+            (ColumnType::Json(_), value) => format_json(value).to_bytes(self.length),
+            _ => value.to_bytes(self.length),
+        }
+    }
 }
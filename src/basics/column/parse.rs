@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::basics::{Value, value::{NumericValue, TimestampValue}};
+use crate::basics::{Value, value::{NumericValue, TimestampValue, Decimal, Interval, parse_date, parse_time, parse_timestamp, parse_json, format_json}};
 
 use super::{NumericType, TimestampType, ColumnType, TextType};
 
@@ -21,6 +21,8 @@ impl NumericType {
 
             NumericType::Float32 => value.parse::<f32>().map(|v| Value::Numeric(NumericValue::Float32(v))).map_err(|e| e.to_string()), 
             NumericType::Float64 => value.parse::<f64>().map(|v| Value::Numeric(NumericValue::Float64(v))).map_err(|e| e.to_string()), 
+
+            NumericType::Decimal(p, s) => value.parse::<Decimal>().and_then(|v| v.fit(*p, *s)).map(|v| Value::Numeric(NumericValue::Decimal(v))),
         } 
     }
 }
@@ -48,10 +50,22 @@ impl TextType {
 impl TimestampType {
     pub fn parse(&self, value: &str) -> Result<Value, String> {
         match self {
+            // timestamps stored as numbers also accept ISO-8601 timestamps
+            TimestampType::Seconds | TimestampType::Milliseconds | TimestampType::Microseconds | TimestampType::Nanoseconds
+                if value.contains(['-', ':']) => {
+                let (micros, offset) = parse_timestamp(value)?;
+                TimestampValue::from_instant(micros, offset, self).map(Value::Timestamp)
+            },
+
             TimestampType::Seconds => value.parse::<u64>().map(|v| Value::Timestamp(TimestampValue::Seconds(v))).map_err(|e| e.to_string()),
             TimestampType::Milliseconds => value.parse::<u64>().map(|v| Value::Timestamp(TimestampValue::Milliseconds(v))).map_err(|e| e.to_string()),
             TimestampType::Microseconds => value.parse::<u64>().map(|v| Value::Timestamp(TimestampValue::Microseconds(v))).map_err(|e| e.to_string()),
             TimestampType::Nanoseconds => value.parse::<u64>().map(|v| Value::Timestamp(TimestampValue::Nanoseconds(v))).map_err(|e| e.to_string()),
+
+            TimestampType::Zoned => parse_timestamp(value).map(|(micros, offset)| Value::Timestamp(TimestampValue::Zoned(micros, offset))),
+            TimestampType::Date => parse_date(value).map(|v| Value::Timestamp(TimestampValue::Date(v))),
+            TimestampType::Time => parse_time(value).map(|v| Value::Timestamp(TimestampValue::Time(v))),
+            TimestampType::Interval => value.parse::<Interval>().map(|v| Value::Timestamp(TimestampValue::Interval(v))),
         }
     }
 }
@@ -66,6 +80,8 @@ impl ColumnType {
             ColumnType::Boolean => value.parse::<bool>().map(Value::Boolean).map_err(|e| e.to_string()),
             ColumnType::Binary => value.parse::<Bytes>().map(|v| Value::Binary(v.0)).map_err(|e| e.to_string()),
             ColumnType::Array(_t) => todo!("column array parsing"),
+            ColumnType::Vector(dimension) => parse_vector(value, *dimension),
+            ColumnType::Json(length) => parse_json(value).and_then(|json| fit_json(json, *length)),
             ColumnType::Enum => todo!("column enum parsing"),
             ColumnType::UUID => todo!("column uuid parsing"),
         };
@@ -74,6 +90,45 @@ impl ColumnType {
     }
 }
 
+/// Converts a value written to a JSON column, text is parsed as a JSON document like
+/// 'ColumnType::parse' does, other values are stored as their JSON
+pub(super) fn to_json(value: &Value, length: u32) -> Result<Value, String> {
+    match value {
+        Value::Text(text) => parse_json(text).and_then(|json| fit_json(json, length)),
+        value => fit_json(value.clone(), length),
+    }
+}
+
+/// Checks that the compact JSON of 'value' fits into 'length' bytes and returns the value as it
+/// is read back from storage
+pub(super) fn fit_json(value: Value, length: u32) -> Result<Value, String> {
+    let json = format_json(&value);
+    if json.len() > length as usize {
+        return Err(format!("JSON is {} bytes long, at most {} bytes fit", json.len(), length))
+    }
+
+    // stored JSON null reads back as NULL, timestamps as text
+    parse_json(&json)
+}
+
+/// Parses a vector written as '[x, y, z]'
+fn parse_vector(value: &str, dimension: u32) -> Result<Value, String> {
+    let inner = value.trim()
+        .strip_prefix('[').and_then(|v| v.strip_suffix(']'))
+        .ok_or("Vector must be enclosed in brackets".to_string())?;
+
+    let values = inner.split(',')
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| v.trim().parse::<f32>().map(|v| Value::Numeric(NumericValue::Float32(v))).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if values.len() != dimension as usize {
+        return Err(format!("Expected {} dimensions, got {}", dimension, values.len()))
+    }
+
+    Ok(Value::Array(values))
+}
+
 struct Bytes(Vec<u8>);
 impl FromStr for Bytes {
     type Err = String;
@@ -1,8 +1,8 @@
 use std::fmt::Display;
 
-use crate::basics::{Value, value::{TimestampValue, NumericValue}};
+use crate::basics::{Value, value::{TimestampValue, NumericValue, Decimal}};
 
-use super::{ColumnType, Column, TimestampType, NumericType, TextType};
+use super::{ColumnType, Column, TimestampType, NumericType, TextType, parse::to_json};
 
 pub trait Transform {
     /// Transforms a Value to the column's data type if possible and needed
@@ -20,6 +20,11 @@ impl Column {
 
 impl Transform for Value {
     fn transform(&self, c: &Column, ct: &ColumnType) -> Result<Value, String> {
+        // any value can be stored as JSON, text must be a JSON document
+        if let (ColumnType::Json(length), false) = (ct, self.is_null()) {
+            return to_json(self, *length).map_err(|e| format!("Invalid JSON for column '{}': {}", c.name, e))
+        }
+
         match self {
             Value::Text(v) => v.transform(c, ct),
             Value::Numeric(v) => v.transform(c, ct),
@@ -27,6 +32,8 @@ impl Transform for Value {
             Value::Boolean(v) => v.transform(c, ct),
             Value::Binary(v) => v.transform(c, ct),
             Value::Array(v) => v.transform(c, ct),
+            Value::Object(_) => Err(format!("Column '{}' cannot store an object", c.name)),
+            Value::Function(_) => Err(format!("Column '{}' cannot store a function", c.name)),
             Value::Enum(v) => v.transform(c, ct),
             Value::UUID(v) => v.transform(c, ct),
             Value::Null => {
@@ -44,6 +51,10 @@ impl Transform for str {
     fn transform(&self, c: &Column, ct: &ColumnType) -> Result<Value, String> {
         let data_type = match ct {
             ColumnType::Text(t) => t,
+            // decimals are written as text to keep every digit exact
+            ColumnType::Numeric(NumericType::Decimal(_, _)) => return c.validate(self),
+            // ISO-8601 dates, times, timestamps and intervals
+            ColumnType::Timestamp(_) => return c.validate(self),
             _ => return Err(format!("Column '{}' is not a text type", c.name))
         };
 
@@ -176,6 +187,30 @@ impl Transform for NumericValue {
             (NumericValue::Float64(n), NumericType::Float32) => NumericValue::Float32(*n as f32),
             (NumericValue::Float64(n), NumericType::Float64) => NumericValue::Float64(*n as f64),
 
+
+
+            (NumericValue::Decimal(n), NumericType::IntU8) if n.is_integer() => NumericValue::IntU8(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntU16) if n.is_integer() => NumericValue::IntU16(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntU32) if n.is_integer() => NumericValue::IntU32(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntU64) if n.is_integer() => NumericValue::IntU64(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntI8) if n.is_integer() => NumericValue::IntI8(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntI16) if n.is_integer() => NumericValue::IntI16(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntI32) if n.is_integer() => NumericValue::IntI32(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::IntI64) if n.is_integer() => NumericValue::IntI64(convert_number(n.trunc(), data_type, self)?),
+            (NumericValue::Decimal(n), NumericType::Float32) => NumericValue::Float32(n.to_f64() as f32),
+            (NumericValue::Decimal(n), NumericType::Float64) => NumericValue::Float64(n.to_f64()),
+
+            // every number converts to a decimal, it is rounded to the scale of the column
+            (n, NumericType::Decimal(p, s)) => {
+                let decimal = match *n {
+                    NumericValue::Float32(f) => Decimal::from_float(f)?,
+                    NumericValue::Float64(f) => Decimal::from_float(f)?,
+                    _ => n.to_decimal().unwrap(),
+                };
+
+                NumericValue::Decimal(decimal.fit(*p, *s).map_err(|e| format!("{}, required by column '{}'", e, c.name))?)
+            },
+
             _ => return Err(format!("Could not transform numeric value {:?} to {:?}, required by column '{}'", self, data_type, c.name))
         };
 
@@ -212,6 +247,15 @@ impl Transform for TimestampValue {
             (TimestampValue::Nanoseconds(s), TimestampType::Milliseconds) => TimestampValue::Milliseconds(s / 1_000_000),
             (TimestampValue::Nanoseconds(s), TimestampType::Microseconds) => TimestampValue::Microseconds(s / 1_000),
             (TimestampValue::Nanoseconds(s), TimestampType::Nanoseconds) => TimestampValue::Nanoseconds(*s),
+
+            (TimestampValue::Time(t), TimestampType::Time) => TimestampValue::Time(*t),
+            (TimestampValue::Interval(i), TimestampType::Interval) => TimestampValue::Interval(*i),
+            (TimestampValue::Time(_) | TimestampValue::Interval(_), _) | (_, TimestampType::Time | TimestampType::Interval)
+                => return Err(format!("Could not transform timestamp value {} to {:?}, required by column '{}'", self, data_type, c.name)),
+
+            // other points in time convert through microseconds since the epoch, zoned
+            // timestamps become dates in their own timezone
+            (_, data_type) => TimestampValue::from_instant(self.instant_micros()?, self.offset(), data_type)?,
         };
 
         Ok(Value::Timestamp(timestamp_value))
@@ -244,7 +288,21 @@ impl Transform for Vec<u8> {
 
 impl Transform for Vec<Value> {
     fn transform(&self, c: &Column, ct: &ColumnType) -> Result<Value, String> {
-        if let ColumnType::Array(t) = &ct {
+        if let ColumnType::Vector(dimension) = &ct {
+            if self.len() != *dimension as usize {
+                return Err(format!("Invalid vector for column '{}', expected {} dimensions, got {}", c.name, dimension, self.len()))
+            }
+
+            let element_type = ColumnType::Numeric(NumericType::Float32);
+            let transformed = self.iter()
+                .map(|value| match value {
+                    Value::Null => Err(format!("Vector for column '{}' cannot contain NULL values", c.name)),
+                    value => value.transform(c, &element_type)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Value::Array(transformed))
+        } else if let ColumnType::Array(t) = &ct {
             let mut transformed = Vec::with_capacity(self.len());
 
             for value in self {
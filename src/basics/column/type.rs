@@ -19,6 +19,9 @@ pub enum NumericType {
 
     Float32,
     Float64,
+
+    /// Exact number with 'precision' digits, 'scale' of them after the decimal point
+    Decimal(u8, u8),
 }
 
 #[derive(Debug, Clone)]
@@ -27,8 +30,12 @@ pub enum TimestampType {
     Milliseconds,
     Microseconds,
     Nanoseconds,
-    // Date,
-    // Time,
+    /// Timestamp with an offset from UTC
+    Zoned,
+    Date,
+    /// Time of day
+    Time,
+    Interval,
 }
 
 #[derive(Debug, Clone)]
@@ -39,8 +46,12 @@ pub enum ColumnType {
     Boolean,
     Binary,
     Array(Box<ColumnType>),
+    /// Fixed number of f32 values, stored like a f32 array
+    Vector(u32),
     Enum,
     UUID,
+    /// JSON document of at most this many bytes, stored as compact JSON text
+    Json(u32),
 }
 
 impl ColumnType {
@@ -59,14 +70,24 @@ impl ColumnType {
 
                 NumericType::Float32 => 4,
                 NumericType::Float64 => 8,
+
+                NumericType::Decimal(_, _) => 16,
             },
             ColumnType::Text(t) => match t {
                 TextType::Char => 1,
                 TextType::Variable => todo!("variable length text"),
                 TextType::Fixed(len) => *len,
             },
-            ColumnType::Timestamp(_) => 8,
+            ColumnType::Timestamp(t) => match t {
+                TimestampType::Zoned => 12,
+                TimestampType::Date => 4,
+                TimestampType::Interval => 16,
+                _ => 8,
+            },
             ColumnType::Boolean => 1,
+            // a flag byte tells a NULL apart from a zero vector
+            ColumnType::Vector(dimension) => 1 + dimension * 4,
+            ColumnType::Json(length) => *length,
             _ => todo!("column type len for {:?}", self),
         }
     }
@@ -1,4 +1,4 @@
-use super::{Column, ColumnType, NumericType, TimestampType, TextType};
+use super::{Column, ColumnType, NumericType, TimestampType, TextType, parse::to_json};
 
 use crate::basics::{Value, value::{NumericValue, TimestampValue}};
 
@@ -39,6 +39,10 @@ impl Column {
 
 impl Validate for Value {
     fn validate(&self, c: &Column, ct: &ColumnType) -> Result<(), String> {
+        if let (ColumnType::Json(length), false) = (ct, self.is_null()) {
+            return to_json(self, *length).map(|_| ()).map_err(|e| format!("Invalid JSON for column '{}': {}", c.name, e))
+        }
+
         match self {
             Value::Text(v) => v.validate(c, ct),
             Value::Numeric(v) => v.validate(c, ct),
@@ -46,6 +50,8 @@ impl Validate for Value {
             Value::Boolean(v) => v.validate(c, ct),
             Value::Binary(v) => v.validate(c, ct),
             Value::Array(v) => v.validate(c, ct),
+            Value::Object(_) => Err(format!("Column '{}' cannot store an object", c.name)),
+            Value::Function(_) => Err(format!("Column '{}' cannot store a function", c.name)),
             Value::Enum(v) => v.validate(c, ct),
             Value::UUID(v) => v.validate(c, ct),
             Value::Null => {
@@ -105,6 +111,8 @@ impl Validate for NumericValue {
             (NumericValue::Float32(_), NumericType::Float32) => Ok(()),
             (NumericValue::Float64(_), NumericType::Float64) => Ok(()),
 
+            (NumericValue::Decimal(n), NumericType::Decimal(p, s)) if n.scale == *s && n.precision() <= *p => Ok(()),
+
             // TODO: import value.to_type()
             _ => Err(format!("Invalid numeric value for column '{}', expected: {:?}, got: {:?}", 
                     c.name, data_type, self))
@@ -124,6 +132,10 @@ impl Validate for TimestampValue {
             (TimestampValue::Milliseconds(_), TimestampType::Milliseconds) => Ok(()),
             (TimestampValue::Microseconds(_), TimestampType::Microseconds) => Ok(()),
             (TimestampValue::Nanoseconds(_), TimestampType::Nanoseconds) => Ok(()),
+            (TimestampValue::Zoned(_, _), TimestampType::Zoned) => Ok(()),
+            (TimestampValue::Date(_), TimestampType::Date) => Ok(()),
+            (TimestampValue::Time(_), TimestampType::Time) => Ok(()),
+            (TimestampValue::Interval(_), TimestampType::Interval) => Ok(()),
  
             // TODO: import value.to_type()
             _ => Err(format!("Invalid timestamp value for column '{}', expected: {:?}, got: {:?}", 
@@ -158,7 +170,17 @@ impl Validate for Vec<u8> {
 
 impl Validate for Vec<Value> {
     fn validate(&self, c: &Column, ct: &ColumnType) -> Result<(), String> {
-        if let ColumnType::Array(t) = &ct {
+        if let ColumnType::Vector(dimension) = &ct {
+            if self.len() != *dimension as usize {
+                return Err(format!("Invalid vector for column '{}', expected {} dimensions, got {}", c.name, dimension, self.len()))
+            }
+
+            for value in self {
+                value.validate(c, &ColumnType::Numeric(NumericType::Float32))?;
+            }
+
+            Ok(())
+        } else if let ColumnType::Array(t) = &ct {
             for value in self {
                 value.validate(c, t)?;
             }
@@ -1,9 +1,11 @@
 pub mod column;
 pub mod row;
 pub mod table;
+pub mod trigger;
 pub mod value;
 
 pub use column::Column;
 pub use row::Row;
 pub use table::Table;
+pub use trigger::Trigger;
 pub use value::Value;
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use super::{Column, Value, value::{ToBytes, FromBytes}};
+use super::{Column, Value, value::FromBytes};
 
 impl Display for Row {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -105,10 +105,7 @@ impl Row {
     pub fn convert_to_bytes(&self, columns: &Vec<Column>) -> Vec<u8> {
         let mut bytes = vec![self.flags];
 
-        let values = self.values.iter().enumerate().flat_map(|(i, v)| {
-            let length = columns[i].length;
-            v.to_bytes(length)
-        });
+        let values = self.values.iter().enumerate().flat_map(|(i, v)| columns[i].value_to_bytes(v));
 
         bytes.extend(values);
 
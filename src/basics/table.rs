@@ -1,8 +1,8 @@
 use std::{path::PathBuf, collections::HashMap};
 
-use crate::{basics::column::Column, file::data::Data, utils::log, query::KeyVal, auth::RlsPolicy};
+use crate::{basics::column::Column, file::data::Data, utils::log, query::KeyVal, auth::RlsPolicy, index::Index};
 
-use super::row::Row;
+use super::{row::Row, trigger::Trigger};
 
 #[derive(Debug)]
 pub struct Table {
@@ -11,14 +11,14 @@ pub struct Table {
     pub data: Data,
     pub read_only: bool,
     // pub constraints: Vec<Constraint>,
-    // pub triggers: Vec<Trigger>,
-    // pub indexes: Vec<Index>,
     // pub rules: Vec<Rule>,
     // pub partitions: Vec<Partition>,
     // pub comments: Vec<Comment>,
     // pub privileges: Vec<Privilege>,
     pub rls_enabled: bool,
     pub policies: HashMap<String, RlsPolicy>, 
+    pub indexes: HashMap<String, Index>,
+    pub triggers: Vec<Trigger>,
 }
 
 impl Table {
@@ -189,19 +189,36 @@ impl Table {
             return Err(format!("Row does not match table '{}' column count ({}/{})", self.name, row.len(), self.columns.len()))
         }
 
-        for (i, column) in self.columns.iter().enumerate() {
-            if column.unique {
-                let value = row.get(i).unwrap();
-                if self.data
-                    .iter()
-                    .filter(|r| !r.is_deleted())
-                    .any(|r| r.get(i).unwrap() == value) {
-                    return Err(format!("Value '{}' for column '{}' is not unique", value, column.name));
-                }
-            }
+        if let Some((_, i)) = self.find_conflict(row, &self.get_unique_column_indexes(), None) {
+            let value = row.get(i).unwrap();
+            return Err(format!("Value '{}' for column '{}' is not unique", value, self.columns[i].name));
         }
         Ok(())
     }
+
+    pub fn get_unique_column_indexes(&self) -> Vec<usize> {
+        self.columns.iter().enumerate().filter_map(|(i, c)| c.unique.then_some(i)).collect()
+    }
+
+    /// Finds the first non-deleted row which has the same value as 'row' in any of the given
+    /// columns, the row at index 'skip' is ignored
+    ///
+    /// Returns (row_index, column_index) of the conflict
+    pub fn find_conflict(&self, row: &Row, column_indexes: &[usize], skip: Option<usize>) -> Option<(usize, usize)> {
+        for &i in column_indexes {
+            let value = row.get(i)?;
+            let conflict = self.data
+                .iter()
+                .enumerate()
+                .filter(|(index, r)| !r.is_deleted() && Some(*index) != skip)
+                .find(|(_, r)| r.get(i) == Some(value));
+
+            if let Some((index, _)) = conflict {
+                return Some((index, i))
+            }
+        }
+        None
+    }
 }
 
 impl Default for Table {
@@ -213,6 +230,8 @@ impl Default for Table {
             read_only: false,
             rls_enabled: true,
             policies: HashMap::new(),
+            indexes: HashMap::new(),
+            triggers: Vec::new(),
         }
     }
 }
@@ -0,0 +1,55 @@
+use super::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Function which is run for every row affected by an insert, update or delete on a table
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    /// Name of the function to execute, it takes no arguments
+    pub function: String,
+}
+
+impl Trigger {
+    pub fn new(name: &str, timing: TriggerTiming, event: TriggerEvent, function: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            timing,
+            event,
+            function: function.to_owned(),
+        }
+    }
+}
+
+impl TriggerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerEvent::Insert => "insert",
+            TriggerEvent::Update => "update",
+            TriggerEvent::Delete => "delete",
+        }
+    }
+}
+
+impl Table {
+    /// Returns the triggers which run 'timing' the 'event', in the order they were created
+    pub fn triggers_for(&self, timing: TriggerTiming, event: TriggerEvent) -> Vec<Trigger> {
+        self.triggers.iter()
+            .filter(|trigger| trigger.timing == timing && trigger.event == event)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,245 @@
+use std::{fmt::Display, str::FromStr, cmp::Ordering};
+
+/// Largest number of digits a decimal can hold, every 38 digit number fits into i128
+pub const MAX_PRECISION: u8 = 38;
+
+/// Scale added to the larger scale of the operands in a decimal division
+const DIVISION_SCALE: u8 = 6;
+
+/// Exact fixed point number, 'value' is the number multiplied by 10^scale
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: u8,
+}
+
+/// How digits dropped by 'Decimal::round' are handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    /// Half away from zero, 2.5 -> 3 and -2.5 -> -3
+    HalfUp,
+    /// Half to the nearest even digit, 2.5 -> 2 and 3.5 -> 4
+    HalfEven,
+    /// Towards zero
+    Truncate,
+    /// Towards negative infinity
+    Floor,
+    /// Towards positive infinity
+    Ceiling,
+}
+
+fn pow10(exponent: u8) -> Option<i128> {
+    10_i128.checked_pow(exponent as u32)
+}
+
+impl Decimal {
+    pub fn new(value: i128, scale: u8) -> Self {
+        Self { value, scale }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    /// True if there are no non-zero digits after the decimal point
+    pub fn is_integer(&self) -> bool {
+        self.value % pow10(self.scale).unwrap() == 0
+    }
+
+    /// Number of significant digits, at least as many as the scale
+    pub fn precision(&self) -> u8 {
+        let digits = self.value.unsigned_abs().checked_ilog10().map_or(1, |d| d as u8 + 1);
+        digits.max(self.scale)
+    }
+
+    /// Converts a float through its shortest exact representation, so 0.1 becomes 0.1 and not
+    /// the closest binary fraction
+    pub fn from_float<F: Into<f64> + Display + Copy>(value: F) -> Result<Self, String> {
+        if !value.into().is_finite() {
+            return Err(format!("Cannot convert {} to a decimal", value))
+        }
+
+        value.to_string().parse()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10_f64.powi(self.scale as i32)
+    }
+
+    /// Integer part of the decimal, digits after the decimal point are dropped
+    pub fn trunc(&self) -> i128 {
+        self.value / pow10(self.scale).unwrap()
+    }
+
+    /// Changes the scale, dropped digits are rounded with 'rounding'.
+    /// Returns None if the value overflows
+    pub fn round(&self, scale: u8, rounding: Rounding) -> Option<Self> {
+        if scale >= self.scale {
+            let value = self.value.checked_mul(pow10(scale - self.scale)?)?;
+            return Some(Self::new(value, scale))
+        }
+
+        let divisor = pow10(self.scale - scale)?;
+        let quotient = self.value / divisor;
+        let remainder = self.value % divisor;
+        if remainder == 0 {
+            return Some(Self::new(quotient, scale))
+        }
+
+        let sign = self.value.signum();
+        let half = remainder.unsigned_abs().cmp(&(divisor.unsigned_abs() / 2));
+        let away = match rounding {
+            Rounding::HalfUp => half != Ordering::Less,
+            Rounding::HalfEven => half == Ordering::Greater || (half == Ordering::Equal && quotient % 2 != 0),
+            Rounding::Truncate => false,
+            Rounding::Floor => sign < 0,
+            Rounding::Ceiling => sign > 0,
+        };
+
+        let value = if away { quotient.checked_add(sign)? } else { quotient };
+        Some(Self::new(value, scale))
+    }
+
+    /// Rounds to 'scale' (half up) and checks that the result has at most 'precision' digits,
+    /// used to store a decimal in a 'decimal(precision, scale)' column
+    pub fn fit(&self, precision: u8, scale: u8) -> Result<Self, String> {
+        let overflow = || format!("Decimal {} does not fit into decimal({}, {})", self, precision, scale);
+
+        let decimal = self.round(scale, Rounding::HalfUp).ok_or_else(overflow)?;
+        match decimal.precision() > precision {
+            true => Err(overflow()),
+            false => Ok(decimal),
+        }
+    }
+
+    /// Brings both decimals to the larger of their scales
+    fn align(self, other: Self) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.round(scale, Rounding::Truncate)?.value, other.round(scale, Rounding::Truncate)?.value, scale))
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Self::new(l.checked_add(r)?, scale))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Self::new(l.checked_sub(r)?, scale))
+    }
+
+    /// The scale of the product is the sum of the scales, at most 'MAX_PRECISION'
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        let value = self.value.checked_mul(other.value)?;
+        let product = Self::new(value, self.scale.checked_add(other.scale)?);
+
+        match product.scale > MAX_PRECISION {
+            true => product.round(MAX_PRECISION, Rounding::HalfUp),
+            false => Some(product),
+        }
+    }
+
+    /// The quotient gets 'DIVISION_SCALE' more digits than the larger scale of the operands,
+    /// the last digit is rounded half up. Returns None on overflow or division by zero
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.is_zero() {
+            return None
+        }
+
+        let scale = self.scale.max(other.scale).saturating_add(DIVISION_SCALE).min(MAX_PRECISION);
+        // one extra digit to round the quotient
+        let numerator = self.value.checked_mul(pow10(scale + 1 + other.scale - self.scale)?)?;
+        let quotient = Self::new(numerator / other.value, scale + 1);
+
+        quotient.round(scale, Rounding::HalfUp)
+    }
+
+    pub fn checked_rem(self, other: Self) -> Option<Self> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Self::new(l.checked_rem(r)?, scale))
+    }
+
+    pub fn checked_pow(self, exponent: u32) -> Option<Self> {
+        (0..exponent).try_fold(Self::new(1, 0), |result, _| result.checked_mul(self))
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self::new(self.value.checked_neg()?, self.scale))
+    }
+}
+
+impl From<i128> for Decimal {
+    fn from(value: i128) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    /// Parses '[+-]digits[.digits]', the scale is the number of digits after the point
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid decimal '{}'", s);
+
+        let (negative, digits) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim().strip_prefix('+').unwrap_or(s.trim())),
+        };
+
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(invalid())
+        }
+
+        let mut value: i128 = 0;
+        for c in integer.chars().chain(fraction.chars()) {
+            let digit = c.to_digit(10).ok_or_else(invalid)?;
+            value = value.checked_mul(10).and_then(|v| v.checked_add(digit as i128))
+                .ok_or(format!("Decimal '{}' has more than {} digits", s, MAX_PRECISION))?;
+        }
+
+        let scale = u8::try_from(fraction.len()).ok().filter(|scale| *scale <= MAX_PRECISION)
+            .ok_or(format!("Decimal '{}' has more than {} digits", s, MAX_PRECISION))?;
+
+        Ok(Self::new(if negative { -value } else { value }, scale))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.value.unsigned_abs().to_string();
+        let sign = if self.value < 0 { "-" } else { "" };
+
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits)
+        }
+
+        let digits = format!("{:0>width$}", digits, width = self.scale as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal { }
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    /// Compares exactly, falls back to floats if aligning the scales overflows
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.align(*other) {
+            Some((l, r, _)) => l.cmp(&r),
+            None => self.to_f64().partial_cmp(&other.to_f64()).unwrap_or(Ordering::Equal),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::{string::FromUtf8Error, array::TryFromSliceError};
 
 use crate::basics::column::{ColumnType, TextType, NumericType, TimestampType};
 
-use super::{Value, NumericValue, TimestampValue};
+use super::{Value, NumericValue, TimestampValue, Decimal, Interval, parse_json};
 
 pub trait FromBytes {
     type EnumType;
@@ -13,9 +13,9 @@ impl FromBytes for Value {
     type EnumType = ColumnType;
 
     fn from_bytes(bytes: &[u8], column_type: &ColumnType) -> Result<Self, String> {
-        // HINT: temporary solution for NULL values, currently only for Text
+        // HINT: temporary solution for NULL values, currently only for Text and Json
         // TODO: implement a different way to store NULL values
-        if matches!(column_type, ColumnType::Text(_)) && bytes.iter().all(|b| *b == 0) {
+        if matches!(column_type, ColumnType::Text(_) | ColumnType::Json(_)) && bytes.iter().all(|b| *b == 0) {
             return Ok(Value::Null)
         }
 
@@ -49,6 +49,18 @@ impl FromBytes for Value {
                 Value::Timestamp(v)
             },
             ColumnType::Boolean => Value::Boolean(bytes[0] != 0),
+            // the flag byte is 0 for NULL, a zero vector is stored with flag 1
+            ColumnType::Vector(_) if bytes[0] == 0 => Value::Null,
+            ColumnType::Vector(_) => {
+                let values = bytes[1..].chunks(4)
+                    .map(|chunk| NumericValue::from_bytes(chunk, &NumericType::Float32).map(Value::Numeric))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Value::Array(values)
+            },
+            ColumnType::Json(_) => {
+                let json = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+                parse_json(json.trim_end_matches('\0'))?
+            },
 
             _ => todo!("Value::from_bytes for {:?}", column_type)
         };
@@ -74,6 +86,7 @@ impl FromBytes for NumericValue {
             NumericType::IntU64 => NumericValue::IntU64(u64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
             NumericType::Float32 => NumericValue::Float32(f32::from_be_bytes(bytes.try_into().map_err(map_err)?)),
             NumericType::Float64 => NumericValue::Float64(f64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
+            NumericType::Decimal(_, s) => NumericValue::Decimal(Decimal::new(i128::from_be_bytes(bytes.try_into().map_err(map_err)?), *s)),
         };
 
         Ok(numeric_value)
@@ -91,6 +104,21 @@ impl FromBytes for TimestampValue {
             TimestampType::Milliseconds => TimestampValue::Milliseconds(u64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
             TimestampType::Microseconds => TimestampValue::Microseconds(u64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
             TimestampType::Nanoseconds => TimestampValue::Nanoseconds(u64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
+            TimestampType::Zoned => {
+                let (micros, offset) = bytes.split_at_checked(8).ok_or("Invalid zoned timestamp length")?;
+                TimestampValue::Zoned(i64::from_be_bytes(micros.try_into().map_err(map_err)?), i32::from_be_bytes(offset.try_into().map_err(map_err)?))
+            },
+            TimestampType::Date => TimestampValue::Date(i32::from_be_bytes(bytes.try_into().map_err(map_err)?)),
+            TimestampType::Time => TimestampValue::Time(u64::from_be_bytes(bytes.try_into().map_err(map_err)?)),
+            TimestampType::Interval => {
+                let (months, rest) = bytes.split_at_checked(4).ok_or("Invalid interval length")?;
+                let (days, micros) = rest.split_at_checked(4).ok_or("Invalid interval length")?;
+                TimestampValue::Interval(Interval::new(
+                    i32::from_be_bytes(months.try_into().map_err(map_err)?),
+                    i32::from_be_bytes(days.try_into().map_err(map_err)?),
+                    i64::from_be_bytes(micros.try_into().map_err(map_err)?),
+                ))
+            },
         };
 
         Ok(timestamp_value)
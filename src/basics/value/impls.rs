@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use super::{NumericValue, TimestampValue, Value};
+use super::{NumericValue, TimestampValue, Value, Decimal, time::{format_timestamp, format_date, format_time}};
 
 impl Display for NumericValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -15,6 +15,7 @@ impl Display for NumericValue {
             NumericValue::IntU64(n) => write!(f, "{}", n),
             NumericValue::Float32(n) => write!(f, "{}", n),
             NumericValue::Float64(n) => write!(f, "{}", n),
+            NumericValue::Decimal(n) => write!(f, "{}", n),
         }
     }
 }
@@ -26,6 +27,10 @@ impl Display for TimestampValue {
             TimestampValue::Milliseconds(t) => write!(f, "{}", t),
             TimestampValue::Microseconds(t) => write!(f, "{}", t),
             TimestampValue::Nanoseconds(t) => write!(f, "{}", t),
+            TimestampValue::Zoned(t, offset) => write!(f, "{}", format_timestamp(*t, *offset)),
+            TimestampValue::Date(d) => write!(f, "{}", format_date(*d as i64)),
+            TimestampValue::Time(t) => write!(f, "{}", format_time(*t as i64)),
+            TimestampValue::Interval(i) => write!(f, "{}", i),
         }
     }
 }
@@ -39,8 +44,10 @@ impl Display for Value {
             Value::Boolean(s) => write!(f, "{}", s),
             Value::Binary(s) => write!(f, "{:?}", s),
             Value::Array(s) => write!(f, "[{}]", s.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(", ")),
+            Value::Object(s) => write!(f, "{{{}}}", s.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<String>>().join(", ")),
             Value::Enum(s) => write!(f, "{}", s),
             Value::UUID(s) => write!(f, "{}", s),
+            Value::Function(c) => write!(f, "function {}({})", c.function.name, c.function.params.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ")),
             Value::Null => write!(f, "NULL"),
         }
     }
@@ -54,6 +61,13 @@ impl PartialOrd for NumericValue {
 
 impl Ord for NumericValue {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // decimals compare exactly with each other and with integers
+        if let (Some(l), Some(r)) = (self.to_decimal(), other.to_decimal()) {
+            if matches!(self, NumericValue::Decimal(_)) || matches!(other, NumericValue::Decimal(_)) {
+                return l.cmp(&r)
+            }
+        }
+
         let self_f64 = self.to_f64();
         let other_f64 = other.to_f64();
 
@@ -63,6 +77,10 @@ impl Ord for NumericValue {
 
 impl PartialEq for NumericValue {
     fn eq(&self, other: &Self) -> bool {
+        if matches!(self, NumericValue::Decimal(_)) || matches!(other, NumericValue::Decimal(_)) {
+            return self.cmp(other) == std::cmp::Ordering::Equal
+        }
+
         let self_f64 = self.to_f64();
         let other_f64 = other.to_f64();
 
@@ -72,6 +90,28 @@ impl PartialEq for NumericValue {
 
 impl Eq for NumericValue { }
 
+impl PartialOrd for TimestampValue {
+    /// Points in time compare by their instant whatever their unit or offset, times of day and
+    /// intervals only compare with their own kind
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (TimestampValue::Time(l), TimestampValue::Time(r)) => l.partial_cmp(r),
+            (TimestampValue::Interval(l), TimestampValue::Interval(r)) => l.partial_cmp(r),
+            (TimestampValue::Nanoseconds(l), TimestampValue::Nanoseconds(r)) => l.partial_cmp(r),
+            _ => match (self.instant_micros(), other.instant_micros()) {
+                (Ok(l), Ok(r)) => l.partial_cmp(&r),
+                _ => None,
+            }
+        }
+    }
+}
+
+impl PartialEq for TimestampValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+    }
+}
+
 impl NumericValue {
     pub fn to_f64(&self) -> f64 {
         match *self {
@@ -87,6 +127,17 @@ impl NumericValue {
 
             NumericValue::Float32(v) => v as f64,
             NumericValue::Float64(v) => v,
+
+            NumericValue::Decimal(v) => v.to_f64(),
+        }
+    }
+
+    /// Converts integers and decimals to an exact decimal, None for floats
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        match *self {
+            NumericValue::Float32(_) | NumericValue::Float64(_) => None,
+            NumericValue::Decimal(v) => Some(v),
+            _ => Some(Decimal::from(self.to_i128())),
         }
     }
 
@@ -104,6 +155,8 @@ impl NumericValue {
 
             NumericValue::Float32(v) => v as i128,
             NumericValue::Float64(v) => v as i128,
+
+            NumericValue::Decimal(v) => v.trunc(),
         }
     }
 }
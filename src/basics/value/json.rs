@@ -0,0 +1,310 @@
+use super::{Value, NumericValue};
+
+/// Serializes a value as compact JSON. Timestamps, enums and uuids become strings, binary data
+/// becomes an array of bytes, functions and floats which are not finite become null
+pub fn format_json(value: &Value) -> String {
+    let mut json = String::new();
+    write_json(value, &mut json);
+    json
+}
+
+fn write_json(value: &Value, json: &mut String) {
+    match value {
+        Value::Null | Value::Function(_) => json.push_str("null"),
+        Value::Boolean(b) => json.push_str(if *b { "true" } else { "false" }),
+        Value::Numeric(n) => match n {
+            NumericValue::Float32(f) if !f.is_finite() => json.push_str("null"),
+            NumericValue::Float64(f) if !f.is_finite() => json.push_str("null"),
+            n => json.push_str(&n.to_string()),
+        },
+        Value::Text(s) | Value::Enum(s) | Value::UUID(s) => write_string(s, json),
+        Value::Timestamp(t) => write_string(&t.to_iso().unwrap_or_else(|_| t.to_string()), json),
+        Value::Binary(bytes) => {
+            let bytes = bytes.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+            json.push_str(&format!("[{}]", bytes.join(",")));
+        },
+        Value::Array(values) => {
+            json.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 { json.push(',') }
+                write_json(value, json);
+            }
+            json.push(']');
+        },
+        Value::Object(entries) => {
+            json.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 { json.push(',') }
+                write_string(key, json);
+                json.push(':');
+                write_json(value, json);
+            }
+            json.push('}');
+        },
+    }
+}
+
+fn write_string(s: &str, json: &mut String) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Parses a JSON document. Integers become 'int' (or 'uint' if they only fit unsigned), other
+/// numbers become 'float', a repeated object key keeps the last value
+pub fn parse_json(json: &str) -> Result<Value, String> {
+    let mut parser = JsonParser { chars: json.chars().collect(), position: 0 };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("end of JSON"))
+    }
+
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonParser {
+    fn error(&self, expected: &str) -> String {
+        match self.chars.get(self.position) {
+            Some(c) => format!("Invalid JSON, expected {} but found '{}' at position {}", expected, c, self.position),
+            None => format!("Invalid JSON, expected {} but found end of input", expected),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("'{}'", c)))
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, String> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("'{}'", keyword)))
+            }
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::Text(self.string()?)),
+            Some('t') => self.keyword("true", Value::Boolean(true)),
+            Some('f') => self.keyword("false", Value::Boolean(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some('-' | '0'..='9') => self.number(),
+            _ => Err(self.error("JSON value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut object = Value::Object(vec![]);
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(object)
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("object key"))
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            object.set_member(&key, value)?;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(object)
+                },
+                _ => return Err(self.error("',' or '}'"))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Value::Array(values))
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(values))
+                },
+                _ => return Err(self.error("',' or ']'"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("'\"'"))?;
+            self.position += 1;
+
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("escape character"))?;
+                    self.position += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => string.push(escaped),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'u' => string.push(self.unicode_escape()?),
+                        _ => {
+                            self.position -= 1;
+                            return Err(self.error("escape character"))
+                        }
+                    }
+                },
+                c if (c as u32) < 0x20 => {
+                    self.position -= 1;
+                    return Err(self.error("escaped control character"))
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Parses the digits of a '\u' escape, surrogate pairs are combined into one character
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("valid unicode escape"))
+        }
+
+        if self.peek() != Some('\\') || self.chars.get(self.position + 1) != Some(&'u') {
+            return Err(self.error("low surrogate"))
+        }
+        self.position += 2;
+
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("low surrogate"))
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| self.error("valid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self.peek().and_then(|c| c.to_digit(16)).ok_or_else(|| self.error("hex digit"))?;
+            value = value * 16 + digit;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let from = parser.position;
+            while matches!(parser.peek(), Some('0'..='9')) {
+                parser.position += 1;
+            }
+            parser.position > from
+        };
+
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        if !digits(self) {
+            return Err(self.error("digit"))
+        }
+
+        let mut integer = true;
+        if self.peek() == Some('.') {
+            self.position += 1;
+            integer = false;
+            if !digits(self) {
+                return Err(self.error("digit"))
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.position += 1;
+            integer = false;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("digit"))
+            }
+        }
+
+        let number = self.chars[start..self.position].iter().collect::<String>();
+        if integer {
+            if let Ok(n) = number.parse::<i64>() {
+                return Ok(Value::Numeric(NumericValue::IntI64(n)))
+            }
+            if let Ok(n) = number.parse::<u64>() {
+                return Ok(Value::Numeric(NumericValue::IntU64(n)))
+            }
+        }
+
+        match number.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Numeric(NumericValue::Float64(n))),
+            _ => Err(format!("Invalid JSON, number '{}' is out of range", number))
+        }
+    }
+}
@@ -2,9 +2,17 @@ mod impls;
 mod from_bytes;
 mod to_bytes;
 mod as_inner;
+mod decimal;
+mod time;
+mod json;
 
 pub use from_bytes::FromBytes;
 pub use to_bytes::ToBytes;
+pub use decimal::{Decimal, Rounding, MAX_PRECISION};
+pub use time::{Interval, parse_date, parse_time, parse_timestamp, parse_offset};
+pub use json::{parse_json, format_json};
+
+use crate::function::Closure;
 
 #[derive(Debug, Clone)]
 pub enum NumericValue {
@@ -20,14 +28,23 @@ pub enum NumericValue {
 
     Float32(f32),
     Float64(f64),
+
+    Decimal(Decimal),
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub enum TimestampValue {
     Seconds(u64),
     Milliseconds(u64),
     Microseconds(u64),
     Nanoseconds(u64),
+    /// Microseconds since the epoch and the offset from UTC in seconds it is shown in
+    Zoned(i64, i32),
+    /// Days since 1970-01-01
+    Date(i32),
+    /// Microseconds since midnight
+    Time(u64),
+    Interval(Interval),
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -38,8 +55,12 @@ pub enum Value {
     Boolean(bool),
     Binary(Vec<u8>),
     Array(Vec<Value>),
+    /// Key value pairs in insertion order, keys are unique
+    Object(Vec<(String, Value)>),
     Enum(String),
     UUID(String),
+    /// Function which can be called, passed as an argument or stored in a variable
+    Function(Closure),
     Null,
 }
 
@@ -48,9 +69,56 @@ impl Value {
         matches!(self, Value::Null)
     }
 
-    pub fn like(&self, value: &Value) -> bool {
-        match (self, value) {
-            (Value::Text(s1), Value::Text(s2)) => s1.contains(s2),
+    /// Value of the key 'member' if this is an object
+    pub fn get_member(&self, member: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(key, _)| key == member).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follows 'path' through nested objects, None if a key is missing
+    pub fn get_path(&self, path: &[String]) -> Option<&Value> {
+        path.iter().try_fold(self, |value, member| value.get_member(member))
+    }
+
+    pub fn get_member_mut(&mut self, member: &str) -> Option<&mut Value> {
+        match self {
+            Value::Object(entries) => entries.iter_mut().find(|(key, _)| key == member).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Sets the key 'member' of an object, a new key is appended after the existing ones
+    ///
+    /// Errors if this is not an object
+    pub fn set_member(&mut self, member: &str, value: Value) -> Result<(), String> {
+        let entries = match self {
+            Value::Object(entries) => entries,
+            _ => return Err(format!("Cannot set member '{}' of a value which is not an object", member))
+        };
+
+        match entries.iter_mut().find(|(key, _)| key == member) {
+            Some((_, existing)) => *existing = value,
+            None => entries.push((member.to_string(), value)),
+        }
+
+        Ok(())
+    }
+
+    /// Sql 'like' match, '%' matches any sequence of characters, '_' matches a single character
+    /// and '\\' escapes the next character
+    pub fn like(&self, pattern: &Value) -> bool {
+        match (self, pattern) {
+            (Value::Text(text), Value::Text(pattern)) => like_match(text, pattern),
+            _ => false,
+        }
+    }
+
+    /// Case insensitive version of 'like'
+    pub fn ilike(&self, pattern: &Value) -> bool {
+        match (self, pattern) {
+            (Value::Text(text), Value::Text(pattern)) => like_match(&text.to_lowercase(), &pattern.to_lowercase()),
             _ => false,
         }
     }
@@ -76,3 +144,63 @@ impl Value {
         todo!()
     }
 }
+
+enum LikePart {
+    /// '%'
+    Any,
+    /// '_'
+    One,
+    Char(char),
+}
+
+fn like_match(text: &str, pattern: &str) -> bool {
+    let mut parts = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        parts.push(match c {
+            '%' => LikePart::Any,
+            '_' => LikePart::One,
+            // trailing escape matches itself
+            '\\' => LikePart::Char(chars.next().unwrap_or('\\')),
+            c => LikePart::Char(c),
+        })
+    }
+
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut t, mut p) = (0, 0);
+    // last '%' as (part index after it, text index it currently matches up to)
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match parts.get(p) {
+            Some(LikePart::Any) => {
+                p += 1;
+                backtrack = Some((p, t));
+                continue
+            },
+            Some(LikePart::One) => {
+                t += 1;
+                p += 1;
+                continue
+            },
+            Some(LikePart::Char(c)) if *c == text[t] => {
+                t += 1;
+                p += 1;
+                continue
+            },
+            _ => ()
+        }
+
+        // mismatch, let the last '%' consume one more character
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp;
+                t = bt + 1;
+                backtrack = Some((bp, t));
+            },
+            None => return false
+        }
+    }
+
+    parts[p..].iter().all(|part| matches!(part, LikePart::Any))
+}
@@ -0,0 +1,604 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::basics::column::TimestampType;
+
+use super::TimestampValue;
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// Largest offset from UTC a zoned timestamp can have, in seconds
+const MAX_OFFSET: i32 = 18 * 3600;
+
+/// Calendar duration, months and days are kept apart from the time part because their length
+/// depends on the date they are added to
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self { months, days, micros }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        Some(Self::new(
+            self.months.checked_add(other.months)?,
+            self.days.checked_add(other.days)?,
+            self.micros.checked_add(other.micros)?,
+        ))
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self::new(self.months.checked_neg()?, self.days.checked_neg()?, self.micros.checked_neg()?))
+    }
+
+    /// Approximate length in microseconds, a month counts as 30 days
+    pub fn total_micros(&self) -> i64 {
+        (self.months as i64 * 30 + self.days as i64) * MICROS_PER_DAY + self.micros
+    }
+
+    fn is_zero(&self) -> bool {
+        self.months == 0 && self.days == 0 && self.micros == 0
+    }
+}
+
+impl PartialOrd for Interval {
+    /// Intervals compare by their approximate length, so 'P1M' equals 'P30D'
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        // the length of the largest intervals does not fit into i64
+        let total = |i: &Self| (i.months as i128 * 30 + i.days as i128) * MICROS_PER_DAY as i128 + i.micros as i128;
+        total(self).partial_cmp(&total(other))
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic gregorian calendar
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// Year, month and day of a number of days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Adds calendar months to a date, the day is clamped to the length of the new month
+fn add_months(days: i64, months: i32) -> i64 {
+    let (year, month, day) = civil_from_days(days);
+    let total = year * 12 + (month as i64 - 1) + months as i64;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+
+    days_from_civil(year, month, day.min(days_in_month(year, month)))
+}
+
+fn format_fraction(micros: i64) -> String {
+    match micros {
+        0 => String::new(),
+        m => format!(".{:06}", m).trim_end_matches('0').to_string(),
+    }
+}
+
+/// Formats a date as 'YYYY-MM-DD'
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats microseconds since midnight as 'HH:MM:SS' with an optional fraction
+pub fn format_time(micros: i64) -> String {
+    format!("{:02}:{:02}:{:02}{}",
+        micros / MICROS_PER_HOUR,
+        micros % MICROS_PER_HOUR / MICROS_PER_MINUTE,
+        micros % MICROS_PER_MINUTE / MICROS_PER_SECOND,
+        format_fraction(micros % MICROS_PER_SECOND))
+}
+
+/// Formats an offset from UTC in seconds as 'Z' or '+HH:MM'
+pub fn format_offset(offset: i32) -> String {
+    if offset == 0 {
+        return "Z".to_string()
+    }
+
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+}
+
+/// Formats an instant as ISO-8601 'YYYY-MM-DDTHH:MM:SS+HH:MM' in the time of 'offset'
+pub fn format_timestamp(micros: i64, offset: i32) -> String {
+    let local = micros + offset as i64 * MICROS_PER_SECOND;
+    format!("{}T{}{}",
+        format_date(local.div_euclid(MICROS_PER_DAY)),
+        format_time(local.rem_euclid(MICROS_PER_DAY)),
+        format_offset(offset))
+}
+
+/// Reads the parts of ISO-8601 strings
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(s: &'a str) -> Self {
+        Self { chars: s.trim().chars().peekable() }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn digits(&mut self) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+        digits
+    }
+
+    /// Reads exactly 'count' digits
+    fn number(&mut self, count: usize) -> Option<i64> {
+        let digits = (0..count).map(|_| self.chars.next_if(|c| c.is_ascii_digit())).collect::<Option<String>>()?;
+        digits.parse().ok()
+    }
+
+    /// Reads an optional '.fraction' as microseconds, extra digits are dropped
+    fn fraction(&mut self) -> Option<i64> {
+        if !self.eat('.') {
+            return Some(0)
+        }
+
+        let digits = self.digits();
+        if digits.is_empty() {
+            return None
+        }
+
+        format!("{:0<6}", &digits[..digits.len().min(6)]).parse().ok()
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+}
+
+fn scan_date(scanner: &mut Scanner) -> Option<i64> {
+    let year = scanner.number(4)?;
+    scanner.eat('-').then_some(())?;
+    let month = scanner.number(2)? as u32;
+    scanner.eat('-').then_some(())?;
+    let day = scanner.number(2)? as u32;
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None
+    }
+
+    Some(days_from_civil(year, month, day))
+}
+
+fn scan_time(scanner: &mut Scanner) -> Option<i64> {
+    let hour = scanner.number(2)?;
+    scanner.eat(':').then_some(())?;
+    let minute = scanner.number(2)?;
+    let second = if scanner.eat(':') { scanner.number(2)? } else { 0 };
+    let fraction = scanner.fraction()?;
+
+    if hour > 23 || minute > 59 || second > 59 {
+        return None
+    }
+
+    Some(hour * MICROS_PER_HOUR + minute * MICROS_PER_MINUTE + second * MICROS_PER_SECOND + fraction)
+}
+
+fn scan_offset(scanner: &mut Scanner) -> Option<i32> {
+    if scanner.eat('Z') || scanner.eat('z') {
+        return Some(0)
+    }
+
+    let sign = if scanner.eat('+') { 1 } else if scanner.eat('-') { -1 } else { return None };
+    let hours = scanner.number(2)?;
+    scanner.eat(':');
+    let minutes = if scanner.is_done() { 0 } else { scanner.number(2)? };
+
+    let offset = sign * (hours * 3600 + minutes * 60) as i32;
+    (minutes < 60 && offset.abs() <= MAX_OFFSET).then_some(offset)
+}
+
+/// Parses an ISO-8601 date 'YYYY-MM-DD' into days since 1970-01-01
+pub fn parse_date(s: &str) -> Result<i32, String> {
+    let mut scanner = Scanner::new(s);
+    let days = scan_date(&mut scanner).filter(|_| scanner.is_done()).ok_or(format!("Invalid date '{}', expected 'YYYY-MM-DD'", s))?;
+    i32::try_from(days).map_err(|_| format!("Date '{}' is out of range", s))
+}
+
+/// Parses an ISO-8601 time 'HH:MM[:SS[.ffffff]]' into microseconds since midnight
+pub fn parse_time(s: &str) -> Result<u64, String> {
+    let mut scanner = Scanner::new(s);
+    let micros = scan_time(&mut scanner).filter(|_| scanner.is_done()).ok_or(format!("Invalid time '{}', expected 'HH:MM:SS'", s))?;
+    Ok(micros as u64)
+}
+
+/// Parses an offset from UTC, 'Z', 'UTC', '+HH', '+HHMM' or '+HH:MM', into seconds
+pub fn parse_offset(s: &str) -> Result<i32, String> {
+    if s.trim().eq_ignore_ascii_case("utc") {
+        return Ok(0)
+    }
+
+    let mut scanner = Scanner::new(s);
+    scan_offset(&mut scanner).filter(|_| scanner.is_done()).ok_or(format!("Invalid timezone offset '{}', expected 'Z' or '+HH:MM'", s))
+}
+
+/// Parses an ISO-8601 timestamp 'YYYY-MM-DD[THH:MM[:SS[.ffffff]]][Z|+HH:MM]' into microseconds
+/// since the epoch and its offset from UTC, timestamps without an offset are in UTC
+pub fn parse_timestamp(s: &str) -> Result<(i64, i32), String> {
+    let invalid = || format!("Invalid timestamp '{}', expected 'YYYY-MM-DDTHH:MM:SS+HH:MM'", s);
+
+    let mut scanner = Scanner::new(s);
+    let days = scan_date(&mut scanner).ok_or_else(invalid)?;
+    let time = match scanner.eat('T') || scanner.eat('t') || scanner.eat(' ') {
+        true => scan_time(&mut scanner).ok_or_else(invalid)?,
+        false => 0,
+    };
+    let offset = match scanner.is_done() {
+        true => 0,
+        false => scan_offset(&mut scanner).filter(|_| scanner.is_done()).ok_or_else(invalid)?,
+    };
+
+    let local = days * MICROS_PER_DAY + time;
+    Ok((local - offset as i64 * MICROS_PER_SECOND, offset))
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    /// Parses an ISO-8601 duration 'P[nY][nM][nW][nD][T[nH][nM][n[.f]S]]', a leading '-'
+    /// negates the whole interval and components may be negative
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid interval '{}', expected an ISO-8601 duration like 'P1DT2H'", s);
+
+        let mut scanner = Scanner::new(s);
+        let negative = scanner.eat('-');
+        if !scanner.eat('P') {
+            return Err(invalid())
+        }
+
+        let mut interval = Interval::default();
+        let (mut time, mut empty) = (false, true);
+        while !scanner.is_done() {
+            if !time && scanner.eat('T') {
+                time = true;
+                continue
+            }
+
+            let sign = if scanner.eat('-') { -1 } else { 1 };
+            let digits = scanner.digits();
+            let value = digits.parse::<i64>().map_err(|_| invalid())? * sign;
+            let fraction = scanner.fraction().ok_or_else(invalid)? * sign;
+            let unit = scanner.chars.next().ok_or_else(invalid)?;
+
+            if fraction != 0 && !(time && unit == 'S') {
+                return Err(invalid())
+            }
+
+            let scaled = |factor: i64| value.checked_mul(factor).ok_or_else(invalid);
+            let to_i32 = |v: i64| i32::try_from(v).map_err(|_| invalid());
+            let component = match (time, unit) {
+                (false, 'Y') => Interval::new(to_i32(scaled(12)?)?, 0, 0),
+                (false, 'M') => Interval::new(to_i32(value)?, 0, 0),
+                (false, 'W') => Interval::new(0, to_i32(scaled(7)?)?, 0),
+                (false, 'D') => Interval::new(0, to_i32(value)?, 0),
+                (true, 'H') => Interval::new(0, 0, scaled(MICROS_PER_HOUR)?),
+                (true, 'M') => Interval::new(0, 0, scaled(MICROS_PER_MINUTE)?),
+                (true, 'S') => Interval::new(0, 0, scaled(MICROS_PER_SECOND)?.checked_add(fraction).ok_or_else(invalid)?),
+                _ => return Err(invalid())
+            };
+            interval = interval.checked_add(component).ok_or_else(invalid)?;
+            empty = false;
+        }
+
+        if empty {
+            return Err(invalid())
+        }
+
+        match negative {
+            true => interval.checked_neg().ok_or_else(invalid),
+            false => Ok(interval),
+        }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "PT0S")
+        }
+
+        write!(f, "P")?;
+        if self.months / 12 != 0 { write!(f, "{}Y", self.months / 12)? }
+        if self.months % 12 != 0 { write!(f, "{}M", self.months % 12)? }
+        if self.days != 0 { write!(f, "{}D", self.days)? }
+        if self.micros == 0 {
+            return Ok(())
+        }
+
+        write!(f, "T")?;
+        let hours = self.micros / MICROS_PER_HOUR;
+        let minutes = self.micros % MICROS_PER_HOUR / MICROS_PER_MINUTE;
+        let micros = self.micros % MICROS_PER_MINUTE;
+        if hours != 0 { write!(f, "{}H", hours)? }
+        if minutes != 0 { write!(f, "{}M", minutes)? }
+        if micros != 0 {
+            let sign = if micros < 0 { "-" } else { "" };
+            let micros = micros.abs();
+            write!(f, "{}{}{}S", sign, micros / MICROS_PER_SECOND, format_fraction(micros % MICROS_PER_SECOND))?
+        }
+
+        Ok(())
+    }
+}
+
+impl TimestampValue {
+    pub fn timestamp_type(&self) -> TimestampType {
+        match self {
+            TimestampValue::Seconds(_) => TimestampType::Seconds,
+            TimestampValue::Milliseconds(_) => TimestampType::Milliseconds,
+            TimestampValue::Microseconds(_) => TimestampType::Microseconds,
+            TimestampValue::Nanoseconds(_) => TimestampType::Nanoseconds,
+            TimestampValue::Zoned(_, _) => TimestampType::Zoned,
+            TimestampValue::Date(_) => TimestampType::Date,
+            TimestampValue::Time(_) => TimestampType::Time,
+            TimestampValue::Interval(_) => TimestampType::Interval,
+        }
+    }
+
+    /// Offset from UTC in seconds the value is shown in, 0 for everything but zoned timestamps
+    pub fn offset(&self) -> i32 {
+        match self {
+            TimestampValue::Zoned(_, offset) => *offset,
+            _ => 0,
+        }
+    }
+
+    /// Point in time as microseconds since the epoch, dates are at midnight UTC.
+    /// Nanoseconds are truncated to microseconds
+    pub fn instant_micros(&self) -> Result<i64, String> {
+        let overflow = || format!("Timestamp {} is out of range", self);
+        let scaled = |value: u64, factor: i64| i64::try_from(value).ok().and_then(|v| v.checked_mul(factor)).ok_or_else(overflow);
+
+        match *self {
+            TimestampValue::Seconds(s) => scaled(s, MICROS_PER_SECOND),
+            TimestampValue::Milliseconds(ms) => scaled(ms, 1_000),
+            TimestampValue::Microseconds(us) => scaled(us, 1),
+            TimestampValue::Nanoseconds(ns) => scaled(ns / 1_000, 1),
+            TimestampValue::Zoned(us, _) => Ok(us),
+            TimestampValue::Date(days) => Ok(days as i64 * MICROS_PER_DAY),
+            TimestampValue::Time(_) | TimestampValue::Interval(_) => Err(format!("Value {} is not a point in time", self)),
+        }
+    }
+
+    /// Microseconds since the epoch in the local time of the value
+    fn local_micros(&self) -> Result<i64, String> {
+        Ok(self.instant_micros()? + self.offset() as i64 * MICROS_PER_SECOND)
+    }
+
+    /// Creates a point in time of 'timestamp_type' from microseconds since the epoch, 'offset'
+    /// is used by zoned timestamps and to find the local date of dates
+    pub fn from_instant(micros: i64, offset: i32, timestamp_type: &TimestampType) -> Result<Self, String> {
+        let before_epoch = || format!("Timestamp {} is before 1970 and cannot be stored as {:?}", format_timestamp(micros, offset), timestamp_type);
+        let unsigned = |value: i64| u64::try_from(value).map_err(|_| before_epoch());
+
+        let value = match timestamp_type {
+            TimestampType::Seconds => TimestampValue::Seconds(unsigned(micros.div_euclid(MICROS_PER_SECOND))?),
+            TimestampType::Milliseconds => TimestampValue::Milliseconds(unsigned(micros.div_euclid(1_000))?),
+            TimestampType::Microseconds => TimestampValue::Microseconds(unsigned(micros)?),
+            TimestampType::Nanoseconds => TimestampValue::Nanoseconds(unsigned(micros)?.checked_mul(1_000).ok_or("Timestamp is out of range for nanoseconds")?),
+            TimestampType::Zoned => TimestampValue::Zoned(micros, offset),
+            TimestampType::Date => {
+                let days = (micros + offset as i64 * MICROS_PER_SECOND).div_euclid(MICROS_PER_DAY);
+                TimestampValue::Date(i32::try_from(days).map_err(|_| "Date is out of range".to_string())?)
+            },
+            TimestampType::Time | TimestampType::Interval => return Err(format!("{:?} is not a point in time", timestamp_type)),
+        };
+
+        Ok(value)
+    }
+
+    /// Adds a calendar interval, months and days are added to the local date so 'P1M' keeps the
+    /// day of the month (clamped to the month's length) and 'P1D' keeps the time of day
+    pub fn add_interval(&self, interval: &Interval) -> Result<Self, String> {
+        let overflow = || format!("Overflow when adding interval {} to {}", interval, self);
+
+        let value = match *self {
+            TimestampValue::Interval(i) => TimestampValue::Interval(i.checked_add(*interval).ok_or_else(overflow)?),
+            TimestampValue::Time(micros) => {
+                if interval.months != 0 || interval.days != 0 {
+                    return Err(format!("Cannot add interval {} to time {}, only hours, minutes and seconds can be added", interval, self))
+                }
+
+                TimestampValue::Time((micros as i64 + interval.micros).rem_euclid(MICROS_PER_DAY) as u64)
+            },
+            TimestampValue::Date(days) => {
+                if interval.micros != 0 {
+                    return Err(format!("Cannot add interval {} to date {}, only years, months and days can be added", interval, self))
+                }
+
+                let days = add_months(days as i64, interval.months) + interval.days as i64;
+                TimestampValue::Date(i32::try_from(days).map_err(|_| overflow())?)
+            },
+            _ => {
+                let local = self.local_micros()?;
+                let days = add_months(local.div_euclid(MICROS_PER_DAY), interval.months) + interval.days as i64;
+                let local = days.checked_mul(MICROS_PER_DAY)
+                    .and_then(|d| d.checked_add(local.rem_euclid(MICROS_PER_DAY)))
+                    .and_then(|d| d.checked_add(interval.micros))
+                    .ok_or_else(overflow)?;
+
+                let offset = self.offset();
+                TimestampValue::from_instant(local - offset as i64 * MICROS_PER_SECOND, offset, &self.timestamp_type())?
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Subtracts an interval, or two values of the same kind which gives the interval between
+    /// them. The difference of two points in time is split into days and time
+    pub fn sub(&self, other: &Self) -> Result<Self, String> {
+        let value = match (self, other) {
+            (_, TimestampValue::Interval(interval)) => {
+                let interval = interval.checked_neg().ok_or(format!("Overflow when subtracting interval {}", interval))?;
+                return self.add_interval(&interval)
+            },
+            (TimestampValue::Time(l), TimestampValue::Time(r)) => Interval::new(0, 0, *l as i64 - *r as i64),
+            (TimestampValue::Date(l), TimestampValue::Date(r)) => Interval::new(0, l - r, 0),
+            (TimestampValue::Time(_), _) | (_, TimestampValue::Time(_)) | (TimestampValue::Interval(_), _)
+                => return Err(format!("Cannot subtract {} from {}", other, self)),
+            _ => {
+                let micros = self.instant_micros()? - other.instant_micros()?;
+                let days = i32::try_from(micros / MICROS_PER_DAY).map_err(|_| "Interval is out of range".to_string())?;
+                Interval::new(0, days, micros % MICROS_PER_DAY)
+            }
+        };
+
+        Ok(TimestampValue::Interval(value))
+    }
+
+    /// Returns a field of the value in its local time, 'year', 'month', 'day', 'hour', 'minute',
+    /// 'second', 'millisecond' and 'microsecond' (of the second), 'dow' (0 is sunday), 'doy',
+    /// 'epoch' (seconds) or 'timezone' (offset in seconds)
+    pub fn extract(&self, field: &str) -> Result<i64, String> {
+        let invalid = || format!("Cannot extract '{}' from {}", field, self);
+
+        if let TimestampValue::Interval(interval) = self {
+            return match field {
+                "year" => Ok(interval.months as i64 / 12),
+                "month" => Ok(interval.months as i64 % 12),
+                "day" => Ok(interval.days as i64),
+                "hour" => Ok(interval.micros / MICROS_PER_HOUR),
+                "minute" => Ok(interval.micros % MICROS_PER_HOUR / MICROS_PER_MINUTE),
+                "second" => Ok(interval.micros % MICROS_PER_MINUTE / MICROS_PER_SECOND),
+                "millisecond" => Ok(interval.micros % MICROS_PER_SECOND / 1_000),
+                "microsecond" => Ok(interval.micros % MICROS_PER_SECOND),
+                "epoch" => Ok(interval.total_micros() / MICROS_PER_SECOND),
+                _ => Err(invalid())
+            }
+        }
+
+        let (days, time) = match self {
+            TimestampValue::Time(micros) => (None, *micros as i64),
+            _ => {
+                let local = self.local_micros()?;
+                (Some(local.div_euclid(MICROS_PER_DAY)), local.rem_euclid(MICROS_PER_DAY))
+            }
+        };
+
+        let date = days.map(civil_from_days);
+        let value = match (field, date) {
+            ("year", Some((year, _, _))) => year,
+            ("month", Some((_, month, _))) => month as i64,
+            ("day", Some((_, _, day))) => day as i64,
+            ("dow", Some(_)) => (days.unwrap() + 4).rem_euclid(7),
+            ("doy", Some((year, _, _))) => days.unwrap() - days_from_civil(year, 1, 1) + 1,
+            ("epoch", Some(_)) => self.instant_micros()?.div_euclid(MICROS_PER_SECOND),
+            ("timezone", Some(_)) => self.offset() as i64,
+            ("hour", _) => time / MICROS_PER_HOUR,
+            ("minute", _) => time % MICROS_PER_HOUR / MICROS_PER_MINUTE,
+            ("second", _) => time % MICROS_PER_MINUTE / MICROS_PER_SECOND,
+            ("millisecond", _) => time % MICROS_PER_SECOND / 1_000,
+            ("microsecond", _) => time % MICROS_PER_SECOND,
+            _ => return Err(invalid())
+        };
+
+        Ok(value)
+    }
+
+    /// Truncates the value to the start of its 'year', 'month', 'day', 'hour', 'minute' or
+    /// 'second' in its local time
+    pub fn truncate(&self, unit: &str) -> Result<Self, String> {
+        let time_unit = match unit {
+            "hour" => Some(MICROS_PER_HOUR),
+            "minute" => Some(MICROS_PER_MINUTE),
+            "second" => Some(MICROS_PER_SECOND),
+            "day" | "month" | "year" => None,
+            _ => return Err(format!("Invalid unit '{}' to truncate to, expected 'year', 'month', 'day', 'hour', 'minute' or 'second'", unit))
+        };
+
+        let value = match (self, time_unit) {
+            (TimestampValue::Interval(_), _) => return Err(format!("Cannot truncate interval {}", self)),
+            (TimestampValue::Time(micros), Some(unit)) => TimestampValue::Time(*micros - *micros % unit as u64),
+            (TimestampValue::Time(_), None) => return Err(format!("Cannot truncate time {} to a {}", self, unit)),
+            (TimestampValue::Date(_), Some(_)) => self.clone(),
+            _ => {
+                let local = self.local_micros()?;
+                let local = match time_unit {
+                    Some(time_unit) => local - local.rem_euclid(time_unit),
+                    None => {
+                        let (year, month, _) = civil_from_days(local.div_euclid(MICROS_PER_DAY));
+                        let days = match unit {
+                            "year" => days_from_civil(year, 1, 1),
+                            "month" => days_from_civil(year, month, 1),
+                            _ => local.div_euclid(MICROS_PER_DAY),
+                        };
+                        days * MICROS_PER_DAY
+                    }
+                };
+
+                let offset = self.offset();
+                TimestampValue::from_instant(local - offset as i64 * MICROS_PER_SECOND, offset, &self.timestamp_type())?
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Formats the value as ISO-8601, timestamps stored as plain numbers are shown in UTC
+    pub fn to_iso(&self) -> Result<String, String> {
+        let iso = match self {
+            TimestampValue::Date(days) => format_date(*days as i64),
+            TimestampValue::Time(micros) => format_time(*micros as i64),
+            TimestampValue::Interval(interval) => interval.to_string(),
+            _ => format_timestamp(self.instant_micros()?, self.offset()),
+        };
+
+        Ok(iso)
+    }
+}
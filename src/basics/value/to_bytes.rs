@@ -15,6 +15,7 @@ impl ToBytes for Value {
             Value::Boolean(b) => b.to_bytes(length),
             Value::Binary(b) => b.clone(), 
             Value::Array(a) => a.iter().flat_map(|v| v.to_bytes(length)).collect(),
+            Value::Object(_) | Value::Function(_) => self.to_string().to_bytes(length),
             Value::Enum(e) => e.to_bytes(length), 
             Value::UUID(u) => u.as_bytes().to_vec(), 
 
@@ -44,6 +45,8 @@ impl ToBytes for NumericValue {
             NumericValue::IntU64(n) => n.to_be_bytes().to_vec(),
             NumericValue::Float32(n) => n.to_be_bytes().to_vec(),
             NumericValue::Float64(n) => n.to_be_bytes().to_vec(),
+            // the scale is stored in the column type
+            NumericValue::Decimal(n) => n.value.to_be_bytes().to_vec(),
         }
     }
 }
@@ -55,6 +58,10 @@ impl ToBytes for TimestampValue {
             TimestampValue::Milliseconds(n) => n.to_be_bytes().to_vec(),
             TimestampValue::Microseconds(n) => n.to_be_bytes().to_vec(),
             TimestampValue::Nanoseconds(n) => n.to_be_bytes().to_vec(),
+            TimestampValue::Zoned(micros, offset) => [micros.to_be_bytes().as_slice(), &offset.to_be_bytes()].concat(),
+            TimestampValue::Date(days) => days.to_be_bytes().to_vec(),
+            TimestampValue::Time(micros) => micros.to_be_bytes().to_vec(),
+            TimestampValue::Interval(i) => [i.months.to_be_bytes().as_slice(), &i.days.to_be_bytes(), &i.micros.to_be_bytes()].concat(),
         }
     }
 }
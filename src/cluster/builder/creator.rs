@@ -1,6 +1,6 @@
 use std::{path::Path, sync::{Arc, RwLock}, collections::HashMap, rc::Rc};
 
-use crate::{utils::{log, disk}, cluster::{Cluster, ClusterSettings}, database::{DatabaseBuilder, Database, Run}, auth::{User, Hashish}};
+use crate::{utils::{log, disk}, cluster::{Cluster, ClusterSettings}, database::{DatabaseBuilder, Database, Run}, auth::{User, Hashish}, basics::Value};
 
 use super::ClusterBuilder;
 
@@ -33,6 +33,7 @@ impl ClusterBuilder {
             settings,
             roles: Default::default(),
             users,
+            functions: Vec::new(),
         };
 
         log::success("cluster created");
@@ -65,9 +66,10 @@ impl ClusterBuilder {
         let mut user = User::new(Self::INTERNAL_SUPERUSER_NAME, &hash);
         user.is_superuser = true;
 
-        let query = format!("query users insert name:'{}' hash:'{}' is_superuser:{}", user.name, user.hash, user.is_superuser);
+        let query = "query users insert name:$1 hash:$2 is_superuser:$3".to_string();
+        let params = [Value::Text(user.name.clone()), Value::Text(user.hash.clone()), Value::Boolean(user.is_superuser)];
         let options = Cluster::root_run_options(internal.clone(), settings);
-        Database::run(internal, query, Rc::new(options))?;
+        Database::run_with(internal, query, &params, Rc::new(options))?;
 
         users.insert(user.name.clone(), user);
 
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, path::Path, sync::{Arc, RwLock}, rc::Rc};
 
-use crate::{utils::{log, disk}, database::{DatabaseBuilder, Database, Run}, basics::Value, auth::{Role, Privilege, User}};
+use crate::{utils::{log, disk}, database::{DatabaseBuilder, Database, Run, PreparedStatement}, basics::{Value, value::NumericValue}, auth::{Role, Privilege, User}};
 
 use super::{super::{Cluster, ClusterSettings}, ClusterBuilder};
 
@@ -51,6 +51,7 @@ impl ClusterBuilder {
             settings,
             roles,
             users,
+            functions: Vec::new(),
         };
 
         log::success("cluster loaded");
@@ -59,7 +60,8 @@ impl ClusterBuilder {
 
     fn load_roles(internal: Arc<RwLock<Database>>, settings: &ClusterSettings) -> Result<HashMap<u64, Role>, String> {
         log::info("loading internal roles");
-        let result = Self::run_query("query roles select *", "roles", internal.clone(), settings)?;
+        let result = Self::run_query(&Database::prepare("query roles select *".to_string())?, &[], "roles", internal.clone(), settings)?;
+        let privileges_query = Database::prepare("query privileges select * where role_id == $1".to_string())?;
 
         // parse roles
         let mut roles = HashMap::new();
@@ -75,8 +77,8 @@ impl ClusterBuilder {
 
             let role = roles.entry(id).or_insert(Role::new(name));
 
-            let query = format!("query privileges select * where role_id == {}", id);
-            let result = Self::run_query(&query, "privileges", internal.clone(), settings)?;
+            let params = [Value::Numeric(NumericValue::IntU64(id))];
+            let result = Self::run_query(&privileges_query, &params, "privileges", internal.clone(), settings)?;
 
             for row in result {
                 let row = row.as_array().ok_or("invalid privilege row")?;
@@ -97,7 +99,8 @@ impl ClusterBuilder {
 
     fn load_users(internal: Arc<RwLock<Database>>, roles: &HashMap<u64, Role>, settings: &ClusterSettings) -> Result<HashMap<String, User>, String> {
         log::info("loading internal users");
-        let result = Self::run_query("query users select *", "users", internal.clone(), settings)?;
+        let result = Self::run_query(&Database::prepare("query users select *".to_string())?, &[], "users", internal.clone(), settings)?;
+        let user_roles_query = Database::prepare("query user_roles select * where user_id == $1".to_string())?;
 
         // parse users
         let mut users = HashMap::new();
@@ -117,8 +120,8 @@ impl ClusterBuilder {
                 user.is_superuser = true;
             }
 
-            let query = format!("query user_roles select * where user_id == {}", id);
-            let result = Self::run_query(&query, "user_roles", internal.clone(), settings)?;
+            let params = [Value::Numeric(NumericValue::IntU64(id))];
+            let result = Self::run_query(&user_roles_query, &params, "user_roles", internal.clone(), settings)?;
 
             for row in result {
                 let row = row.as_array().ok_or("invalid user_role row")?;
@@ -143,9 +146,9 @@ impl ClusterBuilder {
     /// Helper function to run a query and extract data from it's result
     ///
     /// 'what' is a description of the item we are querying, used for error messages
-    fn run_query(query: &str, what: &str, internal: Arc<RwLock<Database>>, settings: &ClusterSettings) -> Result<Vec<Value>, String> {
+    fn run_query(statement: &PreparedStatement, params: &[Value], what: &str, internal: Arc<RwLock<Database>>, settings: &ClusterSettings) -> Result<Vec<Value>, String> {
         let options = Cluster::root_run_options(internal.clone(), settings);
-        let query_result = Database::run(internal, query.to_string(), Rc::new(options))
+        let query_result = Database::execute(internal, statement, params, Rc::new(options))
             .or(Err(format!("failed to query {}", what)))?; 
 
         let result;
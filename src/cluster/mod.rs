@@ -6,7 +6,7 @@ pub use settings::ClusterSettings;
 
 use std::{collections::HashMap, sync::{Arc, RwLock}, rc::Rc};
 
-use crate::{database::{Database, RunOptions, Run, QueryResult, DatabaseBuilder}, auth::{Role, User, Hashish}, utils::is_valid_name, syntax::context::Ctx, basics::Value};
+use crate::{database::{Database, RunOptions, RunLimits, CancelHandle, Run, QueryResult, DatabaseBuilder}, auth::{Role, User, Hashish}, utils::is_valid_name, syntax::context::Ctx, basics::Value, function::Function};
 
 pub struct Cluster {
     pub databases: HashMap<String, Arc<RwLock<Database>>>,
@@ -15,6 +15,8 @@ pub struct Cluster {
 
     pub internal: Arc<RwLock<Database>>,
     pub settings: ClusterSettings,
+    /// Native functions registered for every database, including the ones created later
+    pub functions: Vec<Function>,
 }
 
 impl Cluster {
@@ -25,6 +27,7 @@ impl Cluster {
             users: HashMap::default(),
             internal: internal,
             settings: settings.clone(),
+            functions: Vec::new(),
         }
     }
 
@@ -48,13 +51,16 @@ impl Cluster {
             auth_user: Self::root_user_rc(),
             cluster: Arc::new(RwLock::new(cluster)),
             is_schema: false,
+            limits: RunLimits::default(),
+            cancel: CancelHandle::new(),
+            threads: RunOptions::default_threads(),
         }
     }
 
-    /// Run a query as root cluster user
-    fn run_as_root(&self, query: String) -> Result<QueryResult, String> {
+    /// Run a query as root cluster user, 'params' are bound to its placeholders
+    fn run_as_root(&self, query: &str, params: &[Value]) -> Result<QueryResult, String> {
         let options = Cluster::root_run_options(self.internal.clone(), &self.settings);
-        Database::run(self.internal.clone(), query, Rc::new(options))
+        Database::run_with(self.internal.clone(), query.to_string(), params, Rc::new(options))
     }
 
     /// Create a new physical database in the cluster
@@ -71,12 +77,34 @@ impl Cluster {
             return Err("Database name invalid".to_string())
         }
 
-        let database = DatabaseBuilder::new(name, &self.settings.root_dir).create()?;
+        let mut database = DatabaseBuilder::new(name, &self.settings.root_dir).create()?;
+        for function in &self.functions {
+            database.register_function(function.clone())?;
+        }
         self.databases.insert(name.to_string(), Arc::new(RwLock::new(database)));
 
         Ok(())
     }
 
+    /// Register a native function created with 'Function::native' for every database of the
+    /// cluster, databases created afterwards get it as well
+    pub fn register_function(&mut self, function: Function) -> Result<(), String> {
+        for (name, database) in &self.databases {
+            let database = database.read().map_err(|e| e.to_string())?;
+            if database.functions.contains_key(&function.name) {
+                return Err(format!("Function '{}' already exists in database {}", function.name, name))
+            }
+        }
+
+        for database in self.databases.values() {
+            let mut database = database.write().map_err(|e| e.to_string())?;
+            database.register_function(function.clone())?;
+        }
+
+        self.functions.push(function);
+        Ok(())
+    }
+
     /// Create a new physical user in the cluster
     pub fn create_user(&mut self, name: &str, password: &str, is_superuser: bool, ctx: &Ctx) -> Result<(), String> {
         if self.users.contains_key(name) {
@@ -92,8 +120,8 @@ impl Cluster {
         user.is_superuser = is_superuser;
 
         if !ctx.is_schema() { 
-            let query = format!("query users insert name:'{}' hash:'{}' is_superuser:{}", user.name, user.hash, user.is_superuser);
-            self.run_as_root(query)?;
+            let params = [Value::Text(user.name.clone()), Value::Text(user.hash.clone()), Value::Boolean(user.is_superuser)];
+            self.run_as_root("query users insert name:$1 hash:$2 is_superuser:$3", &params)?;
         }
     
         self.users.insert(name.to_string(), user);
@@ -113,8 +141,7 @@ impl Cluster {
         let role = Role::new(name);
 
         if !ctx.is_schema() { 
-            let query = format!("query roles insert name:'{}'", role.name);
-            self.run_as_root(query)?;
+            self.run_as_root("query roles insert name:$1", &[Value::Text(role.name.clone())])?;
         }
 
         self.roles.insert(name.to_string(), role);
@@ -132,13 +159,12 @@ impl Cluster {
         }
 
         if !ctx.is_schema() {
-            let user_id = self.query_and_extract_single(format!("query users select id where name == '{}'", to))?
-                .as_numeric().ok_or("User id is not numeric")?.to_i128();
-            let role_id = self.query_and_extract_single(format!("query roles select id where name == '{}'", role))?
-                .as_numeric().ok_or("Role id is not numeric")?.to_i128();
+            let user_id = self.query_and_extract_single("query users select id where name == $1", &[Value::Text(to.to_string())])?;
+            user_id.as_numeric().ok_or("User id is not numeric")?;
+            let role_id = self.query_and_extract_single("query roles select id where name == $1", &[Value::Text(role.to_string())])?;
+            role_id.as_numeric().ok_or("Role id is not numeric")?;
 
-            let query = format!("query user_roles insert role_id:{} user_id:{}", role_id, user_id);
-            self.run_as_root(query)?;
+            self.run_as_root("query user_roles insert role_id:$1 user_id:$2", &[role_id, user_id])?;
         }
 
         let role = self.roles.get(role).unwrap().clone();
@@ -148,8 +174,8 @@ impl Cluster {
     }
 
     /// Helper function to run a query which returns a single value in a single row
-    fn query_and_extract_single(&self, query: String) -> Result<Value, String> {
-        let result = self.run_as_root(query)?;
+    fn query_and_extract_single(&self, query: &str, params: &[Value]) -> Result<Value, String> {
+        let result = self.run_as_root(query, params)?;
 
         match result.data {
             Value::Array(a) => {
@@ -41,6 +41,9 @@ impl DatabaseBuilder {
         }
 
         database.purge()?;
+        for table in &mut database.tables {
+            table.load_indexes()?;
+        }
         database.add_builtin_functions();
 
         Ok(())
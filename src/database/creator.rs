@@ -1,6 +1,6 @@
 use std::io::Write;
 
-use crate::{basics::table::Table, syntax::{ast::{Node, SDL, CreateSDL}, stringify::ToSchemaString, context::Ctx}, utils::{disk, is_valid_name}, auth::RlsPolicy};
+use crate::{basics::{table::Table, Trigger}, syntax::{ast::{Node, SDL, CreateSDL}, stringify::ToSchemaString, context::Ctx}, function::{Function, FunctionBody}, utils::{disk, is_valid_name}, auth::RlsPolicy, index::Index};
 
 use super::Database;
 
@@ -72,4 +72,110 @@ impl Database {
 
         Ok(())
     }
+
+    /// Create a new index on a table column, and update the schema file
+    pub fn create_index(&mut self, table_name: &str, index: Index, ctx: &Ctx) -> Result<(), String> {
+        if !is_valid_name(&index.name) {
+            return Err("Index name invalid".to_string())
+        }
+
+        let table = match self.get_table_mut(table_name) {
+            Some(table) => table,
+            None => return Err(format!("Table {} does not exist", table_name))
+        };
+
+        let node = Node::SDL(SDL::Create(CreateSDL::Index {
+            name: index.name.clone(),
+            table: table_name.to_string(),
+            column: index.column.clone(),
+            kind: index.kind(),
+        }));
+
+        // Index is built from existing rows here, in schema mode the loader will do it
+        table.add_index(index)?;
+
+        if !ctx.is_schema() {
+            let mut schema = node.to_schema_string(0)?;
+            schema.push_str(";\n");
+
+            if let Err(e) = self.schema.write_all(schema.as_bytes()) {
+                return Err(format!("Error writing schema: {}", e))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a new trigger on a table, and update the schema file
+    pub fn create_trigger(&mut self, table_name: &str, trigger: Trigger, ctx: &Ctx) -> Result<(), String> {
+        if !is_valid_name(&trigger.name) {
+            return Err("Trigger name invalid".to_string())
+        }
+
+        let table = match self.get_table(table_name) {
+            Some(table) => table,
+            None => return Err(format!("Table {} does not exist", table_name))
+        };
+
+        if table.triggers.iter().any(|t| t.name == trigger.name) {
+            return Err(format!("Trigger {} already exists", trigger.name))
+        }
+
+        if !ctx.is_schema() {
+            if !self.functions.contains_key(&trigger.function) {
+                return Err(format!("Function {} does not exist", trigger.function))
+            }
+
+            let node = Node::SDL(SDL::Create(CreateSDL::Trigger {
+                table: table_name.to_string(),
+                trigger: trigger.clone().into()
+            }));
+
+            let mut schema = node.to_schema_string(0)?;
+            schema.push_str(";\n");
+
+            if let Err(e) = self.schema.write_all(schema.as_bytes()) {
+                return Err(format!("Error writing schema: {}", e))
+            }
+        }
+
+        let table = self.get_table_mut(table_name).expect("Table should exist");
+        table.triggers.push(trigger);
+
+        Ok(())
+    }
+
+    /// Create a new custom function, or replace an existing one, and update the schema file
+    pub fn create_function(&mut self, function: Function, replace: bool, ctx: &Ctx) -> Result<(), String> {
+        if self.functions.contains_key(&function.name) && !replace {
+            return Err(format!("Function '{}' already exists", function.name))
+        }
+
+        if !ctx.is_schema() {
+            let block = match &function.body {
+                FunctionBody::Custom(block) => block.clone(),
+                _ => return Err(format!("Built-in function '{}' cannot be written to the schema", function.name))
+            };
+
+            let node = Node::SDL(SDL::Create(CreateSDL::Function {
+                name: function.name.clone(),
+                parameters: function.params.clone(),
+                return_type: function.return_type.clone(),
+                block: Box::new(block),
+                replace,
+                token: 0,
+            }));
+
+            let mut schema = node.to_schema_string(0)?;
+            schema.push_str(";\n");
+
+            if let Err(e) = self.schema.write_all(schema.as_bytes()) {
+                return Err(format!("Error writing schema: {}", e))
+            }
+        }
+
+        self.functions.insert(function.name.clone(), function);
+
+        Ok(())
+    }
 }
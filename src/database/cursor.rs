@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use crate::{syntax::{runner::{Runner, SelectCursor}, context::Ctx, ast::SelectQuery}, basics::{Value, Column}};
+
+/// Result set of a select query which is built in batches of 'fetch_size' rows, instead of all at
+/// once. The database is locked only while a batch is being fetched.
+pub struct Cursor {
+    runner: Runner,
+    ctx: Ctx,
+    select: SelectQuery,
+    cursor: SelectCursor,
+    fetch_size: usize,
+    /// Rows fetched but not yet returned by the iterator
+    buffer: VecDeque<Value>,
+    exhausted: bool,
+}
+
+impl Cursor {
+    pub(super) fn new(runner: Runner, ctx: Ctx, select: SelectQuery, cursor: SelectCursor, fetch_size: usize) -> Self {
+        Self {
+            runner,
+            ctx,
+            select,
+            cursor,
+            fetch_size,
+            buffer: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Columns of the fetched rows
+    pub fn columns(&self) -> &[Column] {
+        self.cursor.columns()
+    }
+
+    pub fn fetch_size(&self) -> usize {
+        self.fetch_size
+    }
+
+    pub fn set_fetch_size(&mut self, fetch_size: usize) -> Result<(), String> {
+        if fetch_size == 0 {
+            return Err("Fetch size must be greater than 0".to_string())
+        }
+
+        self.fetch_size = fetch_size;
+        Ok(())
+    }
+
+    /// Fetch the next 'fetch_size' rows, returns an empty vector once the cursor is exhausted
+    pub fn fetch(&mut self) -> Result<Vec<Value>, String> {
+        self.fetch_many(self.fetch_size)
+    }
+
+    /// Fetch at most 'amount' next rows, returns an empty vector once the cursor is exhausted
+    pub fn fetch_many(&mut self, amount: usize) -> Result<Vec<Value>, String> {
+        let mut rows = self.buffer.drain(..amount.min(self.buffer.len())).collect::<Vec<_>>();
+        if self.exhausted || rows.len() == amount {
+            return Ok(rows)
+        }
+
+        self.runner.restart_limits();
+        let fetched = self.cursor.fetch(&self.runner, &self.select, amount - rows.len(), &self.ctx);
+        match fetched {
+            Ok(fetched) => {
+                self.exhausted = rows.len() + fetched.len() < amount;
+                rows.extend(fetched);
+                Ok(rows)
+            },
+            Err(err) => {
+                self.exhausted = true;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = Result<Value, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            if self.exhausted { return None }
+
+            match self.fetch() {
+                Ok(rows) => self.buffer.extend(rows),
+                Err(err) => return Some(Err(err))
+            }
+        }
+
+        self.buffer.pop_front().map(Ok)
+    }
+}
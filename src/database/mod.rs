@@ -1,13 +1,15 @@
 mod builder;
 mod run;
 mod creator;
+mod cursor;
 
 pub use builder::DatabaseBuilder;
-pub use run::{QueryResult, Run, RunOptions};
+pub use run::{QueryResult, Run, RunOptions, RunLimits, CancelHandle, PreparedStatement};
+pub use cursor::Cursor;
 
 use std::{collections::HashMap, fs::File, sync::{Arc, RwLock}};
 
-use crate::{basics::Table, function::Function};
+use crate::{basics::Table, function::{Function, FunctionBody}};
 
 #[derive(Debug)]
 pub struct Database {
@@ -71,4 +73,19 @@ impl Database {
     pub fn get_table_mut(&mut self, name: &str) -> Option<&mut Table> {
         self.tables.iter_mut().find(|table| table.name == name)
     }
+
+    /// Register a native function created with 'Function::native', it's available to every
+    /// script run on this database until it is unloaded
+    pub fn register_function(&mut self, function: Function) -> Result<(), String> {
+        if !matches!(function.body, FunctionBody::Native(_)) {
+            return Err(format!("Function '{}' is not a native function", function.name))
+        }
+
+        if self.functions.contains_key(&function.name) {
+            return Err(format!("Function '{}' already exists", function.name))
+        }
+
+        self.functions.insert(function.name.clone(), function);
+        Ok(())
+    }
 }
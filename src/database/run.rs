@@ -1,8 +1,8 @@
-use std::{sync::{Arc, RwLock}, rc::Rc};
+use std::{sync::{Arc, RwLock, atomic::{AtomicBool, Ordering}}, rc::Rc, time::Duration};
 
-use crate::{syntax::{runner::Runner, tokenizer::Tokenizer, context::{RunnerContext}, parser::Parser}, basics::Value, auth::User, cluster::Cluster};
+use crate::{syntax::{runner::Runner, tokenizer::Tokenizer, context::{RunnerContext}, parser::{Parser, ParserError, SourceMap}, checker::TypeChecker, ast::{Node, Query}, token::{TokenKind, Literal}}, basics::Value, auth::User, cluster::Cluster};
 
-use super::Database;
+use super::{Database, Cursor};
 
 pub struct QueryResult {
     pub amount: usize,
@@ -17,6 +17,41 @@ pub struct RunOptions {
 
     // runner options
     pub is_schema: bool,
+    pub limits: RunLimits,
+    pub cancel: CancelHandle,
+    /// Number of threads large table scans are split across, 1 disables parallel scans
+    pub threads: usize,
+}
+
+/// Limits of a single run, 'None' means unlimited
+#[derive(Debug, Clone, Default)]
+pub struct RunLimits {
+    /// Wall-clock time the run may take, for cursors it is measured per fetch
+    pub timeout: Option<Duration>,
+    /// Maximum number of evaluated nodes
+    pub max_steps: Option<usize>,
+    /// Maximum nesting of custom function calls
+    pub max_depth: Option<usize>,
+    /// Maximum number of rows returned by a select query
+    pub max_rows: Option<usize>,
+}
+
+/// Shared flag which stops a running script with an error, it can be triggered from any thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 impl RunOptions {
@@ -26,29 +61,105 @@ impl RunOptions {
             auth_user,
             cluster,
             is_schema: false,
+            limits: RunLimits::default(),
+            cancel: CancelHandle::new(),
+            threads: Self::default_threads(),
         }
     }
 
+    /// Available parallelism of the machine
+    pub fn default_threads() -> usize {
+        std::thread::available_parallelism().map_or(1, |threads| threads.get())
+    }
+
+    pub fn with_limits(mut self, limits: RunLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns a handle which cancels every run using these options
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
     pub fn new_rc(cluster_user: Rc<User>, auth_user: Rc<User>, cluster: Arc<RwLock<Cluster>>) -> Rc<Self> {
         Rc::new(Self::new(cluster_user, auth_user, cluster))
     }
 }
 
+/// Tokenized and parsed script, which can be executed many times with different parameters
+pub struct PreparedStatement {
+    ast: Node,
+    parameters: usize,
+    source: SourceMap,
+}
+
+impl PreparedStatement {
+    /// Number of values expected by the statement, the highest '$n' placeholder
+    pub fn parameters(&self) -> usize {
+        self.parameters
+    }
+}
+
 pub trait Run {
     fn run(database: Arc<RwLock<Database>>, input: String, options: Rc<RunOptions>) -> Result<QueryResult, String>;
+    /// Run 'input' with 'params' bound to its '$1', '$2', ... placeholders
+    fn run_with(database: Arc<RwLock<Database>>, input: String, params: &[Value], options: Rc<RunOptions>) -> Result<QueryResult, String>;
+    /// Tokenize and parse 'input' once, so it can be executed multiple times
+    fn prepare(input: String) -> Result<PreparedStatement, String>;
+    /// Statically check a prepared statement against the schema of 'database', every error is
+    /// reported with the token it was found at
+    fn check(database: &Database, statement: &PreparedStatement) -> Result<(), ParserError>;
+    /// Execute a prepared statement with 'params' bound to its placeholders, it is checked first
+    fn execute(database: Arc<RwLock<Database>>, statement: &PreparedStatement, params: &[Value], options: Rc<RunOptions>) -> Result<QueryResult, String>;
+    /// Open a cursor over a prepared select query, its rows are built 'fetch_size' at a time
+    fn cursor(database: Arc<RwLock<Database>>, statement: &PreparedStatement, params: &[Value], fetch_size: usize, options: Rc<RunOptions>) -> Result<Cursor, String>;
 }
 
 impl Run for Database {
     fn run(database: Arc<RwLock<Database>>, input: String, options: Rc<RunOptions>) -> Result<QueryResult, String> {
+        Self::run_with(database, input, &[], options)
+    }
+
+    fn run_with(database: Arc<RwLock<Database>>, input: String, params: &[Value], options: Rc<RunOptions>) -> Result<QueryResult, String> {
+        let statement = Self::prepare(input)?;
+        Self::execute(database, &statement, params, options)
+    }
+
+    fn prepare(input: String) -> Result<PreparedStatement, String> {
         let tokens = Tokenizer::new(input).tokenize()?;
-        let ast = Parser::new(tokens).parse().or_else(|e| { 
+        let parameters = tokens.iter().filter_map(|token| match token.kind {
+            TokenKind::Literal(Literal::Parameter(index)) => Some(index),
+            _ => None
+        }).max().unwrap_or(0);
+
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().or_else(|e| { 
             let error = e.errors().get(0).ok_or("ParserError is empty after parser returned an error")?;
             Err(format!("{} at {:?}", error.message(), error.token()))
         })?;
+
+        Ok(PreparedStatement { ast, parameters, source: parser.source_map() })
+    }
+
+    fn check(database: &Database, statement: &PreparedStatement) -> Result<(), ParserError> {
+        TypeChecker::new(database, &statement.source).check(&statement.ast)
+    }
+
+    fn execute(database: Arc<RwLock<Database>>, statement: &PreparedStatement, params: &[Value], options: Rc<RunOptions>) -> Result<QueryResult, String> {
+        if params.len() != statement.parameters {
+            return Err(format!("Statement expects {} parameters, got {}", statement.parameters, params.len()))
+        }
+
+        // schema scripts were checked when they first ran
+        if !options.is_schema {
+            Self::check_statement(&database, statement)?;
+        }
+
         let runner = Runner::new(database);
-        let ctx = RunnerContext::new_ctx(options);
+        let ctx = RunnerContext::new_ctx_with(options, params.to_vec());
 
-        match runner.run(&ast, &ctx) {
+        match runner.run(&statement.ast, &ctx) {
             Ok(result) => match result {
                 Some(result) => match result {
                     Value::Array(array) => {
@@ -74,4 +185,41 @@ impl Run for Database {
             Err(err) => Err(err.to_string())
         }
     }
+
+    fn cursor(database: Arc<RwLock<Database>>, statement: &PreparedStatement, params: &[Value], fetch_size: usize, options: Rc<RunOptions>) -> Result<Cursor, String> {
+        if params.len() != statement.parameters {
+            return Err(format!("Statement expects {} parameters, got {}", statement.parameters, params.len()))
+        }
+        if fetch_size == 0 {
+            return Err("Fetch size must be greater than 0".to_string())
+        }
+
+        let select = match &statement.ast {
+            Node::Block(nodes) => match nodes.as_slice() {
+                [Node::Query(Query::Select(select))] => select.clone(),
+                _ => return Err("Cursor statement must be a single select query".to_string())
+            },
+            _ => return Err("Cursor statement must be a single select query".to_string())
+        };
+        Self::check_statement(&database, statement)?;
+
+        let runner = Runner::new(database);
+        let ctx = RunnerContext::new_ctx_with(options, params.to_vec());
+        let cursor = runner.open_cursor(&select, &ctx)?;
+
+        Ok(Cursor::new(runner, ctx, select, cursor, fetch_size))
+    }
+}
+
+impl Database {
+    /// Type checks a statement before it runs, all errors are joined into one message
+    fn check_statement(database: &Arc<RwLock<Database>>, statement: &PreparedStatement) -> Result<(), String> {
+        let database = database.read().map_err(|e| e.to_string())?;
+        Self::check(&database, statement).map_err(|e| {
+            e.errors().iter()
+                .map(|error| format!("{} at {:?}", error.message(), error.token()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
 }
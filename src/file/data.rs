@@ -139,6 +139,20 @@ impl Data {
     pub fn len(&self) -> usize {
         self.rows.len()
     }
+
+    /// Returns the path of the loaded file
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Returns the index of a row borrowed from this data
+    pub fn index_of(&self, row: &Row) -> Option<usize> {
+        let range = self.rows.as_ptr_range();
+        let row = row as *const Row;
+
+        if !range.contains(&row) { return None }
+        Some(unsafe { row.offset_from(range.start) } as usize)
+    }
 }
 
 impl Data {
@@ -199,6 +213,38 @@ impl Data {
     pub fn read_disk(&mut self, _: &Vec<Column>) -> Result<(), String> {
         Ok(())
     }
+
+    /// Opens a separate reader over the data file, which reads rows one by one without storing
+    /// them in memory
+    pub fn stream(&self, columns: &[Column]) -> Result<RowStream, String> {
+        if !self.loaded { return Err("data not loaded".to_string()) }
+
+        let path = self.path.as_ref().unwrap();
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let entry_size = Table::get_row_prefix_length() + columns.iter().fold(0, |acc, c| acc + c.length) as usize;
+
+        Ok(RowStream {
+            reader: BufReader::new(file),
+            columns: columns.to_vec(),
+            buffer: vec![0u8; entry_size],
+        })
+    }
+}
+
+/// Sequential reader of rows stored in a data file, including deleted ones
+pub struct RowStream {
+    reader: BufReader<File>,
+    columns: Vec<Column>,
+    buffer: Vec<u8>,
+}
+
+impl Iterator for RowStream {
+    type Item = Result<Row, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_exact(&mut self.buffer).ok()?;
+        Some(Row::convert_from_bytes(&self.buffer, &self.columns))
+    }
 }
 
 impl Default for Data {
@@ -43,6 +43,9 @@ impl Purge for Table {
         self.data.writer_flush()?;
         self.data.writer_truncate()?;
 
+        // row positions changed, so indexes have to be rebuilt
+        self.rebuild_indexes()?;
+
         Ok(())
     }
 }
@@ -1,4 +1,4 @@
-use crate::basics::{Table, value::ToBytes};
+use crate::basics::Table;
 
 impl Table {
     /// Returns the number of bytes prefixing each row (metadata, flags, etc.)
@@ -50,8 +50,10 @@ impl Table {
             self.data.writer_write(&row_bytes)?; 
         }
        
+        let start = self.data.len();
         self.data.buffer_apply();
         self.data.writer_flush()?;
+        self.index_appended_rows(start);
 
         Ok(())
     }
@@ -72,8 +74,7 @@ impl Table {
         let buffers_with_col_idx = column_indexes
             .iter()
             .map(|&column_index| {
-                let column_length = self.columns[column_index].length;
-                let buffer = row.get(column_index).unwrap().to_bytes(column_length);
+                let buffer = self.columns[column_index].value_to_bytes(row.get(column_index).unwrap());
 
                 (column_index, buffer)
             })
@@ -88,6 +89,7 @@ impl Table {
             self.data.writer_flush()?;
         }
 
+        self.index_updated_row(row_index, &column_indexes);
         Ok(())
     }
 
@@ -100,6 +102,7 @@ impl Table {
         self.data.writer_seek(row_offset as u64)?;
         self.data.writer_write(&[flags])?;
         self.data.writer_flush()?; 
+        self.index_flagged_row(index);
 
         Ok(())
     }
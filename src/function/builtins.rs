@@ -1,8 +1,8 @@
 use std::{time::{SystemTime, UNIX_EPOCH}};
 
-use crate::{database::Database, syntax::{ast::Type, context::Ctx, runner::Runner}, basics::{Value, value::{TimestampValue, NumericValue}}, random::Random, lock::UnsafeRwLock};
+use crate::{database::Database, syntax::{ast::Type, context::Ctx, runner::{Runner, SequenceState}}, basics::{Value, value::{TimestampValue, NumericValue, Decimal, Rounding, MAX_PRECISION, Interval, parse_date as iso_date, parse_time as iso_time, parse_timestamp as iso_timestamp, parse_offset, parse_json as json_value, format_json}}, random::Random, lock::UnsafeRwLock, index::{Metric, to_vector}};
 
-use super::Function;
+use super::{Function, Closure};
 
 type DatabaseType = UnsafeRwLock<Database>;
 
@@ -12,9 +12,23 @@ impl Database {
             print(),
             println(),
             now(),
+            today(),
+            parse_date(),
+            parse_time(),
+            parse_timestamp(),
+            parse_interval(),
+            format_iso(),
+            extract(),
+            date_trunc(),
+            at_timezone(),
+            parse_json(),
+            to_json(),
             floor(),
             ceil(),
             round(),
+            round_even(),
+            trunc(),
+            decimal(),
             abs(),
             sqrt(),
             pow(),
@@ -23,6 +37,13 @@ impl Database {
             random_range(),
             format(),
             seq(),
+            l2(),
+            cosine(),
+            dot(),
+            map(),
+            filter(),
+            reduce(),
+            sort_by(),
         ];
 
         for function in functions {
@@ -72,6 +93,212 @@ fn now() -> Function {
     Function::built_in(name, params, return_type, body)
 }
 
+fn today() -> Function {
+    let name = "today";
+    let params = vec![];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, _: &[Value], _: &Ctx, _: &Runner| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        Ok(Some(Value::Timestamp(TimestampValue::Date((now / 86_400) as i32))))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+/// Returns the text argument 'name' of a function, None if it is null
+fn text_argument<'a>(args: &'a [Value], index: usize, name: &str, function: &str) -> Result<Option<&'a String>, String> {
+    match args.get(index) {
+        Some(Value::Text(text)) => Ok(Some(text)),
+        Some(Value::Null) => Ok(None),
+        _ => Err(format!("Function '{}' expects argument '{}' to be of type 'string'", function, name))
+    }
+}
+
+/// Returns the date, time, timestamp or interval argument 'name' of a function, None if it is null
+fn time_argument<'a>(args: &'a [Value], index: usize, name: &str, function: &str) -> Result<Option<&'a TimestampValue>, String> {
+    match args.get(index) {
+        Some(Value::Timestamp(timestamp)) => Ok(Some(timestamp)),
+        Some(Value::Null) => Ok(None),
+        _ => Err(format!("Function '{}' expects argument '{}' to be a date, time, timestamp or interval", function, name))
+    }
+}
+
+fn parse_date() -> Function {
+    let name = "parse_date";
+    let params = vec![("text", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match text_argument(args, 0, "text", "parse_date")? {
+            Some(text) => Value::Timestamp(TimestampValue::Date(iso_date(text)?)),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn parse_time() -> Function {
+    let name = "parse_time";
+    let params = vec![("text", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match text_argument(args, 0, "text", "parse_time")? {
+            Some(text) => Value::Timestamp(TimestampValue::Time(iso_time(text)?)),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+/// Parses an ISO-8601 timestamp into a timestamp which keeps its offset from UTC
+fn parse_timestamp() -> Function {
+    let name = "parse_timestamp";
+    let params = vec![("text", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match text_argument(args, 0, "text", "parse_timestamp")? {
+            Some(text) => {
+                let (micros, offset) = iso_timestamp(text)?;
+                Value::Timestamp(TimestampValue::Zoned(micros, offset))
+            },
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn parse_interval() -> Function {
+    let name = "parse_interval";
+    let params = vec![("text", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match text_argument(args, 0, "text", "parse_interval")? {
+            Some(text) => Value::Timestamp(TimestampValue::Interval(text.parse::<Interval>()?)),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn format_iso() -> Function {
+    let name = "format_iso";
+    let params = vec![("value", Type::Any)];
+    let return_type = Type::String;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match time_argument(args, 0, "value", "format_iso")? {
+            Some(value) => Value::Text(value.to_iso()?),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn extract() -> Function {
+    let name = "extract";
+    let params = vec![("field", Type::String), ("value", Type::Any)];
+    let return_type = Type::Int;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let field = text_argument(args, 0, "field", "extract")?.ok_or("Function 'extract' expects argument 'field'")?;
+        let value = match time_argument(args, 1, "value", "extract")? {
+            Some(value) => Value::Numeric(NumericValue::IntI64(value.extract(field)?)),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn date_trunc() -> Function {
+    let name = "date_trunc";
+    let params = vec![("unit", Type::String), ("value", Type::Any)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let unit = text_argument(args, 0, "unit", "date_trunc")?.ok_or("Function 'date_trunc' expects argument 'unit'")?;
+        let value = match time_argument(args, 1, "value", "date_trunc")? {
+            Some(value) => Value::Timestamp(value.truncate(unit)?),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+/// Shows a point in time with another offset from UTC, e.g. '+02:00', 'Z' or 'UTC'
+fn at_timezone() -> Function {
+    let name = "at_timezone";
+    let params = vec![("value", Type::Any), ("offset", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let offset = text_argument(args, 1, "offset", "at_timezone")?.ok_or("Function 'at_timezone' expects argument 'offset'")?;
+        let offset = parse_offset(offset)?;
+        let value = match time_argument(args, 0, "value", "at_timezone")? {
+            Some(value) => Value::Timestamp(TimestampValue::Zoned(value.instant_micros()?, offset)),
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn parse_json() -> Function {
+    let name = "parse_json";
+    let params = vec![("text", Type::String)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = match text_argument(args, 0, "text", "parse_json")? {
+            Some(text) => json_value(text)?,
+            None => Value::Null,
+        };
+
+        Ok(Some(value))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn to_json() -> Function {
+    let name = "to_json";
+    let params = vec![("value", Type::Any)];
+    let return_type = Type::String;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = args.first().ok_or("Expected argument 'value'")?;
+        Ok(Some(Value::Text(format_json(value))))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
 fn floor() -> Function {
     let name = "floor";
     let params = vec![("value", Type::Float)];
@@ -84,7 +311,9 @@ fn floor() -> Function {
                 => Ok(Some(Value::Numeric(NumericValue::Float32(f.floor())))),
             Value::Numeric(NumericValue::Float64(f)) 
                 => Ok(Some(Value::Numeric(NumericValue::Float64(f.floor())))),
-            _ => Err("Expected argument 'value' to be of type 'float'".to_string())
+            Value::Numeric(NumericValue::Decimal(d))
+                => Ok(Some(Value::Numeric(NumericValue::Decimal(d.round(0, Rounding::Floor).ok_or("Decimal overflow in floor")?)))),
+            _ => Err("Expected argument 'value' to be of type 'float' or 'decimal'".to_string())
         }
     };
 
@@ -103,32 +332,114 @@ fn ceil() -> Function {
                 => Ok(Some(Value::Numeric(NumericValue::Float32(f.ceil())))),
             Value::Numeric(NumericValue::Float64(f)) 
                 => Ok(Some(Value::Numeric(NumericValue::Float64(f.ceil())))),
-            _ => Err("Expected argument 'value' to be of type 'float'".to_string())
+            Value::Numeric(NumericValue::Decimal(d))
+                => Ok(Some(Value::Numeric(NumericValue::Decimal(d.round(0, Rounding::Ceiling).ok_or("Decimal overflow in ceil")?)))),
+            _ => Err("Expected argument 'value' to be of type 'float' or 'decimal'".to_string())
         }
     };
 
     Function::built_in(name, params, return_type, body)
 }
 
+/// Rounds a float or decimal 'value' to 'precision' digits after the decimal point
+fn round_number(rounding: Rounding, name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    let value = args.first().ok_or(format!("Function '{}' expects argument 'value'", name))?;
+    let precision = match args.get(1) {
+        Some(Value::Numeric(NumericValue::IntI64(p))) => *p,
+        _ => return Err(format!("Function '{}' expects argument 'precision' to be of type 'int'", name))
+    };
+
+    let float = |f: f64| -> f64 {
+        match rounding {
+            Rounding::HalfUp => f.round(),
+            Rounding::HalfEven => f.round_ties_even(),
+            Rounding::Truncate => f.trunc(),
+            Rounding::Floor => f.floor(),
+            Rounding::Ceiling => f.ceil(),
+        }
+    };
+
+    let value = match value {
+        Value::Numeric(NumericValue::Float32(f)) => {
+            let factor = 10.0_f64.powi(precision as i32);
+            NumericValue::Float32((float(*f as f64 * factor) / factor) as f32)
+        },
+        Value::Numeric(NumericValue::Float64(f)) => {
+            let factor = 10.0_f64.powi(precision as i32);
+            NumericValue::Float64(float(f * factor) / factor)
+        },
+        Value::Numeric(NumericValue::Decimal(d)) => {
+            let scale = u8::try_from(precision).ok().filter(|p| *p <= MAX_PRECISION)
+                .ok_or(format!("Function '{}' expects argument 'precision' to be between 0 and {} for decimals", name, MAX_PRECISION))?;
+            NumericValue::Decimal(d.round(scale, rounding).ok_or(format!("Decimal overflow in {} of {}", name, d))?)
+        },
+        Value::Null => return Ok(Some(Value::Null)),
+        _ => return Err(format!("Function '{}' expects argument 'value' to be of type 'float' or 'decimal'", name))
+    };
+
+    Ok(Some(Value::Numeric(value)))
+}
+
 fn round() -> Function {
     let name = "round";
-    let params = vec![("value", Type::Float), ("precision", Type::Int)];
-    let return_type = Type::Float;
+    let params = vec![("value", Type::Any), ("precision", Type::Int)];
+    let return_type = Type::Any;
 
     let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
-        let value = args.get(0).ok_or("Expected argument 'value'")?;
-        let precision = args.get(1).ok_or("Expected argument 'precision'")?;
-        match (value, precision) {
-            (Value::Numeric(NumericValue::Float32(f)), Value::Numeric(NumericValue::IntI64(p))) => match p {
-                0 => Ok(Some(Value::Numeric(NumericValue::Float32(f.round())))),
-                _ => Ok(Some(Value::Numeric(NumericValue::Float32((f * 10.0_f32.powi(*p as i32)).round() / 10.0_f32.powi(*p as i32))))), 
-            }
-            (Value::Numeric(NumericValue::Float64(f)), Value::Numeric(NumericValue::IntI64(p))) => match p {
-                0 => Ok(Some(Value::Numeric(NumericValue::Float64(f.round())))),
-                _ => Ok(Some(Value::Numeric(NumericValue::Float64((f * 10.0_f64.powi(*p as i32)).round() / 10.0_f64.powi(*p as i32))))),
-            }
-            _ => Err("Expected arguments 'value' and 'precision' to be of type 'float'".to_string())
-        }
+        round_number(Rounding::HalfUp, "round", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn round_even() -> Function {
+    let name = "round_even";
+    let params = vec![("value", Type::Any), ("precision", Type::Int)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        round_number(Rounding::HalfEven, "round_even", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn trunc() -> Function {
+    let name = "trunc";
+    let params = vec![("value", Type::Any), ("precision", Type::Int)];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        round_number(Rounding::Truncate, "trunc", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn decimal() -> Function {
+    let name = "decimal";
+    let params = vec![("value", Type::Any), ("scale", Type::Int)];
+    let return_type = Type::Decimal;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        let value = args.first().ok_or("Expected argument 'value'")?;
+        let scale = match args.get(1) {
+            Some(Value::Numeric(NumericValue::IntI64(s))) => u8::try_from(*s).ok().filter(|s| *s <= MAX_PRECISION)
+                .ok_or(format!("Expected argument 'scale' to be between 0 and {}", MAX_PRECISION))?,
+            _ => return Err("Expected argument 'scale' to be of type 'int'".to_string())
+        };
+
+        let decimal = match value {
+            Value::Text(s) => s.parse::<Decimal>()?,
+            Value::Numeric(NumericValue::Float32(f)) => Decimal::from_float(*f)?,
+            Value::Numeric(NumericValue::Float64(f)) => Decimal::from_float(*f)?,
+            Value::Numeric(n) => n.to_decimal().unwrap(),
+            Value::Null => return Ok(Some(Value::Null)),
+            _ => return Err("Expected argument 'value' to be of type 'string' or 'number'".to_string())
+        };
+
+        let decimal = decimal.round(scale, Rounding::HalfUp).ok_or(format!("Decimal overflow, {} does not fit with scale {}", decimal, scale))?;
+        Ok(Some(Value::Numeric(NumericValue::Decimal(decimal))))
     };
 
     Function::built_in(name, params, return_type, body)
@@ -199,7 +510,8 @@ fn len() -> Function {
         match value {
             Value::Text(s) => Ok(Some(Value::Numeric(NumericValue::IntU64(s.len() as u64)))),
             Value::Array(a) => Ok(Some(Value::Numeric(NumericValue::IntU64(a.len() as u64)))),
-            _ => Err("Expected argument 'value' to be of type 'text' or 'array'".to_string())
+            Value::Object(o) => Ok(Some(Value::Numeric(NumericValue::IntU64(o.len() as u64)))),
+            _ => Err("Expected argument 'value' to be of type 'text', 'array' or 'object'".to_string())
         }
     };
 
@@ -330,23 +642,22 @@ fn seq() -> Function {
         let table = db.get_table(&table).ok_or(&format!("Table '{}' not found", table))?;
         let column = table.get_column(&column).ok_or(&format!("Column '{}' not found", column))?;
 
-        let query = format!("query {} select {} order {} desc limit 1", table.name, column.name, column.name);
-        let max_id = match runner.run_raw(&query, ctx) {
-            // array of rows
-            Ok(Some(Value::Array(a))) => match a.get(0) {
-                // array of selected values in the row
-                Some(Value::Array(a)) => match a.get(0) {
-                    Some(Value::Numeric(n)) => Some(n.to_i128()),
-                    Some(_) => return Err("Expected query result to return a number".to_string()),
-                    None => None,
-                },
-                Some(_) => return Err("Expected query result to return an array of rows".to_string()),
-                None => None,
-            }
-            Ok(_) => return Err("Expected query to return an array".to_string()),
-            Err(e) => return Err(e),
+        // during an insert the maximum is kept between rows, only newly buffered rows are scanned
+        let index = table.get_column_index(&column.name)?;
+        let state = match runner.sequence(&table.name, &column.name) {
+            Some(state) => state,
+            None => SequenceState { max: stored_max(&table.name, &column.name, ctx, runner)?, scanned: 0 },
         };
 
+        // rows buffered by a running insert are not visible to the query yet
+        let buffered = &table.data.buf_rows;
+        let buffered_max_id = buffered[state.scanned.min(buffered.len())..].iter().filter_map(|row| match row.get(index) {
+            Some(Value::Numeric(n)) => Some(n.to_i128()),
+            _ => None,
+        }).max();
+        let max_id = state.max.max(buffered_max_id);
+        runner.set_sequence(&table.name, &column.name, SequenceState { max: max_id, scanned: buffered.len() });
+
         if max_id == Some(i64::MAX as i128) {
             return Err("Sequence overflow".to_string());
         }
@@ -364,3 +675,205 @@ fn seq() -> Function {
 
     Function::built_in(name, params, return_type, body)
 }
+
+/// Highest value of 'column' stored in 'table'
+fn stored_max(table: &str, column: &str, ctx: &Ctx, runner: &Runner) -> Result<Option<i128>, String> {
+    let query = format!("query {} select {} order {} desc limit 1", table, column, column);
+    match runner.run_raw(&query, ctx)? {
+        // array of rows
+        Some(Value::Array(a)) => match a.first() {
+            // array of selected values in the row
+            Some(Value::Array(a)) => match a.first() {
+                Some(Value::Numeric(n)) => Ok(Some(n.to_i128())),
+                Some(_) => Err("Expected query result to return a number".to_string()),
+                None => Ok(None),
+            },
+            Some(_) => Err("Expected query result to return an array of rows".to_string()),
+            None => Ok(None),
+        }
+        _ => Err("Expected query to return an array".to_string()),
+    }
+}
+
+/// Applies 'metric' to two vectors (arrays of numbers) of equal dimension
+fn vector_metric(metric: Metric, name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    let a = args.first().ok_or(format!("Function '{}' expects argument 'a'", name))?;
+    let b = args.get(1).ok_or(format!("Function '{}' expects argument 'b'", name))?;
+    if a.is_null() || b.is_null() {
+        return Ok(Some(Value::Null))
+    }
+
+    let (a, b) = match (to_vector(a), to_vector(b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Err(format!("Function '{}' expects arguments 'a' and 'b' to be arrays of numbers", name))
+    };
+
+    if a.len() != b.len() {
+        return Err(format!("Function '{}' expects vectors of equal dimension, got {} and {}", name, a.len(), b.len()))
+    }
+
+    Ok(Some(Value::Numeric(NumericValue::Float64(metric.apply(&a, &b)))))
+}
+
+fn l2() -> Function {
+    let name = "l2";
+    let params = vec![("a", Type::Array(Box::new(Type::Float))), ("b", Type::Array(Box::new(Type::Float)))];
+    let return_type = Type::Float;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        vector_metric(Metric::L2, "l2", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn cosine() -> Function {
+    let name = "cosine";
+    let params = vec![("a", Type::Array(Box::new(Type::Float))), ("b", Type::Array(Box::new(Type::Float)))];
+    let return_type = Type::Float;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        vector_metric(Metric::Cosine, "cosine", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn dot() -> Function {
+    let name = "dot";
+    let params = vec![("a", Type::Array(Box::new(Type::Float))), ("b", Type::Array(Box::new(Type::Float)))];
+    let return_type = Type::Float;
+
+    let body = |_: DatabaseType, args: &[Value], _: &Ctx, _: &Runner| {
+        vector_metric(Metric::Dot, "dot", args)
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+/// Returns the array and function arguments of a higher-order function, None if the array is null
+fn callback_arguments<'a>(args: &'a [Value], name: &str) -> Result<Option<(&'a Vec<Value>, &'a Closure)>, String> {
+    let closure = match args.get(1) {
+        Some(Value::Function(closure)) => closure,
+        _ => return Err(format!("Function '{}' expects argument 'function' to be a function", name))
+    };
+
+    match args.first() {
+        Some(Value::Array(array)) => Ok(Some((array, closure))),
+        Some(Value::Null) => Ok(None),
+        _ => Err(format!("Function '{}' expects argument 'array' to be an array", name))
+    }
+}
+
+/// Calls 'closure' with 'arguments', a callback without a return value returns null
+fn callback(runner: &Runner, closure: &Closure, arguments: Vec<Value>, ctx: &Ctx) -> Result<Value, String> {
+    Ok(runner.call_closure(closure, arguments, ctx)?.unwrap_or(Value::Null))
+}
+
+fn map() -> Function {
+    let name = "map";
+    let params = vec![("array", Type::Array(Box::new(Type::Any))), ("function", Type::Function(vec![Type::Any], Box::new(Type::Any)))];
+    let return_type = Type::Array(Box::new(Type::Any));
+
+    let body = |_: DatabaseType, args: &[Value], ctx: &Ctx, runner: &Runner| {
+        let (array, closure) = match callback_arguments(args, "map")? {
+            Some(arguments) => arguments,
+            None => return Ok(Some(Value::Null))
+        };
+
+        let values = array.iter()
+            .map(|value| callback(runner, closure, vec![value.clone()], ctx))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Some(Value::Array(values)))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn filter() -> Function {
+    let name = "filter";
+    let params = vec![("array", Type::Array(Box::new(Type::Any))), ("function", Type::Function(vec![Type::Any], Box::new(Type::Boolean)))];
+    let return_type = Type::Array(Box::new(Type::Any));
+
+    let body = |_: DatabaseType, args: &[Value], ctx: &Ctx, runner: &Runner| {
+        let (array, closure) = match callback_arguments(args, "filter")? {
+            Some(arguments) => arguments,
+            None => return Ok(Some(Value::Null))
+        };
+
+        let mut values = vec![];
+        for value in array {
+            match callback(runner, closure, vec![value.clone()], ctx)? {
+                Value::Boolean(true) => values.push(value.clone()),
+                Value::Boolean(false) => {},
+                result => return Err(format!("Function 'filter' expects the function to return a boolean, got '{:?}'", runner.get_type(&result)))
+            }
+        }
+
+        Ok(Some(Value::Array(values)))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn reduce() -> Function {
+    let name = "reduce";
+    let params = vec![
+        ("array", Type::Array(Box::new(Type::Any))),
+        ("function", Type::Function(vec![Type::Any, Type::Any], Box::new(Type::Any))),
+        ("initial", Type::Any),
+    ];
+    let return_type = Type::Any;
+
+    let body = |_: DatabaseType, args: &[Value], ctx: &Ctx, runner: &Runner| {
+        let (array, closure) = match callback_arguments(args, "reduce")? {
+            Some(arguments) => arguments,
+            None => return Ok(Some(Value::Null))
+        };
+        let initial = args.get(2).ok_or("Expected argument 'initial'")?;
+
+        let mut accumulator = initial.clone();
+        for value in array {
+            accumulator = callback(runner, closure, vec![accumulator, value.clone()], ctx)?;
+        }
+
+        Ok(Some(accumulator))
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
+
+fn sort_by() -> Function {
+    let name = "sort_by";
+    let params = vec![("array", Type::Array(Box::new(Type::Any))), ("function", Type::Function(vec![Type::Any], Box::new(Type::Any)))];
+    let return_type = Type::Array(Box::new(Type::Any));
+
+    let body = |_: DatabaseType, args: &[Value], ctx: &Ctx, runner: &Runner| {
+        let (array, closure) = match callback_arguments(args, "sort_by")? {
+            Some(arguments) => arguments,
+            None => return Ok(Some(Value::Null))
+        };
+
+        let mut keyed = array.iter()
+            .map(|value| Ok((callback(runner, closure, vec![value.clone()], ctx)?, value.clone())))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // the sort is stable, values with equal keys keep their order
+        let mut error = None;
+        keyed.sort_by(|(a, _), (b, _)| match a.partial_cmp(b) {
+            Some(ordering) => ordering,
+            None => {
+                error.get_or_insert_with(|| format!("Function 'sort_by' cannot compare keys '{}' and '{}'", a, b));
+                std::cmp::Ordering::Equal
+            }
+        });
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(Some(Value::Array(keyed.into_iter().map(|(_, value)| value).collect()))),
+        }
+    };
+
+    Function::built_in(name, params, return_type, body)
+}
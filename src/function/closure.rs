@@ -0,0 +1,49 @@
+use std::{sync::{Arc, Mutex}, cmp::Ordering};
+
+use crate::syntax::context::ScopeValue;
+
+use super::Function;
+
+/// Function used as a value, e.g. an anonymous function or a declared function passed by name.
+/// Anonymous functions share the cells of the variables they use with the scope they were
+/// created in, changes on either side are seen by the other.
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub function: Arc<Function>,
+    captured: Arc<Mutex<Vec<(String, ScopeValue)>>>,
+}
+
+impl Closure {
+    pub fn new(function: Function, captured: Vec<(String, ScopeValue)>) -> Self {
+        Self {
+            function: Arc::new(function),
+            captured: Arc::new(Mutex::new(captured)),
+        }
+    }
+
+    /// Cells of the captured variables
+    pub fn captured(&self) -> Vec<(String, ScopeValue)> {
+        self.captured.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replaces the captured variables, e.g. once every function of a module is declared
+    pub fn capture(&self, captured: Vec<(String, ScopeValue)>) {
+        *self.captured.lock().unwrap_or_else(|e| e.into_inner()) = captured;
+    }
+}
+
+/// Function values are only equal to copies of themselves
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.function, &other.function)
+    }
+}
+
+impl PartialOrd for Closure {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self == other {
+            true => Some(Ordering::Equal),
+            false => None,
+        }
+    }
+}
@@ -1,22 +1,30 @@
 use crate::{database::Database, basics::Value, syntax::{ast::{Node, Type}, context::Ctx, runner::Runner}, lock::UnsafeRwLock};
 
 mod builtins;
+mod closure;
 
-#[derive(Debug)]
+pub use closure::Closure;
+
+#[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
     pub params: Vec<(String, Type)>,
     pub return_type: Type,
     pub body: FunctionBody,
+    /// Whether callers need the execute privilege, always true for custom functions
+    pub restricted: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FunctionBody {
     BuiltIn(BuiltIn),
+    /// Function registered by the host application, its arguments and return value are type
+    /// checked
+    Native(BuiltIn),
     Custom(Node),
 }
 
-type BuiltIn = fn(UnsafeRwLock<Database>, &[Value], ctx: &Ctx, runner: &Runner) -> Result<Option<Value>, String>;
+pub type BuiltIn = fn(UnsafeRwLock<Database>, &[Value], ctx: &Ctx, runner: &Runner) -> Result<Option<Value>, String>;
 
 impl Function {
     pub fn new(name: String, params: Vec<(String, Type)>, return_type: Type, body: FunctionBody) -> Self {
@@ -24,6 +32,7 @@ impl Function {
             name,
             params,
             return_type,
+            restricted: matches!(body, FunctionBody::Custom(_)),
             body,
         }
     }
@@ -37,6 +46,23 @@ impl Function {
         )
     }
 
+    /// Create a native function which can be registered with 'Database::register_function' or
+    /// 'Cluster::register_function'
+    pub fn native(name: &str, params: Vec<(&str, Type)>, return_type: Type, body: BuiltIn) -> Self {
+        Self::new(
+            name.to_string(),
+            params.into_iter().map(|(name, t)| (name.to_string(), t)).collect(),
+            return_type,
+            FunctionBody::Native(body)
+        )
+    }
+
+    /// Require the execute privilege to call this function
+    pub fn restricted(mut self) -> Self {
+        self.restricted = true;
+        self
+    }
+
     pub fn custom(name: &str, params: &[(String, Type)], return_type: &Type, body: &Node) -> Self {
         Self::new(
             name.to_string(),
@@ -46,11 +72,4 @@ impl Function {
         )
     }
 
-    pub fn call(&self, db: UnsafeRwLock<Database>, args: &[Value], ctx: &Ctx, runner: &Runner) -> Result<Option<Value>, String> {
-        match &self.body {
-            FunctionBody::BuiltIn(f) => f(db, args, ctx, runner),
-            // TODO: Implement custom function call
-            FunctionBody::Custom(_) => unimplemented!("Cannot call custom function directly")
-        }
-    }
 }
@@ -0,0 +1,246 @@
+use std::collections::{HashMap, BTreeMap};
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 document length normalization
+const B: f64 = 0.75;
+
+/// Inverted index over the words of a text column
+#[derive(Debug, Default)]
+pub struct FullTextIndex {
+    /// term -> row index -> positions of the term in the row text
+    postings: HashMap<String, BTreeMap<usize, Vec<usize>>>,
+    /// row index -> number of terms in the row text
+    lengths: BTreeMap<usize, usize>,
+    /// row index -> distinct terms of the row text, to remove a row without scanning every term
+    terms: BTreeMap<usize, Vec<String>>,
+    total_length: usize,
+}
+
+/// Part of a query which has to match for a row to match its group
+#[derive(Debug, PartialEq)]
+enum Clause {
+    Term(String),
+    /// Terms which have to appear next to each other, in order
+    Phrase(Vec<String>),
+}
+
+/// Splits text into lowercase alphanumeric terms
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+impl FullTextIndex {
+    /// Indexes the text of row at 'row', any previous text of the row is removed first
+    pub fn insert(&mut self, row: usize, text: &str) {
+        self.remove(row);
+
+        let terms = tokenize(text);
+        for (position, term) in terms.iter().enumerate() {
+            self.postings.entry(term.clone()).or_default()
+                .entry(row).or_default()
+                .push(position);
+        }
+
+        self.total_length += terms.len();
+        self.lengths.insert(row, terms.len());
+
+        let mut distinct = terms;
+        distinct.sort_unstable();
+        distinct.dedup();
+        self.terms.insert(row, distinct);
+    }
+
+    /// Removes row at 'row' from the index
+    pub fn remove(&mut self, row: usize) {
+        let length = match self.lengths.remove(&row) {
+            Some(length) => length,
+            None => return
+        };
+
+        self.total_length -= length;
+        for term in self.terms.remove(&row).unwrap_or_default() {
+            if let Some(rows) = self.postings.get_mut(&term) {
+                rows.remove(&row);
+                if rows.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.postings.clear();
+        self.lengths.clear();
+        self.terms.clear();
+        self.total_length = 0;
+    }
+
+    /// Returns the relevance score of every row matching 'query'
+    ///
+    /// Whitespace separated terms must all match, 'OR' separates alternative groups of terms and
+    /// double quoted phrases must match as consecutive terms. Rows are scored with BM25, a row
+    /// matching several groups gets the sum of their scores
+    pub fn search(&self, query: &str) -> Result<HashMap<usize, f64>, String> {
+        let mut scores = HashMap::new();
+
+        for group in parse_query(query)? {
+            if group.is_empty() { continue }
+
+            let matches = group.iter().map(|clause| self.matching_rows(clause)).collect::<Vec<_>>();
+            let (first, rest) = matches.split_first().unwrap();
+
+            for &row in first.keys() {
+                if !rest.iter().all(|rows| rows.contains_key(&row)) { continue }
+
+                let score = matches.iter().map(|rows| self.score(rows.len(), rows[&row], row)).sum::<f64>();
+                *scores.entry(row).or_insert(0.0) += score;
+            }
+        }
+
+        Ok(scores)
+    }
+
+    /// Returns the rows matching 'clause' with the number of times it occurs in each row
+    fn matching_rows(&self, clause: &Clause) -> HashMap<usize, usize> {
+        match clause {
+            Clause::Term(term) => match self.postings.get(term) {
+                Some(rows) => rows.iter().map(|(&row, positions)| (row, positions.len())).collect(),
+                None => HashMap::new()
+            },
+            Clause::Phrase(terms) => {
+                let postings = match terms.iter().map(|term| self.postings.get(term)).collect::<Option<Vec<_>>>() {
+                    Some(postings) => postings,
+                    None => return HashMap::new()
+                };
+                let (first, rest) = postings.split_first().unwrap();
+
+                let mut rows = HashMap::new();
+                for (&row, positions) in first.iter() {
+                    let next_positions = match rest.iter().map(|rows| rows.get(&row)).collect::<Option<Vec<_>>>() {
+                        Some(next_positions) => next_positions,
+                        None => continue
+                    };
+
+                    let occurrences = positions.iter()
+                        .filter(|&&start| next_positions.iter().enumerate().all(|(i, positions)| {
+                            positions.binary_search(&(start + i + 1)).is_ok()
+                        }))
+                        .count();
+
+                    if occurrences > 0 {
+                        rows.insert(row, occurrences);
+                    }
+                }
+
+                rows
+            }
+        }
+    }
+
+    /// BM25 score of a clause which matches 'matching' rows and occurs 'frequency' times in 'row'
+    fn score(&self, matching: usize, frequency: usize, row: usize) -> f64 {
+        let rows = self.lengths.len() as f64;
+        let matching = matching as f64;
+        let frequency = frequency as f64;
+        let length = self.lengths.get(&row).copied().unwrap_or(0) as f64;
+        let average_length = (self.total_length as f64 / rows).max(1.0);
+
+        let idf = (1.0 + (rows - matching + 0.5) / (matching + 0.5)).ln();
+        idf * frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length / average_length))
+    }
+}
+
+impl FullTextIndex {
+    /// Serializes the postings, one term per line as 'term row:position,position row:position'
+    pub fn to_index_string(&self) -> String {
+        let mut terms = self.postings.keys().collect::<Vec<_>>();
+        terms.sort_unstable();
+
+        let mut string = String::new();
+        for term in terms {
+            string.push_str(term);
+            for (row, positions) in &self.postings[term] {
+                let positions = positions.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                string.push_str(&format!(" {}:{}", row, positions.join(",")));
+            }
+            string.push('\n');
+        }
+
+        string
+    }
+
+    /// Parses postings serialized by 'to_index_string'
+    pub fn from_index_string<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut index = FullTextIndex::default();
+        let invalid = |line: &str| format!("Invalid full-text index entry '{}'", line);
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut parts = line.split(' ');
+            let term = parts.next().ok_or_else(|| invalid(line))?;
+
+            let mut rows = BTreeMap::new();
+            for part in parts {
+                let (row, positions) = part.split_once(':').ok_or_else(|| invalid(line))?;
+                let row = row.parse::<usize>().map_err(|_| invalid(line))?;
+                let positions = positions.split(',')
+                    .map(|p| p.parse::<usize>().map_err(|_| invalid(line)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                *index.lengths.entry(row).or_insert(0) += positions.len();
+                index.total_length += positions.len();
+                index.terms.entry(row).or_default().push(term.to_string());
+                rows.insert(row, positions);
+            }
+
+            index.postings.insert(term.to_string(), rows);
+        }
+
+        Ok(index)
+    }
+}
+
+/// Parses a query into groups of clauses, groups are separated by 'OR' and every clause of a
+/// group has to match
+fn parse_query(query: &str) -> Result<Vec<Vec<Clause>>, String> {
+    if !query.matches('"').count().is_multiple_of(2) {
+        return Err(format!("Unterminated phrase in full-text query '{}'", query))
+    }
+
+    let mut groups = vec![vec![]];
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let phrase = chars.by_ref().take_while(|&c| c != '"').collect::<String>();
+
+            let terms = tokenize(&phrase);
+            match terms.len() {
+                0 => (),
+                1 => groups.last_mut().unwrap().push(Clause::Term(terms.into_iter().next().unwrap())),
+                _ => groups.last_mut().unwrap().push(Clause::Phrase(terms)),
+            }
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' { break }
+                word.push(c);
+                chars.next();
+            }
+
+            match word.as_str() {
+                "OR" => groups.push(vec![]),
+                "AND" => (),
+                _ => groups.last_mut().unwrap().extend(tokenize(&word).into_iter().map(Clause::Term)),
+            }
+        }
+    }
+
+    Ok(groups)
+}
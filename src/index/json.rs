@@ -0,0 +1,82 @@
+use std::collections::{HashMap, BTreeMap, BTreeSet};
+
+use crate::basics::{Value, value::format_json};
+
+/// Equality index over the value at a path of a json column, rows where the path is missing
+/// are indexed as null
+#[derive(Debug, Default)]
+pub struct JsonIndex {
+    /// key of the value -> rows holding it
+    entries: HashMap<String, BTreeSet<usize>>,
+    /// row index -> key of its value
+    keys: BTreeMap<usize, String>,
+}
+
+/// Key under which a value is indexed, numbers are compared as floats so every number equal to
+/// another one gets the same key (e.g. 5, 5.0 and 5.00)
+pub fn json_key(value: &Value) -> String {
+    match value {
+        Value::Numeric(n) => format!("{}", n.to_f64()),
+        value => format_json(value),
+    }
+}
+
+impl JsonIndex {
+    /// Indexes 'value' of row at 'row', replacing its previous value
+    pub fn insert(&mut self, row: usize, value: &Value) {
+        self.remove(row);
+
+        let key = json_key(value);
+        self.entries.entry(key.clone()).or_default().insert(row);
+        self.keys.insert(row, key);
+    }
+
+    pub fn remove(&mut self, row: usize) {
+        let key = match self.keys.remove(&row) {
+            Some(key) => key,
+            None => return
+        };
+
+        if let Some(rows) = self.entries.get_mut(&key) {
+            rows.remove(&row);
+            if rows.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
+    }
+
+    /// Returns the rows whose value equals 'value'
+    pub fn lookup(&self, value: &Value) -> BTreeSet<usize> {
+        self.entries.get(&json_key(value)).cloned().unwrap_or_default()
+    }
+
+    /// Serializes the index, one 'row key' entry per line
+    pub fn to_index_string(&self) -> String {
+        let mut string = String::new();
+        for (row, key) in &self.keys {
+            string.push_str(&format!("{} {}\n", row, key));
+        }
+
+        string
+    }
+
+    /// Parses entries serialized by 'to_index_string'
+    pub fn from_index_string<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut index = JsonIndex::default();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (row, key) = line.split_once(' ').ok_or(format!("Invalid json index entry '{}'", line))?;
+            let row = row.parse::<usize>().map_err(|_| format!("Invalid json index entry '{}'", line))?;
+
+            index.entries.entry(key.to_string()).or_default().insert(row);
+            index.keys.insert(row, key.to_string());
+        }
+
+        Ok(index)
+    }
+}
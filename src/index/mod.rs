@@ -0,0 +1,470 @@
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::PathBuf, sync::atomic::{AtomicU64, Ordering}, time::UNIX_EPOCH};
+
+use crate::{basics::{Table, Value, column::ColumnType}, utils::{disk, log}};
+
+mod fulltext;
+mod vector;
+mod json;
+#[cfg(test)]
+mod tests;
+
+pub use fulltext::{FullTextIndex, tokenize};
+pub use vector::{VectorIndex, Metric, to_vector};
+pub use json::{JsonIndex, json_key};
+
+/// Source of index versions, shared by all indexes so a version is never reused after an index
+/// is recreated
+static VERSION: AtomicU64 = AtomicU64::new(0);
+
+/// Minimum number of journal entries before an index is saved whole again, below the number of
+/// table rows the journal is only appended to
+const CHECKPOINT_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexKind {
+    FullText,
+    /// Approximate nearest-neighbour index over a vector column
+    Vector,
+    /// Equality index over the value at a path of a json column, an empty path indexes the
+    /// whole document
+    Json(Vec<String>),
+}
+
+#[derive(Debug)]
+enum IndexData {
+    FullText(FullTextIndex),
+    Vector(VectorIndex),
+    Json(Vec<String>, JsonIndex),
+}
+
+/// Secondary index over a single table column, it is persisted in its own file next to the
+/// table file. Changes are appended to a journal file as the rows they touched, the whole index
+/// is only written again on a checkpoint
+#[derive(Debug)]
+pub struct Index {
+    pub name: String,
+    pub column: String,
+    data: IndexData,
+    /// Changes on every modification, used to invalidate cached query results
+    version: u64,
+    /// Rows changed since the index was last persisted
+    changed: BTreeSet<usize>,
+    /// Whether the whole content was replaced since it was last saved
+    rebuilt: bool,
+    /// Number of entries in the journal since the index was last saved
+    journaled: usize,
+    /// Stamp of the table file the persisted index matches
+    stamp: Option<String>,
+}
+
+impl Index {
+    pub fn new(name: &str, column: &str, kind: IndexKind) -> Self {
+        let data = match kind {
+            IndexKind::FullText => IndexData::FullText(FullTextIndex::default()),
+            IndexKind::Vector => IndexData::Vector(VectorIndex::default()),
+            IndexKind::Json(path) => IndexData::Json(path, JsonIndex::default()),
+        };
+
+        Self {
+            name: name.to_string(),
+            column: column.to_string(),
+            data,
+            version: VERSION.fetch_add(1, Ordering::Relaxed),
+            changed: BTreeSet::new(),
+            rebuilt: true,
+            journaled: 0,
+            stamp: None,
+        }
+    }
+
+    pub fn kind(&self) -> IndexKind {
+        match &self.data {
+            IndexData::FullText(_) => IndexKind::FullText,
+            IndexData::Vector(_) => IndexKind::Vector,
+            IndexData::Json(path, _) => IndexKind::Json(path.clone()),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn fulltext(&self) -> Option<&FullTextIndex> {
+        match &self.data {
+            IndexData::FullText(index) => Some(index),
+            _ => None
+        }
+    }
+
+    pub fn vector(&self) -> Option<&VectorIndex> {
+        match &self.data {
+            IndexData::Vector(index) => Some(index),
+            _ => None
+        }
+    }
+
+    pub fn json(&self) -> Option<&JsonIndex> {
+        match &self.data {
+            IndexData::Json(_, index) => Some(index),
+            _ => None
+        }
+    }
+
+    /// Checks whether a column of type 'column_type' can be indexed by this index
+    pub fn check_column_type(&self, column_type: &ColumnType) -> Result<(), String> {
+        match (&self.data, column_type) {
+            (IndexData::FullText(_), ColumnType::Text(_)) => Ok(()),
+            (IndexData::FullText(_), _) => Err(format!("Full-text index '{}' requires a text column, '{}' is {:?}", self.name, self.column, column_type)),
+            (IndexData::Vector(_), ColumnType::Vector(_)) => Ok(()),
+            (IndexData::Vector(_), _) => Err(format!("Vector index '{}' requires a vector column, '{}' is {:?}", self.name, self.column, column_type)),
+            (IndexData::Json(_, _), ColumnType::Json(_)) => Ok(()),
+            (IndexData::Json(_, _), _) => Err(format!("Json index '{}' requires a json column, '{}' is {:?}", self.name, self.column, column_type)),
+        }
+    }
+
+    /// Indexes 'value' of row at 'row', replacing its previous value
+    pub fn insert(&mut self, row: usize, value: &Value) {
+        match &mut self.data {
+            IndexData::FullText(index) => match value {
+                Value::Text(text) => index.insert(row, text),
+                _ => index.remove(row),
+            },
+            IndexData::Vector(index) => match to_vector(value) {
+                Some(vector) => index.insert(row, vector),
+                None => index.remove(row),
+            },
+            IndexData::Json(path, index) => index.insert(row, value.get_path(path).unwrap_or(&Value::Null)),
+        }
+        self.touch(row);
+    }
+
+    /// Replaces the index content with the values of 'rows', (row index, value) pairs
+    pub fn build<'a>(&mut self, rows: impl Iterator<Item = (usize, &'a Value)>) {
+        match &mut self.data {
+            IndexData::FullText(index) => {
+                index.clear();
+                for (row, value) in rows {
+                    if let Value::Text(text) = value {
+                        index.insert(row, text)
+                    }
+                }
+            },
+            IndexData::Vector(index) => {
+                let vectors = rows.filter_map(|(row, value)| Some((row, to_vector(value)?))).collect::<BTreeMap<_, _>>();
+                index.build(vectors)
+            },
+            IndexData::Json(path, index) => {
+                index.clear();
+                for (row, value) in rows {
+                    index.insert(row, value.get_path(path).unwrap_or(&Value::Null))
+                }
+            },
+        }
+        self.touch_all();
+    }
+
+    pub fn remove(&mut self, row: usize) {
+        match &mut self.data {
+            IndexData::FullText(index) => index.remove(row),
+            IndexData::Vector(index) => index.remove(row),
+            IndexData::Json(_, index) => index.remove(row),
+        }
+        self.touch(row);
+    }
+
+    pub fn clear(&mut self) {
+        match &mut self.data {
+            IndexData::FullText(index) => index.clear(),
+            IndexData::Vector(index) => index.clear(),
+            IndexData::Json(_, index) => index.clear(),
+        }
+        self.touch_all();
+    }
+
+    fn touch(&mut self, row: usize) {
+        self.version = VERSION.fetch_add(1, Ordering::Relaxed);
+        self.changed.insert(row);
+    }
+
+    fn touch_all(&mut self) {
+        self.version = VERSION.fetch_add(1, Ordering::Relaxed);
+        self.changed.clear();
+        self.rebuilt = true;
+    }
+
+    /// Writes the whole index to 'path' and empties its journal, the first line holds the stamp
+    /// of the table file it was built from
+    pub fn save(&mut self, path: &str, stamp: &str) -> Result<(), String> {
+        let content = match &self.data {
+            IndexData::FullText(index) => index.to_index_string(),
+            IndexData::Vector(index) => index.to_index_string(),
+            IndexData::Json(_, index) => index.to_index_string(),
+        };
+
+        disk::write_file(path, &format!("table {}\n{}", stamp, content))?;
+        disk::write_file(&journal_path(path), "")?;
+
+        self.changed.clear();
+        self.rebuilt = false;
+        self.journaled = 0;
+        self.stamp = Some(stamp.to_string());
+
+        Ok(())
+    }
+
+    /// Appends the rows changed since the index was persisted to the journal of 'path', followed
+    /// by the stamp of the table file they were written to
+    pub fn append(&mut self, path: &str, stamp: &str) -> Result<(), String> {
+        let mut content = self.changed.iter()
+            .map(|row| format!("row {}\n", row))
+            .collect::<String>();
+        content.push_str(&format!("table {}\n", stamp));
+
+        disk::append_file(&journal_path(path), &content)?;
+
+        self.journaled += self.changed.len() + 1;
+        self.changed.clear();
+        self.stamp = Some(stamp.to_string());
+
+        Ok(())
+    }
+
+    /// Reads the index from 'path', returns the rows of its journal which have to be indexed
+    /// again, or None if the file is missing or doesn't match table file 'stamp'
+    pub fn load(&mut self, path: &str, stamp: &str) -> Result<Option<BTreeSet<usize>>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Ok(None)
+        };
+        let journal = fs::read_to_string(journal_path(path)).unwrap_or_default();
+
+        let mut lines = content.lines();
+        let mut saved_stamp = match lines.next().and_then(|line| line.strip_prefix("table ")) {
+            Some(stamp) => stamp,
+            None => return Ok(None)
+        };
+
+        // the journal is valid up to its last stamp, rows after it were not fully written
+        let mut rows = BTreeSet::new();
+        let mut pending = BTreeSet::new();
+        for line in journal.lines() {
+            if let Some(stamp) = line.strip_prefix("table ") {
+                rows.append(&mut pending);
+                saved_stamp = stamp;
+            } else {
+                let row = line.strip_prefix("row ").and_then(|row| row.parse::<usize>().ok())
+                    .ok_or_else(|| format!("Invalid index journal entry '{}'", line))?;
+                pending.insert(row);
+            }
+        }
+
+        if saved_stamp != stamp || !pending.is_empty() {
+            return Ok(None)
+        }
+
+        self.data = match &mut self.data {
+            IndexData::FullText(_) => IndexData::FullText(FullTextIndex::from_index_string(lines)?),
+            IndexData::Vector(_) => IndexData::Vector(VectorIndex::from_index_string(lines)?),
+            IndexData::Json(path, _) => IndexData::Json(std::mem::take(path), JsonIndex::from_index_string(lines)?),
+        };
+        self.version = VERSION.fetch_add(1, Ordering::Relaxed);
+        self.changed.clear();
+        self.rebuilt = false;
+        self.journaled = journal.lines().count();
+        self.stamp = Some(stamp.to_string());
+
+        Ok(Some(rows))
+    }
+}
+
+/// Returns the path to the journal of the index file at 'path'
+fn journal_path(path: &str) -> String {
+    format!("{}.log", path)
+}
+
+impl Table {
+    /// Returns the path to the file of index 'name', it is placed next to the table file
+    pub fn index_path(&self, name: &str) -> Result<String, String> {
+        match self.data.path() {
+            Some(path) => Ok(PathBuf::from(path).with_extension(format!("{}.idx", name)).display().to_string()),
+            None => Err(format!("Table '{}' is not loaded", self.name))
+        }
+    }
+
+    /// Returns the size and modification time of the table file, an index is only loaded if
+    /// the table file is unchanged since the index was persisted
+    fn data_stamp(&self) -> Result<String, String> {
+        let path = match self.data.path() {
+            Some(path) => path,
+            None => return Err(format!("Table '{}' is not loaded", self.name))
+        };
+
+        let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+
+        Ok(format!("{} {}", metadata.len(), modified))
+    }
+
+    /// Returns the index of kind 'kind' on column 'column'
+    pub fn get_index(&self, column: &str, kind: IndexKind) -> Option<&Index> {
+        self.indexes.values().find(|index| index.column == column && index.kind() == kind)
+    }
+
+    /// Adds 'index' to the table, it is built from the current rows and saved if the table is
+    /// loaded
+    pub fn add_index(&mut self, mut index: Index) -> Result<(), String> {
+        if self.indexes.contains_key(&index.name) {
+            return Err(format!("Index '{}' already exists on table '{}'", index.name, self.name))
+        }
+
+        let column = match self.get_column(&index.column) {
+            Some(column) => column,
+            None => return Err(format!("Column '{}' not found in table '{}'", index.column, self.name))
+        };
+        index.check_column_type(&column.data_type)?;
+
+        if self.data.path().is_some() {
+            self.build_index(&mut index)?;
+        }
+
+        self.indexes.insert(index.name.clone(), index);
+        Ok(())
+    }
+
+    /// Loads every index from its file and replays its journal, indexes with a missing or
+    /// stale file are rebuilt from the table rows
+    pub fn load_indexes(&mut self) -> Result<(), String> {
+        let stamp = self.data_stamp()?;
+        let names = self.indexes.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            let path = self.index_path(&name)?;
+            let mut index = self.indexes.remove(&name).unwrap();
+
+            match index.load(&path, &stamp)? {
+                Some(rows) if rows.iter().all(|&row| row < self.data.len()) => {
+                    if !rows.is_empty() {
+                        self.reindex_rows(&mut index, &rows)?;
+                        index.save(&path, &stamp)?;
+                    }
+                },
+                _ => {
+                    log::info(format!("rebuilding index '{}' of table '{}'", name, self.name));
+                    self.build_index(&mut index)?;
+                }
+            }
+
+            self.indexes.insert(name, index);
+        }
+
+        Ok(())
+    }
+
+    /// Indexes the current values of 'rows' again, deleted rows are removed from the index
+    fn reindex_rows(&self, index: &mut Index, rows: &BTreeSet<usize>) -> Result<(), String> {
+        let column_index = self.get_column_index(&index.column)?;
+
+        for &i in rows {
+            let row = self.data.get(i).unwrap();
+            match row.is_deleted() {
+                true => index.remove(i),
+                false => index.insert(i, row.get(column_index).unwrap_or(&Value::Null)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds every index from the table rows, used when row positions change
+    pub fn rebuild_indexes(&mut self) -> Result<(), String> {
+        let names = self.indexes.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            let mut index = self.indexes.remove(&name).unwrap();
+            self.build_index(&mut index)?;
+            self.indexes.insert(name, index);
+        }
+
+        Ok(())
+    }
+
+    fn build_index(&self, index: &mut Index) -> Result<(), String> {
+        let column_index = self.get_column_index(&index.column)?;
+
+        let rows = self.data.iter().enumerate()
+            .filter(|(_, row)| !row.is_deleted())
+            .map(|(i, row)| (i, row.get(column_index).unwrap_or(&Value::Null)));
+        index.build(rows);
+
+        index.save(&self.index_path(&index.name)?, &self.data_stamp()?)
+    }
+
+    /// Persists the changes of every index since it was last persisted. Changed rows are
+    /// appended to the journal, the whole index is saved once the journal holds more rows than
+    /// the table
+    pub fn sync_indexes(&mut self) -> Result<(), String> {
+        if self.indexes.is_empty() { return Ok(()) }
+
+        let stamp = self.data_stamp()?;
+        let checkpoint = self.data.len().max(CHECKPOINT_ENTRIES);
+        let paths = self.indexes.keys()
+            .map(|name| Ok((name.clone(), self.index_path(name)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        for (name, path) in paths {
+            let index = self.indexes.get_mut(&name).unwrap();
+            if index.rebuilt || index.journaled + index.changed.len() > checkpoint {
+                index.save(&path, &stamp)?;
+            } else if !index.changed.is_empty() || index.stamp.as_deref() != Some(stamp.as_str()) {
+                index.append(&path, &stamp)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates the indexes after rows from 'start' were appended
+    pub(crate) fn index_appended_rows(&mut self, start: usize) {
+        if self.indexes.is_empty() { return }
+
+        for index in self.indexes.values_mut() {
+            let column_index = match self.columns.iter().position(|c| c.name == index.column) {
+                Some(column_index) => column_index,
+                None => continue
+            };
+
+            for i in start..self.data.len() {
+                let row = self.data.get(i).unwrap();
+                index.insert(i, row.get(column_index).unwrap_or(&Value::Null));
+            }
+        }
+    }
+
+    /// Updates the indexes after columns 'column_indexes' of row at 'row_index' changed
+    pub(crate) fn index_updated_row(&mut self, row_index: usize, column_indexes: &[usize]) {
+        if self.indexes.is_empty() { return }
+
+        let row = self.data.get(row_index).unwrap();
+        for index in self.indexes.values_mut() {
+            let column_index = match self.columns.iter().position(|c| c.name == index.column) {
+                Some(column_index) if column_indexes.contains(&column_index) => column_index,
+                _ => continue
+            };
+
+            index.insert(row_index, row.get(column_index).unwrap_or(&Value::Null));
+        }
+    }
+
+    /// Updates the indexes after the flags of row at 'row_index' changed
+    pub(crate) fn index_flagged_row(&mut self, row_index: usize) {
+        if self.indexes.is_empty() { return }
+
+        let row = self.data.get(row_index).unwrap();
+        if !row.is_deleted() { return }
+
+        for index in self.indexes.values_mut() {
+            index.remove(row_index);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+
+use crate::utils::test::TestDatabase;
+
+use super::FullTextIndex;
+
+fn products() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table products { id: i64, unique; description: fixed(64); }");
+    db.query(r#"query products insert [{ id: 1, description: "Rust memory safety" }, { id: 2, description: "Go has a garbage collector" }, { id: 3, description: "Rust ownership" }]"#);
+    db.query("create index product_search on products(description) fulltext");
+    db
+}
+
+/// Contents of the index file and of its journal
+fn index_files(db: &TestDatabase) -> (String, String) {
+    let path = db.database.read().unwrap().get_table("products").unwrap().index_path("product_search").unwrap();
+    let journal = fs::read_to_string(format!("{}.log", path)).unwrap_or_default();
+    (fs::read_to_string(path).unwrap(), journal)
+}
+
+#[test]
+fn fulltext_remove_drops_row_terms() {
+    let mut index = FullTextIndex::default();
+    index.insert(0, "rust memory safety");
+    index.insert(1, "rust ownership");
+
+    index.remove(0);
+    assert_eq!(index.to_index_string(), "ownership 1:1\nrust 1:0\n");
+
+    // indexing a row again replaces its previous terms
+    index.insert(1, "garbage collector");
+    assert_eq!(index.to_index_string(), "collector 1:1\ngarbage 1:0\n");
+    assert!(index.search("rust").unwrap().is_empty());
+}
+
+#[test]
+fn fulltext_search_after_changes() {
+    let db = products();
+    assert_eq!(db.query(r#"query products select id where match(description, "rust")"#), "[[1], [3]]");
+
+    db.query(r#"query products update description: "Go ownership" where id == 1"#);
+    db.query("query products delete where id == 3");
+    assert_eq!(db.query(r#"query products select id where match(description, "rust")"#), "[]");
+    assert_eq!(db.query(r#"query products select id where match(description, "ownership")"#), "[[1]]");
+}
+
+#[test]
+fn changes_are_journaled() {
+    let db = products();
+    let (saved, journal) = index_files(&db);
+    assert!(journal.is_empty());
+
+    db.query(r#"query products update description: "Go ownership" where id == 1"#);
+    db.query(r#"query products insert id: 4 description: "Rust macros""#);
+
+    // the index file is only written again on a checkpoint
+    let (index, journal) = index_files(&db);
+    assert_eq!(index, saved);
+    assert_eq!(journal.lines().filter(|line| line.starts_with("row ")).collect::<Vec<_>>(), vec!["row 0", "row 3"]);
+}
+
+#[test]
+fn journal_is_replayed_on_load() {
+    let mut db = products();
+    db.query(r#"query products update description: "Go ownership" where id == 1"#);
+    db.query(r#"query products insert id: 4 description: "Rust macros""#);
+    db.query("query products delete where id == 3");
+
+    db.reload();
+    assert_eq!(db.query(r#"query products select id where match(description, "rust")"#), "[[4]]");
+    assert_eq!(db.query(r#"query products select id where match(description, "ownership")"#), "[[1]]");
+
+    // loading saves the replayed index, the journal starts empty again
+    assert!(index_files(&db).1.is_empty());
+}
+
+#[test]
+fn stale_index_is_rebuilt() {
+    let mut db = products();
+    let path = db.database.read().unwrap().get_table("products").unwrap().index_path("product_search").unwrap();
+
+    // a table changed after the index was persisted, e.g. by a crash before the journal was written
+    let (index, _) = index_files(&db);
+    db.query(r#"query products update description: "Go ownership" where id == 1"#);
+    fs::write(&path, index).unwrap();
+    fs::write(format!("{}.log", path), "").unwrap();
+
+    db.reload();
+    assert_eq!(db.query(r#"query products select id where match(description, "rust")"#), "[[3]]");
+}
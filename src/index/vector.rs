@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::basics::Value;
+
+/// Number of k-means iterations used to place the centroids
+const TRAIN_ITERATIONS: usize = 8;
+/// Number of sampled vectors per centroid used for training
+const TRAIN_SAMPLES_PER_LIST: usize = 16;
+/// Upper bound of the number of lists, so training stays cheap on large tables
+const MAX_LISTS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// Euclidean distance
+    L2,
+    /// Cosine distance, 1 minus the cosine similarity
+    Cosine,
+    /// Dot product, higher values are closer
+    Dot,
+}
+
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "l2" => Some(Metric::L2),
+            "cosine" => Some(Metric::Cosine),
+            "dot" => Some(Metric::Dot),
+            _ => None
+        }
+    }
+
+    /// Returns the metric value of two vectors of equal dimension
+    pub fn apply(&self, a: &[f32], b: &[f32]) -> f64 {
+        let dot = || a.iter().zip(b).map(|(&x, &y)| x as f64 * y as f64).sum::<f64>();
+
+        match self {
+            Metric::L2 => a.iter().zip(b).map(|(&x, &y)| (x as f64 - y as f64).powi(2)).sum::<f64>().sqrt(),
+            Metric::Cosine => {
+                let norm = |v: &[f32]| v.iter().map(|&x| x as f64 * x as f64).sum::<f64>().sqrt();
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 { return 1.0 }
+
+                1.0 - dot() / norms
+            },
+            Metric::Dot => dot(),
+        }
+    }
+
+    /// Same as 'apply', but lower values are always closer
+    fn distance(&self, a: &[f32], b: &[f32]) -> f64 {
+        match self {
+            Metric::Dot => -self.apply(a, b),
+            _ => self.apply(a, b)
+        }
+    }
+}
+
+/// Converts an array of numbers to a vector
+pub fn to_vector(value: &Value) -> Option<Vec<f32>> {
+    match value {
+        Value::Array(values) => values.iter().map(|value| match value {
+            Value::Numeric(n) => Some(n.to_f64() as f32),
+            _ => None
+        }).collect(),
+        _ => None
+    }
+}
+
+/// Approximate nearest-neighbour index (inverted file). Vectors are grouped into lists around
+/// k-means centroids, a search only visits the lists with the closest centroids
+#[derive(Debug, Default)]
+pub struct VectorIndex {
+    centroids: Vec<Vec<f32>>,
+    /// centroid -> rows assigned to it
+    lists: Vec<Vec<usize>>,
+    /// row index -> (list, vector)
+    vectors: BTreeMap<usize, (usize, Vec<f32>)>,
+    /// Number of vectors the centroids were trained on
+    trained: usize,
+}
+
+impl VectorIndex {
+    pub fn insert(&mut self, row: usize, vector: Vec<f32>) {
+        self.remove(row);
+
+        // centroids are retrained once the index grows well past the data they were placed on
+        if self.centroids.is_empty() || self.vectors.len() + 1 > (self.trained * 4).max(64) {
+            self.vectors.insert(row, (0, vector));
+            self.train();
+            return
+        }
+
+        let list = self.nearest_list(&vector);
+        self.lists[list].push(row);
+        self.vectors.insert(row, (list, vector));
+    }
+
+    pub fn remove(&mut self, row: usize) {
+        if let Some((list, _)) = self.vectors.remove(&row) {
+            self.lists[list].retain(|&r| r != row);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = VectorIndex::default();
+    }
+
+    /// Replaces the index content with 'vectors' and trains it once
+    pub fn build(&mut self, vectors: BTreeMap<usize, Vec<f32>>) {
+        self.vectors = vectors.into_iter().map(|(row, vector)| (row, (0, vector))).collect();
+        self.train();
+    }
+
+    /// Places the centroids with k-means over a sample of the vectors and reassigns every vector
+    pub fn train(&mut self) {
+        let count = ((self.vectors.len() as f64).sqrt().ceil() as usize).clamp(1, MAX_LISTS);
+        let step = (self.vectors.len() / count).max(1);
+        let sample_step = (self.vectors.len() / (count * TRAIN_SAMPLES_PER_LIST)).max(1);
+
+        // deterministic initialization with evenly spaced vectors
+        self.centroids = self.vectors.values().step_by(step).take(count).map(|(_, v)| v.clone()).collect();
+        self.trained = self.vectors.len();
+
+        for _ in 0..TRAIN_ITERATIONS {
+            let dimension = match self.centroids.first() {
+                Some(centroid) => centroid.len(),
+                None => break
+            };
+
+            let mut sums = vec![vec![0.0f64; dimension]; self.centroids.len()];
+            let mut counts = vec![0usize; self.centroids.len()];
+            for (_, vector) in self.vectors.values().step_by(sample_step) {
+                let list = self.nearest_list(vector);
+                counts[list] += 1;
+                sums[list].iter_mut().zip(vector).for_each(|(sum, &x)| *sum += x as f64);
+            }
+
+            for ((centroid, sum), count) in self.centroids.iter_mut().zip(sums).zip(counts) {
+                if count == 0 { continue }
+                *centroid = sum.into_iter().map(|x| (x / count as f64) as f32).collect();
+            }
+        }
+
+        self.lists = vec![vec![]; self.centroids.len()];
+        let rows = self.vectors.keys().copied().collect::<Vec<_>>();
+        for row in rows {
+            let list = self.nearest_list(&self.vectors[&row].1);
+            self.lists[list].push(row);
+            self.vectors.get_mut(&row).unwrap().0 = list;
+        }
+    }
+
+    fn nearest_list(&self, vector: &[f32]) -> usize {
+        self.centroids.iter()
+            .map(|centroid| Metric::L2.distance(centroid, vector))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Returns candidate rows for the 'amount' nearest vectors to 'query'. Lists are visited from
+    /// the closest centroid until at least the square root of all lists were visited and there are
+    /// 'amount' candidates
+    pub fn search(&self, query: &[f32], metric: Metric, amount: usize) -> HashSet<usize> {
+        let mut lists = self.centroids.iter()
+            .enumerate()
+            .filter(|(_, centroid)| centroid.len() == query.len())
+            .map(|(i, centroid)| (i, metric.distance(centroid, query)))
+            .collect::<Vec<_>>();
+        lists.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let probes = (self.lists.len() as f64).sqrt().ceil() as usize;
+        let mut candidates = HashSet::new();
+        for (visited, (list, _)) in lists.into_iter().enumerate() {
+            if visited >= probes && candidates.len() >= amount { break }
+            candidates.extend(self.lists[list].iter().copied());
+        }
+
+        candidates
+    }
+}
+
+impl VectorIndex {
+    /// Serializes the index, centroids as 'c x,y,z' lines and rows as 'r row list x,y,z' lines
+    pub fn to_index_string(&self) -> String {
+        let join = |vector: &[f32]| vector.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+
+        let mut string = format!("trained {}\n", self.trained);
+        for centroid in &self.centroids {
+            string.push_str(&format!("c {}\n", join(centroid)));
+        }
+        for (row, (list, vector)) in &self.vectors {
+            string.push_str(&format!("r {} {} {}\n", row, list, join(vector)));
+        }
+
+        string
+    }
+
+    /// Parses an index serialized by 'to_index_string'
+    pub fn from_index_string<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut index = VectorIndex::default();
+        let invalid = |line: &str| format!("Invalid vector index entry '{}'", line);
+        let parse_vector = |s: &str| s.split(',').filter(|x| !x.is_empty()).map(|x| x.parse::<f32>()).collect::<Result<Vec<_>, _>>();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let parts = line.split(' ').collect::<Vec<_>>();
+            match parts.as_slice() {
+                ["trained", trained] => index.trained = trained.parse().map_err(|_| invalid(line))?,
+                ["c", vector] => index.centroids.push(parse_vector(vector).map_err(|_| invalid(line))?),
+                ["r", row, list, vector] => {
+                    let row = row.parse::<usize>().map_err(|_| invalid(line))?;
+                    let list = list.parse::<usize>().map_err(|_| invalid(line))?;
+                    let vector = parse_vector(vector).map_err(|_| invalid(line))?;
+                    index.vectors.insert(row, (list, vector));
+                },
+                _ => return Err(invalid(line))
+            }
+        }
+
+        index.lists = vec![vec![]; index.centroids.len()];
+        for (&row, (list, _)) in &index.vectors {
+            index.lists.get_mut(*list).ok_or_else(|| format!("Invalid vector index list {}", list))?.push(row);
+        }
+
+        Ok(index)
+    }
+}
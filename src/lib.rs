@@ -10,3 +10,4 @@ pub mod random;
 pub mod cluster;
 pub mod auth;
 pub mod lock;
+pub mod index;
@@ -74,6 +74,11 @@ impl<T> UnsafeRwLock<T> {
         Self::new(Arc::new(RwLock::new(value)))
     }
 
+    /// Returns a clone of the wrapped Arc<RwLock<T>>, it should only be locked on other threads
+    pub fn arc(&self) -> Arc<RwLock<T>> {
+        self.inner.clone()
+    }
+
     /// Acquires a read lock and stores it
     pub fn read(&self) -> UnsafeRwLockReadGuard<T> {
         unsafe {
@@ -141,6 +141,7 @@ impl Database {
         // add new row to the table buffer, then write to disk/memory
         table.data.buf_rows.push(new_row.clone());
         table.sync_buffer()?;
+        table.sync_indexes()?;
 
         Ok(QueryResult::from(vec![new_row]))
     }
@@ -185,6 +186,7 @@ impl Database {
             }
         }
 
+        table.sync_indexes()?;
         let query_result = QueryResult::with_amount(updated_rows_count);
         Ok(query_result)
     }
@@ -214,6 +216,7 @@ impl Database {
             }
         }
 
+        table.sync_indexes()?;
         let query_result = QueryResult::with_amount(deleted_rows_count);
         Ok(query_result)
     }
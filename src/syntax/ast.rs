@@ -1,5 +1,7 @@
-use crate::{basics::{Value, Column}, auth::{RlsPolicy, RlsAction, Privilege}};
+use crate::{basics::{Value, Column, Trigger}, auth::{RlsPolicy, RlsAction, Privilege}, index::IndexKind};
 
+/// Nodes which can be reported by the type checker keep a 'token', the index of the token they
+/// start at (or of their name) in the tokens of the parsed script, see 'parser::SourceMap'
 #[derive(Debug, Clone)]
 pub enum Node {
     Block(Vec<Node>),
@@ -18,23 +20,54 @@ pub enum Query {
     Insert(InsertQuery),
     Update(UpdateQuery),
     Delete(DeleteQuery),
+    With(WithQuery),
+}
+
+#[derive(Debug, Clone)]
+pub struct WithQuery {
+    pub recursive: bool,
+    pub ctes: Vec<Cte>,
+    pub query: Box<Query>,
+}
+
+/// Common table expression, 'name [(columns)] as (select [union [all] select])'
+#[derive(Debug, Clone)]
+pub struct Cte {
+    pub name: String,
+    /// Renamed result columns, empty keeps the selected column names
+    pub columns: Vec<String>,
+    pub query: SelectQuery,
+    /// In a recursive 'with' this is the recursive term, evaluated until it returns no new rows
+    pub union: Option<CteUnion>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CteUnion {
+    pub query: SelectQuery,
+    /// 'union all' keeps duplicate rows
+    pub all: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct SelectQuery {
     pub table: String,
+    pub token: usize,
     pub joins: Vec<Join>,
     pub columns: Vec<Node>,
     pub where_clause: Option<Box<Node>>,
     pub order: Option<Box<Node>>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
-    pub exclude: Option<Vec<String>>,
+    /// Columns left out of a '*' selection and their tokens
+    pub exclude: Option<Vec<(String, usize)>>,
+    /// 'as object', rows are returned as objects keyed by column name instead of arrays
+    pub as_objects: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct Join {
     pub table: String,
+    pub token: usize,
     pub on: Node,
     pub join_type: JoinType,
 }
@@ -50,31 +83,57 @@ pub enum JoinType {
 #[derive(Debug, Clone)]
 pub struct InsertQuery {
     pub table: String,
-    pub key_values: Vec<(String, Node)>,
+    pub token: usize,
+    /// Key values of each inserted row
+    pub rows: Vec<Vec<KeyValue>>,
+    pub on_conflict: Option<OnConflict>,
+    pub returning: Option<Vec<Node>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OnConflict {
+    /// Unique columns which trigger the conflict and their tokens, empty means any unique column
+    pub columns: Vec<(String, usize)>,
+    pub action: ConflictAction,
 }
 
+#[derive(Debug, Clone)]
+pub enum ConflictAction {
+    Nothing,
+    Update(Vec<KeyValue>),
+}
+
+/// 'column: value' of an insert or update, with the token of the column name
+pub type KeyValue = (String, usize, Node);
+
 #[derive(Debug, Clone)]
 pub struct UpdateQuery {
     pub table: String,
-    pub key_values: Vec<(String, Node)>,
+    pub token: usize,
+    pub key_values: Vec<KeyValue>,
     pub where_clause: Option<Box<Node>>,
+    pub returning: Option<Vec<Node>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct DeleteQuery {
     pub table: String,
+    pub token: usize,
     pub where_clause: Option<Box<Node>>,
+    pub returning: Option<Vec<Node>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Literal {
-    Identifier(String),
+    Identifier(String, usize),
     Number(Number),
     String(String),
     Boolean(bool),
     Array(Vec<Node>),
-    // Object(Vec<(String, Node)>),
+    Object(Vec<(String, Node)>),
     Null,
+    /// '$n' placeholder, index starts at 1
+    Parameter(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -86,17 +145,42 @@ pub enum Number {
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Assignment { name: String, value: Box<Node> },
+    Assignment { name: String, value: Box<Node>, token: usize },
+    /// Assignment to 'object.member'
+    MemberAssignment { object: Box<Node>, member: String, value: Box<Node> },
     Expression(Expression),
-    Function { name: String, parameters: Vec<(String, Type)>, return_type: Type, block: Box<Node> },
-    Let { name: String, value: Box<Node> },
-    Return(Box<Node>),
-    If { condition: Box<Node>, then_block: Box<Node>, else_block: Option<Box<Node>> },
-    While { condition: Box<Node>, block: Box<Node> },
-    For { initializer: Box<Node>, condition: Box<Node>, action: Box<Node>, block: Box<Node> },
+    Function { name: String, parameters: Vec<(String, Type)>, return_type: Type, block: Box<Node>, token: usize },
+    Let { name: String, value: Box<Node>, token: usize },
+    Return { value: Box<Node>, token: usize },
+    If { condition: Box<Node>, then_block: Box<Node>, else_block: Option<Box<Node>>, token: usize },
+    While { condition: Box<Node>, block: Box<Node>, token: usize },
+    For { initializer: Box<Node>, condition: Box<Node>, action: Box<Node>, block: Box<Node>, token: usize },
+    /// 'for pattern in iterable { }' or 'for (index, pattern) in iterable { }', iterates over
+    /// the elements of an array or the entries of an object
+    ForIn { index: Option<String>, pattern: Pattern, iterable: Box<Node>, block: Box<Node>, token: usize },
     Loop { block: Box<Node> },
-    Break,
-    Continue,
+    Break { token: usize },
+    Continue { token: usize },
+    /// 'throw value', the value is a message or an error object, 'line' is its source line
+    Throw { value: Box<Node>, line: usize, token: usize },
+    /// 'try { } catch (name) { }', 'lines' are the source lines of the statements of 'block',
+    /// used as the position of the errors they raise
+    Try { block: Box<Node>, lines: Vec<usize>, name: String, catch_block: Box<Node> },
+    /// 'import "path"', brings the exports of a module into scope
+    Import { path: String, token: usize },
+    /// 'export fn ...' or 'export let ...' at the top level of a module
+    Export { declaration: Box<Node>, token: usize },
+}
+
+/// Variables bound to each element of a for-in loop
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// The element itself
+    Identifier(String),
+    /// '[a, b]', elements of an array (e.g. a selected row) by position
+    Array(Vec<String>),
+    /// '{a, b}', members of an object by name
+    Object(Vec<String>),
 }
 
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
@@ -105,10 +189,12 @@ pub enum Type {
     Int,
     UInt,
     Float,
+    Decimal,
     String,
     Boolean,
     Pointer(Box<Type>),
     Array(Box<Type>),
+    Object,
     Function(Vec<Type>, Box<Type>),
     Null,
     Any,
@@ -116,19 +202,36 @@ pub enum Type {
 
 #[derive(Debug, Clone)]
 pub enum Expression {
-    Binary { left: Box<Node>, operator: Operator, right: Box<Node> },
+    /// 'left operator right', 'token' is the token of the operator
+    Binary { left: Box<Node>, operator: Operator, right: Box<Node>, token: usize },
     Unary { operator: Operator, right: Box<Node> },
-    Call { name: String, arguments: Vec<Node> },
+    Call { name: String, arguments: Vec<Node>, token: usize },
     Index { object: Box<Node>, index: Box<Node> },
-    Member { object: Box<Node>, member: String },
+    /// 'object.member', 'token' is the token of the member name
+    Member { object: Box<Node>, member: String, token: usize },
     Literal(Literal),
     Dereference(Box<Node>),
+    Window(Window),
+    /// Anonymous function, 'fn (parameters) [-> type] { ... }', it captures the variables it
+    /// uses when evaluated
+    Lambda { parameters: Vec<(String, Type)>, return_type: Type, block: Box<Node>, token: usize },
+}
+
+/// Window function call, 'function(arguments) over (partition by ... order by ...)'
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub function: String,
+    pub arguments: Vec<Node>,
+    pub partition: Vec<Node>,
+    /// (expression, ascending)
+    pub order: Vec<(Node, bool)>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Operator {
     Add, Sub, Mul, Div, Mod, Pow,
     Eq, Ne, Lt, Le, Gt, Ge,
+    Like, ILike, Match,
     And, Or, Not,
     BitAnd, BitOr, BitXor, BitNot,
     ShiftLeft, ShiftRight,
@@ -152,6 +255,10 @@ pub enum CreateSDL {
     RlsPolicy { table: String, policy: Box<RlsPolicy> },
     Role { name: String },
     User { name: String, password: String, is_superuser: bool },
+    Index { name: String, table: String, column: String, kind: IndexKind },
+    Trigger { table: String, trigger: Box<Trigger> },
+    /// Function stored in the database, 'replace' overwrites an existing function
+    Function { name: String, parameters: Vec<(String, Type)>, return_type: Type, block: Box<Node>, replace: bool, token: usize },
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,893 @@
+use std::collections::HashMap;
+
+use crate::{database::Database, basics::{Value, value::NumericValue, column::{ColumnType, NumericType}}, function::FunctionBody};
+
+use super::{ast::{Node, Literal, Number, Statement, Expression, Operator, Type, Query, SelectQuery, InsertQuery, UpdateQuery, DeleteQuery, WithQuery, ConflictAction, SDL, CreateSDL, DropSDL, Pattern}, parser::{SourceMap, ParserError, ASTError}, module, token::{Token, TokenKind}};
+
+/// Signature of a callable function, 'typed' functions (custom and native ones) have their
+/// arguments checked
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<(String, Type)>,
+    return_type: Type,
+    typed: bool,
+}
+
+/// Columns of a table visible in a query, 'None' if they are only known at runtime (e.g. common
+/// table expressions without column names)
+type Columns = Option<Vec<(String, Type)>>;
+
+/// Static checks of a parsed script before it runs. Variable types are inferred from 'let'
+/// statements, function calls are checked against their signature and query columns against
+/// the table schemas. Values only known at runtime are typed as 'any' and never reported.
+pub struct TypeChecker<'a> {
+    database: &'a Database,
+    source: &'a SourceMap,
+    errors: ParserError,
+
+    /// Functions declared by the script, they can be called before their declaration runs
+    functions: HashMap<String, Signature>,
+    /// Tables created or dropped by the script, 'None' if dropped
+    tables: HashMap<String, Columns>,
+    /// Common table expressions of the enclosing 'with' queries
+    ctes: Vec<(String, Columns)>,
+    /// Variable scopes, innermost last
+    scopes: Vec<HashMap<String, Type>>,
+    /// Tables of the enclosing queries by name (or alias), innermost last
+    queries: Vec<Vec<(String, Columns)>>,
+    /// Name and return type of the function being checked, its body sees the variables of its
+    /// callers so unknown names are not reported
+    function: Option<(String, Type)>,
+    loops: usize,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(database: &'a Database, source: &'a SourceMap) -> Self {
+        Self {
+            database,
+            source,
+            errors: ParserError::empty(),
+            functions: HashMap::new(),
+            tables: HashMap::new(),
+            ctes: Vec::new(),
+            scopes: vec![HashMap::new()],
+            queries: Vec::new(),
+            function: None,
+            loops: 0,
+        }
+    }
+
+    /// Checks a parsed script, returns every error found
+    pub fn check(mut self, ast: &Node) -> Result<(), ParserError> {
+        self.declare_functions(ast);
+
+        match ast {
+            Node::Block(statements) => statements.iter().for_each(|statement| { self.node(statement); }),
+            node => { self.node(node); }
+        }
+
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(self.errors)
+        }
+    }
+
+    /// Collects the functions declared anywhere in the script
+    fn declare_functions(&mut self, node: &Node) {
+        match node {
+            Node::Block(nodes) => nodes.iter().for_each(|node| self.declare_functions(node)),
+            Node::Statement(statement) => match statement {
+                Statement::Function { name, parameters, return_type, block, .. } => {
+                    self.functions.insert(name.clone(), Signature { params: parameters.clone(), return_type: return_type.clone(), typed: true });
+                    self.declare_functions(block);
+                },
+                Statement::If { then_block, else_block, .. } => {
+                    self.declare_functions(then_block);
+                    if let Some(else_block) = else_block {
+                        self.declare_functions(else_block);
+                    }
+                },
+                Statement::While { block, .. } | Statement::For { block, .. } | Statement::ForIn { block, .. } |
+                Statement::Loop { block } => self.declare_functions(block),
+                Statement::Try { block, catch_block, .. } => {
+                    self.declare_functions(block);
+                    self.declare_functions(catch_block);
+                },
+                _ => {}
+            },
+            Node::SDL(SDL::Create(CreateSDL::Function { name, parameters, return_type, .. })) => {
+                self.functions.insert(name.clone(), Signature { params: parameters.clone(), return_type: return_type.clone(), typed: true });
+            },
+            _ => {}
+        }
+    }
+
+    /// The token at 'index' of the parsed script, errors of nodes built without tokens point at
+    /// its end
+    fn token(&self, index: usize) -> Token {
+        let tokens = self.source.tokens();
+        match tokens.get(index).or(tokens.last()) {
+            Some(token) => token.clone(),
+            None => Token::new(TokenKind::EOF, 0, 0, 0)
+        }
+    }
+
+    fn error(&mut self, message: String, token: Token) {
+        self.errors.add(ASTError::new(message, token));
+    }
+
+    /// Checks a node and returns the type of its value
+    fn node(&mut self, node: &Node) -> Type {
+        match node {
+            Node::Block(nodes) => {
+                self.scopes.push(HashMap::new());
+                for node in nodes {
+                    self.node(node);
+                }
+                self.scopes.pop();
+
+                match nodes.is_empty() {
+                    true => Type::Void,
+                    false => Type::Any,
+                }
+            },
+            Node::Literal(literal) => self.literal(literal),
+            Node::Statement(statement) => self.statement(statement),
+            Node::Expression(expression) => self.expression(expression),
+            Node::Query(query) => self.query(query),
+            Node::Value(value) => value_type(value),
+            Node::SDL(sdl) => self.sdl(sdl),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Type {
+        match literal {
+            Literal::Identifier(name, token) => self.identifier(name, *token),
+            Literal::Number(Number::Int(_)) => Type::Int,
+            Literal::Number(Number::UInt(_)) => Type::UInt,
+            Literal::Number(Number::Float(_)) => Type::Float,
+            Literal::String(_) => Type::String,
+            Literal::Boolean(_) => Type::Boolean,
+            Literal::Null => Type::Null,
+            Literal::Parameter(_) => Type::Any,
+            Literal::Array(values) => {
+                let mut types = values.iter().map(|value| self.node(value)).collect::<Vec<_>>();
+                types.sort();
+                types.dedup();
+
+                match types.as_slice() {
+                    [element] => Type::Array(Box::new(element.clone())),
+                    _ => Type::Array(Box::new(Type::Any)),
+                }
+            },
+            Literal::Object(entries) => {
+                for (_, value) in entries {
+                    self.node(value);
+                }
+                Type::Object
+            },
+        }
+    }
+
+    fn identifier(&mut self, name: &str, token: usize) -> Type {
+        if name == "*" {
+            return Type::Any
+        }
+
+        match self.resolve(name) {
+            Some(type_) => type_,
+            None if self.functions.contains_key(name) || self.database.functions.contains_key(name) => {
+                // declared functions can be passed by name
+                match self.functions.get(name) {
+                    Some(signature) => Type::Function(
+                        signature.params.iter().map(|(_, type_)| type_.clone()).collect(),
+                        Box::new(signature.return_type.clone())
+                    ),
+                    None => Type::Any,
+                }
+            },
+            None => {
+                if self.function.is_none() {
+                    self.error(format!("Variable '{}' not found", name), self.token(token));
+                }
+                Type::Any
+            }
+        }
+    }
+
+    /// Type of the column or variable 'name', columns of the current row take precedence
+    fn resolve(&self, name: &str) -> Option<Type> {
+        for tables in self.queries.iter().rev() {
+            for (_, columns) in tables {
+                match columns {
+                    Some(columns) => if let Some((_, type_)) = columns.iter().find(|(column, _)| column == name) {
+                        return Some(type_.clone())
+                    },
+                    None => return Some(Type::Any),
+                }
+            }
+        }
+
+        self.variable(name).cloned()
+    }
+
+    fn variable(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Type {
+        match statement {
+            Statement::Let { name, value, token } => {
+                let token = self.token(*token);
+                self.declaration(name, token);
+                let type_ = match self.node(value) {
+                    // the variable can hold any value later on
+                    Type::Null => Type::Any,
+                    type_ => type_,
+                };
+                self.scopes.last_mut().unwrap().insert(name.clone(), type_);
+            },
+            Statement::Assignment { name, value, token } => {
+                let token = self.token(*token);
+                let type_ = self.node(value);
+                self.assign(name, type_, token);
+            },
+            Statement::MemberAssignment { object, value, .. } => {
+                self.node(object);
+                self.node(value);
+            },
+            Statement::Expression(expression) => { self.expression(expression); },
+            Statement::Function { name, parameters, return_type, block, token } => {
+                let token = self.token(*token);
+                self.declaration(name, token.clone());
+                let scopes = std::mem::replace(&mut self.scopes, vec![parameters.iter().cloned().collect()]);
+                self.function_body(name, return_type, block, token);
+                self.scopes = scopes;
+            },
+            Statement::Return { value, token } => {
+                let token = self.token(*token);
+                let type_ = self.node(value);
+                self.check_return(type_, token);
+            },
+            Statement::If { condition, then_block, else_block, token } => {
+                let token = self.token(*token);
+                self.condition("If", condition, token);
+                self.node(then_block);
+                if let Some(else_block) = else_block {
+                    self.node(else_block);
+                }
+            },
+            Statement::While { condition, block, token } => {
+                let token = self.token(*token);
+                self.condition("While", condition, token);
+                self.loop_block(block);
+            },
+            Statement::For { initializer, condition, action, block, token } => {
+                let token = self.token(*token);
+                self.scopes.push(HashMap::new());
+                self.node(initializer);
+                self.condition("For loop", condition, token);
+                self.node(action);
+                self.loop_block(block);
+                self.scopes.pop();
+            },
+            Statement::ForIn { index, pattern, iterable, block, token } => {
+                let token = self.token(*token);
+                let (key, element) = match self.node(iterable) {
+                    Type::Array(element) => (Type::Int, *element),
+                    Type::Object => (Type::String, Type::Any),
+                    Type::Any | Type::Null => (Type::Any, Type::Any),
+                    type_ => {
+                        self.error(format!("For loop cannot iterate over a value of type '{:?}'", type_), token);
+                        (Type::Any, Type::Any)
+                    }
+                };
+
+                let mut scope = HashMap::new();
+                if let Some(index) = index {
+                    scope.insert(index.clone(), key);
+                }
+                match pattern {
+                    Pattern::Identifier(name) => { scope.insert(name.clone(), element); },
+                    Pattern::Array(names) => {
+                        let element = match element {
+                            Type::Array(element) => *element,
+                            _ => Type::Any,
+                        };
+                        scope.extend(names.iter().map(|name| (name.clone(), element.clone())));
+                    },
+                    Pattern::Object(names) => scope.extend(names.iter().map(|name| (name.clone(), Type::Any))),
+                }
+
+                self.scopes.push(scope);
+                self.loop_block(block);
+                self.scopes.pop();
+            },
+            Statement::Loop { block } => self.loop_block(block),
+            Statement::Import { path, token } => {
+                let token = self.token(*token);
+                self.import(path, token);
+            },
+            Statement::Export { token, .. } => {
+                let token = self.token(*token);
+                self.error("Exports are only allowed at the top level of a module".to_string(), token);
+            },
+            Statement::Throw { value, token, .. } => {
+                let token = self.token(*token);
+                let type_ = self.node(value);
+                if !matches!(type_, Type::String | Type::Object | Type::Any) {
+                    self.error(format!("Cannot throw a value of type '{:?}', expected a message or an object", type_), token);
+                }
+            },
+            Statement::Try { block, name, catch_block, .. } => {
+                self.node(block);
+                self.scopes.push(HashMap::from([(name.clone(), Type::Object)]));
+                self.node(catch_block);
+                self.scopes.pop();
+            },
+            Statement::Break { token } | Statement::Continue { token } => {
+                // a function called inside a loop can break out of it
+                if self.loops == 0 && self.function.is_none() {
+                    let name = match statement {
+                        Statement::Break { .. } => "Break",
+                        _ => "Continue",
+                    };
+                    self.error(format!("{} outside of loop", name), self.token(*token));
+                }
+            },
+        }
+
+        Type::Void
+    }
+
+    /// Declares the exports of a module, its statements are checked when it runs
+    fn import(&mut self, path: &str, token: Token) {
+        let statements = match module::load(&module::resolve(path, None)) {
+            Ok(statements) => statements,
+            Err(error) => return self.error(error, token),
+        };
+
+        for statement in statements.iter().filter_map(|(node, _)| module::exported(node)) {
+            match statement {
+                Statement::Function { name, parameters, return_type, .. } => {
+                    self.functions.insert(name.clone(), Signature { params: parameters.clone(), return_type: return_type.clone(), typed: true });
+                },
+                Statement::Let { name, .. } => { self.scopes.last_mut().unwrap().insert(name.clone(), Type::Any); },
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks the body of a function with its parameters already in scope
+    fn function_body(&mut self, name: &str, return_type: &Type, block: &Node, token: Token) {
+        let queries = std::mem::take(&mut self.queries);
+        let function = self.function.replace((name.to_string(), return_type.clone()));
+        let loops = std::mem::replace(&mut self.loops, 0);
+
+        match block {
+            Node::Block(nodes) => for (i, node) in nodes.iter().enumerate() {
+                let type_ = self.node(node);
+                // the last expression of the body is its return value
+                if i == nodes.len() - 1 && matches!(node, Node::Expression(_) | Node::Literal(_)) {
+                    self.check_return(type_, token.clone());
+                }
+            },
+            block => { self.node(block); }
+        }
+
+        self.queries = queries;
+        self.function = function;
+        self.loops = loops;
+    }
+
+    fn check_return(&mut self, type_: Type, token: Token) {
+        let (name, return_type) = match &self.function {
+            // functions without a return type can return anything
+            Some((_, Type::Void)) | None => return,
+            Some((name, return_type)) => (name.clone(), return_type.clone()),
+        };
+
+        // null is returned by lookups which found nothing
+        if type_ != Type::Null && !accepts(&return_type, &type_) {
+            self.error(format!("Function '{}' must return a value of type '{:?}' but returns '{:?}'", name, return_type, type_), token);
+        }
+    }
+
+    fn condition(&mut self, statement: &str, condition: &Node, token: Token) {
+        let type_ = self.node(condition);
+        if !matches!(type_, Type::Boolean | Type::Any) {
+            self.error(format!("{} condition must be a boolean, got '{:?}'", statement, type_), token);
+        }
+    }
+
+    fn loop_block(&mut self, block: &Node) {
+        self.loops += 1;
+        self.node(block);
+        self.loops -= 1;
+    }
+
+    /// Functions and variables of the script cannot take the name of a function of the database
+    fn declaration(&mut self, name: &str, token: Token) {
+        if self.database.functions.contains_key(name) {
+            self.error(format!("Cannot declare '{}', the database has a function with the same name", name), token);
+        }
+    }
+
+    /// Assigns a value of type 'type_' to a variable, it becomes 'any' if it held another type
+    fn assign(&mut self, name: &str, type_: Type, token: Token) {
+        if self.resolve(name).is_some() && self.variable(name).is_none() {
+            // a column of the current row
+            return
+        }
+
+        match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            Some(variable) => if *variable != type_ {
+                *variable = Type::Any
+            },
+            None => if self.function.is_none() {
+                self.error(format!("Variable '{}' not found", name), token);
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Type {
+        match expression {
+            Expression::Binary { left, operator, right, token } => {
+                let (left, right) = (self.node(left), self.node(right));
+                match operator {
+                    Operator::Add | Operator::Sub | Operator::Mul | Operator::Div | Operator::Mod | Operator::Pow |
+                    Operator::And | Operator::Or => self.operation(operator, left, right, *token),
+                    Operator::Eq | Operator::Ne | Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge |
+                    Operator::Like | Operator::ILike | Operator::Match => Type::Boolean,
+                    _ => Type::Any,
+                }
+            },
+            Expression::Unary { operator, right } => {
+                let type_ = self.node(right);
+                match operator {
+                    Operator::Not => Type::Boolean,
+                    Operator::Sub => type_,
+                    _ => Type::Any,
+                }
+            },
+            Expression::Call { name, arguments, token } => self.call(name, arguments, *token),
+            Expression::Index { object, index } => {
+                self.node(object);
+                self.node(index);
+                Type::Any
+            },
+            Expression::Member { object, member, token } => self.member(object, member, *token),
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Dereference(node) => {
+                self.node(node);
+                Type::Any
+            },
+            Expression::Window(window) => {
+                window.arguments.iter().chain(&window.partition).chain(window.order.iter().map(|(node, _)| node)).for_each(|node| {
+                    self.node(node);
+                });
+                Type::Any
+            },
+            Expression::Lambda { parameters, return_type, block, token } => {
+                // anonymous functions see the variables of the scope they are created in
+                let token = self.token(*token);
+                self.scopes.push(parameters.iter().cloned().collect());
+                self.function_body("anonymous", return_type, block, token);
+                self.scopes.pop();
+
+                Type::Function(parameters.iter().map(|(_, type_)| type_.clone()).collect(), Box::new(return_type.clone()))
+            },
+        }
+    }
+
+    /// Type of arithmetic or a logical operation, operands of known types the runner rejects are
+    /// reported
+    fn operation(&mut self, operator: &Operator, left: Type, right: Type, token: usize) -> Type {
+        let numeric = |type_: &Type| matches!(type_, Type::Int | Type::UInt | Type::Float | Type::Decimal);
+        let known = |type_: &Type| !matches!(type_, Type::Any | Type::Null | Type::Void | Type::Pointer(_));
+
+        let (valid, type_) = match (operator, &left, &right) {
+            (Operator::And | Operator::Or, _, _) => (left == Type::Boolean && right == Type::Boolean, Type::Boolean),
+            (_, left, right) if numeric(left) && numeric(right) => (true, if left == right { left.clone() } else { Type::Any }),
+            (Operator::Add, Type::String, Type::String) => (true, Type::String),
+            (Operator::Add, Type::Array(_), Type::Array(_)) => (true, Type::Any),
+            (Operator::Add | Operator::Sub, Type::Boolean, Type::Boolean) => (true, Type::Boolean),
+            _ => (false, Type::Any),
+        };
+
+        if !valid && known(&left) && known(&right) {
+            let operation = match operator {
+                Operator::Add => "add",
+                Operator::Sub => "subtract",
+                Operator::Mul => "multiply",
+                Operator::Div => "divide",
+                Operator::Mod => "take the modulo of",
+                Operator::Pow => "take the power of",
+                Operator::And => "apply '&&' to",
+                _ => "apply '||' to",
+            };
+            self.error(format!("Cannot {} '{:?}' and '{:?}'", operation, left, right), self.token(token));
+        }
+
+        type_
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Node], token: usize) -> Type {
+        let token = self.token(token);
+        // full-text functions take the column itself, they are checked when they run
+        if name == "match" || name == "score" {
+            return Type::Any
+        }
+
+        let types = arguments.iter().map(|argument| self.node(argument)).collect::<Vec<_>>();
+
+        // functions of the database take precedence over the functions of the script
+        let signature = match self.database.functions.get(name) {
+            Some(function) => Signature {
+                params: function.params.clone(),
+                return_type: function.return_type.clone(),
+                typed: !matches!(function.body, FunctionBody::BuiltIn(_)),
+            },
+            None => match self.functions.get(name) {
+                Some(signature) => signature.clone(),
+                None => match self.variable(name) {
+                    // variable holding a function, its parameter names are unknown
+                    Some(Type::Function(params, return_type)) => Signature {
+                        params: params.iter().enumerate().map(|(i, type_)| (format!("#{}", i + 1), type_.clone())).collect(),
+                        return_type: *return_type.clone(),
+                        typed: true,
+                    },
+                    Some(_) if self.function.is_some() => return Type::Any,
+                    Some(type_) => {
+                        let type_ = type_.clone();
+                        match type_ {
+                            Type::Any => {},
+                            type_ => self.error(format!("Variable '{}' of type '{:?}' is not a function", name, type_), token),
+                        }
+                        return Type::Any
+                    },
+                    None => {
+                        if self.function.is_none() {
+                            self.error(format!("Function '{}' not found", name), token);
+                        }
+                        return Type::Any
+                    }
+                }
+            }
+        };
+
+        if signature.params.len() != types.len() {
+            self.error(format!("Function '{}' expects {} arguments, got {}", name, signature.params.len(), types.len()), token);
+        } else if signature.typed {
+            for ((param, expected), type_) in signature.params.iter().zip(&types) {
+                if !accepts(expected, type_) {
+                    self.error(format!("Function '{}' expects argument '{}' to be of type '{:?}' but got '{:?}'", name, param, expected, type_), token.clone());
+                }
+            }
+        }
+
+        match signature.return_type {
+            // built-in functions declare no precise return type
+            Type::Void => Type::Any,
+            _ if !signature.typed => Type::Any,
+            return_type => return_type,
+        }
+    }
+
+    fn member(&mut self, object: &Node, member: &str, token: usize) -> Type {
+        let (root, root_token) = match object {
+            Node::Literal(Literal::Identifier(root, root_token)) => (root, *root_token),
+            object => {
+                self.node(object);
+                return Type::Any
+            }
+        };
+
+        // 'table.column' of a table in the query, unless a variable has the same name
+        let table = self.queries.iter().rev()
+            .find_map(|tables| tables.iter().find(|(name, _)| name == root))
+            .filter(|_| self.variable(root).is_none())
+            .cloned();
+
+        match table {
+            Some((table, Some(columns))) => {
+                if member == "*" {
+                    return Type::Any
+                }
+                match columns.iter().find(|(column, _)| column == member) {
+                    Some((_, type_)) => type_.clone(),
+                    None => {
+                        self.error(format!("Column '{}' not found in table '{}'", member, table), self.token(token));
+                        Type::Any
+                    }
+                }
+            },
+            Some((_, None)) => Type::Any,
+            // members of objects and json columns
+            None => {
+                self.identifier(root, root_token);
+                Type::Any
+            }
+        }
+    }
+
+    /// Columns of table 'name', 'None' (as the outer option) if it doesn't exist
+    fn table(&self, name: &str) -> Option<Columns> {
+        if let Some((_, columns)) = self.ctes.iter().rev().find(|(cte, _)| cte == name) {
+            return Some(columns.clone())
+        }
+        if let Some(columns) = self.tables.get(name) {
+            return columns.clone().map(Some)
+        }
+
+        let table = self.database.get_table(name)?;
+        Some(Some(table.columns.iter().map(|column| (column.name.clone(), column_type(&column.data_type, column.not_null))).collect()))
+    }
+
+    /// Looks up the table of a query, unknown tables are reported outside of functions, since a
+    /// function can run after the table is created
+    fn query_table(&mut self, name: &str, token: usize) -> Columns {
+        match self.table(name) {
+            Some(columns) => columns,
+            None => {
+                if self.function.is_none() {
+                    self.error(format!("Table '{}' does not exist in database '{}'", name, self.database.name), self.token(token));
+                }
+                None
+            }
+        }
+    }
+
+    /// Checks that 'column' is a column of a table with 'columns'
+    fn column(&mut self, table: &str, columns: &Columns, column: &str, token: usize) {
+        if let Some(columns) = columns {
+            if !columns.iter().any(|(name, _)| name == column) {
+                self.error(format!("Column '{}' not found in table '{}'", column, table), self.token(token));
+            }
+        }
+    }
+
+    fn query(&mut self, query: &Query) -> Type {
+        match query {
+            Query::Select(select) => self.select(select),
+            Query::Insert(insert) => self.insert(insert),
+            Query::Update(update) => self.update(update),
+            Query::Delete(delete) => self.delete(delete),
+            Query::With(with) => self.with(with),
+        }
+
+        Type::Any
+    }
+
+    fn select(&mut self, select: &SelectQuery) {
+        let columns = self.query_table(&select.table, select.token);
+        let mut tables = vec![(select.table.clone(), columns.clone())];
+        for join in &select.joins {
+            let columns = self.query_table(&join.table, join.token);
+            tables.push((join.table.clone(), columns));
+        }
+
+        self.queries.push(tables);
+        for join in &select.joins {
+            self.node(&join.on);
+        }
+        for column in &select.columns {
+            match column {
+                // plain names are columns of the base table
+                Node::Literal(Literal::Identifier(name, token)) if name != "*" => self.column(&select.table, &columns, name, *token),
+                node => { self.node(node); }
+            }
+        }
+        if let Some(where_clause) = &select.where_clause {
+            self.node(where_clause);
+        }
+        if let Some(order) = &select.order {
+            match order.as_ref() {
+                Node::Expression(Expression::Unary { right, .. }) if matches!(right.as_ref(), Node::Literal(Literal::Identifier(..))) => {
+                    if let Node::Literal(Literal::Identifier(name, token)) = right.as_ref() {
+                        self.column(&select.table, &columns, name, *token);
+                    }
+                },
+                order => { self.node(order); }
+            }
+        }
+        for (name, token) in select.exclude.iter().flatten() {
+            self.column(&select.table, &columns, name, *token);
+        }
+        self.queries.pop();
+    }
+
+    fn insert(&mut self, insert: &InsertQuery) {
+        let columns = self.query_table(&insert.table, insert.token);
+
+        for row in &insert.rows {
+            for (name, token, value) in row {
+                self.column(&insert.table, &columns, name, *token);
+                self.node(value);
+            }
+        }
+
+        if let Some(on_conflict) = &insert.on_conflict {
+            for (name, token) in &on_conflict.columns {
+                self.column(&insert.table, &columns, name, *token);
+            }
+
+            if let ConflictAction::Update(key_values) = &on_conflict.action {
+                self.queries.push(vec![(insert.table.clone(), columns.clone()), ("excluded".to_string(), columns.clone())]);
+                for (name, token, value) in key_values {
+                    self.column(&insert.table, &columns, name, *token);
+                    self.node(value);
+                }
+                self.queries.pop();
+            }
+        }
+
+        self.returning(&insert.table, columns, &insert.returning);
+    }
+
+    fn update(&mut self, update: &UpdateQuery) {
+        let columns = self.query_table(&update.table, update.token);
+
+        self.queries.push(vec![(update.table.clone(), columns.clone())]);
+        for (name, token, value) in &update.key_values {
+            self.column(&update.table, &columns, name, *token);
+            self.node(value);
+        }
+        if let Some(where_clause) = &update.where_clause {
+            self.node(where_clause);
+        }
+        self.queries.pop();
+
+        self.returning(&update.table, columns, &update.returning);
+    }
+
+    fn delete(&mut self, delete: &DeleteQuery) {
+        let columns = self.query_table(&delete.table, delete.token);
+
+        self.queries.push(vec![(delete.table.clone(), columns.clone())]);
+        if let Some(where_clause) = &delete.where_clause {
+            self.node(where_clause);
+        }
+        self.queries.pop();
+
+        self.returning(&delete.table, columns, &delete.returning);
+    }
+
+    fn returning(&mut self, table: &str, columns: Columns, returning: &Option<Vec<Node>>) {
+        let returning = match returning {
+            Some(returning) => returning,
+            None => return
+        };
+
+        self.queries.push(vec![(table.to_string(), columns.clone())]);
+        for node in returning {
+            match node {
+                Node::Literal(Literal::Identifier(name, token)) if name != "*" => self.column(table, &columns, name, *token),
+                node => { self.node(node); }
+            }
+        }
+        self.queries.pop();
+    }
+
+    fn with(&mut self, with: &WithQuery) {
+        let ctes = self.ctes.len();
+
+        for cte in &with.ctes {
+            let columns = match cte.columns.is_empty() {
+                true => self.selected_columns(&cte.query),
+                false => Some(cte.columns.iter().map(|name| (name.clone(), Type::Any)).collect()),
+            };
+
+            self.select(&cte.query);
+            // the recursive term selects from the expression itself
+            self.ctes.push((cte.name.clone(), columns));
+            if let Some(union) = &cte.union {
+                self.select(&union.query);
+            }
+        }
+
+        self.query(&with.query);
+        self.ctes.truncate(ctes);
+    }
+
+    /// Names of the columns a select query returns, 'None' if it selects computed values
+    fn selected_columns(&self, select: &SelectQuery) -> Columns {
+        let tables = std::iter::once(&select.table).chain(select.joins.iter().map(|join| &join.table));
+        let mut columns = vec![];
+
+        for node in &select.columns {
+            let names = match node {
+                Node::Literal(Literal::Identifier(name, _)) if name == "*" => tables.clone()
+                    .map(|table| self.table(table).flatten())
+                    .collect::<Option<Vec<_>>>()?
+                    .concat(),
+                Node::Literal(Literal::Identifier(name, _)) => vec![(name.clone(), Type::Any)],
+                Node::Expression(Expression::Member { object, member, .. }) => match object.as_ref() {
+                    Node::Literal(Literal::Identifier(table, _)) if member == "*" => self.table(table).flatten()?,
+                    Node::Literal(Literal::Identifier(..)) => vec![(member.clone(), Type::Any)],
+                    _ => return None
+                },
+                _ => return None
+            };
+            columns.extend(names.into_iter().map(|(name, _)| (name, Type::Any)));
+        }
+
+        if let Some(exclude) = &select.exclude {
+            columns.retain(|(name, _)| !exclude.iter().any(|(excluded, _)| excluded == name));
+        }
+        Some(columns)
+    }
+
+    /// Tracks the tables created and dropped by the script and checks the bodies of created
+    /// functions, bodies of policies and triggers are checked when they run
+    fn sdl(&mut self, sdl: &SDL) -> Type {
+        match sdl {
+            SDL::Create(CreateSDL::Table { name, columns }) => {
+                let columns = columns.iter().map(|column| (column.name.clone(), column_type(&column.data_type, column.not_null))).collect();
+                self.tables.insert(name.clone(), Some(columns));
+            },
+            SDL::Create(CreateSDL::Function { name, parameters, return_type, block, token, .. }) => {
+                let token = self.token(*token);
+                let scopes = std::mem::replace(&mut self.scopes, vec![parameters.iter().cloned().collect()]);
+                self.function_body(name, return_type, block, token);
+                self.scopes = scopes;
+            },
+            SDL::Drop(DropSDL::Table { name }) => {
+                self.tables.insert(name.clone(), None);
+            },
+            SDL::Drop(DropSDL::Column { name, table }) => {
+                if let Some(Some(mut columns)) = self.table(table) {
+                    columns.retain(|(column, _)| column != name);
+                    self.tables.insert(table.clone(), Some(columns));
+                }
+            },
+            _ => {}
+        }
+
+        Type::Void
+    }
+}
+
+/// Whether a value of type 'type_' can be passed where 'expected' is declared, like
+/// 'Runner::check_type' but values of unknown type are accepted
+fn accepts(expected: &Type, type_: &Type) -> bool {
+    match (expected, type_) {
+        (Type::Any, _) | (_, Type::Any) => true,
+        (Type::Array(expected), Type::Array(type_)) => accepts(expected, type_),
+        (Type::Function(expected, _), Type::Function(params, _)) => expected.len() == params.len() &&
+            expected.iter().zip(params).all(|(expected, param)| accepts(param, expected)),
+        (Type::Void, Type::Null) => true,
+        (expected, type_) => expected == type_,
+    }
+}
+
+/// Script type of the values of a column, nullable columns can hold any type
+fn column_type(data_type: &ColumnType, not_null: bool) -> Type {
+    if !not_null {
+        return Type::Any
+    }
+
+    match data_type {
+        ColumnType::Numeric(NumericType::IntI64) => Type::Int,
+        ColumnType::Numeric(NumericType::IntU64) => Type::UInt,
+        ColumnType::Numeric(NumericType::Float64) => Type::Float,
+        ColumnType::Numeric(NumericType::Decimal(..)) => Type::Decimal,
+        ColumnType::Text(_) => Type::String,
+        ColumnType::Boolean => Type::Boolean,
+        _ => Type::Any,
+    }
+}
+
+fn value_type(value: &Value) -> Type {
+    match value {
+        Value::Numeric(NumericValue::IntI64(_)) => Type::Int,
+        Value::Numeric(NumericValue::IntU64(_)) => Type::UInt,
+        Value::Numeric(NumericValue::Float64(_)) => Type::Float,
+        Value::Numeric(NumericValue::Decimal(_)) => Type::Decimal,
+        Value::Text(_) => Type::String,
+        Value::Boolean(_) => Type::Boolean,
+        Value::Object(_) => Type::Object,
+        Value::Null => Type::Null,
+        _ => Type::Any,
+    }
+}
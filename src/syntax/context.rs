@@ -1,98 +1,137 @@
-use std::{rc::Rc, cell::RefCell, collections::HashMap, borrow::Cow, sync::{Arc, RwLock}, ptr};
+use std::{rc::Rc, cell::RefCell, collections::{HashMap, BTreeSet}, sync::{Arc, RwLock, Mutex}, ptr};
 
-use crate::{basics::{Row, Value, Table}, auth::User, database::RunOptions, cluster::Cluster};
+use crate::{basics::{Row, Value, Table}, auth::User, database::{RunOptions, RunLimits, CancelHandle}, cluster::Cluster};
 
-pub type Ctx<'a> = Rc<RunnerContext<'a>>;
-type Scope<'a> = Rc<RefCell<HashMap<String, ScopeValue<'a>>>>;
-type ScopeValue<'a> = Rc<RefCell<Cow<'a, Value>>>;
+pub type Ctx = Rc<RunnerContext>;
+type Scope = Rc<RefCell<HashMap<String, ScopeValue>>>;
+/// Cell holding the value of a variable, function values created in a scope share the cells of
+/// the variables they use with it
+pub type ScopeValue = Arc<Mutex<Value>>;
+/// key: (table_name, column_name) -> (table_index, column_index)
+type JoinedColumnMap = HashMap<(String, String), (usize, usize)>;
 
 /// Struct to hold the context of a runner, it is passed around to all runner functions
-pub struct RunnerContext<'a> {
+pub struct RunnerContext {
     /// Variables in the current scope
-    variables: Scope<'a>,
+    variables: Scope,
     /// The current row being processed by the query runner
     ///
     /// Pointer will be valid as long as the context isn't stored anywhere. Since the row is
     /// borrowed from read lock on the database and context is scoped to that function call,
     /// nothing can access the row in the meantime.
     current_row: RefCell<Option<*const Row>>,
-    current_column_map: Option<HashMap<String, usize>>,
+    current_column_map: Option<Rc<HashMap<String, usize>>>,
 
     // row representation of 'joined rows'
     pub current_unsafe_row: RefCell<*const Vec<*const Row>>,
-    // key: (table_name, column_name) -> (table_index, column_index)
-    pub current_unsafe_column_map: RefCell<HashMap<(String, String), (usize, usize)>>,
+    pub current_unsafe_column_map: RefCell<Rc<JoinedColumnMap>>,
 
     options: Rc<RunOptions>, // rc for easier cloning when scoping
-    pub parent: Option<Ctx<'a>>,
+    /// Values bound to '$n' placeholders, shared by all scopes
+    parameters: Rc<Vec<Value>>,
+    /// Evaluated common table expressions declared in this scope
+    ctes: RefCell<HashMap<String, Rc<Table>>>,
+    /// Rows of the statement which fired the trigger running in this scope
+    trigger_rows: RefCell<Option<TriggerRows>>,
+    /// Whether the variables and common table expressions of the parent scopes are hidden, set
+    /// for the bodies of database functions, row level security policies and column defaults
+    boundary: bool,
+    pub parent: Option<Ctx>,
 }
 
-pub trait RunnerContextScope<'a> {
+/// Rows of a statement which fired a trigger, the trigger function accesses them as
+/// 'old.<column>' and 'new.<column>'
+pub struct TriggerRows {
+    pub columns: HashMap<String, usize>,
+    /// Row before the update or delete, None for inserts
+    pub old: Option<Row>,
+    /// Row written by the insert or update, None for deletes
+    pub new: Option<Row>,
+    /// Whether 'new' can be modified, only before triggers can change the written row
+    pub modifiable: bool,
+}
+
+pub trait RunnerContextScope {
     /// Create a new context with the current context as the parent
-    fn scoped(parent: Ctx<'a>) -> Self;
-    fn scoped_with(parent: Ctx<'a>, column_map: HashMap<String, usize>) -> Self; 
+    fn scoped(parent: Ctx) -> Self;
+    fn scoped_with(parent: Ctx, column_map: HashMap<String, usize>) -> Self; 
+    /// Create a new context with the current context as the parent, which doesn't see the
+    /// variables, placeholders and common table expressions of its parents
+    fn bounded(parent: Ctx) -> Self;
 }
-impl<'a> RunnerContextScope<'a> for RunnerContext<'a> {
-    fn scoped(parent: Ctx<'a>) -> Self {
+impl RunnerContextScope for RunnerContext {
+    fn scoped(parent: Ctx) -> Self {
         let mut ctx = RunnerContext::new(parent.options.clone());
+        ctx.parameters = parent.parameters.clone();
         ctx.parent = Some(parent.clone());
         ctx
     }
 
-    fn scoped_with(parent: Ctx<'a>, column_map: HashMap<String, usize>) -> Self {
+    fn scoped_with(parent: Ctx, column_map: HashMap<String, usize>) -> Self {
         let mut scoped = Self::scoped(parent); 
         scoped.set_column_map(column_map);
         scoped
     }
+
+    fn bounded(parent: Ctx) -> Self {
+        let mut ctx = RunnerContext::new(parent.options.clone());
+        ctx.boundary = true;
+        ctx.parent = Some(parent);
+        ctx
+    }
 }
-impl<'a> RunnerContextScope<'a> for Ctx<'a> {
-    fn scoped(parent: Ctx<'a>) -> Ctx {
+impl RunnerContextScope for Ctx {
+    fn scoped(parent: Ctx) -> Ctx {
         let mut ctx = RunnerContext::new(parent.options.clone());
+        ctx.parameters = parent.parameters.clone();
         ctx.parent = Some(parent.clone());
         Rc::new(ctx)
     }
 
-    fn scoped_with(parent: Ctx<'a>, column_map: HashMap<String, usize>) -> Self {
+    fn scoped_with(parent: Ctx, column_map: HashMap<String, usize>) -> Self {
         let mut scoped = RunnerContext::scoped(parent); 
         scoped.set_column_map(column_map);
         Rc::new(scoped)
     }
+
+    fn bounded(parent: Ctx) -> Self {
+        Rc::new(RunnerContext::bounded(parent))
+    }
 }
 
-pub trait RunnerContextVariable<'a> {
+pub trait RunnerContextVariable {
     /// Get the nearest variable 'name', or the nearest column if in a row context (row takes
     /// precedence over variables in a scope)
     ///
     /// Errors if the variable is not found
-    fn get(&self, name: &str) -> Result<ScopeValue<'a>, String>;
+    fn get(&self, name: &str) -> Result<ScopeValue, String>;
+    /// Get a copy of the value of the nearest variable or column 'name'
+    ///
+    /// Errors if the variable is not found
+    fn value(&self, name: &str) -> Result<Value, String>;
     /// Assign value to nearest variable 'name'
     ///
     /// Errors if the variable is not found
     fn assign(&self, name: &str, value: Value) -> Result<(), String>;
     /// Declare (or shadow) a new variable 'name' in the current scope
     fn declare(&self, name: &str, value: Value);
+    /// Declare a new variable 'name' in the current scope which shares its cell with the scope
+    /// 'value' was taken from
+    fn share(&self, name: &str, value: ScopeValue);
     /// Drop the variable 'name' from the current scope
     ///
     /// Errors if the variable is not found in the current scope
     fn drop(&self, name: &str) -> Result<(), String>;
 }
 
-impl<'a> RunnerContextVariable<'a> for Ctx<'a> {
-    fn get(&self, name: &str) -> Result<ScopeValue<'a>, String> {
+impl RunnerContextVariable for Ctx {
+    fn get(&self, name: &str) -> Result<ScopeValue, String> {
         let mut current = Some(self);
 
         while let Some(ctx) = current {
-            // check if variable is a column in the current row
-            let column_map = ctx.column_map();
-            let row = ctx.row();
-            if column_map.is_some() && row.is_some() {
-                let row = unsafe { &*row.unwrap() };
-
-                if let Some(index) = column_map.unwrap().get(name) {
-                    let value = row.get(*index).unwrap();
-                    let wrapped = Rc::new(RefCell::new(Cow::Borrowed(value)));
-                    return Ok(wrapped)
-                }
+            // check if variable is a column in the current row, columns are not shared
+            if let Some(value) = ctx.column(name) {
+                return Ok(Arc::new(Mutex::new(value.clone())))
             }
 
             // check if variable exists in this scope
@@ -101,7 +140,25 @@ impl<'a> RunnerContextVariable<'a> for Ctx<'a> {
             }
 
             // move to the parent context
-            current = ctx.parent.as_ref();
+            current = ctx.visible_parent();
+        }
+
+        Err(format!("Variable '{}' not found", name))
+    }
+
+    fn value(&self, name: &str) -> Result<Value, String> {
+        let mut current = Some(self);
+
+        while let Some(ctx) = current {
+            if let Some(value) = ctx.column(name) {
+                return Ok(value.clone())
+            }
+
+            if let Some(v) = ctx.variables.borrow().get(name) {
+                return Ok(v.lock().unwrap_or_else(|e| e.into_inner()).clone())
+            }
+
+            current = ctx.visible_parent();
         }
 
         Err(format!("Variable '{}' not found", name))
@@ -109,7 +166,7 @@ impl<'a> RunnerContextVariable<'a> for Ctx<'a> {
 
     fn assign(&self, name: &str, value: Value) -> Result<(), String> {
         match self.get(name) {
-            Ok(v) => *v.borrow_mut() = Cow::Owned(value),
+            Ok(v) => *v.lock().unwrap_or_else(|e| e.into_inner()) = value,
             Err(e) => Err(e)?,
         }
 
@@ -117,9 +174,11 @@ impl<'a> RunnerContextVariable<'a> for Ctx<'a> {
     }
 
     fn declare(&self, name: &str, value: Value) {
-        let name = name.to_string();
-        let value = Rc::new(RefCell::new(Cow::Owned(value)));
-        self.variables.borrow_mut().insert(name, value);    
+        self.share(name, Arc::new(Mutex::new(value)));
+    }
+
+    fn share(&self, name: &str, value: ScopeValue) {
+        self.variables.borrow_mut().insert(name.to_string(), value);
     }
 
     fn drop(&self, name: &str) -> Result<(), String> {
@@ -131,20 +190,95 @@ impl<'a> RunnerContextVariable<'a> for Ctx<'a> {
     }
 }
 
-pub trait RunnerContextFields<'a> {
+/// Owned copy of a context which can be sent to another thread, it holds the run options,
+/// parameters and the visible variables (including columns of the current rows) an expression
+/// uses. Used to evaluate expressions on worker threads
+pub struct DetachedContext {
+    cluster_user: User,
+    auth_user: User,
+    cluster: Arc<RwLock<Cluster>>,
+    is_schema: bool,
+    limits: RunLimits,
+    cancel: CancelHandle,
+    parameters: Vec<Value>,
+    variables: HashMap<String, Value>,
+}
+
+impl RunnerContext {
+    /// Copy the context so it can be sent to another thread, only the variables in 'names' are
+    /// copied
+    pub fn detach(&self, names: &BTreeSet<String>) -> DetachedContext {
+        let mut variables = HashMap::new();
+        let mut current = Some(self);
+
+        // nearest values take precedence, row columns before variables of the same scope
+        while let Some(ctx) = current {
+            if let (Some(column_map), Some(row)) = (ctx.column_map(), ctx.row()) {
+                let row = unsafe { &*row };
+                for (name, index) in column_map.iter().filter(|(name, _)| names.contains(*name)) {
+                    if let Some(value) = row.get(*index) {
+                        variables.entry(name.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+            }
+
+            for (name, value) in ctx.variables.borrow().iter().filter(|(name, _)| names.contains(*name)) {
+                variables.entry(name.clone()).or_insert_with(|| value.lock().unwrap_or_else(|e| e.into_inner()).clone());
+            }
+
+            current = ctx.visible_parent().map(Rc::as_ref);
+        }
+
+        DetachedContext {
+            cluster_user: self.cluster_user().as_ref().clone(),
+            auth_user: self.auth_user().as_ref().clone(),
+            cluster: self.cluster().clone(),
+            is_schema: self.is_schema(),
+            limits: self.limits().clone(),
+            cancel: self.options.cancel.clone(),
+            parameters: self.parameters.as_ref().clone(),
+            variables,
+        }
+    }
+}
+
+impl DetachedContext {
+    /// Create a new root context on the current thread
+    pub fn attach(&self) -> Ctx {
+        let options = RunOptions {
+            cluster_user: Rc::new(self.cluster_user.clone()),
+            auth_user: Rc::new(self.auth_user.clone()),
+            cluster: self.cluster.clone(),
+            is_schema: self.is_schema,
+            limits: self.limits.clone(),
+            cancel: self.cancel.clone(),
+            // worker threads do not split their work any further
+            threads: 1,
+        };
+
+        let ctx = RunnerContext::new_ctx_with(Rc::new(options), self.parameters.clone());
+        for (name, value) in &self.variables {
+            ctx.declare(name, value.clone());
+        }
+
+        ctx
+    }
+}
+
+pub trait RunnerContextFields {
     fn row(&self) -> Option<*const Row>;
     fn column_map(&self) -> Option<&HashMap<String, usize>>;
     fn set_row(&self, row: &Row);
     fn set_column_map(&mut self, column_map: HashMap<String, usize>);
 }
 
-impl<'a> RunnerContextFields<'a> for RunnerContext<'a> {
+impl RunnerContextFields for RunnerContext {
     fn row(&self) -> Option<*const Row> {
         *self.current_row.borrow()
     }
 
     fn column_map(&self) -> Option<&HashMap<String, usize>> {
-        self.current_column_map.as_ref()
+        self.current_column_map.as_deref()
     }
 
     fn set_row(&self, row: &Row) {
@@ -152,28 +286,78 @@ impl<'a> RunnerContextFields<'a> for RunnerContext<'a> {
     }
 
     fn set_column_map(&mut self, column_map: HashMap<String, usize>) {
-        self.current_column_map = Some(column_map); 
+        self.current_column_map = Some(Rc::new(column_map)); 
     }
 }
 
-impl<'a> RunnerContext<'a> {
+impl RunnerContext {
     pub fn new(options: Rc<RunOptions>) -> Self {
         Self {
             variables: Rc::new(RefCell::new(HashMap::new())),
             current_row: RefCell::new(None),
             current_column_map: None,
             options,
+            parameters: Rc::new(Vec::new()),
+            ctes: RefCell::new(HashMap::new()),
+            trigger_rows: RefCell::new(None),
+            boundary: false,
             parent: None,
 
             current_unsafe_row: RefCell::new(ptr::null()),
-            current_unsafe_column_map: RefCell::new(HashMap::new()),
+            current_unsafe_column_map: RefCell::new(Rc::new(HashMap::new())),
         }
     }
 
-    pub fn new_ctx(options: Rc<RunOptions>) -> Ctx<'a> {
+    pub fn new_ctx(options: Rc<RunOptions>) -> Ctx {
         Rc::new(RunnerContext::new(options))
     }
 
+    /// Create a new context with the same options but no variables or placeholders, e.g. to
+    /// run a module
+    pub fn isolated(&self) -> Ctx {
+        RunnerContext::new_ctx(self.options.clone())
+    }
+
+    /// Cells of the variables declared in this scope, not in its parents
+    pub fn scope_variables(&self) -> Vec<(String, ScopeValue)> {
+        self.variables.borrow().iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Parent whose variables are visible in this scope, none past a boundary
+    fn visible_parent(&self) -> Option<&Ctx> {
+        match self.boundary {
+            true => None,
+            false => self.parent.as_ref(),
+        }
+    }
+
+    /// Value of column 'name' of the current row of this scope
+    fn column(&self, name: &str) -> Option<&Value> {
+        let (column_map, row) = (self.column_map()?, self.row()?);
+        let row = unsafe { &*row };
+        column_map.get(name).and_then(|index| row.get(*index))
+    }
+
+    /// Create a bounded context which sees the current row of this scope, including its joined
+    /// row, to evaluate row level security policies
+    pub fn row_scope(self: &Rc<Self>) -> Ctx {
+        let mut ctx = RunnerContext::bounded(self.clone());
+        ctx.current_column_map = self.current_column_map.clone();
+        ctx.current_row = RefCell::new(self.row());
+        ctx.current_unsafe_row = RefCell::new(*self.current_unsafe_row.borrow());
+        ctx.current_unsafe_column_map = RefCell::new(self.current_unsafe_column_map.borrow().clone());
+        Rc::new(ctx)
+    }
+
+    /// Create a new context with values bound to '$1', '$2', ... placeholders
+    pub fn new_ctx_with(options: Rc<RunOptions>, parameters: Vec<Value>) -> Ctx {
+        let mut ctx = RunnerContext::new(options);
+        ctx.parameters = Rc::new(parameters);
+        Rc::new(ctx)
+    }
+
     pub fn cluster_user(&self) -> &Rc<User> {
         &self.options.cluster_user
     }
@@ -189,9 +373,74 @@ impl<'a> RunnerContext<'a> {
     pub fn is_schema(&self) -> bool {
         self.options.is_schema
     }
+
+    pub fn threads(&self) -> usize {
+        self.options.threads
+    }
+
+    pub fn limits(&self) -> &RunLimits {
+        &self.options.limits
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.options.cancel.is_cancelled()
+    }
+
+    /// Get the nearest common table expression 'name', expressions declared outside of the
+    /// current function or policy are not visible
+    pub fn get_cte(&self, name: &str) -> Option<Rc<Table>> {
+        let mut current = Some(self);
+
+        while let Some(ctx) = current {
+            if let Some(table) = ctx.ctes.borrow().get(name) {
+                return Some(table.clone())
+            }
+
+            current = ctx.visible_parent().map(Rc::as_ref);
+        }
+
+        None
+    }
+
+    /// Declare a common table expression in the current scope
+    pub fn add_cte(&self, table: Table) {
+        self.ctes.borrow_mut().insert(table.name.clone(), Rc::new(table));
+    }
+
+    /// Get the trigger rows of the nearest scope which runs a trigger
+    pub fn trigger_rows(&self) -> Option<&RefCell<Option<TriggerRows>>> {
+        let mut current = Some(self);
+
+        while let Some(ctx) = current {
+            if ctx.trigger_rows.borrow().is_some() {
+                return Some(&ctx.trigger_rows)
+            }
+
+            current = ctx.parent.as_deref();
+        }
+
+        None
+    }
+
+    /// Make 'rows' visible to the trigger function run in this scope
+    pub fn set_trigger_rows(&self, rows: TriggerRows) {
+        *self.trigger_rows.borrow_mut() = Some(rows);
+    }
+
+    /// Take the trigger rows of this scope, with any modifications made by the trigger function
+    pub fn take_trigger_rows(&self) -> Option<TriggerRows> {
+        self.trigger_rows.borrow_mut().take()
+    }
+
+    /// Get the value bound to the '$index' placeholder, index starts at 1
+    pub fn parameter(&self, index: usize) -> Result<&Value, String> {
+        index.checked_sub(1)
+            .and_then(|i| self.parameters.get(i))
+            .ok_or(format!("Parameter '${}' is not bound", index))
+    }
 }
 
-impl<'a> RunnerContext<'a> {
+impl RunnerContext {
     /// Get the row value based on member access in select query 
     pub fn get_from(&self, table: &str, column: &str) -> Result<&Value, String> {
         let map = self.current_unsafe_column_map.borrow();
@@ -222,6 +471,13 @@ impl<'a> RunnerContext<'a> {
         *self.current_unsafe_row.borrow_mut() = row;
     }
 
+    /// Unsets the current and the joined row, for rows which are dropped while the context is
+    /// still in use
+    pub fn clear_rows(&self) {
+        *self.current_row.borrow_mut() = None;
+        *self.current_unsafe_row.borrow_mut() = ptr::null();
+    }
+
     pub fn set_joined_tables(&self, tables: &Vec<*const Table>) {
         let mut map = HashMap::new();
 
@@ -233,6 +489,20 @@ impl<'a> RunnerContext<'a> {
             }
         }
 
-        *self.current_unsafe_column_map.borrow_mut() = map;
+        *self.current_unsafe_column_map.borrow_mut() = Rc::new(map);
+    }
+
+    /// Same as 'set_joined_tables', but every joined row entry belongs to the same table which is
+    /// accessed under a different name (e.g. the existing and the 'excluded' row in upserts)
+    pub fn set_aliased_tables(&self, table: &Table, aliases: &[&str]) {
+        let mut map = HashMap::new();
+
+        for (table_index, alias) in aliases.iter().enumerate() {
+            for (column_index, column) in table.columns.iter().enumerate() {
+                map.insert((alias.to_string(), column.name.clone()), (table_index, column_index));
+            }
+        }
+
+        *self.current_unsafe_column_map.borrow_mut() = Rc::new(map);
     }
 }
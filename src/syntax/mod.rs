@@ -5,3 +5,5 @@ pub mod token;
 pub mod runner;
 pub mod context;
 pub mod stringify;
+pub mod checker;
+pub mod module;
@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use super::{ast::{Node, Statement}, parser::Parser, tokenizer::Tokenizer};
+
+/// Resolves the path of an 'import', relative paths are relative to the directory of the
+/// importing module, or to the working directory for the script itself
+pub fn resolve(path: &str, importer: Option<&Path>) -> PathBuf {
+    let path = Path::new(path);
+    let resolved = match importer.and_then(Path::parent) {
+        Some(directory) if path.is_relative() => directory.join(path),
+        _ => path.to_path_buf(),
+    };
+
+    // canonical paths identify a module however it is imported, unless it doesn't exist
+    resolved.canonicalize().unwrap_or(resolved)
+}
+
+/// Reads and parses a module, returns its statements with the line they start at. Errors name
+/// the file and the line they were found at
+pub fn load(path: &Path) -> Result<Vec<(Node, usize)>, String> {
+    let input = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read module '{}': {}", path.display(), e))?;
+
+    let tokens = Tokenizer::new(input).tokenize()
+        .map_err(|e| format!("Module '{}': {}", path.display(), e))?;
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse().map_err(|e| {
+        e.errors().iter()
+            .map(|error| format!("Module '{}': {} at line {}", path.display(), error.message(), error.token().line + 1))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+
+    let source = parser.source_map();
+    let statements = match ast {
+        Node::Block(statements) => statements,
+        node => vec![node],
+    };
+
+    Ok(statements.into_iter().enumerate()
+        .map(|(i, statement)| {
+            let line = source.statement(i).first().map(|token| token.line + 1).unwrap_or(0);
+            (statement, line)
+        })
+        .collect())
+}
+
+/// Declaration of an exported statement of a module, 'export fn ...' or 'export let ...'
+pub fn exported(node: &Node) -> Option<&Statement> {
+    match node {
+        Node::Statement(Statement::Export { declaration: statement, .. }) => match statement.as_ref() {
+            Node::Statement(statement @ (Statement::Function { .. } | Statement::Let { .. })) => Some(statement),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,12 +1,39 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range};
 
-use crate::{syntax::token::{TokenKind, SDLKeyword}, basics::{Column, column::{ColumnType, NumericType, TextType, TimestampType}}, auth::{RlsPolicy, RlsAction}};
+use crate::{syntax::token::{TokenKind, SDLKeyword}, basics::{Column, column::{ColumnType, NumericType, TextType, TimestampType}, value::MAX_PRECISION, Trigger, trigger::{TriggerTiming, TriggerEvent}}, auth::{RlsPolicy, RlsAction}, index::IndexKind};
 
-use super::{token::{Token, Keyword, Symbol, Literal, Operator, QueryKeyword}, ast::{Node, Statement, Number, self, Expression, Type, SelectQuery, InsertQuery, UpdateQuery, DeleteQuery, CreateSDL, Join, JoinType}};
+use super::{token::{Token, Keyword, Symbol, Literal, Operator, QueryKeyword}, ast::{Node, Statement, Number, self, Expression, Type, SelectQuery, InsertQuery, UpdateQuery, DeleteQuery, CreateSDL, Join, JoinType, OnConflict, ConflictAction, Window, WithQuery, Cte, CteUnion, Pattern, KeyValue}};
+
+/// Name, parameters, return type, block and the token of the name of a function declaration
+type FunctionDeclaration = (String, Vec<(String, Type)>, Type, Node, usize);
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Token range of every parsed top level statement
+    statements: Vec<Range<usize>>,
+}
+
+/// Tokens of a parsed script and the token range of each of its top level statements, used to
+/// point at the source of errors found after parsing
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    tokens: Vec<Token>,
+    statements: Vec<Range<usize>>,
+}
+
+impl SourceMap {
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Tokens of the top level statement at 'index', empty if there is none
+    pub fn statement(&self, index: usize) -> &[Token] {
+        match self.statements.get(index) {
+            Some(range) => &self.tokens[range.clone()],
+            None => &[],
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -16,7 +43,7 @@ pub struct ASTError {
 }
 
 impl ASTError {
-    fn new(message: String, token: Token) -> Self {
+    pub(crate) fn new(message: String, token: Token) -> Self {
         Self { message, token }
     }
 
@@ -133,7 +160,12 @@ impl ParserError {
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self { tokens, current: 0, statements: Vec::new() }
+    }
+
+    /// Consumes the parser, returning its tokens and the ranges of the parsed statements
+    pub fn source_map(self) -> SourceMap {
+        SourceMap { tokens: self.tokens, statements: self.statements }
     }
 
     pub fn parse(&mut self) -> Result<Node, ParserError> {
@@ -147,7 +179,10 @@ impl Parser {
 
             previous_current = self.current;
             match self.statement() {
-                Ok(statement) => statements.push(statement),
+                Ok(statement) => {
+                    statements.push(statement);
+                    self.statements.push(previous_current..self.current);
+                },
                 Err(error) => {
                     if previous_current == self.current {
                         // Prevent infinite loop
@@ -238,7 +273,6 @@ impl Parser {
         self.tokens.get(self.current)
     }
 
-    #[allow(dead_code)]
     fn next(&self) -> Option<&Token> {
         self.tokens.get(self.current + 1)
     }
@@ -265,6 +299,7 @@ impl Parser {
     }
 
     fn keyword(&mut self) -> Result<Node, ParserError> {
+        let lambda = matches!(self.next(), Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. }));
         let token = self.current_token("keyword")?;
 
         let node = if let TokenKind::Keyword(ref keyword) = token.kind {
@@ -273,10 +308,16 @@ impl Parser {
                 // Keyword::Else => self.else_statement(),
                 Keyword::While => return self.while_statement(),
                 Keyword::For => return self.for_statement(),
+                // 'fn (' starts an anonymous function
+                Keyword::Function if lambda => return self.expression(),
                 Keyword::Function => return self.function_declaration_statement(),
                 Keyword::Return => return self.return_statement(),
-                Keyword::Break => Node::Statement(Statement::Break),
-                Keyword::Continue => Node::Statement(Statement::Continue),
+                Keyword::Throw => return self.throw_statement(),
+                Keyword::Try => return self.try_statement(),
+                Keyword::Import => return self.import_statement(),
+                Keyword::Export => return self.export_statement(),
+                Keyword::Break => Node::Statement(Statement::Break { token: self.current }),
+                Keyword::Continue => Node::Statement(Statement::Continue { token: self.current }),
                 Keyword::Let => return self.let_statement(),
                 // Keyword::Const => self.const_statement(),
                 k if k.is_literal() => return self.keyword_literal(),
@@ -291,7 +332,19 @@ impl Parser {
     }
 
     fn for_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
         self.expect(TokenKind::Keyword(Keyword::For))?;
+
+        // 'for (i, x) in' and 'for x in', other loops start with '(initializer;'
+        if !matches!(self.current(), Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. })) {
+            return self.for_in_statement(false, token)
+        }
+        let pair = matches!(self.tokens.get(self.current + 1), Some(Token { kind: TokenKind::Identifier(_), .. })) &&
+            matches!(self.tokens.get(self.current + 2), Some(Token { kind: TokenKind::Symbol(Symbol::Comma), .. }));
+        if pair {
+            return self.for_in_statement(true, token)
+        }
+
         self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
 
         let initializer = self.statement()?;
@@ -310,10 +363,79 @@ impl Parser {
             condition: Box::new(condition),
             action: Box::new(action),
             block: Box::new(block), 
+            token,
         }))
     }
 
+    fn for_in_statement(&mut self, pair: bool, token: usize) -> Result<Node, ParserError> {
+        let index = match pair {
+            true => {
+                self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+                let index = self.variable_name("index variable")?;
+                self.expect(TokenKind::Symbol(Symbol::Comma))?;
+                Some(index)
+            },
+            false => None,
+        };
+
+        let pattern = self.pattern()?;
+        if pair {
+            self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+        }
+        self.expect(TokenKind::Keyword(Keyword::In))?;
+
+        let iterable = match self.current_token("iterable")?.kind {
+            TokenKind::Query(_) => self.query()?,
+            _ => self.expression()?,
+        };
+        let block = self.block()?;
+
+        Ok(Node::Statement(Statement::ForIn {
+            index,
+            pattern,
+            iterable: Box::new(iterable),
+            block: Box::new(block),
+            token,
+        }))
+    }
+
+    /// Variables of a for-in loop, 'x', '[a, b]' or '{a, b}'
+    fn pattern(&mut self) -> Result<Pattern, ParserError> {
+        let close = match self.current_token("loop variable")?.kind {
+            TokenKind::Symbol(Symbol::LeftBracket) => Symbol::RightBracket,
+            TokenKind::Symbol(Symbol::LeftBrace) => Symbol::RightBrace,
+            _ => return Ok(Pattern::Identifier(self.variable_name("loop variable")?)),
+        };
+        self.advance();
+
+        let mut names = vec![];
+        while !matches!(self.current(), Some(Token { kind: TokenKind::Symbol(ref symbol), .. }) if *symbol == close) {
+            names.push(self.variable_name("loop variable")?);
+            match self.current() {
+                Some(Token { kind: TokenKind::Symbol(Symbol::Comma), .. }) => self.advance(),
+                _ => break,
+            }
+        }
+        self.expect(TokenKind::Symbol(close.clone()))?;
+
+        Ok(match close {
+            Symbol::RightBracket => Pattern::Array(names),
+            _ => Pattern::Object(names),
+        })
+    }
+
+    fn variable_name(&mut self, expected: &str) -> Result<String, ParserError> {
+        let name = match self.current_token(expected)?.kind {
+            TokenKind::Identifier(ref name) => name.clone(),
+            _ => Err(self.expected(expected))?
+        };
+        self.advance();
+
+        Ok(name)
+    }
+
     fn while_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
         self.expect(TokenKind::Keyword(Keyword::While))?;
 
         let condition = self.expression()?;
@@ -321,11 +443,13 @@ impl Parser {
 
         Ok(Node::Statement(Statement::While { 
             condition: Box::new(condition), 
-            block: Box::new(block) 
+            block: Box::new(block),
+            token,
         }))
     }
 
     fn return_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
         self.expect(TokenKind::Keyword(Keyword::Return))?;
 
         let value = match self.current() {
@@ -336,12 +460,13 @@ impl Parser {
             _ => self.expression()?,
         };
 
-        Ok(Node::Statement(Statement::Return(Box::new(value))))
+        Ok(Node::Statement(Statement::Return { value: Box::new(value), token }))
     }
 
     fn let_statement(&mut self) -> Result<Node, ParserError> {
         self.expect(TokenKind::Keyword(Keyword::Let))?;
 
+        let token = self.current;
         let name =  match self.current_token("identifier")? {
             Token { kind: TokenKind::Identifier(name), .. } => name.clone(),
             _ => Err(self.expected("identifier"))?
@@ -351,7 +476,7 @@ impl Parser {
 
         let value = self.statement()?;
 
-        Ok(Node::Statement(Statement::Let { name, value: Box::new(value) }))
+        Ok(Node::Statement(Statement::Let { name, value: Box::new(value), token }))
     }
 
     fn keyword_literal(&mut self) -> Result<Node, ParserError> {
@@ -372,8 +497,15 @@ impl Parser {
     }
 
     fn function_declaration_statement(&mut self) -> Result<Node, ParserError> {
+        let (name, parameters, return_type, block, token) = self.function_declaration()?;
+        Ok(Node::Statement(Statement::Function { name, parameters, return_type, block: Box::new(block), token }))
+    }
+
+    /// Parses 'fn name(...) -> type { ... }'
+    fn function_declaration(&mut self) -> Result<FunctionDeclaration, ParserError> {
         self.expect(TokenKind::Keyword(Keyword::Function))?;
 
+        let token = self.current;
         let name = match self.current() {
             Some(Token { kind: TokenKind::Identifier(name), .. }) => name.clone(), 
             _ => Err(self.expected("function name"))?
@@ -384,7 +516,18 @@ impl Parser {
         let return_type = self.return_type()?;
         let block = self.block()?;
 
-        Ok(Node::Statement(Statement::Function { name, parameters, return_type, block: Box::new(block) }))
+        Ok((name, parameters, return_type, block, token))
+    }
+
+    fn lambda(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
+        self.expect(TokenKind::Keyword(Keyword::Function))?;
+
+        let parameters = self.parameters()?;
+        let return_type = self.return_type()?;
+        let block = self.block()?;
+
+        Ok(Node::Expression(Expression::Lambda { parameters, return_type, block: Box::new(block), token }))
     }
 
     fn parameters(&mut self) -> Result<Vec<(String, Type)>, ParserError> {
@@ -402,6 +545,12 @@ impl Parser {
                 TokenKind::Identifier(ref name) => {
                     let name = name.clone();
                     self.advance(); 
+                    // parameters without a type accept any value
+                    if !matches!(self.current(), Some(Token { kind: TokenKind::Symbol(Symbol::Colon), .. })) {
+                        parameters.push((name, Type::Any));
+                        continue
+                    }
+
                     match self.parameter_type() {
                         Ok(parameter_type) => parameters.push((name, parameter_type)),
                         Err(error) => parser_error.add(error)
@@ -434,8 +583,11 @@ impl Parser {
                     "int" => Type::Int,
                     "uint" => Type::UInt,
                     "float" => Type::Float,
+                    "decimal" => Type::Decimal,
                     "string" => Type::String,
                     "bool" => Type::Boolean,
+                    "object" => Type::Object,
+                    "any" => Type::Any,
                     _ => Err(self.expected("valid type"))?
                 };
                 self.advance();
@@ -445,6 +597,29 @@ impl Parser {
                 self.advance();
                 Type::Pointer(Box::new(self.type_declaration()?))
             }
+            // 'fn(int, string) -> bool'
+            TokenKind::Keyword(Keyword::Function) => {
+                self.advance();
+                self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+                let mut parameters = Vec::new();
+                while !matches!(self.current(), Some(Token { kind: TokenKind::Symbol(Symbol::RightParenthesis), .. }) | None) {
+                    parameters.push(self.type_declaration()?);
+                    if matches!(self.current(), Some(Token { kind: TokenKind::Symbol(Symbol::Comma), .. })) {
+                        self.advance();
+                    }
+                }
+                self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+                let return_type = match self.current() {
+                    Some(Token { kind: TokenKind::Symbol(Symbol::Arrow), .. }) => {
+                        self.advance();
+                        self.type_declaration()?
+                    },
+                    _ => Type::Void
+                };
+                Type::Function(parameters, Box::new(return_type))
+            }
             TokenKind::Symbol(Symbol::LeftBracket) => {
                 self.advance();
                 let declared_type = self.type_declaration()?;
@@ -474,6 +649,7 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
         self.expect(TokenKind::Keyword(Keyword::If))?;
 
         let condition = self.expression()?;
@@ -498,22 +674,86 @@ impl Parser {
         Ok(Node::Statement(Statement::If {
             condition: Box::new(condition),
             then_block: Box::new(then_block),        
-            else_block: else_block.map(Box::new)
+            else_block: else_block.map(Box::new),
+            token,
         }))
     }
 
+    fn throw_statement(&mut self) -> Result<Node, ParserError> {
+        let (token, line) = (self.current, self.current_token("throw")?.line + 1);
+        self.expect(TokenKind::Keyword(Keyword::Throw))?;
+
+        let value = self.expression()?;
+
+        Ok(Node::Statement(Statement::Throw { value: Box::new(value), line, token }))
+    }
+
+    fn import_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
+        self.expect(TokenKind::Keyword(Keyword::Import))?;
+
+        let path = match self.current_token("module path")?.kind {
+            TokenKind::Literal(Literal::String(ref path)) => path.clone(),
+            _ => Err(self.expected("module path"))?
+        };
+        self.advance();
+
+        Ok(Node::Statement(Statement::Import { path, token }))
+    }
+
+    fn export_statement(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
+        self.expect(TokenKind::Keyword(Keyword::Export))?;
+
+        match self.current_token("function or let declaration")?.kind {
+            TokenKind::Keyword(Keyword::Function) | TokenKind::Keyword(Keyword::Let) => {},
+            _ => Err(self.expected("function or let declaration"))?
+        }
+        let declaration = self.statement()?;
+
+        Ok(Node::Statement(Statement::Export { declaration: Box::new(declaration), token }))
+    }
+
+    fn try_statement(&mut self) -> Result<Node, ParserError> {
+        self.expect(TokenKind::Keyword(Keyword::Try))?;
+        let (block, lines) = self.block_lines()?;
+
+        self.expect(TokenKind::Keyword(Keyword::Catch))?;
+        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+        let name = match self.current_token("catch variable")?.kind {
+            TokenKind::Identifier(ref name) => name.clone(),
+            _ => Err(self.expected("catch variable"))?
+        };
+        self.advance();
+        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+        let catch_block = self.block()?;
+
+        Ok(Node::Statement(Statement::Try { block: Box::new(block), lines, name, catch_block: Box::new(catch_block) }))
+    }
+
     fn block(&mut self) -> Result<Node, ParserError> {
+        self.block_lines().map(|(block, _)| block)
+    }
+
+    /// Parses a block, also returns the source line of each of its statements
+    fn block_lines(&mut self) -> Result<(Node, Vec<usize>), ParserError> {
         self.expect(TokenKind::Symbol(Symbol::LeftBrace))?;
         let mut parser_error = ParserError::empty();
         let mut statements = Vec::new();
+        let mut lines = Vec::new();
 
         while let Some(token) = self.current() {
             let mut added_error = false;
             match token.kind {
                 TokenKind::Symbol(Symbol::RightBrace) => break,
                 _ => {
+                    let line = token.line + 1;
                     match self.statement() {
-                        Ok(statement) => statements.push(statement),
+                        Ok(statement) => {
+                            statements.push(statement);
+                            lines.push(line);
+                        },
                         Err(error) => {
                             parser_error.extend(error);
                             added_error = true;
@@ -540,7 +780,7 @@ impl Parser {
             return Err(parser_error)
         }
 
-        Ok(Node::Block(statements))
+        Ok((Node::Block(statements), lines))
     }
 
     fn expression(&mut self) -> Result<Node, ParserError> {
@@ -563,6 +803,7 @@ impl Parser {
             TokenKind::Symbol(_) => self.symbol()?, 
             TokenKind::Identifier(_) => self.identifier()?, 
             TokenKind::Keyword(ref c) if c.is_literal() => self.keyword_literal()?,
+            TokenKind::Keyword(Keyword::Function) => self.lambda()?,
             // _ => todo!("expression")
             _ => Err(self.expected("expression"))?
         };
@@ -614,6 +855,7 @@ impl Parser {
                 TokenKind::Symbol(Symbol::Period) => {
                     self.advance();
 
+                    let token = self.current;
                     let property = if matches!(self.current(), Some(Token { kind: TokenKind::Operator(Operator::Multiply), .. })) {
                         // * is allowed in select queries
                         self.advance();
@@ -622,7 +864,7 @@ impl Parser {
                         self.identifier_name()?
                     };
 
-                    current = Node::Expression(Expression::Member { object: Box::new(current), member: property });
+                    current = Node::Expression(Expression::Member { object: Box::new(current), member: property, token });
                 },
                 _ => break
             }
@@ -632,11 +874,13 @@ impl Parser {
     }
 
     fn assignment(&mut self, left: Node) -> Result<Node, ParserError> {
+        let operator_token = self.current;
         let token = self.current_token("assignment operator")?.clone(); 
 
-        let identifier = match left {
-            Node::Literal(ast::Literal::Identifier(ref name)) => name.clone(),
-            _ => Err(self.expected_node("identifier before assignment", &left))? 
+        let target = match left {
+            Node::Literal(ast::Literal::Identifier(ref name, name_token)) => (None, name.clone(), name_token),
+            Node::Expression(Expression::Member { ref object, ref member, token }) => (Some(object.clone()), member.clone(), token),
+            _ => Err(self.expected_node("identifier or member before assignment", &left))? 
         };
 
         if let TokenKind::Operator(ref operator) = token.kind {
@@ -657,17 +901,22 @@ impl Parser {
                         ast::Operator::PowAssign => ast::Operator::Pow,
                         _ => Err(self.expected("assignment operator"))?
                     };
-                    Node::Expression(Expression::Binary { left: Box::new(left), operator: ast_operator, right: Box::new(right) })
+                    Node::Expression(Expression::Binary { left: Box::new(left), operator: ast_operator, right: Box::new(right), token: operator_token })
                 }
             };
 
-            Ok(Node::Statement(Statement::Assignment { name: identifier, value: Box::new(value) }))
+            let value = Box::new(value);
+            match target {
+                (None, name, token) => Ok(Node::Statement(Statement::Assignment { name, value, token })),
+                (Some(object), member, _) => Ok(Node::Statement(Statement::MemberAssignment { object, member, value })),
+            }
         } else {
             Err(self.expected("assignment operator"))?
         }
     }
 
     fn binary(&mut self, left: Node) -> Result<Node, ParserError> {
+        let operator_token = self.current;
         let token = self.current_token("binary operator")?.clone();
 
         if let TokenKind::Operator(ref operator) = token.kind {
@@ -677,7 +926,7 @@ impl Parser {
             let ast_operator = operator.to_ast_operator();
             let right = self.expression()?;
 
-            Ok(Node::Expression(Expression::Binary { left: Box::new(left), operator: ast_operator, right: Box::new(right) }))
+            Ok(Node::Expression(Expression::Binary { left: Box::new(left), operator: ast_operator, right: Box::new(right), token: operator_token }))
         } else {
             Err(self.expected("binary operator"))?
         }
@@ -688,9 +937,17 @@ impl Parser {
 
         let literal = if let TokenKind::Literal(ref literal) = token.kind {
             match literal {
-                Literal::Int(value) => ast::Literal::Number(Number::Int(value.parse().unwrap())),
+                Literal::Int(value) => match value.parse::<i64>() {
+                    Ok(value) => ast::Literal::Number(Number::Int(value)),
+                    // literals too large for a signed integer are unsigned
+                    Err(_) => match value.parse::<u64>() {
+                        Ok(value) => ast::Literal::Number(Number::UInt(value)),
+                        Err(_) => Err(self.expected("integer literal which fits into 64 bits"))?
+                    }
+                },
                 Literal::Float(value) => ast::Literal::Number(Number::Float(value.parse().unwrap())),
                 Literal::String(value) => ast::Literal::String(value.clone()),
+                Literal::Parameter(index) => ast::Literal::Parameter(*index),
             }
         } else {
             Err(self.expected("literal"))?
@@ -701,11 +958,13 @@ impl Parser {
     }
 
     fn symbol(&mut self) -> Result<Node, ParserError> {
+        let object_start = self.is_object_start();
         let token = self.current_token("symbol")?;
         
         if let TokenKind::Symbol(ref symbol) = token.kind {
             match symbol {
                 Symbol::LeftParenthesis => self.group(),
+                Symbol::LeftBrace if object_start => self.object(),
                 Symbol::LeftBrace => self.block(),
                 Symbol::LeftBracket => self.array(),
                 // _ => todo!("symbol")
@@ -754,6 +1013,27 @@ impl Parser {
         Ok(Node::Literal(ast::Literal::Array(elements)))
     }
 
+    /// Whether the '{' at the current token starts an object literal instead of a block, that
+    /// is '{}' or '{' followed by 'key:'
+    fn is_object_start(&self) -> bool {
+        match self.next().map(|token| &token.kind) {
+            Some(TokenKind::Symbol(Symbol::RightBrace)) => true,
+            Some(TokenKind::Identifier(_)) | Some(TokenKind::Literal(Literal::String(_))) => matches!(
+                self.tokens.get(self.current + 2),
+                Some(Token { kind: TokenKind::Symbol(Symbol::Colon), .. })
+            ),
+            _ => false
+        }
+    }
+
+    fn object(&mut self) -> Result<Node, ParserError> {
+        self.expect(TokenKind::Symbol(Symbol::LeftBrace))?;
+        let entries = self.query_key_values()?.into_iter().map(|(key, _, value)| (key, value)).collect();
+        self.expect(TokenKind::Symbol(Symbol::RightBrace))?;
+
+        Ok(Node::Literal(ast::Literal::Object(entries)))
+    }
+
     fn identifier_name(&mut self) -> Result<String, ASTError> {
         let token = self.current_token("identifier")?;
 
@@ -768,17 +1048,15 @@ impl Parser {
     }
     
     fn identifier(&mut self) -> Result<Node, ParserError> {
+        let token = self.current;
         let identifier = self.identifier_name()?;
 
         let node = match self.current() {
-            Some(token) => match token.kind {
-                TokenKind::Symbol(Symbol::LeftParenthesis) => {
-                    let arguments = self.arguments()?;
-                    Node::Expression(Expression::Call { name: identifier, arguments })
-                },
-                _ => Node::Literal(ast::Literal::Identifier(identifier))
+            Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. }) => {
+                let arguments = self.arguments()?;
+                Node::Expression(Expression::Call { name: identifier, arguments, token })
             },
-            None => Node::Literal(ast::Literal::Identifier(identifier))
+            _ => Node::Literal(ast::Literal::Identifier(identifier, token))
         };
 
         self.encapsulate_index_or_member(node)
@@ -818,22 +1096,104 @@ impl Parser {
 // QUERY
 impl Parser {
     fn query(&mut self) -> Result<Node, ParserError> {
+        if let Some(Token { kind: TokenKind::Query(QueryKeyword::With), .. }) = self.current() {
+            return self.with_query()
+        }
+
         self.expect(TokenKind::Query(QueryKeyword::Query))?;
         
+        let token = self.current;
         let table_name = self.string_or_identifier()?;
 
         match self.current() {
-            Some(token) => match token.kind {
-                TokenKind::Query(QueryKeyword::Select) => self.select_query(table_name),
-                TokenKind::Query(QueryKeyword::Insert) => self.insert_query(table_name),
-                TokenKind::Query(QueryKeyword::Update) => self.update_query(table_name),
-                TokenKind::Query(QueryKeyword::Delete) => self.delete_query(table_name),
+            Some(current) => match current.kind {
+                TokenKind::Query(QueryKeyword::Select) => self.select_query(table_name, token),
+                TokenKind::Query(QueryKeyword::Insert) => self.insert_query(table_name, token),
+                TokenKind::Query(QueryKeyword::Update) => self.update_query(table_name, token),
+                TokenKind::Query(QueryKeyword::Delete) => self.delete_query(table_name, token),
                 _ => Err(self.expected("valid query type"))?
             },
             _ => Err(self.expected("query type"))?
         }
     }
 
+    /// Parses 'with [recursive] cte, ... <query>'
+    fn with_query(&mut self) -> Result<Node, ParserError> {
+        self.expect(TokenKind::Query(QueryKeyword::With))?;
+
+        let recursive = match self.current() {
+            Some(token) if token.kind == TokenKind::Identifier("recursive".to_string()) => {
+                self.advance();
+                true
+            },
+            _ => false
+        };
+
+        let mut ctes = Vec::new();
+        loop {
+            ctes.push(self.with_cte()?);
+            match self.current() {
+                Some(token) if token.kind == TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                _ => break
+            }
+        }
+
+        let query = match self.query()? {
+            Node::Query(query) => query,
+            node => Err(self.expected_node("query", &node))?
+        };
+
+        Ok(Node::Query(ast::Query::With(WithQuery { recursive, ctes, query: Box::new(query) })))
+    }
+
+    /// Parses 'name [(columns)] as (select [union [all] select])'
+    fn with_cte(&mut self) -> Result<Cte, ParserError> {
+        let name = self.identifier_name()?;
+
+        let mut columns = Vec::new();
+        if let Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. }) = self.current() {
+            self.advance();
+            loop {
+                columns.push(self.identifier_name()?);
+                match self.current() {
+                    Some(token) if token.kind == TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                    _ => break
+                }
+            }
+            self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+        }
+
+        self.expect(TokenKind::Query(QueryKeyword::As))?;
+        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+        let query = self.cte_select()?;
+        let union = match self.current() {
+            Some(token) if token.kind == TokenKind::Query(QueryKeyword::Union) => {
+                self.advance();
+                let all = match self.current() {
+                    Some(token) if token.kind == TokenKind::Identifier("all".to_string()) => {
+                        self.advance();
+                        true
+                    },
+                    _ => false
+                };
+                Some(CteUnion { query: self.cte_select()?, all })
+            },
+            _ => None
+        };
+
+        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+        Ok(Cte { name, columns, query, union })
+    }
+
+    fn cte_select(&mut self) -> Result<SelectQuery, ParserError> {
+        match self.query()? {
+            Node::Query(ast::Query::Select(select)) => Ok(select),
+            node => Err(self.expected_node("select query", &node))?
+        }
+    }
+
     fn string_or_identifier(&mut self) -> Result<String, ASTError> {
         let token = self.current_token("string or identifier")?;
 
@@ -847,17 +1207,19 @@ impl Parser {
         Ok(value)
     }
 
-    fn delete_query(&mut self, table_name: String) -> Result<Node, ParserError> {
+    fn delete_query(&mut self, table_name: String, token: usize) -> Result<Node, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Delete))?;
         let query = DeleteQuery {
             table: table_name,
-            where_clause: Some(self.query_where()?)
+            token,
+            where_clause: Some(self.query_where()?),
+            returning: self.query_returning()?,
         };
 
         Ok(Node::Query(ast::Query::Delete(query)))
     }
 
-    fn update_query(&mut self, table_name: String) -> Result<Node, ParserError> {
+    fn update_query(&mut self, table_name: String, token: usize) -> Result<Node, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Update))?;
         let mut parser_error = ParserError::empty();
 
@@ -877,48 +1239,135 @@ impl Parser {
             }
         };
 
+        let returning = match self.query_returning() {
+            Ok(returning) => returning,
+            Err(error) => {
+                parser_error.extend(error);
+                None
+            }
+        };
+
         if !parser_error.is_empty() {
             return Err(parser_error)
         }
 
         let query = UpdateQuery {
             table: table_name,
+            token,
             key_values,
             where_clause: Some(where_clause),
+            returning,
         };
 
         Ok(Node::Query(ast::Query::Update(query)))
     }
 
-    fn insert_query(&mut self, table_name: String) -> Result<Node, ParserError> {
+    fn insert_query(&mut self, table_name: String, token: usize) -> Result<Node, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Insert))?;
+        let rows = match self.current() {
+            Some(token) if token.kind == TokenKind::Symbol(Symbol::LeftBracket) => self.insert_rows()?,
+            _ => vec![self.query_key_values()?]
+        };
+
+        let on_conflict = match self.current() {
+            Some(token) if token.kind == TokenKind::Keyword(Keyword::On) => Some(self.insert_on_conflict()?),
+            _ => None
+        };
+
         let query = InsertQuery {
             table: table_name,
-            key_values: self.query_key_values()?,
+            token,
+            rows,
+            on_conflict,
+            returning: self.query_returning()?,
         };
 
         Ok(Node::Query(ast::Query::Insert(query)))
     }
 
-    fn query_key_values(&mut self) -> Result<Vec<(String, Node)>, ParserError> {
+    /// Parses '[{ key: value, ... }, ...]' rows of a bulk insert
+    fn insert_rows(&mut self) -> Result<Vec<Vec<KeyValue>>, ParserError> {
+        self.expect(TokenKind::Symbol(Symbol::LeftBracket))?;
+
+        let mut rows = Vec::new();
+        while let Some(token) = self.current() {
+            match token.kind {
+                TokenKind::Symbol(Symbol::RightBracket) => break,
+                TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                _ => {
+                    self.expect(TokenKind::Symbol(Symbol::LeftBrace))?;
+                    rows.push(self.query_key_values()?);
+                    self.expect(TokenKind::Symbol(Symbol::RightBrace))?;
+                }
+            }
+        }
+
+        self.expect(TokenKind::Symbol(Symbol::RightBracket))?;
+        Ok(rows)
+    }
+
+    fn insert_on_conflict(&mut self) -> Result<OnConflict, ParserError> {
+        self.expect(TokenKind::Keyword(Keyword::On))?;
+        self.expect(TokenKind::Identifier("conflict".to_string()))?;
+
+        let mut columns = Vec::new();
+        if let Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. }) = self.current() {
+            self.advance();
+
+            while let Some(token) = self.current() {
+                match token.kind {
+                    TokenKind::Symbol(Symbol::RightParenthesis) => break,
+                    TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                    _ => {
+                        let token = self.current;
+                        columns.push((self.string_or_identifier()?, token))
+                    }
+                }
+            }
+
+            self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+        }
+
+        self.expect(TokenKind::Identifier("do".to_string()))?;
+
+        let action = match self.current_token("conflict action")?.kind {
+            TokenKind::Identifier(ref action) if action == "nothing" => {
+                self.advance();
+                ConflictAction::Nothing
+            },
+            TokenKind::Query(QueryKeyword::Update) => {
+                self.advance();
+                self.expect(TokenKind::Identifier("set".to_string()))?;
+                ConflictAction::Update(self.query_key_values()?)
+            },
+            _ => Err(self.expected("valid conflict action"))?
+        };
+
+        Ok(OnConflict { columns, action })
+    }
+
+    fn query_key_values(&mut self) -> Result<Vec<KeyValue>, ParserError> {
         let mut parser_error = ParserError::empty();
         let mut key_values = Vec::new();
 
         while let Some(token) = self.current() {
             match token.kind {
                 TokenKind::Query(_) => break,
+                TokenKind::Keyword(Keyword::On) => break,
                 TokenKind::Symbol(Symbol::Semicolon) => break,
+                TokenKind::Symbol(Symbol::RightBrace) => break,
+                TokenKind::Symbol(Symbol::Comma) => self.advance(),
                 TokenKind::EOF => break,
                 TokenKind::Identifier(ref key) |
                 TokenKind::Literal(Literal::String(ref key)) => {
-                    let key = key.clone();
+                    let (key, token) = (key.clone(), self.current);
                     self.advance();
                     if let Err(error) = self.expect(TokenKind::Symbol(Symbol::Colon)) {
                         parser_error.add(error);
                         continue;
                     }
                     let value = self.expression()?;
-                    key_values.push((key, value))
+                    key_values.push((key, token, value))
                 },
                 _ => parser_error.add(self.expected("key value"))
             }
@@ -931,12 +1380,13 @@ impl Parser {
         Ok(key_values)
     }
 
-    fn select_query(&mut self, table_name: String) -> Result<Node, ParserError> {
+    fn select_query(&mut self, table_name: String, token: usize) -> Result<Node, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Select))?;
 
         let mut parser_error = ParserError::empty();
         let mut query = SelectQuery {
             table: table_name,
+            token,
             joins: Vec::new(),
             columns: Vec::new(),
             where_clause: None,
@@ -944,19 +1394,23 @@ impl Parser {
             limit: None,
             offset: None,
             exclude: None,
+            as_objects: false,
         };
 
         while let Some(token) = self.current() {
             match token.kind {
                 TokenKind::Symbol(Symbol::Semicolon) |
+                TokenKind::Symbol(Symbol::RightParenthesis) |
+                // block of a for-in loop over the result
+                TokenKind::Symbol(Symbol::LeftBrace) |
                 TokenKind::Query(_) => break,
                 TokenKind::Symbol(Symbol::Comma) => { self.advance(); },
                 TokenKind::Operator(Operator::Multiply) => {
+                    query.columns.push(Node::Literal(ast::Literal::Identifier("*".to_string(), self.current)));
                     self.advance();
-                    query.columns.push(Node::Literal(ast::Literal::Identifier("*".to_string())))
                 },
                 TokenKind::Identifier(_) => {
-                    match self.identifier() {
+                    match self.identifier().and_then(|node| self.select_window(node)) {
                         Ok(node) => query.columns.push(node),
                         Err(error) => parser_error.extend(error)
                     }
@@ -974,6 +1428,11 @@ impl Parser {
                 TokenKind::Query(QueryKeyword::Limit) => query.limit = Some(self.query_limit()?),
                 TokenKind::Query(QueryKeyword::Offset) => query.offset = Some(self.query_offset()?),
                 TokenKind::Query(QueryKeyword::Exclude) => query.exclude = Some(self.query_exclude()?),
+                TokenKind::Query(QueryKeyword::As) => {
+                    self.advance();
+                    self.expect(TokenKind::Identifier("object".to_string()))?;
+                    query.as_objects = true;
+                },
                 _ => break
             }
         };
@@ -985,6 +1444,65 @@ impl Parser {
         Ok(Node::Query(ast::Query::Select(query)))
     }
 
+    /// Parses an optional 'over ([partition by ...] [order by ...])' clause after a function call
+    fn select_window(&mut self, node: Node) -> Result<Node, ParserError> {
+        match self.current() {
+            Some(token) if token.kind == TokenKind::Identifier("over".to_string()) => self.advance(),
+            _ => return Ok(node)
+        }
+
+        let (function, arguments) = match node {
+            Node::Expression(Expression::Call { name, arguments, .. }) => (name, arguments),
+            _ => Err(self.expected_node("window function call", &node))?
+        };
+
+        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+        let mut partition = Vec::new();
+        if let Some(Token { kind: TokenKind::Identifier(ref value), .. }) = self.current() {
+            if value == "partition" {
+                self.advance();
+                self.expect(TokenKind::Identifier("by".to_string()))?;
+
+                loop {
+                    partition.push(self.expression()?);
+                    match self.current() {
+                        Some(token) if token.kind == TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                        _ => break
+                    }
+                }
+            }
+        }
+
+        let mut order = Vec::new();
+        if let Some(Token { kind: TokenKind::Query(QueryKeyword::Order), .. }) = self.current() {
+            self.advance();
+            self.expect(TokenKind::Identifier("by".to_string()))?;
+
+            loop {
+                let node = self.expression()?;
+                let ascending = match self.current() {
+                    Some(Token { kind: TokenKind::Identifier(ref value), .. }) if value == "asc" || value == "desc" => {
+                        let ascending = value == "asc";
+                        self.advance();
+                        ascending
+                    },
+                    _ => true
+                };
+                order.push((node, ascending));
+
+                match self.current() {
+                    Some(token) if token.kind == TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                    _ => break
+                }
+            }
+        }
+
+        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+        Ok(Node::Expression(Expression::Window(Window { function, arguments, partition, order })))
+    }
+
     fn select_joins(&mut self) -> Result<Vec<Join>, ParserError> {
         let mut parser_error = ParserError::empty();
         let mut joins = Vec::new();
@@ -1007,6 +1525,7 @@ impl Parser {
                 _ => JoinType::Inner,
             };
 
+            let token = self.current;
             let table = self.string_or_identifier()
                 .or_else(|e| { parser_error.add(e); Err("") })
                 .unwrap_or("".to_string());
@@ -1020,7 +1539,7 @@ impl Parser {
 
 
             if parser_error.is_empty() {
-                joins.push(Join { table, on, join_type })
+                joins.push(Join { table, token, on, join_type })
             }
         }
 
@@ -1036,10 +1555,17 @@ impl Parser {
         Ok(Box::new(self.expression()?)) 
     } 
 
-    fn query_order(&mut self) -> Result<Box<Node>, ASTError> {
+    fn query_order(&mut self) -> Result<Box<Node>, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Order))?;
         
-        let column = self.string_or_identifier()?;
+        // either a column name or a function call, e.g. 'score(description, "query")'
+        let key = match (self.current(), self.next()) {
+            (Some(Token { kind: TokenKind::Identifier(_), .. }), Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. })) => self.identifier()?,
+            _ => {
+                let token = self.current;
+                Node::Literal(ast::Literal::Identifier(self.string_or_identifier()?, token))
+            }
+        };
     
         let order = match self.current() {
             Some(Token { kind: TokenKind::Identifier(value), .. }) => {
@@ -1049,13 +1575,16 @@ impl Parser {
                     _ => Err(self.expected("valid order value"))?
                 }
             }
+            // 'column++' and 'column--' are short for asc and desc
+            Some(Token { kind: TokenKind::Operator(Operator::Increment), .. }) => Operator::Increment,
+            Some(Token { kind: TokenKind::Operator(Operator::Decrement), .. }) => Operator::Decrement,
             _ => Err(self.expected("order value"))?,
         };
         self.advance();
 
         Ok(Box::new(Node::Expression(ast::Expression::Unary {
             operator: order.to_ast_operator(),
-            right: Box::new(Node::Literal(ast::Literal::Identifier(column)))
+            right: Box::new(key)
         })))
     }
 
@@ -1085,7 +1614,33 @@ impl Parser {
         }
     }
 
-    fn query_exclude(&mut self) -> Result<Vec<String>, ParserError> {
+    /// Parses an optional 'returning' clause, '*' or a list of expressions
+    fn query_returning(&mut self) -> Result<Option<Vec<Node>>, ParserError> {
+        match self.current() {
+            Some(token) if token.kind == TokenKind::Query(QueryKeyword::Returning) => self.advance(),
+            _ => return Ok(None)
+        }
+
+        let mut columns = Vec::new();
+        loop {
+            match self.current() {
+                Some(token) if token.kind == TokenKind::Operator(Operator::Multiply) => {
+                    columns.push(Node::Literal(ast::Literal::Identifier("*".to_string(), self.current)));
+                    self.advance();
+                },
+                _ => columns.push(self.expression()?)
+            }
+
+            match self.current() {
+                Some(token) if token.kind == TokenKind::Symbol(Symbol::Comma) => self.advance(),
+                _ => break
+            }
+        }
+
+        Ok(Some(columns))
+    }
+
+    fn query_exclude(&mut self) -> Result<Vec<(String, usize)>, ParserError> {
         self.expect(TokenKind::Query(QueryKeyword::Exclude))?;
 
         let mut parser_error = ParserError::empty();
@@ -1097,7 +1652,7 @@ impl Parser {
                 TokenKind::Symbol(Symbol::Semicolon) => { break; },
                 TokenKind::Symbol(Symbol::Comma) => { },
                 TokenKind::Identifier(ref column) => {
-                    columns.push(column.clone());
+                    columns.push((column.clone(), self.current));
                     self.advance();
                 },
                 _ => parser_error.add(self.expected("column name"))
@@ -1149,6 +1704,10 @@ impl Parser {
                 TokenKind::SDL(SDLKeyword::Policy) => self.create_policy()?,
                 TokenKind::SDL(SDLKeyword::User) => self.create_user()?,
                 TokenKind::SDL(SDLKeyword::Role) => self.create_role()?,
+                TokenKind::Identifier(ref ident) if ident == "index" => self.create_index()?,
+                TokenKind::Identifier(ref ident) if ident == "trigger" => self.create_trigger()?,
+                TokenKind::Keyword(Keyword::Function) => self.create_function()?,
+                TokenKind::Identifier(ref ident) if ident == "or" => self.create_function()?,
                 _ => Err(self.expected("valid sdl create object"))?
             },
             None => Err(self.expected("sdl type"))?
@@ -1238,6 +1797,38 @@ impl Parser {
                     "i64" => ColumnType::Numeric(NumericType::IntI64),
                     "f32" => ColumnType::Numeric(NumericType::Float32),
                     "f64" => ColumnType::Numeric(NumericType::Float64),
+                    "decimal" => {
+                        self.advance();
+                        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+                        let token = self.current_token("decimal precision")?;
+                        let precision = match &token.kind {
+                            TokenKind::Literal(Literal::Int(precision)) => precision.parse::<u8>().ok(),
+                            _ => None
+                        };
+                        let precision = match precision {
+                            Some(precision) if (1..=MAX_PRECISION).contains(&precision) => precision,
+                            _ => Err(self.expected(format!("decimal precision between 1 and {}", MAX_PRECISION)))?
+                        };
+                        self.advance();
+
+                        self.expect(TokenKind::Symbol(Symbol::Comma))?;
+
+                        let token = self.current_token("decimal scale")?;
+                        let scale = match &token.kind {
+                            TokenKind::Literal(Literal::Int(scale)) => scale.parse::<u8>().ok(),
+                            _ => None
+                        };
+                        let scale = match scale {
+                            Some(scale) if scale <= precision => scale,
+                            _ => Err(self.expected("decimal scale not larger than the precision"))?
+                        };
+                        self.advance();
+
+                        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+                        return Ok(ColumnType::Numeric(NumericType::Decimal(precision, scale)))
+                    },
 
                     "char" => ColumnType::Text(TextType::Char),
                     "variable" => Err(self.expected("fixed size, variable size not supported"))?, 
@@ -1259,6 +1850,10 @@ impl Parser {
 
                     "time" => {
                         self.advance();
+                        // 'time' without a unit is a time of day
+                        if !matches!(self.current(), Some(Token { kind: TokenKind::Symbol(Symbol::LeftParenthesis), .. })) {
+                            return Ok(ColumnType::Timestamp(TimestampType::Time))
+                        }
                         self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
                         
                         let token = self.current_token("timestamp type")?;
@@ -1269,6 +1864,7 @@ impl Parser {
                                     "ms" => TimestampType::Milliseconds,
                                     "us" => TimestampType::Microseconds,
                                     "ns" => TimestampType::Nanoseconds,
+                                    "tz" => TimestampType::Zoned,
                                     _ => Err(self.expected("valid timestamp type"))? 
                                 }
                             }
@@ -1280,8 +1876,43 @@ impl Parser {
                         return Ok(ColumnType::Timestamp(time_type))
                     },
 
+                    "date" => ColumnType::Timestamp(TimestampType::Date),
+                    "interval" => ColumnType::Timestamp(TimestampType::Interval),
+
                     "bool" => ColumnType::Boolean,
 
+                    "vector" => {
+                        self.advance();
+                        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+                        let token = self.current_token("vector dimension")?;
+                        let dimension = match &token.kind {
+                            TokenKind::Literal(Literal::Int(dimension)) => dimension.parse().unwrap(),
+                            _ => Err(self.expected("vector dimension"))?
+                        };
+                        self.advance();
+
+                        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+                        return Ok(ColumnType::Vector(dimension))
+                    },
+
+                    "json" => {
+                        self.advance();
+                        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+
+                        let token = self.current_token("json size")?;
+                        let size = match &token.kind {
+                            TokenKind::Literal(Literal::Int(size)) => size.parse().unwrap(),
+                            _ => Err(self.expected("json size"))?
+                        };
+                        self.advance();
+
+                        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+                        return Ok(ColumnType::Json(size))
+                    },
+
                     _ => Err(self.expected("valid column type"))?
                 }
             },
@@ -1312,12 +1943,16 @@ impl Parser {
         let table = self.identifier_name()?;
         self.expect(TokenKind::Symbol(Symbol::Period))?;
         
+        // words after a '.' are never keywords, the action is read as an identifier
         let action = match self.current_token("policy action")?.kind {
-            TokenKind::Query(QueryKeyword::Select) => RlsAction::Select,
-            TokenKind::Query(QueryKeyword::Insert) => RlsAction::Insert,
-            TokenKind::Query(QueryKeyword::Update) => RlsAction::Update,
-            TokenKind::Query(QueryKeyword::Delete) => RlsAction::Delete,
-            TokenKind::Identifier(ref ident) if ident == "all" => RlsAction::All,
+            TokenKind::Identifier(ref ident) => match ident.as_str() {
+                "select" => RlsAction::Select,
+                "insert" => RlsAction::Insert,
+                "update" => RlsAction::Update,
+                "delete" => RlsAction::Delete,
+                "all" => RlsAction::All,
+                _ => Err(self.expected("valid policy action"))?
+            },
             _ => Err(self.expected("valid policy action"))?
         };
         self.advance();
@@ -1352,6 +1987,77 @@ impl Parser {
         Ok(CreateSDL::Role { name })
     }
 
+    fn create_index(&mut self) -> Result<CreateSDL, ParserError> {
+        self.advance();
+        let name = self.identifier_name()?;
+
+        self.expect(TokenKind::Keyword(Keyword::On))?;
+        let table = self.identifier_name()?;
+        self.expect(TokenKind::Symbol(Symbol::LeftParenthesis))?;
+        let column = self.identifier_name()?;
+
+        // json indexes can index a path inside the column, e.g. 'data.user.id'
+        let mut path = Vec::new();
+        while let Some(Token { kind: TokenKind::Symbol(Symbol::Period), .. }) = self.current() {
+            self.advance();
+            path.push(self.identifier_name()?);
+        }
+        self.expect(TokenKind::Symbol(Symbol::RightParenthesis))?;
+
+        let kind = match self.current_token("index kind")?.kind {
+            TokenKind::Identifier(ref ident) if ident == "fulltext" && path.is_empty() => IndexKind::FullText,
+            TokenKind::Identifier(ref ident) if ident == "vector" && path.is_empty() => IndexKind::Vector,
+            TokenKind::Identifier(ref ident) if ident == "json" => IndexKind::Json(path),
+            _ => Err(self.expected("valid index kind"))?
+        };
+        self.advance();
+
+        Ok(CreateSDL::Index { name, table, column, kind })
+    }
+
+    fn create_trigger(&mut self) -> Result<CreateSDL, ParserError> {
+        self.advance();
+        let name = self.identifier_name()?;
+
+        let timing = match self.current_token("trigger timing")?.kind {
+            TokenKind::Identifier(ref ident) if ident == "before" => TriggerTiming::Before,
+            TokenKind::Identifier(ref ident) if ident == "after" => TriggerTiming::After,
+            _ => Err(self.expected("valid trigger timing"))?
+        };
+        self.advance();
+
+        let event = match self.current_token("trigger event")?.kind {
+            TokenKind::Query(QueryKeyword::Insert) => TriggerEvent::Insert,
+            TokenKind::Query(QueryKeyword::Update) => TriggerEvent::Update,
+            TokenKind::Query(QueryKeyword::Delete) => TriggerEvent::Delete,
+            _ => Err(self.expected("valid trigger event"))?
+        };
+        self.advance();
+
+        self.expect(TokenKind::Keyword(Keyword::On))?;
+        let table = self.identifier_name()?;
+        self.expect(TokenKind::SDL(SDLKeyword::Execute))?;
+        let function = self.identifier_name()?;
+
+        let trigger = Box::new(Trigger::new(&name, timing, event, &function));
+        Ok(CreateSDL::Trigger { table, trigger })
+    }
+
+    /// Parses 'create [or replace] fn name(...) -> type { ... }'
+    fn create_function(&mut self) -> Result<CreateSDL, ParserError> {
+        let replace = matches!(self.current_token("function")?.kind, TokenKind::Identifier(ref ident) if ident == "or");
+        if replace {
+            self.advance();
+            match self.current_token("replace")?.kind {
+                TokenKind::Identifier(ref ident) if ident == "replace" => self.advance(),
+                _ => Err(self.expected("replace"))?
+            };
+        }
+
+        let (name, parameters, return_type, block, token) = self.function_declaration()?;
+        Ok(CreateSDL::Function { name, parameters, return_type, block: Box::new(block), replace, token })
+    }
+
     fn grant(&mut self) -> Result<Node, ParserError> {
         self.expect(TokenKind::SDL(SDLKeyword::Grant))?;
         
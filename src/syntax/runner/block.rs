@@ -25,13 +25,15 @@ impl Runner {
     }
 
     pub(super) fn eval_block(&self, nodes: &Vec<Node>, ctx: &Ctx) -> Result<BlockResult, String> {
-        let len = nodes.len() - 1;
+        // loops evaluate their block on every iteration, even an empty one
+        self.check_limits(ctx)?;
+        let len = nodes.len().saturating_sub(1);
         let ctx = &Ctx::scoped(ctx.clone());
 
         for (i, node) in nodes.iter().enumerate() {
             if let Node::Statement(statement) = node {
                 match statement {
-                    Statement::Return(value) => {
+                    Statement::Return { value, .. } => {
                         let value = match self.run(value, ctx) {
                             Ok(value) => value,
                             Err(e) => return Err(e)
@@ -41,14 +43,14 @@ impl Runner {
                             None => return Err("Cannot return a statement without a value".to_string())
                         }
                     }
-                    Statement::Break => {
+                    Statement::Break { .. } => {
                         if !*self.inside_loop.borrow() {
                             Err("Break outside of loop".to_string())?
                         }
                         self.break_loop.replace(true);
                         return Ok(BlockResult::Break);
                     },
-                    Statement::Continue => {
+                    Statement::Continue { .. } => {
                         if !*self.inside_loop.borrow() {
                             Err("Continue outside of loop".to_string())?
                         }
@@ -61,7 +63,11 @@ impl Runner {
 
             let value = match self.run(node, ctx) {
                 Ok(value) => value,
-                Err(e) => return Err(e)
+                Err(e) => {
+                    // outer blocks overwrite it, a 'try' reads the index of its own statement
+                    self.failed_statement.replace(Some(i));
+                    return Err(e)
+                }
             };
 
             if *self.break_loop.borrow() { return Ok(BlockResult::Break); }
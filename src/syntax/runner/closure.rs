@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+
+use crate::{syntax::{ast::{Node, Type, Literal, Statement, Expression, Query, SelectQuery}, context::{RunnerContextScope, RunnerContextVariable}}, basics::Value, function::{Function, Closure}, auth::{Authorize, action::FunctionAction}};
+
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
+
+impl Runner {
+    /// Creates a function value, the cells of the variables used by its body are captured from
+    /// the scope it's created in
+    pub(super) fn eval_lambda(&self, parameters: &[(String, Type)], return_type: &Type, block: &Node, ctx: &Ctx) -> RunnerResult {
+        let mut function = Function::custom("anonymous", parameters, return_type, block);
+        // anonymous functions are not database objects, there is no privilege to execute them
+        function.restricted = false;
+
+        let mut names = BTreeSet::new();
+        identifiers(block, &mut names);
+
+        let captured = names.into_iter()
+            .filter(|name| !parameters.iter().any(|(parameter, _)| parameter == name))
+            .filter_map(|name| Some((name.clone(), ctx.get(&name).ok()?)))
+            .collect();
+
+        Ok(Some(Value::Function(Closure::new(function, captured))))
+    }
+
+    /// Calls a function value, its captured variables are shared with the scope it was created in
+    pub fn call_closure(&self, closure: &Closure, arguments: Vec<Value>, ctx: &Ctx) -> RunnerResult {
+        let function = &closure.function;
+        if function.restricted {
+            function.authorize(ctx.cluster_user(), FunctionAction::Execute).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+        }
+
+        if function.params.len() != arguments.len() {
+            return Err(format!("Function '{}' expects {} arguments, got {}", function.name, function.params.len(), arguments.len()));
+        }
+
+        let captured = closure.captured();
+        if captured.is_empty() {
+            return self.execute_function(function, arguments, ctx)
+        }
+
+        let scope = &Ctx::scoped(ctx.clone());
+        for (name, value) in captured {
+            scope.share(&name, value);
+        }
+
+        self.execute_function(function, arguments, scope)
+    }
+}
+
+/// Collects every name used by a node, names which are not variables are ignored when capturing
+pub(super) fn identifiers(node: &Node, names: &mut BTreeSet<String>) {
+    match node {
+        Node::Block(nodes) => nodes.iter().for_each(|node| identifiers(node, names)),
+        Node::Literal(literal) => literal_identifiers(literal, names),
+        Node::Statement(statement) => match statement {
+            Statement::Assignment { name, value, .. } => {
+                names.insert(name.clone());
+                identifiers(value, names);
+            },
+            Statement::Let { value, .. } => identifiers(value, names),
+            Statement::MemberAssignment { object, value, .. } => {
+                identifiers(object, names);
+                identifiers(value, names);
+            },
+            Statement::Expression(expression) => expression_identifiers(expression, names),
+            Statement::Function { block, .. } => identifiers(block, names),
+            Statement::Return { value, .. } => identifiers(value, names),
+            Statement::If { condition, then_block, else_block, .. } => {
+                identifiers(condition, names);
+                identifiers(then_block, names);
+                if let Some(else_block) = else_block {
+                    identifiers(else_block, names);
+                }
+            },
+            Statement::While { condition, block, .. } => {
+                identifiers(condition, names);
+                identifiers(block, names);
+            },
+            Statement::For { initializer, condition, action, block, .. } => {
+                [initializer, condition, action, block].into_iter().for_each(|node| identifiers(node, names));
+            },
+            Statement::ForIn { iterable, block, .. } => {
+                identifiers(iterable, names);
+                identifiers(block, names);
+            },
+            Statement::Loop { block } => identifiers(block, names),
+            Statement::Throw { value, .. } => identifiers(value, names),
+            Statement::Export { declaration: node, .. } => identifiers(node, names),
+            Statement::Import { .. } => {},
+            Statement::Try { block, catch_block, .. } => {
+                identifiers(block, names);
+                identifiers(catch_block, names);
+            },
+            Statement::Break { .. } | Statement::Continue { .. } => {},
+        },
+        Node::Expression(expression) => expression_identifiers(expression, names),
+        Node::Query(query) => query_identifiers(query, names),
+        Node::Value(_) | Node::SDL(_) => {},
+    }
+}
+
+fn literal_identifiers(literal: &Literal, names: &mut BTreeSet<String>) {
+    match literal {
+        Literal::Identifier(name, _) => { names.insert(name.clone()); },
+        Literal::Array(nodes) => nodes.iter().for_each(|node| identifiers(node, names)),
+        Literal::Object(entries) => entries.iter().for_each(|(_, node)| identifiers(node, names)),
+        _ => {},
+    }
+}
+
+fn expression_identifiers(expression: &Expression, names: &mut BTreeSet<String>) {
+    match expression {
+        Expression::Binary { left, right, .. } => {
+            identifiers(left, names);
+            identifiers(right, names);
+        },
+        Expression::Unary { right, .. } => identifiers(right, names),
+        Expression::Call { name, arguments, .. } => {
+            names.insert(name.clone());
+            arguments.iter().for_each(|node| identifiers(node, names));
+        },
+        Expression::Index { object, index } => {
+            identifiers(object, names);
+            identifiers(index, names);
+        },
+        Expression::Member { object, .. } | Expression::Dereference(object) => identifiers(object, names),
+        Expression::Literal(literal) => literal_identifiers(literal, names),
+        Expression::Window(window) => {
+            window.arguments.iter().chain(&window.partition).chain(window.order.iter().map(|(node, _)| node))
+                .for_each(|node| identifiers(node, names));
+        },
+        Expression::Lambda { block, .. } => identifiers(block, names),
+    }
+}
+
+fn query_identifiers(query: &Query, names: &mut BTreeSet<String>) {
+    match query {
+        Query::Select(select) => select_identifiers(select, names),
+        Query::Insert(insert) => {
+            insert.rows.iter().flatten().for_each(|(_, _, node)| identifiers(node, names));
+            insert.returning.iter().flatten().for_each(|node| identifiers(node, names));
+        },
+        Query::Update(update) => {
+            update.key_values.iter().for_each(|(_, _, node)| identifiers(node, names));
+            update.where_clause.iter().for_each(|node| identifiers(node, names));
+            update.returning.iter().flatten().for_each(|node| identifiers(node, names));
+        },
+        Query::Delete(delete) => {
+            delete.where_clause.iter().for_each(|node| identifiers(node, names));
+            delete.returning.iter().flatten().for_each(|node| identifiers(node, names));
+        },
+        Query::With(with) => {
+            for cte in &with.ctes {
+                select_identifiers(&cte.query, names);
+                if let Some(union) = &cte.union {
+                    select_identifiers(&union.query, names);
+                }
+            }
+            query_identifiers(&with.query, names);
+        },
+    }
+}
+
+fn select_identifiers(select: &SelectQuery, names: &mut BTreeSet<String>) {
+    select.columns.iter().for_each(|node| identifiers(node, names));
+    select.joins.iter().for_each(|join| identifiers(&join.on, names));
+    select.where_clause.iter().chain(&select.order).for_each(|node| identifiers(node, names));
+}
@@ -0,0 +1,111 @@
+use crate::{syntax::{ast::{WithQuery, Cte}, context::{Ctx, RunnerContextScope}}, basics::{Value, Row, Table, Column}};
+
+use super::{Runner, RunnerResult};
+
+/// Maximum number of iterations of a recursive common table expression
+const MAX_RECURSION: usize = 10_000;
+
+impl Runner {
+    pub(super) fn eval_with(&self, with: &WithQuery, ctx: &Ctx) -> RunnerResult {
+        let ctx = &Ctx::scoped(ctx.clone());
+
+        // each expression can use the ones declared before it
+        for cte in &with.ctes {
+            let table = self.eval_cte(cte, with.recursive, ctx)?;
+            ctx.add_cte(table);
+        }
+
+        self.eval_query(&with.query, ctx)
+    }
+
+    /// Evaluates a common table expression into a temporary table
+    fn eval_cte(&self, cte: &Cte, recursive: bool, ctx: &Ctx) -> Result<Table, String> {
+        let (columns, mut rows) = self.eval_select_rows(&cte.query, ctx)?;
+        let columns = match cte.columns.len() {
+            0 => columns,
+            n if n == columns.len() => columns.into_iter().zip(&cte.columns).map(|(column, name)| {
+                Column::new(name, column.data_type)
+            }).collect(),
+            n => return Err(format!("Expression '{}' has {} columns, but {} column names were specified", cte.name, columns.len(), n))
+        };
+
+        let union = match &cte.union {
+            Some(union) => union,
+            None => return Ok(cte_table(&cte.name, &columns, rows))
+        };
+
+        if !union.all {
+            rows = distinct(vec![], rows);
+        }
+
+        if !recursive {
+            let (union_columns, union_rows) = self.eval_select_rows(&union.query, ctx)?;
+            check_union_columns(cte, &columns, &union_columns)?;
+
+            match union.all {
+                true => rows.extend(union_rows),
+                false => rows = distinct(rows, union_rows),
+            }
+            return Ok(cte_table(&cte.name, &columns, rows))
+        }
+
+        // the recursive term only sees rows produced by the previous iteration
+        let mut working = rows.clone();
+        let mut iterations = 0;
+        while !working.is_empty() {
+            iterations += 1;
+            if iterations > MAX_RECURSION {
+                return Err(format!("Recursive expression '{}' exceeded {} iterations", cte.name, MAX_RECURSION))
+            }
+
+            let ctx = &Ctx::scoped(ctx.clone());
+            ctx.add_cte(cte_table(&cte.name, &columns, working));
+
+            let (union_columns, union_rows) = self.eval_select_rows(&union.query, ctx)?;
+            check_union_columns(cte, &columns, &union_columns)?;
+
+            working = match union.all {
+                true => union_rows,
+                false => {
+                    let known = rows.len();
+                    rows = distinct(rows, union_rows);
+                    rows.split_off(known)
+                }
+            };
+            rows.extend(working.iter().cloned());
+        }
+
+        Ok(cte_table(&cte.name, &columns, rows))
+    }
+}
+
+fn check_union_columns(cte: &Cte, columns: &[Column], union_columns: &[Column]) -> Result<(), String> {
+    if columns.len() != union_columns.len() {
+        return Err(format!("Union in expression '{}' must select {} columns, got {}", cte.name, columns.len(), union_columns.len()))
+    }
+    Ok(())
+}
+
+/// Appends rows which are not yet present in 'rows'
+fn distinct(mut rows: Vec<Value>, new_rows: Vec<Value>) -> Vec<Value> {
+    for row in new_rows {
+        if !rows.contains(&row) {
+            rows.push(row)
+        }
+    }
+    rows
+}
+
+fn cte_table(name: &str, columns: &[Column], rows: Vec<Value>) -> Table {
+    let mut table = Table::new(name);
+    table.columns = columns.to_vec();
+    table.rls_enabled = false;
+
+    table.data.buf_rows = rows.into_iter().map(|row| match row {
+        Value::Array(values) => Row::from_values(values),
+        value => Row::from_values(vec![value]),
+    }).collect();
+    table.data.buffer_apply();
+
+    table
+}
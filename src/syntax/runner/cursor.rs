@@ -0,0 +1,589 @@
+use std::{collections::HashMap, ptr, vec::IntoIter};
+
+use crate::{syntax::{ast::{SelectQuery, Node, Literal, Operator, Expression}, context::{RunnerContextScope, RunnerContextFields}, stringify::ToSchemaString}, basics::{Value, Row, Table, Column, column::{ColumnType, TextType}}, auth::{Authorize, action::TableAction, RlsAction}, database::Database, file::data::{LoadMode, RowStream}};
+
+use super::{Runner, Ctx, error::ErrorKind};
+
+/// Select query whose matching rows are located when it is opened, but built only when fetched
+pub struct SelectCursor {
+    columns: Vec<Column>,
+    /// Number of rows built so far, checked against the maximum result rows
+    fetched: usize,
+    selection: Selection,
+    source: CursorSource,
+}
+
+/// Resolved select columns
+struct Selection {
+    /// one entry is (table_index, column_index)
+    columns: Vec<(usize, usize)>,
+    /// indexes of expression columns in the select query
+    special: Vec<usize>,
+}
+
+enum CursorSource {
+    /// Joined row index paired with the row index in every joined table, 'None' is a missing
+    /// outer join row
+    Memory {
+        rows: IntoIter<(usize, Vec<Option<usize>>)>,
+        /// key: selected_at, value: window function values by joined row index
+        windows: HashMap<usize, HashMap<usize, Value>>,
+    },
+    /// Rows read from the table file, filtered while fetching
+    Disk {
+        stream: RowStream,
+        skip: usize,
+        remaining: Option<usize>,
+    },
+}
+
+impl SelectCursor {
+    /// Columns of the fetched rows
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Consumes the cursor, returning its columns
+    pub fn into_columns(self) -> Vec<Column> {
+        self.columns
+    }
+
+    /// Builds at most 'amount' next rows, an empty result means the cursor is exhausted.
+    ///
+    /// # Note
+    /// 'select' and 'ctx' must be the same ones the cursor was opened with. Rows deleted after
+    /// the cursor was opened are skipped, updated rows are returned with their new values.
+    pub fn fetch(&mut self, runner: &Runner, select: &SelectQuery, amount: usize, ctx: &Ctx) -> Result<Vec<Value>, String> {
+        let database = runner.database.read();
+        let mut result = vec![];
+
+        match &mut self.source {
+            CursorSource::Memory { rows, windows } => {
+                let mut tables = vec![Runner::cursor_table(&database, &select.table, ctx)?];
+                for join in &select.joins {
+                    tables.push(Runner::cursor_table(&database, &join.table, ctx)?);
+                }
+
+                let table = unsafe { &*tables[0] };
+                let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+                let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
+                ctx.set_joined_tables(&tables);
+
+                // locate the rows of this batch, rows deleted after the cursor was opened are skipped
+                let mut joined_rows = vec![];
+                let mut joined_indexes = vec![];
+                while joined_rows.len() < amount {
+                    let (joined_index, positions) = match rows.next() {
+                        Some(row) => row,
+                        None => break
+                    };
+
+                    let mut joined_row = Vec::with_capacity(positions.len());
+                    for (table, position) in tables.iter().zip(positions) {
+                        let table = unsafe { &**table };
+                        match position {
+                            Some(position) => match table.data.get(position) {
+                                Some(row) => joined_row.push(row as *const Row),
+                                None => return Err(format!("Table '{}' was purged while the cursor was open", table.name))
+                            },
+                            None => joined_row.push(ptr::null())
+                        }
+                    }
+
+                    if joined_row.iter().any(|row| !row.is_null() && unsafe { &**row }.is_deleted()) {
+                        continue
+                    }
+
+                    joined_rows.push(joined_row);
+                    joined_indexes.push(joined_index);
+                }
+
+                // expression columns of large batches are evaluated across threads
+                let expressions = self.selection.special.iter().copied()
+                    .filter(|&i| !matches!(select.columns[i], Node::Expression(Expression::Window(_))))
+                    .collect::<Vec<_>>();
+                let nodes = expressions.iter().map(|&i| &select.columns[i]).collect::<Vec<_>>();
+                let threads = runner.scan_threads(&nodes, joined_rows.len(), ctx);
+                let computed = if nodes.is_empty() || threads < 2 {
+                    vec![]
+                } else {
+                    let all_rows = (0..joined_rows.len()).collect::<Vec<_>>();
+                    runner.eval_parallel(&nodes, &joined_rows, &all_rows, threads, ctx)?
+                };
+                let mut computed = computed.into_iter();
+
+                let null_base_row = Row::from_values(vec![Value::Null; table.columns.len()]);
+                for (joined_row, joined_index) in joined_rows.iter().zip(joined_indexes) {
+                    let mut values = computed.next().unwrap_or_default();
+                    let row = runner.build_row(select, &self.selection, joined_row, &null_base_row, |selected_at| {
+                        match expressions.iter().position(|&i| i == selected_at) {
+                            Some(position) if !values.is_empty() => Some(std::mem::replace(&mut values[position], Value::Null)),
+                            _ => windows.get_mut(&selected_at).and_then(|values| values.remove(&joined_index)),
+                        }
+                    }, ctx)?;
+                    self.fetched += 1;
+                    runner.check_rows(self.fetched, ctx)?;
+                    result.push(row);
+                }
+            },
+            CursorSource::Disk { stream, skip, remaining } => {
+                let table = match database.get_table(&select.table) {
+                    Some(table) => table,
+                    None => return Err(format!("Table '{}' does not exist in database '{}'", select.table, database.name))
+                };
+
+                let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+                let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
+                ctx.set_joined_tables(&vec![table as *const Table]);
+                let policies = table.police(ctx.cluster_user(), RlsAction::Select);
+
+                let null_base_row = Row::from_values(vec![Value::Null; table.columns.len()]);
+                while result.len() < amount && *remaining != Some(0) {
+                    let row = match stream.next() {
+                        Some(row) => row?,
+                        None => break
+                    };
+                    if row.is_deleted() { continue }
+
+                    // the row is read by policies and the where clause as '<column>' and
+                    // '<table>.<column>', the context references it only during this iteration
+                    let joined_row = vec![&row as *const Row];
+                    ctx.set_row(&row);
+                    ctx.set_joined_row(&joined_row);
+                    let built = (|| {
+                        // check rls
+                        if !runner.eval_policies(&policies, ctx)? || !runner.eval_where(select, ctx)? {
+                            return Ok(None)
+                        }
+
+                        if *skip > 0 {
+                            *skip -= 1;
+                            return Ok(None)
+                        }
+                        if let Some(remaining) = remaining.as_mut() {
+                            *remaining -= 1;
+                        }
+
+                        runner.build_row(select, &self.selection, &joined_row, &null_base_row, |_| None, ctx).map(Some)
+                    })();
+                    ctx.clear_rows();
+
+                    if let Some(row) = built? {
+                        self.fetched += 1;
+                        runner.check_rows(self.fetched, ctx)?;
+                        result.push(row);
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Runner {
+    /// Opens a cursor over the rows matching a select query, it runs authorization, rls, where,
+    /// window, order, offset and limit evaluation. Tables with load mode 'disk' are filtered
+    /// lazily while fetching.
+    pub fn open_cursor(&self, select: &SelectQuery, ctx: &Ctx) -> Result<SelectCursor, String> {
+        let database = self.database.read();
+        // common table expressions shadow database tables
+        let cte = ctx.get_cte(&select.table);
+        let table = match cte.as_deref() {
+            Some(table) => table,
+            None => match database.get_table(&select.table) {
+                Some(table) => table,
+                None => return Err(self.raise(ErrorKind::NotFound, format!("Table '{}' does not exist in database '{}'", select.table, database.name)))
+            }
+        };
+
+        if table.data.load_mode == LoadMode::Disk {
+            return self.open_disk_cursor(table, select, ctx)
+        }
+
+        // Perform joins on base table, it also runs authorization checks and rls checks
+        let joined_tables = self.perform_joins(table, &select.joins, ctx)?;
+
+        let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+        let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
+        ctx.set_joined_tables(&joined_tables.tables);
+
+        let (selection, columns) = self.select_columns(select, table, &joined_tables.tables)?;
+        let null_base_row = Row::from_values(vec![Value::Null; table.columns.len()]);
+
+        // json indexes narrow down the rows the where clause is evaluated on
+        let scan_rows = match self.json_candidates(select, table, ctx)? {
+            Some(candidates) => (0..joined_tables.data.len()).filter(|&i| {
+                let row = joined_tables.data[i][0];
+                !row.is_null() && table.data.index_of(unsafe { &*row }).is_some_and(|index| candidates.contains(&index))
+            }).collect::<Vec<_>>(),
+            None => (0..joined_tables.data.len()).collect(),
+        };
+
+        // evaluate where clause on each joined row, large scans are split across threads
+        let mut row_indexes = vec![];
+        let threads = match &select.where_clause {
+            Some(node) => self.scan_threads(&[node.as_ref()], scan_rows.len(), ctx),
+            None => 1
+        };
+        if threads > 1 {
+            let where_clause = select.where_clause.as_deref().unwrap();
+            let results = self.eval_parallel(&[where_clause], &joined_tables.data, &scan_rows, threads, ctx)?;
+
+            for (i, mut values) in scan_rows.into_iter().zip(results) {
+                if where_result(values.pop())? {
+                    row_indexes.push(i)
+                }
+            }
+        } else {
+            for i in scan_rows {
+                let joined_row = &joined_tables.data[i];
+                ctx.set_joined_row(joined_row);
+                let unsafe_row = joined_row.first().expect("Joined row has no base table row");
+                if unsafe_row.is_null() {
+                    ctx.set_row(&null_base_row);
+                } else {
+                    let row = unsafe { &*(*unsafe_row) };
+                    ctx.set_row(row);
+                }
+
+                if self.eval_where(select, ctx)? {
+                    row_indexes.push(i)
+                }
+            }
+        }
+
+        // nearest-neighbour queries only sort rows found by the vector index
+        if let Some(candidates) = self.nearest_candidates(select, table, ctx)? {
+            row_indexes.retain(|&i| {
+                let row = joined_tables.data[i][0];
+                !row.is_null() && table.data.index_of(unsafe { &*row }).is_some_and(|index| candidates.contains(&index))
+            });
+        }
+
+        // evaluate window functions over the filtered rows, before they are sorted and limited
+        let mut windows = HashMap::new();
+        for &selected_at in &selection.special {
+            if let Node::Expression(Expression::Window(window)) = &select.columns[selected_at] {
+                let values = self.eval_window(window, &joined_tables.data, &row_indexes, &null_base_row, ctx)?;
+                windows.insert(selected_at, values);
+            }
+        }
+
+        // sort rows by an expression, e.g. a full-text relevance score
+        let order_expression = match select.order.as_deref() {
+            Some(Node::Expression(Expression::Unary { operator, right })) => match right.as_ref() {
+                Node::Literal(Literal::Identifier(..)) => None,
+                node => Some((node, operator)),
+            },
+            _ => None
+        };
+        if let Some((node, operator)) = order_expression {
+            let ascending = match operator {
+                Operator::Inc => true,
+                Operator::Dec => false,
+                _ => return Err("Order operator must be 'inc' or 'dec'".to_string())
+            };
+
+            let mut keys = HashMap::with_capacity(row_indexes.len());
+            for &i in &row_indexes {
+                let joined_row = &joined_tables.data[i];
+                ctx.set_joined_row(joined_row);
+                let unsafe_row = joined_row.first().expect("Joined row has no base table row");
+                if unsafe_row.is_null() {
+                    ctx.set_row(&null_base_row);
+                } else {
+                    ctx.set_row(unsafe { &*(*unsafe_row) });
+                }
+
+                keys.insert(i, self.run(node, ctx)?.unwrap_or(Value::Null));
+            }
+
+            row_indexes.sort_by(|i, j| {
+                let (a, b) = (&keys[i], &keys[j]);
+                let ordering = if ascending { a.partial_cmp(b) } else { b.partial_cmp(a) };
+                ordering.expect("Cannot compare values")
+            })
+        }
+
+        // sort rows by a column
+        if let Some(order) = select.order.as_ref().filter(|_| order_expression.is_none()) {
+            let order = match order.as_ref() {
+                Node::Expression(Expression::Unary { operator, right }) => {
+                    let name = match **right {
+                        Node::Literal(Literal::Identifier(ref name, _)) => name,
+                        _ => return Err("Order node must be a column name".to_string())
+                    };
+
+                    let ascending = match operator {
+                        Operator::Inc => true,
+                        Operator::Dec => false,
+                        _ => return Err("Order operator must be 'inc' or 'dec'".to_string())
+                    };
+
+                    // TODO: use check_exists in all uses of get_column
+                    if table.get_column(name).is_none() {
+                        return Err(format!("Column '{}' does not exist in table '{}'", name, table.name))
+                    }
+
+                    let column_i = table.get_column_index(name).unwrap();
+                    let table_i = 0;
+
+                    (table_i, column_i, ascending)
+                },
+                _ => return Err("Order must be a unary expression".to_string())
+            };
+
+            // sort row indexes by order column
+            row_indexes.sort_by(|&i, &j| {
+                let row_a = joined_tables.data.get(i).expect("Cannot get row with row_index")
+                    .get(order.0).expect("Cannot get joined row with order table index");
+                let row_b = joined_tables.data.get(j).expect("Cannot get row with row_index")
+                    .get(order.0).expect("Cannot get joined row with order table index");
+
+                let a = {
+                    if row_a.is_null() {
+                        &Value::Null
+                    } else {
+                        let row_a = unsafe { &*(*row_a) };
+                        row_a.get(order.1).expect("Cannot get row value with order index")
+                    }
+                };
+
+                let b = {
+                    if row_b.is_null() {
+                        &Value::Null
+                    } else {
+                        let row_b = unsafe { &*(*row_b) };
+                        row_b.get(order.1).expect("Cannot get row value with order index")
+                    }
+                };
+
+                if order.2 {
+                    a.partial_cmp(b).expect("Cannot compare values")
+                } else {
+                    b.partial_cmp(a).expect("Cannot compare values")
+                }
+            })
+        }
+
+        // offset rows
+        if let Some(offset) = select.offset {
+            row_indexes.drain(0..offset.min(row_indexes.len()));
+        }
+
+        // limit rows
+        if let Some(limit) = select.limit {
+            row_indexes.truncate(limit);
+        }
+
+        // keep row positions instead of pointers, so the database can be unlocked between fetches
+        let rows = row_indexes.into_iter().map(|i| {
+            let joined_row = &joined_tables.data[i];
+            let positions = joined_row.iter().zip(&joined_tables.tables).map(|(row, table)| {
+                if row.is_null() { return None }
+
+                let table = unsafe { &**table };
+                let position = table.data.index_of(unsafe { &**row }).expect("Joined row does not belong to its table");
+                Some(position)
+            }).collect();
+
+            (i, positions)
+        }).collect::<Vec<_>>();
+
+        Ok(SelectCursor {
+            columns,
+            fetched: 0,
+            selection,
+            source: CursorSource::Memory { rows: rows.into_iter(), windows },
+        })
+    }
+
+    /// Rows of disk tables are not stored in memory, so only plain selects are supported, which
+    /// can be evaluated row by row
+    fn open_disk_cursor(&self, table: &Table, select: &SelectQuery, ctx: &Ctx) -> Result<SelectCursor, String> {
+        let has_window = select.columns.iter().any(|node| matches!(node, Node::Expression(Expression::Window(_))));
+        if !select.joins.is_empty() || select.order.is_some() || has_window {
+            return Err(format!("Joins, order and window functions are not supported on table '{}' with load mode 'disk'", table.name))
+        }
+
+        table.authorize(ctx.cluster_user(), TableAction::Select).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+
+        let (selection, columns) = self.select_columns(select, table, &[table as *const Table])?;
+        let stream = table.data.stream(&table.columns)?;
+
+        Ok(SelectCursor {
+            columns,
+            fetched: 0,
+            selection,
+            source: CursorSource::Disk { stream, skip: select.offset.unwrap_or(0), remaining: select.limit },
+        })
+    }
+
+    /// Get a table by name, common table expressions shadow database tables. The pointer is valid
+    /// as long as the database lock and the context are
+    fn cursor_table(database: &Database, name: &str, ctx: &Ctx) -> Result<*const Table, String> {
+        if let Some(cte) = ctx.get_cte(name) {
+            return Ok(std::rc::Rc::as_ptr(&cte))
+        }
+
+        match database.get_table(name) {
+            Some(table) => Ok(table as *const Table),
+            None => Err(format!("Table '{}' does not exist in database '{}'", name, database.name))
+        }
+    }
+
+    fn eval_where(&self, select: &SelectQuery, ctx: &Ctx) -> Result<bool, String> {
+        match &select.where_clause {
+            Some(node) => where_result(self.run(node, ctx)?),
+            None => Ok(true)
+        }
+    }
+
+    /// Resolves selected columns of the joined tables, returns them with the result columns
+    fn select_columns(&self, select: &SelectQuery, table: &Table, tables: &[*const Table]) -> Result<(Selection, Vec<Column>), String> {
+        let mut special_selected_columns = vec![];
+        // one entry is (table_index, column_index)
+        let mut selected_columns = vec![];
+        // one entry is (selected_at, table_index)
+        let mut select_alls = vec![];
+        for (selected_i, node) in select.columns.iter().enumerate() {
+            match node {
+                Node::Literal(Literal::Identifier(name, _)) => {
+                    if name == "*" {
+                        // reverse order since '*' expansion is also reversed
+                        for table_index in (0..tables.len()).rev() {
+                            select_alls.push((selected_i, table_index))
+                        }
+                    } else {
+                        let table_index = 0; // base table
+                        let column_index = table.get_column_index(name)?;
+                        selected_columns.push((table_index, column_index));
+                    }
+                },
+                Node::Expression(Expression::Member { object, member, .. }) => {
+                    // members which are not 'table.column' are evaluated, e.g. json paths
+                    let table_index = match **object {
+                        Node::Literal(Literal::Identifier(ref table_name, _)) => tables.iter().position(|t| {
+                            let t = unsafe { &*(*t) };
+                            t.name == *table_name
+                        }),
+                        _ => None
+                    };
+                    let table_index = match table_index {
+                        Some(i) => i,
+                        None => {
+                            special_selected_columns.push(selected_i);
+                            continue
+                        }
+                    };
+
+                    if member == "*" {
+                        select_alls.push((selected_i, table_index))
+                    } else {
+                        let table = unsafe { &*tables[table_index] };
+                        let column_index = table.get_column_index(member)?;
+                        selected_columns.push((table_index, column_index))
+                    }
+                }
+                _ => { special_selected_columns.push(selected_i); }
+            }
+        }
+
+        // TODO: update index in special columns
+        // Prepare selected columns with select all
+        for (selected_at, table_index) in select_alls {
+            let table = unsafe { &*tables[table_index] };
+
+            let missing_columns = (0..table.columns.len()).filter_map(|i| {
+                if !selected_columns.iter().any(|(ti, ci)| *ti == table_index && *ci == i) {
+                    Some((table_index, i))
+                } else { None }
+            }).collect::<Vec<_>>();
+
+            // reversed order so we can insert at 'i' and keep correct column order
+            for missing in missing_columns.iter().rev() {
+                selected_columns.insert(selected_at, *missing);
+            }
+        }
+
+        // TODO: update index in special columns
+        // Remove excluded columns
+        if let Some(exclude) = &select.exclude {
+            let exclude = exclude.iter().map(|(name, _)| table.get_column_index(name)).collect::<Result<Vec<_>, _>>()?;
+            selected_columns.retain(|(ti, ci)| !exclude.iter().any(|i| *ci == *i && *ti == 0));
+        }
+
+        // result columns, special columns are named after their expression
+        let mut result_columns = selected_columns.iter().map(|(ti, ci)| {
+            let column = &unsafe { &*tables[*ti] }.columns[*ci];
+            Column::new(&column.name, column.data_type.clone())
+        }).collect::<Vec<_>>();
+        for &i in &special_selected_columns {
+            let name = match &select.columns[i] {
+                Node::Expression(Expression::Call { name, .. }) => name.clone(),
+                Node::Expression(Expression::Window(window)) => window.function.clone(),
+                node => node.to_schema_string(0)?.trim().to_string(),
+            };
+            let column = Column::new(&name, ColumnType::Text(TextType::Variable));
+            result_columns.insert(i.min(result_columns.len()), column);
+        }
+
+        let selection = Selection {
+            columns: selected_columns,
+            special: special_selected_columns,
+        };
+
+        Ok((selection, result_columns))
+    }
+
+    /// Builds one result row, 'precomputed' returns the value of a special column if it was
+    /// already evaluated (window functions, parallel scans)
+    fn build_row(&self, select: &SelectQuery, selection: &Selection, joined_row: &Vec<*const Row>, null_base_row: &Row, mut precomputed: impl FnMut(usize) -> Option<Value>, ctx: &Ctx) -> Result<Value, String> {
+        let mut result_row = Vec::with_capacity(selection.columns.len() + selection.special.len());
+
+        // evaluate columns
+        for (ti, ci) in &selection.columns {
+            let unsafe_row = joined_row.get(*ti).expect("Cannot get row with table index");
+            if unsafe_row.is_null() {
+                result_row.push(Value::Null);
+                continue
+            }
+
+            let row = unsafe { &*(*unsafe_row) };
+            let value = row.get(*ci).expect("Cannot get row value with column index");
+            result_row.push(value.clone());
+        }
+
+        // set row variables to be used in special columns
+        if !selection.special.is_empty() {
+            ctx.set_joined_row(joined_row);
+            let unsafe_row = joined_row[0];
+            if unsafe_row.is_null() {
+                ctx.set_row(null_base_row);
+            } else {
+                ctx.set_row(unsafe { &*unsafe_row });
+            }
+        }
+
+        // evaluate special columns
+        for &i in &selection.special {
+            let node = &select.columns[i];
+            let value = match (precomputed(i), node) {
+                (Some(value), _) => value,
+                (None, Node::Expression(Expression::Window(_))) => Value::Null,
+                (None, node) => self.run(node, ctx)?.expect("Special column must return a value"),
+            };
+            result_row.insert(i, value)
+        }
+
+        Ok(Value::Array(result_row))
+    }
+}
+
+fn where_result(value: Option<Value>) -> Result<bool, String> {
+    match value {
+        Some(Value::Boolean(value)) => Ok(value),
+        _ => Err("Where clause must return a boolean value".to_string()),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{syntax::{ast::Node, context::{RunnerContextScope, RunnerContextVariable}}, basics::{Value, Column, value::NumericValue}};
+
+use super::{Runner, Ctx, RunnerResult};
+
+impl Runner {
+    /// Records the kind of an error where it's raised, returns its message to be returned as the
+    /// error. A 'try' catching the error passes the kind to its 'catch' block
+    pub(super) fn raise(&self, kind: ErrorKind, message: String) -> String {
+        self.raised.replace(Some((message.clone(), kind)));
+        message
+    }
+
+    /// Converts 'value' to be stored in 'column'
+    pub(super) fn column_value(&self, column: &Column, value: &Value) -> Result<Value, String> {
+        column.transform_value(value).map_err(|e| self.value_error(column, value, e))
+    }
+
+    /// Raises the error of a value 'column' can't store, NULL in a not null column violates a
+    /// constraint while other values have the wrong type
+    pub(super) fn value_error(&self, column: &Column, value: &Value, message: String) -> String {
+        let kind = match value {
+            Value::Null if column.not_null => ErrorKind::Constraint,
+            _ => ErrorKind::Type,
+        };
+        self.raise(kind, message)
+    }
+
+    /// Raises an error, 'value' is either its message or an object with a 'message' and an
+    /// optional 'kind'. The error is caught by the nearest 'try' or stops the script.
+    pub(super) fn eval_throw(&self, value: &Node, line: usize, ctx: &Ctx) -> RunnerResult {
+        let value = self.run(value, ctx)?.ok_or("Cannot throw a statement without a value")?;
+
+        let error = match value {
+            Value::Text(message) => error_value("error", &message, line),
+            Value::Object(_) => {
+                if !matches!(value.get_member("message"), Some(Value::Text(_))) {
+                    return Err("Thrown objects must have a 'message' of type 'string'".to_string())
+                }
+                let kind = match value.get_member("kind") {
+                    Some(Value::Text(kind)) => kind.clone(),
+                    _ => "error".to_string(),
+                };
+
+                // a rethrown error keeps the position it was first raised at
+                let mut error = value.clone();
+                error.set_member("kind", Value::Text(kind))?;
+                if error.get_member("line").is_none() {
+                    error.set_member("line", Value::Numeric(NumericValue::IntU64(line as u64)))?;
+                }
+                error
+            },
+            value => return Err(format!("Cannot throw a value of type '{:?}', expected a message or an object", self.get_type(&value)))
+        };
+
+        let message = error_message(&error);
+        self.thrown.replace(Some((message.clone(), error)));
+        Err(message)
+    }
+
+    /// Runs 'block', an error it raises is passed to 'catch_block' as the variable 'name'
+    pub(super) fn eval_try(&self, block: &Node, lines: &[usize], name: &str, catch_block: &Node, ctx: &Ctx) -> RunnerResult {
+        self.failed_statement.replace(None);
+        self.raised.replace(None);
+
+        let message = match self.run(block, ctx) {
+            Ok(value) => return Ok(value),
+            Err(message) => message,
+        };
+
+        // a cancelled run or one over its limits has to stop, the catch block can't run
+        self.check_limits(ctx)?;
+
+        let error = match self.thrown.take() {
+            Some((thrown, error)) if thrown == message => error,
+            _ => {
+                let line = self.failed_statement.take().and_then(|i| lines.get(i)).copied().unwrap_or(0);
+                let kind = match self.raised.take() {
+                    Some((raised, kind)) if raised == message => kind.name(),
+                    _ => "runtime",
+                };
+                error_value(kind, &message, line)
+            }
+        };
+
+        let ctx = &Ctx::scoped(ctx.clone());
+        ctx.declare(name, error);
+        self.run(catch_block, ctx)
+    }
+}
+
+/// Error object with a 'kind', a 'message' and the source 'line' it was raised at (0 if unknown)
+fn error_value(kind: &str, message: &str, line: usize) -> Value {
+    Value::Object(vec![
+        ("kind".to_string(), Value::Text(kind.to_string())),
+        ("message".to_string(), Value::Text(message.to_string())),
+        ("line".to_string(), Value::Numeric(NumericValue::IntU64(line as u64))),
+    ])
+}
+
+/// Message an uncaught error stops the script with
+fn error_message(error: &Value) -> String {
+    let message = match error.get_member("message") {
+        Some(Value::Text(message)) => message.as_str(),
+        _ => "",
+    };
+
+    match error.get_member("line") {
+        Some(Value::Numeric(line)) if line.to_i128() > 0 => format!("{} at line {}", message, line),
+        _ => message.to_string(),
+    }
+}
+
+/// Kind of an error raised by the runner, errors raised without one are 'runtime' errors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ErrorKind {
+    /// A unique or not null constraint is violated
+    Constraint,
+    /// The user lacks a privilege or is denied by a row level security policy
+    Auth,
+    /// A value has the wrong type for a parameter, return value, column or operation
+    Type,
+    /// A variable, function or table doesn't exist
+    NotFound,
+}
+
+impl ErrorKind {
+    /// Name of the kind, as seen by 'catch' blocks
+    fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Constraint => "constraint",
+            ErrorKind::Auth => "auth",
+            ErrorKind::Type => "type",
+            ErrorKind::NotFound => "not_found",
+        }
+    }
+}
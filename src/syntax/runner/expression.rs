@@ -1,17 +1,19 @@
-use crate::{syntax::ast::{Expression, Node, Literal}, basics::Value};
+use crate::{syntax::{ast::{Expression, Node, Literal}, context::RunnerContextVariable}, basics::Value};
 
-use super::{Runner, Ctx, RunnerResult};
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
 
 impl Runner {
     pub(super) fn eval_expression(&self, expression: &Expression, ctx: &Ctx) -> RunnerResult {
         match expression {
-            Expression::Binary { left, operator, right }
+            Expression::Binary { left, operator, right, .. }
                 => self.eval_binary(left, operator, right, ctx), 
             Expression::Unary { operator, right } => self.eval_unary(operator, right, ctx),
-            Expression::Call { name, arguments } => self.eval_call(name, arguments, ctx),
+            Expression::Call { name, arguments, .. } => self.eval_call(name, arguments, ctx),
             Expression::Literal(value) => self.eval_literal(value, ctx),
             Expression::Index { object, index } => self.eval_index(object, index, ctx),
-            Expression::Member { object, member } => self.eval_member(object, member, ctx),
+            Expression::Member { object, member, .. } => self.eval_member(object, member, ctx),
+            Expression::Window(window) => Err(format!("Window function '{}' can only be used in select columns", window.function)),
+            Expression::Lambda { parameters, return_type, block, .. } => self.eval_lambda(parameters, return_type, block, ctx),
 
             _ => unimplemented!("expression")
         }
@@ -24,43 +26,48 @@ impl Runner {
         if let Value::Array(array) = object {
             let index = match index {
                 Value::Numeric(numeric) => numeric.to_i128(), 
-                _ => return Err(format!("Array cannot be indexed with {:?}", self.get_type(&index)))
+                _ => return Err(self.raise(ErrorKind::Type, format!("Array cannot be indexed with {:?}", self.get_type(&index))))
             };
 
-            if index < 0 || index >= array.len() as i128 || index >= usize::MAX as i128 {
-                return Err(format!("Index '{}' out of bounds, array length is {}", index, array.len()))
+            // like a missing key, an index out of bounds is null
+            if index < 0 || index >= array.len() as i128 {
+                return Ok(Some(Value::Null))
             }
 
             let value = array.into_iter().nth(index as usize).unwrap();
             return Ok(Some(value))
         }
 
-        Err(format!("Value of type {:?} cannot be indexed", self.get_type(&object)))
+        // string keys work like member notation
+        if let Value::Text(key) = index {
+            return Ok(Some(object.get_member(&key).cloned().unwrap_or(Value::Null)))
+        }
+
+        if let Value::Null = object {
+            return Ok(Some(Value::Null))
+        }
+
+        Err(self.raise(ErrorKind::Type, format!("Value of type {:?} cannot be indexed", self.get_type(&object))))
     }
 
     fn eval_member(&self, object: &Box<Node>, member: &str, ctx: &Ctx) -> RunnerResult {
-        // let object = self.run(object, ctx)?.ok_or("Object cannot be a statement with no return value")?;
-        //
-        // TODO: Implement objects
-        // if let Value::Object(object) = object {
-        //     if let Some(value) = object.get(member) {
-        //         return Ok(Some(value.clone()))
-        //     }
-        //
-        //     return Err(format!("Member '{}' not found in object", member))
-        // }
-        //
-        // Err(format!("Value of type {:?} cannot be accessed by member notation", self.get_type(&object)))
-
-        // HINT: temporary implementation to add support in joins (e.g. "users.name")
-        let table = match **object {
-            Node::Literal(Literal::Identifier(ref identifier)) => identifier,
-            _ => return Err("Currently only identifiers (table names) are supported in member notation".to_string()) 
-        };
+        // identifiers can name a trigger row or a joined table (e.g. "users.name"), a variable
+        // is only looked up if there is no such table column
+        if let Node::Literal(Literal::Identifier(ref identifier, _)) = **object {
+            if let Some(value) = self.eval_trigger_row(identifier, member, ctx) {
+                return value
+            }
 
-        let value = ctx.get_from(table, member)?;
-        let value = value.clone();
+            match ctx.get_from(identifier, member) {
+                Ok(value) => return Ok(Some(value.clone())),
+                Err(error) if ctx.get(identifier).is_err() => return Err(error),
+                Err(_) => ()
+            }
+        }
 
-        Ok(Some(value))
+        // like JSON paths, a missing key is null and so are members of values which are not
+        // objects, documents don't need the same shape to be queried
+        let object = self.run(object, ctx)?.ok_or("Object cannot be a statement with no return value")?;
+        Ok(Some(object.get_member(member).cloned().unwrap_or(Value::Null)))
     }
 }
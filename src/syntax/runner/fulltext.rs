@@ -0,0 +1,107 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{syntax::{ast::{Node, Literal, Expression}, context::{Ctx, RunnerContextFields}}, basics::{Value, Row, Table, value::NumericValue}, index::{Index, IndexKind}};
+
+use super::{Runner, RunnerResult};
+
+/// Key of cached full-text query results, (table, index, index version, query)
+pub(super) type FullTextKey = (String, String, u64, String);
+
+impl Runner {
+    /// Evaluates 'match(column, query)' and 'score(column, query)' on the current row. 'match'
+    /// returns whether the column text matches the query, 'score' returns its relevance (0 when
+    /// it does not match). The column must have a full-text index
+    pub(super) fn eval_fulltext(&self, name: &str, arguments: &[Node], ctx: &Ctx) -> RunnerResult {
+        if arguments.len() != 2 {
+            return Err(format!("Function '{}' expects 2 arguments, got {}", name, arguments.len()))
+        }
+
+        let query = match self.run(&arguments[1], ctx)? {
+            Some(Value::Text(query)) => query,
+            _ => return Err(format!("Function '{}' expects argument 'query' to be text", name))
+        };
+
+        let (column, row) = fulltext_column(&arguments[0], ctx)?;
+        let score = match row {
+            Some(row) => {
+                let row = unsafe { &*row };
+                let database = self.database.read();
+                let (table, row_index) = match database.tables.iter().find_map(|table| Some((table, table.data.index_of(row)?))) {
+                    Some(found) => found,
+                    // null base row of a right join
+                    None if row.iter().all(|value| value.is_null()) => return fulltext_result(name, None),
+                    None => return Err(format!("Function '{}' can only be used on rows of database tables", name))
+                };
+
+                let index = table.get_index(&column, IndexKind::FullText)
+                    .ok_or(format!("Column '{}' of table '{}' has no full-text index", column, table.name))?;
+
+                self.fulltext_scores(table, index, &query)?.get(&row_index).copied()
+            },
+            // null rows of outer joins never match
+            None => None
+        };
+
+        fulltext_result(name, score)
+    }
+
+    /// Returns the scores of all rows matching 'query', results are cached until the index changes
+    fn fulltext_scores(&self, table: &Table, index: &Index, query: &str) -> Result<Rc<HashMap<usize, f64>>, String> {
+        let key = (table.name.clone(), index.name.clone(), index.version(), query.to_string());
+        if let Some(scores) = self.fulltext_cache.borrow().get(&key) {
+            return Ok(scores.clone())
+        }
+
+        let scores = Rc::new(index.fulltext().expect("Index should be a full-text index").search(query)?);
+        self.fulltext_cache.borrow_mut().insert(key, scores.clone());
+
+        Ok(scores)
+    }
+}
+
+/// Resolves the column argument, a column name or 'table.column', to the column name and the
+/// current row it belongs to (None for null rows)
+fn fulltext_column(node: &Node, ctx: &Ctx) -> Result<(String, Option<*const Row>), String> {
+    match node {
+        Node::Literal(Literal::Identifier(column, _)) => {
+            let mut current = Some(ctx);
+            while let Some(ctx) = current {
+                if let (Some(column_map), Some(row)) = (ctx.column_map(), ctx.row()) {
+                    if column_map.contains_key(column) {
+                        return Ok((column.clone(), Some(row)))
+                    }
+                }
+
+                current = ctx.parent.as_ref();
+            }
+
+            Err(format!("Column '{}' not found", column))
+        },
+        Node::Expression(Expression::Member { object, member, .. }) => {
+            let table = match **object {
+                Node::Literal(Literal::Identifier(ref table, _)) => table,
+                _ => return Err("Full-text column must be a column name".to_string())
+            };
+
+            let map = ctx.current_unsafe_column_map.borrow();
+            let (table_index, _) = map.get(&(table.to_string(), member.to_string()))
+                .ok_or(format!("Column '{}' not found in table '{}'", member, table))?;
+
+            let joined_row = *ctx.current_unsafe_row.borrow();
+            if joined_row.is_null() {
+                return Err("No joined row found".to_string())
+            }
+
+            let row = unsafe { &*joined_row }[*table_index];
+            Ok((member.clone(), (!row.is_null()).then_some(row)))
+        },
+        _ => Err("Full-text column must be a column name".to_string())
+    }
+}
+
+fn fulltext_result(name: &str, score: Option<f64>) -> RunnerResult {
+    match name {
+        "match" => Ok(Some(Value::Boolean(score.is_some()))),
+        _ => Ok(Some(Value::Numeric(NumericValue::Float64(score.unwrap_or(0.0)))))
+    }
+}
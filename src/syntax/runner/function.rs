@@ -1,22 +1,41 @@
-use crate::{syntax::{ast::{Node, Type}, context::{RunnerContextScope, RunnerContextVariable}}, basics::Value, function::{Function, FunctionBody}};
+use crate::{syntax::{ast::{Node, Type}, context::{RunnerContextScope, RunnerContextVariable}}, basics::Value, function::{Function, FunctionBody, BuiltIn, Closure}, auth::{Authorize, action::FunctionAction}};
 
-use super::{Runner, Ctx, RunnerResult};
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
 
 impl Runner {
-    pub(super) fn eval_function(&self, name: &str, parameters: &Vec<(String, Type)>, return_type: &Type, block: &Box<Node>) -> RunnerResult {
-        let function = Function::custom(name, parameters, return_type, block);
+    /// Declares a function of the script in 'ctx', it only exists while the script runs
+    pub(super) fn eval_function(&self, name: &str, parameters: &[(String, Type)], return_type: &Type, block: &Node, ctx: &Ctx) -> RunnerResult {
+        self.check_declaration(name)?;
 
-        let mut database = self.database.write().map_err(|_| "Cannot create functions when in read mode")?;
-        if database.functions.contains_key(name) {
-            return Err(format!("Function '{}' already exists", name))
+        let mut function = Function::custom(name, parameters, return_type, block);
+        // functions of a script are not database objects, there is no privilege to execute them
+        function.restricted = false;
+
+        ctx.declare(name, Value::Function(Closure::new(function, vec![])));
+        Ok(None)
+    }
+
+    /// Creates a function in the database, it's written to the schema and callable by every
+    /// user with the execute privilege
+    pub(super) fn eval_create_function(&self, name: &str, parameters: &[(String, Type)], return_type: &Type, block: &Node, replace: bool, ctx: &Ctx) -> RunnerResult {
+        if !ctx.cluster_user().is_superuser {
+            return Err(self.raise(ErrorKind::Auth, "Can't create function, permission denied".to_string()));
         }
 
-        database.functions.insert(name.to_string(), function);
+        let function = Function::custom(name, parameters, return_type, block);
+
+        let mut database = self.database.write().map_err(|_| "Cannot create functions when in read mode")?;
+        database.create_function(function, replace, ctx)?;
 
         Ok(None)
     }
 
     pub(super) fn eval_call(&self, name: &str, arguments: &Vec<Node>, ctx: &Ctx) -> RunnerResult {
+        // full-text functions take the column itself, not its value
+        if name == "match" || name == "score" {
+            return self.eval_fulltext(name, arguments, ctx)
+        }
+
         let arguments = arguments.iter()
             .map(|arg| match self.run(arg, ctx) {
                     Ok(value) => match value {
@@ -27,38 +46,95 @@ impl Runner {
                 }
             )
             .collect::<Result<Vec<Value>, String>>()?; 
+        // functions of the database take precedence, the script can't replace a function which
+        // policies, defaults or triggers call
         let database = self.database.read();
-        
         if let Some(function) = database.functions.get(name) {
+            if function.restricted {
+                function.authorize(ctx.cluster_user(), FunctionAction::Execute).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+            }
+
             if function.params.len() != arguments.len() {
                 return Err(format!("Function '{}' expects {} arguments, got {}", name, function.params.len(), arguments.len()));
             }
 
-            self.execute_function(function, arguments, ctx)
-        } else {
-            Err(format!("Function '{}' not found", name))
+            return self.execute_stored_function(function, arguments, ctx)
+        }
+        drop(database);
+
+        match ctx.value(name) {
+            Ok(Value::Function(closure)) => self.call_closure(&closure, arguments, ctx),
+            Ok(value) => Err(self.raise(ErrorKind::Type, format!("Variable '{}' of type '{:?}' is not a function", name, self.get_type(&value)))),
+            Err(_) => Err(self.raise(ErrorKind::NotFound, format!("Function '{}' not found", name))),
         }
     }
 
-    fn execute_function(&self, function: &Function, arguments: Vec<Value>, ctx: &Ctx) -> RunnerResult {
+    /// Functions and variables of a script cannot take the name of a function of the database,
+    /// calls always run the function of the database
+    pub(super) fn check_declaration(&self, name: &str) -> Result<(), String> {
+        match self.database.read().functions.contains_key(name) {
+            true => Err(format!("Cannot declare '{}', the database has a function with the same name", name)),
+            false => Ok(()),
+        }
+    }
+
+    /// Runs a function of the database, its body doesn't see the variables and common table
+    /// expressions of the caller
+    pub(super) fn execute_stored_function(&self, function: &Function, arguments: Vec<Value>, ctx: &Ctx) -> RunnerResult {
+        match function.body {
+            FunctionBody::Custom(_) => self.execute_function(function, arguments, &Ctx::bounded(ctx.clone())),
+            _ => self.execute_function(function, arguments, ctx),
+        }
+    }
+
+    pub(crate) fn execute_function(&self, function: &Function, arguments: Vec<Value>, ctx: &Ctx) -> RunnerResult {
         // TODO: add type checking for builtins
         let body = match &function.body {
             FunctionBody::Custom(body) => body,
             FunctionBody::BuiltIn(function) => return function(self.database.clone(), &arguments, ctx, self),
+            FunctionBody::Native(body) => return self.execute_native(function, *body, arguments, ctx),
         };
 
         // TODO: create a new context so the fn doesn't have access to the outer scope (?)
         let ctx = &Ctx::scoped(ctx.clone());
         for ((param_name, param_type), value) in function.params.iter().zip(arguments) {
             if !self.check_type(param_type, &value) {
-                return Err(format!("Function '{}' expects argument '{}' to be of type '{:?}' but got '{:?}'",
+                return Err(self.raise(ErrorKind::Type, format!("Function '{}' expects argument '{}' to be of type '{:?}' but got '{:?}'",
                     function.name, param_name, param_type, self.get_type(&value)
-                ));
+                )));
             }
 
             ctx.declare(param_name, value);
         }
 
-        self.run(&body, ctx)
+        self.enter_function(&function.name, ctx)?;
+        let result = self.run(body, ctx);
+        self.leave_function();
+
+        result
+    }
+
+    /// Calls a native function, its arguments and return value have to match the declared types
+    fn execute_native(&self, function: &Function, body: BuiltIn, arguments: Vec<Value>, ctx: &Ctx) -> RunnerResult {
+        for ((param_name, param_type), value) in function.params.iter().zip(&arguments) {
+            if !self.check_type(param_type, value) {
+                return Err(self.raise(ErrorKind::Type, format!("Function '{}' expects argument '{}' to be of type '{:?}' but got '{:?}'",
+                    function.name, param_name, param_type, self.get_type(value)
+                )));
+            }
+        }
+
+        let result = body(self.database.clone(), &arguments, ctx, self)?;
+        // null is accepted for every return type, e.g. for lookups which found nothing
+        let valid = match &result {
+            None | Some(Value::Null) => true,
+            Some(value) => self.check_type(&function.return_type, value),
+        };
+
+        if !valid {
+            return Err(self.raise(ErrorKind::Type, format!("Function '{}' must return a value of type '{:?}'", function.name, function.return_type)))
+        }
+
+        Ok(result)
     }
 }
@@ -1,8 +1,8 @@
-use std::{ptr, collections::HashSet};
+use std::{ptr, collections::HashSet, rc::Rc};
 
 use crate::{basics::{Table, Value, Row}, syntax::{context::{Ctx, RunnerContextFields, RunnerContextScope}, ast::{Node, Join, JoinType}}, auth::{RlsAction, action::TableAction, Authorize}};
 
-use super::Runner;
+use super::{Runner, error::ErrorKind};
 
 impl Runner {
     /// Executes join operations on a base table
@@ -11,22 +11,32 @@ impl Runner {
     pub fn perform_joins(&self, base_table: &Table, joins: &Vec<Join>, ctx: &Ctx) -> Result<UnsafeJoinedTables, String> {
         let database = self.database.read();
 
-        // authorize base table
-        base_table.authorize(&ctx.cluster_user(), TableAction::Select)?;
+        // authorize base table, common table expressions were authorized when evaluated
+        if ctx.get_cte(&base_table.name).is_none() {
+            base_table.authorize(ctx.cluster_user(), TableAction::Select).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+        }
 
         // check if all tables in joins exist, and authorize them
+        let mut tables = vec![];
         for join in joins {
+            if let Some(cte) = ctx.get_cte(&join.table) {
+                // the table is kept alive by the context
+                tables.push(Rc::as_ptr(&cte));
+                continue
+            }
+
             let table = match database.get_table(&join.table) {
                 Some(table) => table,
-                None => return Err(format!("Table '{}' not found", join.table))
+                None => return Err(self.raise(ErrorKind::NotFound, format!("Table '{}' not found", join.table)))
             };
-            table.authorize(&ctx.cluster_user(), TableAction::Select)?;
+            table.authorize(ctx.cluster_user(), TableAction::Select).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+            tables.push(table as *const Table);
         }
 
         // apply joins sequentially
         let mut result = self.transform_table_into_joined(base_table, ctx)?;
-        for join in joins {
-            let current_table = database.get_table(&join.table).expect("Table should exist");
+        for (join, table) in joins.iter().zip(tables) {
+            let current_table = unsafe { &*table };
 
             result = self.apply_join(result, current_table, &join.join_type, &join.on, ctx)?;
         }
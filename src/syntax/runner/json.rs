@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+
+use crate::{syntax::ast::{Node, Literal, Expression, Operator, SelectQuery}, basics::{Table, column::ColumnType}, index::IndexKind};
+
+use super::{Runner, Ctx};
+
+impl Runner {
+    /// Returns the rows of 'table' which can match the where clause according to json indexes,
+    /// e.g. 'where data.user.id == 5'. Comparisons of an indexed json path with a constant can
+    /// be combined with 'and' and 'or', the where clause is still evaluated on every returned row.
+    ///
+    /// Returns None when every row has to be considered, which is the case for queries with
+    /// joins or where clauses without an indexed comparison
+    pub(super) fn json_candidates(&self, select: &SelectQuery, table: &Table, ctx: &Ctx) -> Result<Option<BTreeSet<usize>>, String> {
+        match &select.where_clause {
+            Some(node) if select.joins.is_empty() => self.json_clause_candidates(node, table, ctx),
+            _ => Ok(None)
+        }
+    }
+
+    fn json_clause_candidates(&self, node: &Node, table: &Table, ctx: &Ctx) -> Result<Option<BTreeSet<usize>>, String> {
+        let (left, operator, right) = match node {
+            Node::Expression(Expression::Binary { left, operator, right, .. }) => (left, operator, right),
+            _ => return Ok(None)
+        };
+
+        match operator {
+            Operator::And => {
+                let left = self.json_clause_candidates(left, table, ctx)?;
+                let right = self.json_clause_candidates(right, table, ctx)?;
+                Ok(match (left, right) {
+                    (Some(left), Some(right)) => Some(left.intersection(&right).copied().collect()),
+                    (left, right) => left.or(right),
+                })
+            },
+            Operator::Or => {
+                let left = self.json_clause_candidates(left, table, ctx)?;
+                let right = self.json_clause_candidates(right, table, ctx)?;
+                Ok(match (left, right) {
+                    (Some(left), Some(right)) => Some(left.union(&right).copied().collect()),
+                    _ => None,
+                })
+            },
+            Operator::Eq => match self.json_lookup(left, right, table, ctx)? {
+                Some(rows) => Ok(Some(rows)),
+                None => self.json_lookup(right, left, table, ctx),
+            },
+            _ => Ok(None)
+        }
+    }
+
+    /// Looks up the rows where the json 'path' node equals the 'constant' node
+    fn json_lookup(&self, path: &Node, constant: &Node, table: &Table, ctx: &Ctx) -> Result<Option<BTreeSet<usize>>, String> {
+        let (column, path) = match json_path(path, table) {
+            Some(found) => found,
+            None => return Ok(None)
+        };
+
+        // the compared value must not depend on the row
+        match constant {
+            Node::Literal(Literal::String(_) | Literal::Number(_) | Literal::Boolean(_) | Literal::Null | Literal::Parameter(_)) => (),
+            _ => return Ok(None)
+        }
+
+        let index = match table.get_index(&column, IndexKind::Json(path)) {
+            Some(index) => index.json().expect("Index should be a json index"),
+            None => return Ok(None)
+        };
+
+        match self.run(constant, ctx)? {
+            Some(value) => Ok(Some(index.lookup(&value))),
+            None => Ok(None)
+        }
+    }
+}
+
+/// Resolves 'column.a.b', 'table.column.a' or 'column["a"]["b"]' to a json column of 'table'
+/// and the path inside it
+fn json_path(node: &Node, table: &Table) -> Option<(String, Vec<String>)> {
+    let mut path = vec![];
+    let mut current = node;
+    let root = loop {
+        match current {
+            Node::Expression(Expression::Member { object, member, .. }) => {
+                path.push(member.clone());
+                current = object;
+            },
+            Node::Expression(Expression::Index { object, index }) => match index.as_ref() {
+                Node::Literal(Literal::String(key)) => {
+                    path.push(key.clone());
+                    current = object;
+                },
+                _ => return None
+            },
+            Node::Literal(Literal::Identifier(root, _)) => break root.clone(),
+            _ => return None
+        }
+    };
+    path.reverse();
+
+    let is_json = |name: &str| matches!(table.get_column(name), Some(column) if matches!(column.data_type, ColumnType::Json(_)));
+    if is_json(&root) {
+        return Some((root, path))
+    }
+
+    if root == table.name && !path.is_empty() && is_json(&path[0]) {
+        let column = path.remove(0);
+        return Some((column, path))
+    }
+
+    None
+}
@@ -0,0 +1,66 @@
+use std::time::Instant;
+
+use super::{Runner, Ctx};
+
+impl Runner {
+    /// Resets the run start and the step counter, so the limits apply to the next run only
+    pub fn restart_limits(&self) {
+        self.started.replace(Instant::now());
+        self.steps.replace(0);
+    }
+
+    /// Counts one evaluation step, fails if the run was cancelled or exceeded its time or step
+    /// limit
+    pub(super) fn check_limits(&self, ctx: &Ctx) -> Result<(), String> {
+        if ctx.is_cancelled() {
+            return Err("Run was cancelled".to_string())
+        }
+
+        let limits = ctx.limits();
+        let steps = {
+            let mut steps = self.steps.borrow_mut();
+            *steps += 1;
+            *steps
+        };
+
+        if let Some(max_steps) = limits.max_steps {
+            if steps > max_steps {
+                return Err(format!("Run exceeded the maximum of {} evaluation steps", max_steps))
+            }
+        }
+
+        if let Some(timeout) = limits.timeout {
+            if self.started.borrow().elapsed() > timeout {
+                return Err(format!("Run exceeded the timeout of {:?}", timeout))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enters a custom function call, fails if it exceeds the maximum recursion depth. Every
+    /// successful call must be paired with 'leave_function'
+    pub(super) fn enter_function(&self, name: &str, ctx: &Ctx) -> Result<(), String> {
+        let depth = *self.depth.borrow() + 1;
+        if let Some(max_depth) = ctx.limits().max_depth {
+            if depth > max_depth {
+                return Err(format!("Function '{}' exceeded the maximum recursion depth of {}", name, max_depth))
+            }
+        }
+
+        self.depth.replace(depth);
+        Ok(())
+    }
+
+    pub(super) fn leave_function(&self) {
+        *self.depth.borrow_mut() -= 1;
+    }
+
+    /// Fails if a select query returned more rows than allowed
+    pub(super) fn check_rows(&self, rows: usize, ctx: &Ctx) -> Result<(), String> {
+        match ctx.limits().max_rows {
+            Some(max_rows) if rows > max_rows => Err(format!("Query returned more than {} rows", max_rows)),
+            _ => Ok(())
+        }
+    }
+}
@@ -1,24 +1,32 @@
-use crate::{syntax::{ast::{Literal, Number, Node}, context::RunnerContextVariable}, basics::{Value, value::NumericValue}};
+use crate::{syntax::{ast::{Literal, Number, Node}, context::RunnerContextVariable}, basics::{Value, value::NumericValue}, function::Closure};
 
-use super::{Runner, Ctx, RunnerResult};
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
 
 impl Runner {
     pub(super) fn eval_literal(&self, literal: &Literal, ctx: &Ctx) -> RunnerResult {
         match literal {
-            Literal::Identifier(name) => self.eval_identifier(name, ctx),
+            Literal::Identifier(name, _) => self.eval_identifier(name, ctx),
             Literal::Number(number) => self.eval_number(number),
             Literal::String(value) => Ok(Some(Value::Text(value.clone()))),
             Literal::Boolean(value) => Ok(Some(Value::Boolean(*value))),
             Literal::Array(values) => self.eval_array(values, ctx),
-            Literal::Null => Ok(Some(Value::Null))
+            Literal::Object(entries) => self.eval_object(entries, ctx),
+            Literal::Null => Ok(Some(Value::Null)),
+            Literal::Parameter(index) => Ok(Some(ctx.parameter(*index)?.clone())),
         }
     }
 
     fn eval_identifier(&self, name: &str, ctx: &Ctx) -> RunnerResult {
         // TODO: find a way to return a reference without cloning
-        let value = ctx.get(name)?;
-        let value = value.borrow().clone().into_owned();
-        
+        let value = match ctx.value(name) {
+            Ok(value) => value,
+            // declared functions can be passed by name
+            Err(error) => match self.database.read().functions.get(name) {
+                Some(function) => return Ok(Some(Value::Function(Closure::new(function.clone(), vec![])))),
+                None => return Err(self.raise(ErrorKind::NotFound, error)),
+            }
+        };
+
         Ok(Some(value))
     }
 
@@ -44,4 +52,15 @@ impl Runner {
 
         Ok(Some(Value::Array(result)))
     }
+
+    fn eval_object(&self, entries: &Vec<(String, Node)>, ctx: &Ctx) -> RunnerResult {
+        let mut object = Value::Object(Vec::with_capacity(entries.len()));
+        for (key, value) in entries {
+            let value = self.run(value, ctx)?.ok_or(format!("Invalid value for key '{}', statement has no return value", key))?;
+            // a repeated key overwrites the earlier value
+            object.set_member(key, value)?;
+        }
+
+        Ok(Some(object))
+    }
 }
@@ -1,6 +1,9 @@
-use crate::{syntax::{ast::{Node, Statement}, context::RunnerContextScope}, basics::Value};
+use crate::{syntax::{ast::{Node, Statement, Pattern, Query, SelectQuery}, context::{RunnerContextScope, RunnerContextVariable}}, basics::{Value, value::NumericValue}};
 
-use super::{Runner, BlockResult, Ctx, RunnerResult};
+use super::{Runner, BlockResult, Ctx, RunnerResult, query::row_objects};
+
+/// Number of rows a 'for ... in' loop over a select query fetches at a time
+const FOR_IN_FETCH_SIZE: usize = 256;
 
 impl Runner {
     pub(super) fn eval_loop(&self, block: &Box<Node>, ctx: &Ctx) -> RunnerResult {
@@ -63,6 +66,113 @@ impl Runner {
         Ok(None)
     }
 
+    pub(super) fn eval_for_in(&self, index: &Option<String>, pattern: &Pattern, iterable: &Node, block: &Node, ctx: &Ctx) -> RunnerResult {
+        let block_nodes = match block {
+            Node::Block(ref nodes) => nodes,
+            _ => return Err("For loop block must be a block".to_string())
+        };
+
+        // rows of a select query are fetched in batches instead of being built all at once
+        if let Node::Query(Query::Select(select)) = iterable {
+            let inside_loop = self.inside_loop.replace(true);
+            let result = self.for_in_query(select, index, pattern, block_nodes, ctx);
+            self.inside_loop.replace(inside_loop);
+
+            return result
+        }
+
+        let iterable = self.run(iterable, ctx)?.ok_or("For loop cannot iterate over a statement without a value")?;
+        // arrays give their position and element, objects their key and value
+        let entries = match iterable {
+            Value::Array(values) => values.into_iter().enumerate()
+                .map(|(i, value)| (position(i), value))
+                .collect::<Vec<_>>(),
+            Value::Object(entries) => entries.into_iter()
+                .map(|(key, value)| match index {
+                    Some(_) => (Value::Text(key), value),
+                    None => (Value::Null, Value::Array(vec![Value::Text(key), value])),
+                })
+                .collect(),
+            Value::Null => vec![],
+            value => return Err(format!("For loop cannot iterate over a value of type '{:?}'", self.get_type(&value)))
+        };
+
+        let inside_loop = self.inside_loop.replace(true);
+        let result = self.for_in_entries(entries.into_iter().map(Ok), index, pattern, block_nodes, ctx);
+        self.inside_loop.replace(inside_loop);
+
+        result
+    }
+
+    /// Iterates over the rows of a select query and their positions, the rows are fetched from a
+    /// cursor 'FOR_IN_FETCH_SIZE' at a time. Like other cursors, rows deleted by the loop before
+    /// they are fetched are skipped and updated rows have their new values.
+    fn for_in_query(&self, select: &SelectQuery, index: &Option<String>, pattern: &Pattern, block: &Vec<Node>, ctx: &Ctx) -> RunnerResult {
+        let mut cursor = self.open_cursor(select, ctx)?;
+        let mut batch = vec![].into_iter();
+        let mut fetched = 0;
+
+        let rows = std::iter::from_fn(|| {
+            if batch.len() == 0 {
+                let rows = cursor.fetch(self, select, FOR_IN_FETCH_SIZE, ctx).and_then(|rows| match select.as_objects {
+                    true => row_objects(cursor.columns(), rows),
+                    false => Ok(rows),
+                });
+                match rows {
+                    Ok(rows) => batch = rows.into_iter(),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            let row = batch.next()?;
+            fetched += 1;
+            Some(Ok((position(fetched - 1), row)))
+        });
+
+        self.for_in_entries(rows, index, pattern, block, ctx)
+    }
+
+    fn for_in_entries(&self, entries: impl Iterator<Item = Result<(Value, Value), String>>, index: &Option<String>, pattern: &Pattern, block: &Vec<Node>, ctx: &Ctx) -> RunnerResult {
+        for entry in entries {
+            let (key, value) = entry?;
+            // every iteration gets its own variables
+            let ctx = &Ctx::scoped(ctx.clone());
+            if let Some(index) = index {
+                ctx.declare(index, key);
+            }
+            self.destructure(pattern, value, ctx)?;
+
+            match self.eval_block(block, ctx)? {
+                BlockResult::Return(value) => return Ok(Some(value)),
+                BlockResult::Break => { self.break_loop.replace(false); break; }
+                BlockResult::Continue => { self.continue_loop.replace(false); continue; }
+                BlockResult::End => continue,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Declares the variables of 'pattern', missing elements and members are null
+    fn destructure(&self, pattern: &Pattern, value: Value, ctx: &Ctx) -> Result<(), String> {
+        match (pattern, value) {
+            (Pattern::Identifier(name), value) => ctx.declare(name, value),
+            (Pattern::Array(names), Value::Array(values)) => {
+                let mut values = values.into_iter();
+                for name in names {
+                    ctx.declare(name, values.next().unwrap_or(Value::Null));
+                }
+            },
+            (Pattern::Object(names), value @ Value::Object(_)) => for name in names {
+                ctx.declare(name, value.get_member(name).cloned().unwrap_or(Value::Null));
+            },
+            (Pattern::Array(_), value) => return Err(format!("Cannot destructure a value of type '{:?}' into '[...]', expected an array", self.get_type(&value))),
+            (Pattern::Object(_), value) => return Err(format!("Cannot destructure a value of type '{:?}' into '{{...}}', expected an object", self.get_type(&value))),
+        }
+
+        Ok(())
+    }
+
     pub(super) fn eval_while(&self, condition: &Node, block: &Box<Node>, ctx: &Ctx) -> RunnerResult {
         if !matches!(condition, Node::Expression(_)) {
             return Err("While condition must be an expression".to_string())
@@ -90,3 +200,8 @@ impl Runner {
         Ok(None)
     }
 }
+
+/// Key of the element at position 'i' of an array or a query result
+fn position(i: usize) -> Value {
+    Value::Numeric(NumericValue::IntI64(i as i64))
+}
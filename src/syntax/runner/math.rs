@@ -1,25 +1,13 @@
-use crate::basics::{Value, value::NumericValue};
+use crate::basics::{Value, value::{NumericValue, Decimal, TimestampValue}};
 
-use super::{Runner, RunnerResult};
+use super::{Runner, RunnerResult, error::ErrorKind};
 
 impl Runner {
     pub(super) fn eval_add(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(left + right)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64(left + *right as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 + right)),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64(left + right)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(*left as i64 + right)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 + right)),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left + right)),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left + *right as f64)),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left + *right as f64)),
-
-                _ => unimplemented!("add numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => arithmetic("addition", left, right, u64::checked_add, i64::checked_add, Decimal::checked_add, |l, r| l + r)?,
+            (Value::Timestamp(left), Value::Timestamp(TimestampValue::Interval(right))) => Value::Timestamp(left.add_interval(right)?),
+            (Value::Timestamp(TimestampValue::Interval(left)), Value::Timestamp(right)) => Value::Timestamp(right.add_interval(left)?),
             (Value::Text(left), Value::Text(right)) => Value::Text(format!("{}{}", left, right)),
             (Value::Array(left), Value::Array(right)) => {
                 let mut result = left.clone();
@@ -30,7 +18,7 @@ impl Runner {
             (_, Value::Null) => left.clone(),
             (Value::Boolean(left), Value::Boolean(right)) => Value::Boolean(*left || *right),
 
-            _ => unimplemented!("add {:?}", (left, right))
+            _ => return Err(self.operand_error("add", left, right))
         };
 
         Ok(Some(value))
@@ -38,26 +26,13 @@ impl Runner {
 
     pub(super) fn eval_sub(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(left - right)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64(left - *right as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 - right)),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64(left - right)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(*left as i64 - right)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 - right)),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left - right)),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left - *right as f64)),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left - *right as f64)),
-
-                _ => unimplemented!("sub numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => arithmetic("subtraction", left, right, u64::checked_sub, i64::checked_sub, Decimal::checked_sub, |l, r| l - r)?,
+            (Value::Timestamp(left), Value::Timestamp(right)) => Value::Timestamp(left.sub(right)?),
             (Value::Null, _) => return self.eval_sub(&Value::Numeric(NumericValue::IntI64(0)), right),
             (_, Value::Null) => left.clone(),
             (Value::Boolean(left), Value::Boolean(right)) => Value::Boolean(*left && !*right),
 
-            _ => unimplemented!("sub {:?}", (left, right))
+            _ => return Err(self.operand_error("subtract", left, right))
         };
 
         Ok(Some(value))
@@ -65,25 +40,11 @@ impl Runner {
 
     pub(super) fn eval_mul(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(left * right)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64(left * *right as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 * right)),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64(left * right)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(*left as i64 * right)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 * right)),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left * right)),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left * *right as f64)),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left * *right as f64)),
-
-                _ => unimplemented!("mul numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => arithmetic("multiplication", left, right, u64::checked_mul, i64::checked_mul, Decimal::checked_mul, |l, r| l * r)?,
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("mul {:?}", (left, right))
+            _ => return Err(self.operand_error("multiply", left, right))
         };
 
         Ok(Some(value))
@@ -91,25 +52,11 @@ impl Runner {
 
     pub(super) fn eval_div(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(left / right)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64(left / *right as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 / right)),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64(left / right)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(*left as i64 / right)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 / right)),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left / right)),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left / *right as f64)),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left / *right as f64)),
-
-                _ => unimplemented!("div numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => arithmetic("division", left, right, u64::checked_div, i64::checked_div, Decimal::checked_div, |l, r| l / r)?,
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("div {:?}", (left, right))
+            _ => return Err(self.operand_error("divide", left, right))
         };
 
         Ok(Some(value))
@@ -117,25 +64,11 @@ impl Runner {
 
     pub(super) fn eval_mod(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(left % right)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64(left % *right as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 % right)),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64(left % right)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64(*left as i64 % right)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(*left as f64 % right)),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left % right)),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left % *right as f64)),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left % *right as f64)),
-
-                _ => unimplemented!("mod numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => arithmetic("modulo", left, right, u64::checked_rem, i64::checked_rem, Decimal::checked_rem, |l, r| l % r)?,
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("mod {:?}", (left, right))
+            _ => return Err(self.operand_error("take the modulo of", left, right))
         };
 
         Ok(Some(value))
@@ -143,25 +76,11 @@ impl Runner {
 
     pub(super) fn eval_pow(&self, left: &Value, right: &Value) -> RunnerResult {
         let value = match (left, right) {
-            (Value::Numeric(left), Value::Numeric(right)) => match (left, right) {
-                (NumericValue::IntI64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64((*left as f64).powf(*right as f64) as i64)),
-                (NumericValue::IntI64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntI64((*left as f64).powf(*right as f64) as i64)),
-                (NumericValue::IntI64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64((*left as f64).powf(*right))),
-
-                (NumericValue::IntU64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::IntU64((*left as f64).powf(*right as f64) as u64)),
-                (NumericValue::IntU64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::IntI64((*left as f64).powf(*right as f64) as i64)),
-                (NumericValue::IntU64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64((*left as f64).powf(*right))),
-
-                (NumericValue::Float64(left), NumericValue::Float64(right)) => Value::Numeric(NumericValue::Float64(left.powf(*right))),
-                (NumericValue::Float64(left), NumericValue::IntI64(right)) => Value::Numeric(NumericValue::Float64(left.powf(*right as f64))),
-                (NumericValue::Float64(left), NumericValue::IntU64(right)) => Value::Numeric(NumericValue::Float64(left.powf(*right as f64))),
-
-                _ => unimplemented!("pow numeric {:?}", (left, right))
-            },
+            (Value::Numeric(left), Value::Numeric(right)) => power(left, right)?,
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("pow {:?}", (left, right))
+            _ => return Err(self.operand_error("take the power of", left, right))
         };
 
         Ok(Some(value))
@@ -173,7 +92,7 @@ impl Runner {
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("and {:?}", (left, right))
+            _ => return Err(self.operand_error("apply '&&' to", left, right))
         };
 
         Ok(Some(value))
@@ -185,10 +104,141 @@ impl Runner {
             (Value::Null, _) => Value::Null,
             (_, Value::Null) => Value::Null,
 
-            _ => unimplemented!("or {:?}", (left, right))
+            _ => return Err(self.operand_error("apply '||' to", left, right))
         };
 
         Ok(Some(value))
     }
+
+    /// Raises the error of an operation on operands it isn't defined for
+    fn operand_error(&self, operation: &str, left: &Value, right: &Value) -> String {
+        self.raise(ErrorKind::Type, format!("Cannot {} '{:?}' and '{:?}'", operation, self.get_type(left), self.get_type(right)))
+    }
+}
+
+
+/// Numeric value widened to the largest type of its kind
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Widened {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Decimal(Decimal),
+}
+
+impl Widened {
+    pub(super) fn from(value: &NumericValue) -> Self {
+        match *value {
+            NumericValue::IntU8(v) => Widened::Unsigned(v as u64),
+            NumericValue::IntU16(v) => Widened::Unsigned(v as u64),
+            NumericValue::IntU32(v) => Widened::Unsigned(v as u64),
+            NumericValue::IntU64(v) => Widened::Unsigned(v),
+
+            NumericValue::IntI8(v) => Widened::Signed(v as i64),
+            NumericValue::IntI16(v) => Widened::Signed(v as i64),
+            NumericValue::IntI32(v) => Widened::Signed(v as i64),
+            NumericValue::IntI64(v) => Widened::Signed(v),
+
+            NumericValue::Float32(v) => Widened::Float(v as f64),
+            NumericValue::Float64(v) => Widened::Float(v),
+
+            NumericValue::Decimal(v) => Widened::Decimal(v),
+        }
+    }
+
+    /// Converts an integer to i64, errors if an unsigned value is too large
+    pub(super) fn to_signed(self) -> Result<i64, String> {
+        match self {
+            Widened::Unsigned(v) => i64::try_from(v).map_err(|_| format!("Integer overflow, {} does not fit into a signed integer", v)),
+            Widened::Signed(v) => Ok(v),
+            Widened::Float(v) => Ok(v as i64),
+            Widened::Decimal(v) => i64::try_from(v.trunc()).map_err(|_| format!("Integer overflow, {} does not fit into a signed integer", v)),
+        }
+    }
+}
+
+/// Operands of an arithmetic operation promoted to their common type
+enum Promoted {
+    Unsigned(u64, u64),
+    Signed(i64, i64),
+    Float(f64, f64),
+    Decimal(Decimal, Decimal),
+}
+
+/// Promotes two numbers to a common type, any decimal makes both exact decimals, so 'price - 0.5'
+/// stays exact, otherwise any float makes both floats (Float64). Two unsigned
+/// integers stay unsigned (IntU64) and other integers become signed (IntI64). Mixed integers
+/// are unsigned only if the unsigned one does not fit into i64 and the signed one is positive
+fn promote(left: &NumericValue, right: &NumericValue) -> Result<Promoted, String> {
+    let promoted = match (Widened::from(left), Widened::from(right)) {
+        (Widened::Decimal(_), _) | (_, Widened::Decimal(_)) => Promoted::Decimal(to_decimal(left)?, to_decimal(right)?),
+        (Widened::Float(_), _) | (_, Widened::Float(_)) => Promoted::Float(left.to_f64(), right.to_f64()),
+        (Widened::Unsigned(left), Widened::Unsigned(right)) => Promoted::Unsigned(left, right),
+        (Widened::Unsigned(l), Widened::Signed(r)) if l > i64::MAX as u64 && r >= 0 => Promoted::Unsigned(l, r as u64),
+        (Widened::Signed(l), Widened::Unsigned(r)) if r > i64::MAX as u64 && l >= 0 => Promoted::Unsigned(l as u64, r),
+        (left, right) => Promoted::Signed(left.to_signed()?, right.to_signed()?),
+    };
+
+    Ok(promoted)
+}
+
+/// Converts a number to a decimal, floats through their shortest representation
+fn to_decimal(value: &NumericValue) -> Result<Decimal, String> {
+    match *value {
+        NumericValue::Float32(f) => Decimal::from_float(f),
+        NumericValue::Float64(f) => Decimal::from_float(f),
+        _ => Ok(value.to_decimal().unwrap()),
+    }
+}
+
+/// Applies a binary operation to the promoted operands, integer and decimal operations are
+/// checked and return None on overflow or division by zero
+fn arithmetic(
+    name: &str,
+    left: &NumericValue,
+    right: &NumericValue,
+    unsigned: fn(u64, u64) -> Option<u64>,
+    signed: fn(i64, i64) -> Option<i64>,
+    decimal: fn(Decimal, Decimal) -> Option<Decimal>,
+    float: fn(f64, f64) -> f64
+) -> Result<Value, String> {
+    let promoted = promote(left, right)?;
+    let kind = if let Promoted::Decimal(_, _) = promoted { "Decimal" } else { "Integer" };
+
+    let (result, divisor_zero) = match promoted {
+        Promoted::Unsigned(l, r) => (unsigned(l, r).map(NumericValue::IntU64), r == 0),
+        Promoted::Signed(l, r) => (signed(l, r).map(NumericValue::IntI64), r == 0),
+        Promoted::Float(l, r) => (Some(NumericValue::Float64(float(l, r))), false),
+        Promoted::Decimal(l, r) => (decimal(l, r).map(NumericValue::Decimal), r.is_zero()),
+    };
+
+    match result {
+        Some(result) => Ok(Value::Numeric(result)),
+        None if divisor_zero && (name == "division" || name == "modulo") => Err("Division by zero".to_string()),
+        None => Err(format!("{} overflow in {} of {} and {}", kind, name, left, right)),
+    }
 }
 
+/// Raises 'left' to the power of 'right', negative or fractional exponents give a float
+fn power(left: &NumericValue, right: &NumericValue) -> Result<Value, String> {
+    let overflow = || format!("Numeric overflow in exponentiation of {} and {}", left, right);
+
+    let result = match promote(left, right)? {
+        Promoted::Unsigned(l, r) => {
+            let r = u32::try_from(r).map_err(|_| overflow())?;
+            NumericValue::IntU64(l.checked_pow(r).ok_or_else(overflow)?)
+        },
+        Promoted::Signed(l, r) if r < 0 => NumericValue::Float64((l as f64).powf(r as f64)),
+        Promoted::Signed(l, r) => {
+            let r = u32::try_from(r).map_err(|_| overflow())?;
+            NumericValue::IntI64(l.checked_pow(r).ok_or_else(overflow)?)
+        },
+        Promoted::Float(l, r) => NumericValue::Float64(l.powf(r)),
+        Promoted::Decimal(l, r) => match u32::try_from(r.trunc()) {
+            Ok(exponent) if r.is_integer() => NumericValue::Decimal(l.checked_pow(exponent).ok_or_else(overflow)?),
+            _ => NumericValue::Float64(l.to_f64().powf(r.to_f64())),
+        },
+    };
+
+    Ok(Value::Numeric(result))
+}
@@ -1,4 +1,6 @@
-use std::{cell::RefCell, sync::{Arc, RwLock}};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc, sync::{Arc, RwLock}, time::Instant};
+
+use regex::Regex;
 
 use crate::{basics::Value, database::Database, lock::UnsafeRwLock, syntax::{parser::Parser, tokenizer::Tokenizer}};
 
@@ -15,10 +17,28 @@ mod function;
 mod r#type;
 mod query;
 mod sdl;
+mod window;
+mod cte;
+mod cursor;
+mod parallel;
+mod limit;
+mod fulltext;
+mod vector;
+mod json;
+mod trigger;
+mod closure;
+mod error;
+mod module;
 
 // experimental
 mod join;
 
+#[cfg(test)]
+mod tests;
+
+pub use cursor::SelectCursor;
+pub use query::SequenceState;
+
 type RunnerResult = Result<Option<Value>, String>;
 
 enum BlockResult {
@@ -38,6 +58,28 @@ pub struct Runner {
     inside_loop: RefCell<bool>,
     break_loop: RefCell<bool>,
     continue_loop: RefCell<bool>,
+    /// Compiled patterns of the '=~' operator, so they are not recompiled for every row
+    regex_cache: RefCell<HashMap<String, Regex>>,
+    /// Start of the run and the number of evaluated nodes, checked against 'RunLimits'
+    started: RefCell<Instant>,
+    steps: RefCell<usize>,
+    /// Current nesting of custom function calls
+    depth: RefCell<usize>,
+    /// Current nesting of triggers, a trigger can run statements which fire triggers again
+    trigger_depth: RefCell<usize>,
+    /// Message and error object of the last 'throw', so 'catch' gets the object and not only
+    /// the message
+    thrown: RefCell<Option<(String, Value)>>,
+    /// Message and kind of the last error raised with 'Runner::raise', so 'catch' gets its kind
+    raised: RefCell<Option<(String, error::ErrorKind)>>,
+    /// Index of the statement of the innermost block which failed, set while an error unwinds
+    failed_statement: RefCell<Option<usize>>,
+    /// Paths of the modules being imported, innermost last
+    modules: RefCell<Vec<PathBuf>>,
+    /// Rows matched by full-text queries, so an index is searched once per query and not per row
+    fulltext_cache: RefCell<HashMap<fulltext::FullTextKey, Rc<HashMap<usize, f64>>>>,
+    /// Running values of 'seq' by table and column, only kept while an insert query plans its rows
+    sequences: RefCell<Option<HashMap<(String, String), SequenceState>>>,
 }
 
 impl Runner {
@@ -48,6 +90,17 @@ impl Runner {
             inside_loop: RefCell::new(false),
             break_loop: RefCell::new(false),
             continue_loop: RefCell::new(false),
+            regex_cache: RefCell::new(HashMap::new()),
+            started: RefCell::new(Instant::now()),
+            steps: RefCell::new(0),
+            depth: RefCell::new(0),
+            trigger_depth: RefCell::new(0),
+            thrown: RefCell::new(None),
+            raised: RefCell::new(None),
+            failed_statement: RefCell::new(None),
+            modules: RefCell::new(Vec::new()),
+            fulltext_cache: RefCell::new(HashMap::new()),
+            sequences: RefCell::new(None),
         }
     }
 
@@ -64,6 +117,8 @@ impl Runner {
 
     /// Main entry point for running code execution
     pub fn run(&self, ast: &Node, ctx: &Ctx) -> RunnerResult {
+        self.check_limits(ctx)?;
+
         match ast {
             Node::Literal(value) => self.eval_literal(value, ctx),
             Node::Block(nodes) => self.eval_pure_block(nodes, ctx),
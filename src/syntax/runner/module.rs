@@ -0,0 +1,105 @@
+use crate::{syntax::{ast::{Node, Statement}, context::{RunnerContextVariable, ScopeValue}, module}, basics::Value, function::{Function, Closure}};
+
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
+
+impl Runner {
+    /// Runs a module and declares its exported functions and variables in 'ctx'. Modules run
+    /// in their own context, their functions see the variables and functions of the module.
+    pub(super) fn eval_import(&self, path: &str, ctx: &Ctx) -> RunnerResult {
+        if !ctx.cluster_user().is_superuser {
+            return Err(self.raise(ErrorKind::Auth, "Can't import modules, permission denied".to_string()));
+        }
+
+        let path = module::resolve(path, self.modules.borrow().last().map(|path| path.as_path()));
+        if self.modules.borrow().contains(&path) {
+            let mut cycle = self.modules.borrow().iter()
+                .skip_while(|module| **module != path)
+                .map(|module| format!("'{}'", module.display()))
+                .collect::<Vec<_>>();
+            cycle.push(format!("'{}'", path.display()));
+
+            return Err(format!("Import cycle: {}", cycle.join(" -> ")))
+        }
+
+        let statements = module::load(&path)?;
+
+        self.modules.borrow_mut().push(path.clone());
+        let exports = self.run_module(&statements, ctx);
+        self.modules.borrow_mut().pop();
+
+        let exports = exports.map_err(|e| match is_module_error(&e) {
+            true => e,
+            false => format!("Module '{}': {}", path.display(), e),
+        })?;
+        for (name, value) in exports {
+            ctx.share(&name, value);
+        }
+
+        Ok(None)
+    }
+
+    /// Runs the statements of a module, returns the cells of its exports
+    fn run_module(&self, statements: &[(Node, usize)], ctx: &Ctx) -> Result<Vec<(String, ScopeValue)>, String> {
+        let module_ctx = &ctx.isolated();
+        let mut closures = vec![];
+        let mut exports = vec![];
+
+        for (node, line) in statements {
+            self.run_module_statement(node, module_ctx, &mut closures, &mut exports)
+                .map_err(|e| match is_module_error(&e) {
+                    true => e,
+                    false => format!("{} at line {}", e, line),
+                })?;
+        }
+
+        // every function shares the variables of the module, including the other functions
+        let variables = module_ctx.scope_variables();
+        for closure in &closures {
+            closure.capture(variables.clone());
+        }
+
+        exports.into_iter()
+            .map(|name| Ok((name.clone(), module_ctx.get(&name)?)))
+            .collect()
+    }
+
+    fn run_module_statement(&self, node: &Node, module_ctx: &Ctx, closures: &mut Vec<Closure>, exports: &mut Vec<String>) -> Result<(), String> {
+        let (statement, exported) = match module::exported(node) {
+            Some(statement) => (statement, true),
+            None => match node {
+                Node::Statement(Statement::Export { .. }) => return Err("Only function and let declarations can be exported".to_string()),
+                Node::Statement(statement) => (statement, false),
+                node => {
+                    self.run(node, module_ctx)?;
+                    return Ok(())
+                }
+            }
+        };
+
+        match statement {
+            // functions of a module are values, they are not stored in the database
+            Statement::Function { name, parameters, return_type, block, .. } => {
+                self.check_declaration(name)?;
+
+                let mut function = Function::custom(name, parameters, return_type, block);
+                function.restricted = false;
+
+                let closure = Closure::new(function, vec![]);
+                module_ctx.declare(name, Value::Function(closure.clone()));
+                closures.push(closure);
+            },
+            statement => { self.eval_statement(statement, module_ctx)?; },
+        }
+
+        if let (true, Statement::Function { name, .. } | Statement::Let { name, .. }) = (exported, statement) {
+            exports.push(name.clone());
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether an error was raised by a nested import, it already names the module it comes from
+fn is_module_error(error: &str) -> bool {
+    error.starts_with("Module '") || error.starts_with("Cannot read module") || error.starts_with("Import cycle")
+}
@@ -1,6 +1,11 @@
+use regex::Regex;
+
 use crate::{syntax::ast::{Operator, Node, Literal}, basics::{Value, value::NumericValue}};
 
-use super::{Runner, Ctx, RunnerResult};
+use super::{Runner, Ctx, RunnerResult, math::Widened};
+
+/// Number of compiled '=~' patterns a runner keeps, the cache is cleared when it's full
+pub(super) const REGEX_CACHE_SIZE: usize = 64;
 
 impl Runner {
     pub(super) fn eval_unary(&self, operator: &Operator, right_node: &Box<Node>, ctx: &Ctx) -> RunnerResult {
@@ -12,34 +17,38 @@ impl Runner {
                 _ => Err("Invalid unary not operator".to_string())
             },
             Operator::Sub => match right {
-                Value::Numeric(number) => match number {
-                    NumericValue::IntI64(value) => Ok(Some(Value::Numeric(NumericValue::IntI64(-value)))),
-                    NumericValue::IntU64(value) => Ok(Some(Value::Numeric(NumericValue::IntI64(-(value as i64))))),
-                    NumericValue::Float64(value) => Ok(Some(Value::Numeric(NumericValue::Float64(-value)))),
-                    _ => unimplemented!("unary sub operator {:?}", number)
+                Value::Numeric(number) => match Widened::from(&number) {
+                    Widened::Float(value) => Ok(Some(Value::Numeric(NumericValue::Float64(-value)))),
+                    Widened::Decimal(value) => {
+                        let value = value.checked_neg().ok_or(format!("Decimal overflow in negation of {}", number))?;
+                        Ok(Some(Value::Numeric(NumericValue::Decimal(value))))
+                    },
+                    integer => {
+                        let value = integer.to_signed()?.checked_neg().ok_or(format!("Integer overflow in negation of {}", number))?;
+                        Ok(Some(Value::Numeric(NumericValue::IntI64(value))))
+                    }
                 },
                 Value::Null => Ok(Some(Value::Null)),
                 _ => Err("Invalid unary sub operator".to_string())
             },
             Operator::Inc => match (&right, &**right_node) {
-                (Value::Numeric(_), Node::Literal(Literal::Identifier(ref identifier))) => {
+                (Value::Numeric(_), Node::Literal(Literal::Identifier(ref identifier, _))) => {
                     let value = self.eval_add(&right, &Value::Numeric(NumericValue::IntU64(1)))?.ok_or("eval_add returned None")?;
                     return self.eval_assignment(identifier, &Node::Value(value), ctx)
                 },
                 _ => Err("Invalid unary inc left-hand side".to_string())
             },
             Operator::Dec => match (&right, &**right_node) {
-                (Value::Numeric(_), Node::Literal(Literal::Identifier(ref identifier))) => {
+                (Value::Numeric(_), Node::Literal(Literal::Identifier(ref identifier, _))) => {
                     let value = self.eval_sub(&right, &Value::Numeric(NumericValue::IntU64(1)))?.ok_or("eval_sub returned None")?;
                     return self.eval_assignment(identifier, &Node::Value(value), ctx)
                 },
                 _ => Err("Invalid unary dec left-hand side".to_string())
             },
             Operator::BitNot => match right {
-                Value::Numeric(number) => match number {
-                    NumericValue::IntI64(value) => Ok(Some(Value::Numeric(NumericValue::IntI64(!value)))),
-                    NumericValue::IntU64(value) => Ok(Some(Value::Numeric(NumericValue::IntI64(!(value as i64))))),
-                    _ => Err("Invalid bitwise not number".to_string())
+                Value::Numeric(number) => match Widened::from(&number) {
+                    Widened::Float(_) | Widened::Decimal(_) => Err("Invalid bitwise not number".to_string()),
+                    integer => Ok(Some(Value::Numeric(NumericValue::IntI64(!integer.to_signed()?)))),
                 },
                 Value::Null => Ok(Some(Value::Null)),
                 _ => Err("Invalid bitwise not operator".to_string())
@@ -72,6 +81,9 @@ impl Runner {
             Operator::Le => Ok(Some(Value::Boolean(left <= right))),
             Operator::Gt => Ok(Some(Value::Boolean(left > right))),
             Operator::Ge => Ok(Some(Value::Boolean(left >= right))),
+            Operator::Like => self.eval_like(&left, &right, false),
+            Operator::ILike => self.eval_like(&left, &right, true),
+            Operator::Match => self.eval_match(&left, &right),
             
             Operator::And => self.eval_and(&left, &right),
             Operator::Or => self.eval_or(&left, &right),
@@ -79,4 +91,35 @@ impl Runner {
             _ => Err("Invalid binary operator".to_string())
         }
     }
+
+    fn eval_like(&self, left: &Value, right: &Value, case_insensitive: bool) -> RunnerResult {
+        match (left, right) {
+            (Value::Null, _) | (_, Value::Null) => Ok(Some(Value::Boolean(false))),
+            (Value::Text(_), Value::Text(_)) => match case_insensitive {
+                true => Ok(Some(Value::Boolean(left.ilike(right)))),
+                false => Ok(Some(Value::Boolean(left.like(right)))),
+            },
+            _ => Err("Like operator requires text values".to_string())
+        }
+    }
+
+    fn eval_match(&self, left: &Value, right: &Value) -> RunnerResult {
+        let (text, pattern) = match (left, right) {
+            (Value::Null, _) | (_, Value::Null) => return Ok(Some(Value::Boolean(false))),
+            (Value::Text(text), Value::Text(pattern)) => (text, pattern),
+            _ => return Err("Match operator requires text values".to_string())
+        };
+
+        let mut cache = self.regex_cache.borrow_mut();
+        if !cache.contains_key(pattern) {
+            let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex pattern: {}", e))?;
+            // patterns built per row would grow the cache without limit
+            if cache.len() >= REGEX_CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(pattern.clone(), regex);
+        }
+
+        Ok(Some(Value::Boolean(cache[pattern].is_match(text))))
+    }
 }
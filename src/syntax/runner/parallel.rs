@@ -0,0 +1,132 @@
+use std::{collections::BTreeSet, thread, rc::Rc};
+
+use crate::{syntax::{ast::{Node, Expression, Literal, Operator}, context::{Ctx, RunnerContextScope, RunnerContextFields}}, basics::{Value, Row}};
+
+use super::{Runner, closure::identifiers};
+
+/// Minimum number of rows evaluated by one thread, smaller scans are not worth spawning threads
+pub(super) const MIN_ROWS_PER_THREAD: usize = 4096;
+
+/// Wrapper which lets the joined rows (raw pointers into tables) and the evaluated nodes cross
+/// threads, workers only read through it
+struct Shared<T>(T);
+
+// SAFETY: the rows are owned by the database or by common table expressions of the spawning
+// context. 'eval_parallel' is only called while the spawning thread holds the database read
+// lock (the cursor opens and fetches under it), so no query can write a table, and the context
+// with its expressions outlives the thread scope. Workers only evaluate pure nodes (see
+// 'is_pure'), which never write rows, call functions or run queries, so nothing mutates the
+// pointed-to 'Table'/'Row' data or the nodes until every worker has been joined.
+unsafe impl<T> Send for Shared<T> {}
+unsafe impl<T> Sync for Shared<T> {}
+
+impl Runner {
+    /// Returns the number of threads 'nodes' should be evaluated on over 'rows' rows, 1 means
+    /// the scan should stay on the current thread.
+    ///
+    /// Only nodes which neither access the database nor modify variables are parallelized
+    pub(super) fn scan_threads(&self, nodes: &[&Node], rows: usize, ctx: &Ctx) -> usize {
+        let threads = ctx.threads().min(rows / MIN_ROWS_PER_THREAD);
+        if threads < 2 || !nodes.iter().all(|node| is_pure(node)) {
+            return 1
+        }
+
+        threads
+    }
+
+    /// Evaluates 'nodes' for every joined row at 'rows' indexes on 'threads' worker threads.
+    ///
+    /// Returns the node values of each row, in the order of 'rows'. The joined tables and the
+    /// column map of the base table are taken from 'ctx'
+    pub(super) fn eval_parallel(&self, nodes: &[&Node], joined_rows: &[Vec<*const Row>], rows: &[usize], threads: usize, ctx: &Ctx) -> Result<Vec<Vec<Value>>, String> {
+        // workers only get the variables the nodes use
+        let mut names = BTreeSet::new();
+        nodes.iter().for_each(|node| identifiers(node, &mut names));
+        let detached = ctx.detach(&names);
+        let column_map = ctx.column_map().cloned().unwrap_or_default();
+        let joined_column_map = ctx.current_unsafe_column_map.borrow().as_ref().clone();
+        let database = self.database.arc();
+        let started = *self.started.borrow();
+        let steps = *self.steps.borrow();
+
+        let joined_rows = Shared(joined_rows);
+        let nodes = Shared(nodes);
+        let chunk_size = rows.len().div_ceil(threads);
+
+        let results = thread::scope(|scope| {
+            let workers = rows.chunks(chunk_size).map(|chunk| {
+                let (detached, column_map, joined_column_map, database) = (&detached, &column_map, &joined_column_map, database.clone());
+                let (joined_rows, nodes) = (&joined_rows, &nodes);
+
+                scope.spawn(move || -> Result<(Vec<Vec<Value>>, usize), String> {
+                    let runner = Runner::new(database);
+                    runner.started.replace(started);
+                    runner.steps.replace(steps);
+
+                    let ctx = &Ctx::scoped_with(detached.attach(), column_map.clone());
+                    *ctx.current_unsafe_column_map.borrow_mut() = Rc::new(joined_column_map.clone());
+                    let null_base_row = Row::from_values(vec![Value::Null; column_map.len()]);
+
+                    let mut values = Vec::with_capacity(chunk.len());
+                    for &index in chunk {
+                        let joined_row = &joined_rows.0[index];
+                        ctx.set_joined_row(joined_row);
+                        let base_row = joined_row.first().copied().unwrap_or(std::ptr::null());
+                        if base_row.is_null() {
+                            ctx.set_row(&null_base_row);
+                        } else {
+                            ctx.set_row(unsafe { &*base_row });
+                        }
+
+                        let row = nodes.0.iter()
+                            .map(|node| Ok(runner.run(node, ctx)?.unwrap_or(Value::Null)))
+                            .collect::<Result<Vec<_>, String>>()?;
+                        values.push(row);
+                    }
+
+                    let worker_steps = *runner.steps.borrow() - steps;
+                    Ok((values, worker_steps))
+                })
+            }).collect::<Vec<_>>();
+
+            workers.into_iter()
+                .map(|worker| worker.join().unwrap_or_else(|_| Err("Worker thread panicked".to_string())))
+                .collect::<Vec<_>>()
+        });
+
+        let mut values = Vec::with_capacity(rows.len());
+        for result in results {
+            let (chunk, worker_steps) = result?;
+            *self.steps.borrow_mut() += worker_steps;
+            values.extend(chunk);
+        }
+
+        Ok(values)
+    }
+}
+
+/// Whether evaluating the node only reads the context, it does not access the database or
+/// modify variables
+fn is_pure(node: &Node) -> bool {
+    match node {
+        Node::Literal(literal) | Node::Expression(Expression::Literal(literal)) => match literal {
+            Literal::Array(nodes) => nodes.iter().all(is_pure),
+            Literal::Object(entries) => entries.iter().all(|(_, node)| is_pure(node)),
+            _ => true,
+        },
+        Node::Value(_) => true,
+        Node::Expression(expression) => match expression {
+            Expression::Binary { left, operator, right, .. } => {
+                !matches!(operator,
+                    Operator::Assign | Operator::AddAssign | Operator::SubAssign | Operator::MulAssign |
+                    Operator::DivAssign | Operator::ModAssign | Operator::PowAssign
+                ) && is_pure(left) && is_pure(right)
+            },
+            Expression::Unary { operator, right } => !matches!(operator, Operator::Inc | Operator::Dec) && is_pure(right),
+            Expression::Member { object, .. } => is_pure(object),
+            Expression::Index { object, index } => is_pure(object) && is_pure(index),
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -1,8 +1,69 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{syntax::{ast::{Query, InsertQuery, SelectQuery, UpdateQuery, DeleteQuery, Node, Literal, Operator, Expression}, context::{RunnerContextScope, RunnerContextFields}}, basics::{Value, Row, value::NumericValue}, auth::{Authorize, action::TableAction, RlsAction}};
+use crate::{syntax::{ast::{Query, InsertQuery, SelectQuery, UpdateQuery, DeleteQuery, Node, Literal, ConflictAction, KeyValue}, context::{RunnerContextScope, RunnerContextFields}}, basics::{Value, Row, Table, Column, column::ColumnType, value::NumericValue, trigger::{TriggerTiming, TriggerEvent}}, auth::{Authorize, action::TableAction, RlsAction}};
 
-use super::{Runner, Ctx, RunnerResult};
+use super::{Runner, Ctx, RunnerResult, error::ErrorKind};
+
+/// Outcome of a single row of an insert query
+enum InsertPlan {
+    Insert(Row),
+    /// Existing row at index is updated with the key values, holds the updated row
+    Update(usize, Vec<(usize, Value)>, Row),
+    Skip,
+}
+
+/// Unique values of the rows planned by an insert query, new rows are checked against them
+/// without scanning the whole batch
+#[derive(Default)]
+struct InsertBatch {
+    /// Unique column values of inserted rows
+    inserted: HashSet<UniqueKey>,
+    /// Unique column values of rows updated on conflict
+    updated: HashSet<UniqueKey>,
+    /// Indexes of the rows updated on conflict
+    updated_rows: HashSet<usize>,
+}
+
+/// Column index and the stored bytes of a value, None for NULL
+type UniqueKey = (usize, Option<Vec<u8>>);
+
+impl InsertBatch {
+    fn keys<'a>(table: &'a Table, row: &'a Row, column_indexes: &'a [usize]) -> impl Iterator<Item = UniqueKey> + 'a {
+        column_indexes.iter().filter_map(|&i| {
+            let value = row.get(i)?;
+            Some((i, (!value.is_null()).then(|| table.columns[i].value_to_bytes(value))))
+        })
+    }
+
+    /// First of 'column_indexes' in which 'row' has the same value as an inserted row
+    fn inserted_conflict(&self, table: &Table, row: &Row, column_indexes: &[usize]) -> Option<usize> {
+        Self::keys(table, row, column_indexes).find(|key| self.inserted.contains(key)).map(|(i, _)| i)
+    }
+
+    /// First of 'column_indexes' in which 'row' has the same value as any row of the batch
+    fn conflict(&self, table: &Table, row: &Row, column_indexes: &[usize]) -> Option<usize> {
+        Self::keys(table, row, column_indexes)
+            .find(|key| self.inserted.contains(key) || self.updated.contains(key))
+            .map(|(i, _)| i)
+    }
+
+    fn insert(&mut self, table: &Table, row: &Row) {
+        self.inserted.extend(Self::keys(table, row, &table.get_unique_column_indexes()));
+    }
+
+    fn update(&mut self, table: &Table, index: usize, row: &Row) {
+        self.updated.extend(Self::keys(table, row, &table.get_unique_column_indexes()));
+        self.updated_rows.insert(index);
+    }
+}
+
+/// Running maximum of a 'seq' column during an insert query, the first 'scanned' buffered rows
+/// are included in 'max'
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceState {
+    pub max: Option<i128>,
+    pub scanned: usize,
+}
 
 impl Runner {
     pub(super) fn eval_query(&self, query: &Query, ctx: &Ctx) -> RunnerResult {
@@ -17,6 +78,7 @@ impl Runner {
             Query::Insert(insert) => self.eval_insert(insert, ctx),
             Query::Update(update) => self.eval_update(update, ctx),
             Query::Delete(delete) => self.eval_delete(delete, ctx),
+            Query::With(with) => self.eval_with(with, ctx),
         };
 
         result
@@ -27,6 +89,8 @@ impl Runner {
             return Ok(true)
         }
 
+        // policies see the row, not the variables and common table expressions of the query
+        let ctx = &ctx.row_scope();
         for policy in policies {
             match self.run(policy, ctx)? {
                 Some(Value::Boolean(true)) => return Ok(true),
@@ -39,249 +103,144 @@ impl Runner {
     }
 
     fn eval_select(&self, select: &SelectQuery, ctx: &Ctx) -> RunnerResult {
-        let database = self.database.read();
-        let table = match database.get_table(&select.table) {
+        let (columns, rows) = self.eval_select_rows(select, ctx)?;
+        match select.as_objects {
+            true => Ok(Some(Value::Array(row_objects(&columns, rows)?))),
+            false => Ok(Some(Value::Array(rows))),
+        }
+    }
+
+    /// Evaluates a select query, returns the result columns and rows
+    pub(super) fn eval_select_rows(&self, select: &SelectQuery, ctx: &Ctx) -> Result<(Vec<Column>, Vec<Value>), String> {
+        let mut cursor = self.open_cursor(select, ctx)?;
+        let rows = cursor.fetch(self, select, usize::MAX, ctx)?;
+        Ok((cursor.into_columns(), rows))
+    }
+
+    fn eval_insert(&self, insert: &InsertQuery, ctx: &Ctx) -> RunnerResult {
+        let mut database = self.database.write().map_err(|_| "Cannot call 'query insert' when in read mode")?;
+        let table = match database.get_table_mut(&insert.table) {
             Some(table) => table,
-            None => return Err(format!("Table '{}' does not exist in database '{}'", select.table, database.name))
+            None => return Err(self.raise(ErrorKind::NotFound, format!("Table '{}' does not exist in database '{}'", insert.table, database.name)))
         };
 
-        // Perform joins on base table, it also runs authorization checks and rls checks
-        let joined_tables = self.perform_joins(table, &select.joins, ctx)?;
-
-        let column_map = table.get_column_map(&table.get_column_names()).unwrap();
-        let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
-        ctx.set_joined_tables(&joined_tables.tables);
-
-        // Prepare selected columns
-        let mut special_selected_columns = vec![];
-        // one entry is (table_index, column_index)
-        let mut selected_columns = vec![];
-        // one entry is (selected_at, table_index)
-        let mut select_alls = vec![];
-        for (selected_i, node) in select.columns.iter().enumerate() {
-            match node {
-                Node::Literal(Literal::Identifier(name)) => {
-                    if name == "*" {
-                        // reverse order since '*' expansion is also reversed
-                        for table_index in (0..joined_tables.tables.len()).rev() {
-                            select_alls.push((selected_i, table_index))
-                        }
-                    } else {
-                        let table_index = 0; // base table 
-                        let column_index = table.get_column_index(name)?;
-                        selected_columns.push((table_index, column_index));
-                    }
-                },
-                Node::Expression(Expression::Member { object, member }) => {
-                    let table_name = match **object {
-                        Node::Literal(Literal::Identifier(ref name)) => name,
-                        _ => return Err("Cannot select member from non-identifier column name".to_string())
-                    };
-
-                    let table_index = joined_tables.tables.iter().position(|t| {
-                        let t = unsafe { &*(*t) };
-                        t.name == *table_name
-                    });
-                    let table_index = match table_index {
-                        Some(i) => i,
-                        None => return Err(format!("Table '{}' not found in joined tables", table_name))
-                    };
-
-                    if member == "*" {
-                        select_alls.push((selected_i, table_index))
-                    } else {
-                        let table = unsafe { &*joined_tables.tables[table_index] };
-                        let column_index = table.get_column_index(member)?;
-                        selected_columns.push((table_index, column_index))
-                    }
+        table.authorize(ctx.cluster_user(), TableAction::Insert).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+
+        // all rows are validated before anything is written, new rows are kept in the buffer so
+        // they are visible to defaults and unique checks of the following rows
+        let buffered = table.data.buf_rows.len();
+        let mut plans = vec![];
+        let mut batch = InsertBatch::default();
+        self.sequences.replace(Some(HashMap::new()));
+        for key_values in &insert.rows {
+            match self.plan_insert_row(table, key_values, insert, &mut batch, ctx) {
+                Ok(plan) => plans.push(plan),
+                Err(err) => {
+                    self.sequences.replace(None);
+                    table.data.buf_rows.truncate(buffered);
+                    return Err(err)
                 }
-                _ => { special_selected_columns.push((selected_i, node)); }
             }
         }
-
-        // TODO: update index in special columns
-        // Prepare selected columns with select all
-        for (selected_at, table_index) in select_alls {
-            let table = unsafe { &*joined_tables.tables[table_index] };
-
-            let missing_columns = (0..table.columns.len()).filter_map(|i| {
-                if !selected_columns.iter().any(|(ti, ci)| *ti == table_index && *ci == i) {
-                    Some((table_index, i))
-                } else { None }
-            }).collect::<Vec<_>>();
-
-            // reversed order so we can insert at 'i' and keep correct column order
-            for missing in missing_columns.iter().rev() {
-                selected_columns.insert(selected_at, *missing);
+        self.sequences.replace(None);
+
+        let after_insert = table.triggers_for(TriggerTiming::After, TriggerEvent::Insert);
+        let after_update = table.triggers_for(TriggerTiming::After, TriggerEvent::Update);
+        let has_after = !after_insert.is_empty() || !after_update.is_empty();
+
+        // rows before an 'on conflict' update, None for inserted rows
+        let mut rows = vec![];
+        let mut old_rows = vec![];
+        for plan in plans {
+            match plan {
+                InsertPlan::Insert(row) => {
+                    rows.push(row);
+                    old_rows.push(None);
+                },
+                InsertPlan::Update(index, key_values, row) => {
+                    let existing = table.data.get_mut(index).unwrap();
+                    old_rows.push(has_after.then(|| existing.clone()));
+                    existing.update_with(&key_values);
+                    let column_indexes = key_values.iter().map(|(i, _)| *i).collect();
+                    table.sync_row_parts(index, &column_indexes)?;
+                    rows.push(row)
+                },
+                InsertPlan::Skip => (),
             }
         }
+        table.sync_buffer()?;
+        table.sync_indexes()?;
 
-        // TODO: update index in special columns
-        // Remove excluded columns
-        if let Some(exclude) = &select.exclude {
-            let exclude = exclude.iter().map(|name| table.get_column_index(name)).collect::<Result<Vec<_>, _>>()?;
-            selected_columns.retain(|(ti, ci)| !exclude.iter().any(|i| *ci == *i && *ti == 0));
-        }
-        
-        let null_base_row = Row::from_values(vec![Value::Null; table.columns.len()]);
-        
-        // evaluate where clause on each joined row
-        let mut row_indexes = vec![];
-        for (i, joined_row) in joined_tables.data.iter().enumerate() {
-            ctx.set_joined_row(joined_row);
-            let unsafe_row = joined_row.get(0).expect("Joined row has no base table row");
-            if unsafe_row.is_null() {
-                ctx.set_row(&null_base_row);
-            } else {
-                let row = unsafe { &*(*unsafe_row) };
-                ctx.set_row(row);
+        if has_after {
+            for (old, new) in old_rows.iter().zip(&rows) {
+                match old {
+                    Some(old) => self.fire_triggers(table, &after_update, Some(old), Some(new.clone()), ctx)?,
+                    None => self.fire_triggers(table, &after_insert, None, Some(new.clone()), ctx)?,
+                };
             }
-
-            let where_clause_result = match &select.where_clause {
-                Some(node) => self.run(node, ctx)?,
-                None => Some(Value::Boolean(true))
-            };
-
-            match where_clause_result {
-                Some(Value::Boolean(true)) => row_indexes.push(i),
-                Some(Value::Boolean(false)) => (),
-                _ => return Err("Where clause must return a boolean value".to_string()),
-            };
         }
 
-        // sort rows
-        if let Some(order) = &select.order {
-            let order = match order.as_ref() {
-                Node::Expression(Expression::Unary { operator, right }) => {
-                    let name = match **right {
-                        Node::Literal(Literal::Identifier(ref name)) => name,
-                        _ => return Err("Order node must be a column name".to_string())
-                    };
-
-                    let ascending = match operator {
-                        Operator::Inc => true,
-                        Operator::Dec => false,
-                        _ => return Err("Order operator must be 'inc' or 'dec'".to_string())
-                    };
-
-                    // TODO: use check_exists in all uses of get_column
-                    if table.get_column(name).is_none() {
-                        return Err(format!("Column '{}' does not exist in table '{}'", name, table.name))
-                    }
-
-                    let column_i = table.get_column_index(name).unwrap();
-                    let table_i = 0;
-
-                    (table_i, column_i, ascending)
-                },
-                _ => return Err("Order must be a unary expression".to_string())
-            };
-
-            // sort row indexes by order column
-            row_indexes.sort_by(|&i, &j| {
-                let row_a = joined_tables.data.get(i).expect("Cannot get row with row_index")
-                    .get(order.0).expect("Cannot get joined row with order table index");
-                let row_b = joined_tables.data.get(j).expect("Cannot get row with row_index")
-                    .get(order.0).expect("Cannot get joined row with order table index");
-
-                let a = {
-                    if row_a.is_null() {
-                        &Value::Null
-                    } else {
-                        let row_a = unsafe { &*(*row_a) };
-                        row_a.get(order.1).expect("Cannot get row value with order index")
-                    }
-                };
-
-                let b = {
-                    if row_b.is_null() {
-                        &Value::Null
-                    } else {
-                        let row_b = unsafe { &*(*row_b) };
-                        row_b.get(order.1).expect("Cannot get row value with order index")
-                    }
-                };
-                
-                if order.2 { 
-                    a.partial_cmp(b).expect("Cannot compare values")
-                } else { 
-                    b.partial_cmp(a).expect("Cannot compare values") 
-                }
-            })
+        match &insert.returning {
+            Some(returning) => self.eval_returning(table, &rows, returning, ctx),
+            None => Ok(Some(Value::Array(rows.iter().map(|row| Value::Array(row.iter().cloned().collect())).collect())))
         }
+    }
 
-        // offset rows
-        if let Some(offset) = select.offset {
-            row_indexes.drain(0..offset.min(row_indexes.len()));
-        }
+    /// Running value of 'seq' for a column while an insert query plans its rows
+    pub fn sequence(&self, table: &str, column: &str) -> Option<SequenceState> {
+        let sequences = self.sequences.borrow();
+        sequences.as_ref()?.get(&(table.to_string(), column.to_string())).copied()
+    }
 
-        // limit rows
-        if let Some(limit) = select.limit {
-            row_indexes.truncate(limit);
+    /// Keeps the value of 'seq' for the following rows, outside of insert queries it's dropped
+    pub fn set_sequence(&self, table: &str, column: &str, state: SequenceState) {
+        if let Some(sequences) = self.sequences.borrow_mut().as_mut() {
+            sequences.insert((table.to_string(), column.to_string()), state);
         }
+    }
 
-        // build result set
-        // let result_row_capacity = column_names.len() + special_columns.len();
-        let result_row_capacity = selected_columns.len() + special_selected_columns.len();
-        let mut result_set = Vec::with_capacity(row_indexes.len());
-        for row_index in row_indexes {
-            let joined_row = joined_tables.data.get(row_index).expect("Cannot get joined row with row_index");
-            let mut result_row = Vec::with_capacity(result_row_capacity);
-
-            // evaluate columns
-            for (ti, ci) in &selected_columns {
-                let unsafe_row = joined_row.get(*ti).expect("Cannot get row with table index");
-                if unsafe_row.is_null() {
-                    result_row.push(Value::Null);
-                    continue
-                }
-
-                let row = unsafe { &*(*unsafe_row) };
-                let value = row.get(*ci).expect("Cannot get row value with column index");
-                result_row.push(value.clone());
-            }
+    /// Evaluates the 'returning' columns on each of the affected 'rows', '*' returns all columns
+    fn eval_returning(&self, table: &Table, rows: &[Row], returning: &[Node], ctx: &Ctx) -> RunnerResult {
+        let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+        let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
+        ctx.set_aliased_tables(table, &[&table.name]);
 
-            // set row variables to be used in special columns
-            if special_selected_columns.len() > 0 {
-                ctx.set_joined_row(joined_row);
-            }
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let joined_row = vec![row as *const Row];
+            ctx.set_row(row);
+            ctx.set_joined_row(&joined_row);
 
-            // evaluate special columns
-            for (i, node) in &special_selected_columns {
-                let value = self.run(node, &ctx)?.expect("Special column must return a value");
-                result_row.insert(*i, value)
+            let mut values = Vec::with_capacity(returning.len());
+            for node in returning {
+                match node {
+                    Node::Literal(Literal::Identifier(name, _)) if name == "*" => values.extend(row.iter().cloned()),
+                    node => values.push(self.run(node, ctx)?.unwrap_or(Value::Null))
+                }
             }
-
-            result_set.push(Value::Array(result_row));
+            result.push(Value::Array(values));
         }
 
-        Ok(Some(Value::Array(result_set)))
+        Ok(Some(Value::Array(result)))
     }
 
-    fn eval_insert(&self, insert: &InsertQuery, ctx: &Ctx) -> RunnerResult {
-        let mut database = self.database.write().map_err(|_| "Cannot call 'query insert' when in read mode")?;
-        let table = match database.get_table_mut(&insert.table) {
-            Some(table) => table,
-            None => return Err(format!("Table '{}' does not exist in database '{}'", insert.table, database.name))
-        };
-
-        table.authorize(&ctx.cluster_user(), TableAction::Insert)?;
-
+    /// Evaluates and validates one row of an insert query, a new row is pushed to the table
+    /// buffer and its unique values are added to 'batch'
+    fn plan_insert_row(&self, table: &mut Table, key_values: &[KeyValue], insert: &InsertQuery, batch: &mut InsertBatch, ctx: &Ctx) -> Result<InsertPlan, String> {
         // eval the key_values
-        let mut key_values = vec![];
-        for (key, value) in &insert.key_values {
-            let value = self.run(value, ctx)?.expect(&format!("Value for column '{}' was not evaluated", key));
-            key_values.push((key, value));
+        let mut evaluated = vec![];
+        for (key, _, value) in key_values {
+            let value = self.run(value, ctx)?.ok_or(format!("Value for column '{}' was not evaluated", key))?;
+            evaluated.push((key, value));
         }
         // TODO: check duplicates
 
-        let column_names = insert.key_values.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+        let column_names = key_values.iter().map(|(key, _, _)| key.as_str()).collect::<Vec<_>>();
         
         // check if columns exist in the table
-        // table.check_columns_exist(&column_names)?;
         column_names.iter().map(|name| {
             table.check_column_exists(name)
-        }).collect::<Result<_, _>>()?;
+        }).collect::<Result<(), _>>().map_err(|e| self.raise(ErrorKind::NotFound, e))?;
 
         // check if required columns are present
         let missing_columns = table.columns.iter().filter_map(|column| {
@@ -293,80 +252,178 @@ impl Runner {
             }
         }).collect::<Vec<_>>();
 
-        if missing_columns.len() > 0 {
+        if !missing_columns.is_empty() {
             return Err(format!("Missing required columns: {:?}", missing_columns));
         }
 
         // create row
-        // let row = table.create_row(&key_values)?;
         let mut row = Row::new();
         for (i, column) in table.columns.iter().enumerate() {
-            let value = match key_values.iter().find(|(key, _)| *key == &column.name) {
+            let value = match evaluated.iter().find(|(key, _)| *key == &column.name) {
                 Some((_, value)) => value.clone(),
                 None => match column._default {
                     Some(ref default) => {
-                        match self.run(default, ctx) {
+                        // defaults don't see the variables of the query either
+                        match self.run(default, &Ctx::bounded(ctx.clone())) {
                             Ok(Some(value)) => value,
                             Ok(None) => return Err("Default value must return a value".to_string()),
                             Err(err) => return Err(format!("Error evaluating default value: {}", err))
                         }
                     },
                     None => match column.not_null {
-                        true => return Err(format!("Column '{}' does not allow NULL values", column.name)),
+                        true => return Err(self.raise(ErrorKind::Constraint, format!("Column '{}' does not allow NULL values", column.name))),
                         false => Value::Null,
                     }
                 }
             };
 
-            let parsed_value = column.transform_value(&value)?;
+            let parsed_value = self.column_value(column, &value)?;
             row.set(i, parsed_value)
         }
 
-        // check if row passes all unique constraints
-        table.check_unique(&row)?;
+        // before triggers can modify the row, constraints are checked on the modified row
+        let before = table.triggers_for(TriggerTiming::Before, TriggerEvent::Insert);
+        let row = self.fire_triggers(table, &before, None, Some(row), ctx)?.expect("Insert trigger should keep the new row");
 
         // check rls
         let column_map = table.get_column_map(&table.get_column_names()).unwrap();
         let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
         ctx.set_row(&row);
-        let policies = table.police(&ctx.cluster_user(), RlsAction::Insert);
+        let policies = table.police(ctx.cluster_user(), RlsAction::Insert);
         if !self.eval_policies(&policies, ctx)? {
-            return Err("Insertion violates row level security policy".to_string())
+            return Err(self.raise(ErrorKind::Auth, "Insertion violates row level security policy".to_string()))
         }
 
-        let row_values = row.iter().map(|value| value.clone()).collect();
-        table.data.buf_rows.push(row);
-        table.sync_buffer()?;
+        // resolve conflicts on the target columns, the write lock is held so it's atomic
+        if let Some(on_conflict) = &insert.on_conflict {
+            let column_indexes = self.conflict_column_indexes(table, &on_conflict.columns)?;
+            if let Some((index, _)) = table.find_conflict(&row, &column_indexes, None) {
+                return self.plan_insert_conflict(table, index, &row, &on_conflict.action, batch, ctx)
+            }
+
+            if batch.inserted_conflict(table, &row, &column_indexes).is_some() {
+                return match on_conflict.action {
+                    ConflictAction::Nothing => Ok(InsertPlan::Skip),
+                    ConflictAction::Update(_) => Err("Insert on conflict cannot update a row inserted by the same query".to_string()),
+                }
+            }
+        }
 
-        Ok(Some(Value::Array(vec![Value::Array(row_values)])))
+        // check if row passes all unique constraints
+        table.check_unique(&row).map_err(|e| self.raise(ErrorKind::Constraint, e))?;
+        self.check_unique_in_batch(table, &row, &table.get_unique_column_indexes(), batch)?;
+
+        batch.insert(table, &row);
+        table.data.buf_rows.push(row.clone());
+        Ok(InsertPlan::Insert(row))
+    }
+    
+    /// Returns indexes of the conflict target columns, or all unique columns if 'columns' is empty
+    fn conflict_column_indexes(&self, table: &Table, columns: &[(String, usize)]) -> Result<Vec<usize>, String> {
+        if columns.is_empty() {
+            return Ok(table.get_unique_column_indexes())
+        }
+
+        columns.iter().map(|(name, _)| {
+            let index = table.get_column_index(name)?;
+            if !table.columns[index].unique {
+                return Err(format!("Conflict column '{}' does not have a unique constraint", name))
+            }
+            Ok(index)
+        }).collect()
+    }
+
+    /// Checks 'row' against rows inserted and updated by the current insert query
+    fn check_unique_in_batch(&self, table: &Table, row: &Row, column_indexes: &[usize], batch: &InsertBatch) -> Result<(), String> {
+        match batch.conflict(table, row, column_indexes) {
+            Some(i) => Err(self.raise(ErrorKind::Constraint, format!("Value '{}' for column '{}' is not unique", row.get(i).unwrap(), table.columns[i].name))),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolves an insert conflict with the existing row at 'index', 'excluded' is the row which
+    /// was proposed for insertion
+    fn plan_insert_conflict(&self, table: &Table, index: usize, excluded: &Row, action: &ConflictAction, batch: &mut InsertBatch, ctx: &Ctx) -> Result<InsertPlan, String> {
+        let key_values = match action {
+            ConflictAction::Nothing => return Ok(InsertPlan::Skip),
+            ConflictAction::Update(key_values) => key_values,
+        };
+
+        if batch.updated_rows.contains(&index) {
+            return Err(self.raise(ErrorKind::Constraint, "Insert on conflict cannot update the same row twice".to_string()))
+        }
+
+        table.authorize(ctx.cluster_user(), TableAction::Update).map_err(|e| self.raise(ErrorKind::Auth, e))?;
+
+        let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+        let trigger_ctx = ctx;
+        let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
+
+        // existing row is accessible directly or as '<table>.<column>', proposed row as 'excluded.<column>'
+        let row = table.data.get(index).unwrap();
+        let joined_row = vec![row as *const Row, excluded as *const Row];
+        ctx.set_row(row);
+        ctx.set_joined_row(&joined_row);
+        ctx.set_aliased_tables(table, &[&table.name, "excluded"]);
+
+        // check rls
+        let policies = table.police(ctx.cluster_user(), RlsAction::Update);
+        if !self.eval_policies(&policies, ctx)? {
+            return Err(self.raise(ErrorKind::Auth, "Update violates row level security policy".to_string()))
+        }
+
+        let mut parsed_key_vals = vec![];
+        for (name, _, node) in key_values {
+            let i = table.get_column_index(name)?;
+            let value = self.run(node, ctx)?.ok_or(format!("Value for column '{}' was not evaluated", name))?;
+            parsed_key_vals.push((i, self.column_value(&table.columns[i], &value)?));
+        }
+
+        let mut updated = row.clone();
+        updated.update_with(&parsed_key_vals);
+
+        let before = table.triggers_for(TriggerTiming::Before, TriggerEvent::Update);
+        let updated = self.fire_triggers(table, &before, Some(row), Some(updated), trigger_ctx)?.expect("Update trigger should keep the new row");
+        let parsed_key_vals = changed_values(row, &updated);
+
+        // updated unique columns cannot conflict with any other row
+        let unique_indexes = parsed_key_vals.iter().map(|(i, _)| *i).filter(|&i| table.columns[i].unique).collect::<Vec<_>>();
+        if let Some((_, i)) = table.find_conflict(&updated, &unique_indexes, Some(index)) {
+            return Err(self.raise(ErrorKind::Constraint, format!("Value '{}' for column '{}' is not unique", updated.get(i).unwrap(), table.columns[i].name)))
+        }
+        self.check_unique_in_batch(table, &updated, &unique_indexes, batch)?;
+
+        batch.update(table, index, &updated);
+        Ok(InsertPlan::Update(index, parsed_key_vals, updated))
     }
     
     fn eval_update(&self, update: &UpdateQuery, ctx: &Ctx) -> RunnerResult {
         let mut database = self.database.write().map_err(|_| "Cannot call 'query update' when in read mode")?;
         let table = match database.get_table_mut(&update.table) {
             Some(table) => table,
-            None => return Err(format!("Table '{}' does not exist in database '{}'", update.table, database.name))
+            None => return Err(self.raise(ErrorKind::NotFound, format!("Table '{}' does not exist in database '{}'", update.table, database.name)))
         };
 
-        table.authorize(&ctx.cluster_user(), TableAction::Update)?;
+        table.authorize(ctx.cluster_user(), TableAction::Update).map_err(|e| self.raise(ErrorKind::Auth, e))?;
 
         // eval the key_values
         let mut key_values = vec![];
-        for (key, value) in &update.key_values {
+        for (key, _, value) in &update.key_values {
             let value = self.run(value, ctx)?.expect(&format!("Value for column '{}' was not evaluated", key));
             key_values.push((key, value));
         }
         // TODO: check duplicates
 
-        let column_names = update.key_values.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+        let column_names = update.key_values.iter().map(|(key, _, _)| key.as_str()).collect::<Vec<_>>();
         let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+        let trigger_ctx = ctx;
         let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
 
         // check if columns exist in the table
         // table.check_columns_exist(&column_names)?;
         column_names.iter().map(|name| {
             table.check_column_exists(name)
-        }).collect::<Result<_, _>>()?;
+        }).collect::<Result<(), _>>().map_err(|e| self.raise(ErrorKind::NotFound, e))?;
 
         // check if any of the columns have unique constraints
         let unique_columns = column_names.iter().filter(|name| {
@@ -387,20 +444,27 @@ impl Runner {
                 v => Some(v.to_string())
             };
 
-            let parsed_value = table.columns[i].validate_option(&value_str)?;
+            let parsed_value = match table.columns[i].data_type {
+                // json values are stored as they are, their text is not json
+                ColumnType::Json(_) => self.column_value(&table.columns[i], value)?,
+                _ => table.columns[i].validate_option(&value_str).map_err(|e| self.value_error(&table.columns[i], value, e))?,
+            };
             parsed_key_vals.push((i, parsed_value));
         }
-        let column_indexes = parsed_key_vals.iter().map(|(i, _)| *i).collect::<Vec<_>>();
 
         // HINT: this is somewhat safe, because policies will not be modified via the mutable
         // usage of the table below, and it's faster than cloning
         let policies = table.police(&ctx.cluster_user(), RlsAction::Update).iter().map(|&p| p as *const Node ).collect::<Vec<_>>();
         let policies = policies.into_iter().map(|p| unsafe { &*p as &Node }).collect::<Vec<_>>();
 
-        // evaluate where clause on each row
-        let mut updated_rows_count = 0;
+        let before = table.triggers_for(TriggerTiming::Before, TriggerEvent::Update);
+        let after = table.triggers_for(TriggerTiming::After, TriggerEvent::Update);
+
+        // evaluate where clause and before triggers on each row, rows are written afterwards so
+        // an aborted statement doesn't leave some of them updated
+        let mut updates = vec![];
         for index in 0..table.data.len() {
-            let row = table.data.get_mut(index).unwrap();
+            let row = table.data.get(index).unwrap();
             if row.is_deleted() { continue }
 
             ctx.set_row(row);
@@ -408,7 +472,7 @@ impl Runner {
             // check rls
             // TODO: give access to "new row" in ctx for rls
             if !self.eval_policies(&policies, ctx)? {
-                return Err("Insertion violates row level security policy".to_string())
+                return Err(self.raise(ErrorKind::Auth, "Insertion violates row level security policy".to_string()))
             }
 
             let where_clause_result = match &update.where_clause {
@@ -419,29 +483,61 @@ impl Runner {
 
             match where_clause_result {
                 Ok(Some(Value::Boolean(true))) => {
-                    row.update_with(&parsed_key_vals);
-                    table.sync_row_parts(index, &column_indexes)?;                           
-                    updated_rows_count += 1;
+                    let mut new = row.clone();
+                    new.update_with(&parsed_key_vals);
+                    let new = self.fire_triggers(table, &before, Some(row), Some(new), trigger_ctx)?.expect("Update trigger should keep the new row");
+
+                    // triggers can change unique columns, which cannot conflict with any other row
+                    let unique_indexes = changed_values(row, &new).iter().map(|(i, _)| *i).filter(|&i| table.columns[i].unique).collect::<Vec<_>>();
+                    if let Some((_, i)) = table.find_conflict(&new, &unique_indexes, Some(index)) {
+                        return Err(format!("Value '{}' for column '{}' is not unique", new.get(i).unwrap(), table.columns[i].name))
+                    }
+
+                    updates.push((index, new));
                 },
                 Ok(Some(Value::Boolean(false))) => (),
                 Ok(_) => return Err("Where clause must return a boolean value".to_string()),
                 Err(err) => return Err(err)
             };
         }
+
+        let mut old_rows = vec![];
+        for (index, new) in &updates {
+            let row = table.data.get_mut(*index).unwrap();
+            let key_values = changed_values(row, new);
+            if !after.is_empty() {
+                old_rows.push(row.clone());
+            }
+
+            row.update_with(&key_values);
+            let column_indexes = key_values.iter().map(|(i, _)| *i).collect();
+            table.sync_row_parts(*index, &column_indexes)?;
+        }
+        table.sync_indexes()?;
+
+        for (old, (_, new)) in old_rows.iter().zip(&updates) {
+            self.fire_triggers(table, &after, Some(old), Some(new.clone()), trigger_ctx)?;
+        }
+
+        if let Some(returning) = &update.returning {
+            let updated_rows = updates.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+            return self.eval_returning(table, &updated_rows, returning, ctx)
+        }
         
-        Ok(Some(Value::Numeric(NumericValue::IntU64(updated_rows_count as u64))))
+        Ok(Some(Value::Numeric(NumericValue::IntU64(updates.len() as u64))))
     }
 
     fn eval_delete(&self, delete: &DeleteQuery, ctx: &Ctx) -> RunnerResult {
         let mut database = self.database.write().map_err(|_| "Cannot call 'query delete' when in read mode")?;
         let table = match database.get_table_mut(&delete.table) {
             Some(table) => table,
-            None => return Err(format!("Table '{}' does not exist in database '{}'", delete.table, database.name))
+            None => return Err(self.raise(ErrorKind::NotFound, format!("Table '{}' does not exist in database '{}'", delete.table, database.name)))
         };
 
-        table.authorize(&ctx.cluster_user(), TableAction::Delete)?;
+        table.authorize(ctx.cluster_user(), TableAction::Delete).map_err(|e| self.raise(ErrorKind::Auth, e))?;
 
         let column_map = table.get_column_map(&table.get_column_names()).unwrap();
+        let trigger_ctx = ctx;
         let ctx = &Ctx::scoped_with(ctx.clone(), column_map);
 
         // HINT: this is somewhat safe, because policies will not be modified via the mutable
@@ -449,17 +545,21 @@ impl Runner {
         let policies = table.police(&ctx.cluster_user(), RlsAction::Delete).iter().map(|&p| p as *const Node ).collect::<Vec<_>>();
         let policies = policies.into_iter().map(|p| unsafe { &*p as &Node }).collect::<Vec<_>>();
 
-        // evaluate where clause on each row
-        let mut deleted_rows_count = 0;
+        let before = table.triggers_for(TriggerTiming::Before, TriggerEvent::Delete);
+        let after = table.triggers_for(TriggerTiming::After, TriggerEvent::Delete);
+
+        // evaluate where clause and before triggers on each row, rows are deleted afterwards so
+        // an aborted statement doesn't leave some of them deleted
+        let mut deletes = vec![];
         for index in 0..table.data.len() {
-            let row = table.data.get_mut(index).unwrap();
+            let row = table.data.get(index).unwrap();
             if row.is_deleted() { continue }
             
             ctx.set_row(row); 
 
             // check rls
             if !self.eval_policies(&policies, ctx)? {
-                return Err("Insertion violates row level security policy".to_string())
+                return Err(self.raise(ErrorKind::Auth, "Insertion violates row level security policy".to_string()))
             }
 
             let where_clause_result = match &delete.where_clause {
@@ -470,16 +570,62 @@ impl Runner {
 
             match where_clause_result {
                 Ok(Some(Value::Boolean(true))) => {
-                    row.mark_deleted();
-                    table.sync_flags(index)?;
-                    deleted_rows_count += 1;
+                    self.fire_triggers(table, &before, Some(row), None, trigger_ctx)?;
+                    deletes.push(index);
                 },
                 Ok(Some(Value::Boolean(false))) => (),
                 Ok(v) => return Err(format!("Where clause must return a boolean value, got: {:?}", v)),
                 Err(err) => return Err(err)
             };
         }
+
+        let keep_rows = delete.returning.is_some() || !after.is_empty();
+        let mut deleted_rows = vec![];
+        for &index in &deletes {
+            let row = table.data.get_mut(index).unwrap();
+            row.mark_deleted();
+            if keep_rows {
+                deleted_rows.push(row.clone());
+            }
+            table.sync_flags(index)?;
+        }
+        table.sync_indexes()?;
+
+        for row in &deleted_rows {
+            self.fire_triggers(table, &after, Some(row), None, trigger_ctx)?;
+        }
+
+        if let Some(returning) = &delete.returning {
+            return self.eval_returning(table, &deleted_rows, returning, ctx)
+        }
         
-        Ok(Some(Value::Numeric(NumericValue::IntU64(deleted_rows_count as u64))))
+        Ok(Some(Value::Numeric(NumericValue::IntU64(deletes.len() as u64))))
     }
 }
+
+/// Returns the values of 'new' which differ from 'old', as (column index, value) pairs
+fn changed_values(old: &Row, new: &Row) -> Vec<(usize, Value)> {
+    new.iter().enumerate()
+        .filter(|&(i, value)| old.get(i) != Some(value))
+        .map(|(i, value)| (i, value.clone()))
+        .collect()
+}
+
+/// Rows of an 'as object' select, every row becomes an object keyed by the result column names
+pub(super) fn row_objects(columns: &[Column], rows: Vec<Value>) -> Result<Vec<Value>, String> {
+    let mut objects = Vec::with_capacity(rows.len());
+    for row in rows {
+        let values = match row {
+            Value::Array(values) => values,
+            _ => return Err("Select row is not an array".to_string())
+        };
+
+        let mut object = Value::Object(Vec::with_capacity(columns.len()));
+        for (column, value) in columns.iter().zip(values) {
+            object.set_member(&column.name, value)?;
+        }
+        objects.push(object);
+    }
+
+    Ok(objects)
+}
@@ -1,6 +1,6 @@
-use crate::{syntax::{context::Ctx, ast::{SDL, CreateSDL, GrantSDL}}, basics::{Column, Table}, auth::RlsPolicy};
+use crate::{syntax::{context::Ctx, ast::{SDL, CreateSDL, GrantSDL}}, basics::{Column, Table, Trigger}, auth::RlsPolicy, index::{Index, IndexKind}};
 
-use super::{Runner, RunnerResult};
+use super::{Runner, RunnerResult, error::ErrorKind};
 
 impl Runner {
     pub(super) fn eval_sdl(&self, sdl: &SDL, ctx: &Ctx) -> RunnerResult {
@@ -18,13 +18,16 @@ impl Runner {
             CreateSDL::Table { name, columns } => self.eval_create_table(name, columns, ctx),
             CreateSDL::RlsPolicy { table, policy } => self.eval_create_rls_policy(table, policy, ctx),
             CreateSDL::Role { name } => self.eval_create_role(name, ctx),
-            CreateSDL::User { name, password, is_superuser } => self.eval_create_user(name, password, *is_superuser, ctx)
+            CreateSDL::User { name, password, is_superuser } => self.eval_create_user(name, password, *is_superuser, ctx),
+            CreateSDL::Index { name, table, column, kind } => self.eval_create_index(name, table, column, kind, ctx),
+            CreateSDL::Trigger { table, trigger } => self.eval_create_trigger(table, trigger, ctx),
+            CreateSDL::Function { name, parameters, return_type, block, replace, .. } => self.eval_create_function(name, parameters, return_type, block, *replace, ctx),
         }
     }
 
     fn eval_create_rls_policy(&self, table: &str, policy: &RlsPolicy, ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't create rls policies, permission denied".to_string());
+            return Err(self.raise(ErrorKind::Auth, "Can't create rls policies, permission denied".to_string()));
         }
 
         let mut database = self.database.write().map_err(|_| "Can't create rls when in read mode")?;
@@ -33,9 +36,31 @@ impl Runner {
         Ok(None)
     }
 
+    fn eval_create_index(&self, name: &str, table: &str, column: &str, kind: &IndexKind, ctx: &Ctx) -> RunnerResult {
+        if !ctx.cluster_user().is_superuser {
+            return Err(self.raise(ErrorKind::Auth, "Can't create index, permission denied".to_string()));
+        }
+
+        let mut database = self.database.write().map_err(|_| "Can't create index when in read mode")?;
+        database.create_index(table, Index::new(name, column, kind.clone()), ctx)?;
+
+        Ok(None)
+    }
+
+    fn eval_create_trigger(&self, table: &str, trigger: &Trigger, ctx: &Ctx) -> RunnerResult {
+        if !ctx.cluster_user().is_superuser {
+            return Err(self.raise(ErrorKind::Auth, "Can't create trigger, permission denied".to_string()));
+        }
+
+        let mut database = self.database.write().map_err(|_| "Can't create trigger when in read mode")?;
+        database.create_trigger(table, trigger.clone(), ctx)?;
+
+        Ok(None)
+    }
+
     fn eval_create_database(&self, name: &str, ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't create database, permission denied".to_string());
+            return Err(self.raise(ErrorKind::Auth, "Can't create database, permission denied".to_string()));
         }
 
         let mut cluster = ctx.cluster().write().map_err(|_| "Can't create database when in read mode")?;
@@ -46,7 +71,7 @@ impl Runner {
 
     fn eval_create_table(&self, name: &str, columns: &[Column], ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't create table, permission denied".to_string());
+            return Err(self.raise(ErrorKind::Auth, "Can't create table, permission denied".to_string()));
         }
 
         let mut table = Table::new(name);
@@ -59,7 +84,7 @@ impl Runner {
 
     fn eval_create_role(&self, name: &str, ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't create role, permission denied".to_string());
+            return Err(self.raise(ErrorKind::Auth, "Can't create role, permission denied".to_string()));
         }
 
         let mut cluster = ctx.cluster().write().map_err(|_| "Can't create role when in read mode")?;
@@ -70,7 +95,7 @@ impl Runner {
 
     fn eval_create_user(&self, name: &str, password: &str, is_superuser: bool, ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't create user, permission denied".to_string());
+            return Err(self.raise(ErrorKind::Auth, "Can't create user, permission denied".to_string()));
         }
 
         let mut cluster = ctx.cluster().write().map_err(|_| "Can't create user when in read mode")?;
@@ -89,7 +114,7 @@ impl Runner {
 
     fn eval_grant_role(&self, name: &str, to: &str, ctx: &Ctx) -> RunnerResult {
         if !ctx.cluster_user().is_superuser {
-            return Err("Can't grant role, permission denied".to_string())
+            return Err(self.raise(ErrorKind::Auth, "Can't grant role, permission denied".to_string()))
         }
 
         let mut cluster = ctx.cluster().write().map_err(|_| "Can't grant role when in read mode")?;
@@ -1,25 +1,31 @@
-use crate::{syntax::{ast::{Statement, Node}, context::RunnerContextVariable}, basics::Value};
+use crate::{syntax::{ast::{Statement, Node, Literal, Expression}, context::RunnerContextVariable}, basics::Value};
 
 use super::{Runner, Ctx, RunnerResult};
 
 impl Runner {
     pub(super) fn eval_statement(&self, statement: &Statement, ctx: &Ctx) -> RunnerResult {
         match statement {
-            Statement::Assignment { name, value } => self.eval_assignment(name, value, ctx),
+            Statement::Assignment { name, value, .. } => self.eval_assignment(name, value, ctx),
+            Statement::MemberAssignment { object, member, value } => self.eval_member_assignment(object, member, value, ctx),
             Statement::Expression(_) => unimplemented!("statement expression"),
-            Statement::Function { name, parameters, return_type, block } 
-                => self.eval_function(name, parameters, return_type, block),     
-            Statement::Let { name, value } => self.eval_declaration(name, value, ctx),
-            Statement::If { condition, then_block, else_block } 
+            Statement::Function { name, parameters, return_type, block, .. } 
+                => self.eval_function(name, parameters, return_type, block, ctx),     
+            Statement::Let { name, value, .. } => self.eval_declaration(name, value, ctx),
+            Statement::If { condition, then_block, else_block, .. } 
                 => self.eval_if(condition, then_block, else_block, ctx),
-            Statement::While { condition, block } => self.eval_while(condition, block, ctx),
-            Statement::For { initializer, condition, action, block }
+            Statement::While { condition, block, .. } => self.eval_while(condition, block, ctx),
+            Statement::For { initializer, condition, action, block, .. }
                 => self.eval_for(initializer, condition, action, block, ctx),
+            Statement::ForIn { index, pattern, iterable, block, .. } => self.eval_for_in(index, pattern, iterable, block, ctx),
             Statement::Loop { block } => self.eval_loop(block, ctx),
+            Statement::Import { path, .. } => self.eval_import(path, ctx),
+            Statement::Export { .. } => Err("Exports are only allowed at the top level of a module".to_string()),
+            Statement::Throw { value, line, .. } => self.eval_throw(value, *line, ctx),
+            Statement::Try { block, lines, name, catch_block } => self.eval_try(block, lines, name, catch_block, ctx),
 
-            Statement::Return(_) => Err("Return outside of function".to_string()),
-            Statement::Break => Err("Break outside of loop".to_string()),
-            Statement::Continue => Err("Continue outside of loop".to_string()),
+            Statement::Return { value: _, .. } => Err("Return outside of function".to_string()),
+            Statement::Break { .. } => Err("Break outside of loop".to_string()),
+            Statement::Continue { .. } => Err("Continue outside of loop".to_string()),
         }
     }
 
@@ -46,6 +52,7 @@ impl Runner {
     }
 
     fn eval_declaration(&self, name: &str, value: &Node, ctx: &Ctx) -> RunnerResult {
+        self.check_declaration(name)?;
         let value = self.run(value, ctx)?.ok_or("Cannot declare a statement without a return value")?;
         ctx.declare(name, value);
         Ok(None)
@@ -56,4 +63,40 @@ impl Runner {
         ctx.assign(name, value)?;
         Ok(None)
     }
+
+    fn eval_member_assignment(&self, object: &Node, member: &str, value: &Node, ctx: &Ctx) -> RunnerResult {
+        let value = self.run(value, ctx)?.ok_or("Cannot assign a statement without a return value")?;
+
+        // walk down to the variable, 'a.b.c = 1' sets 'c' of the object at 'a.b'
+        let mut path = vec![member];
+        let mut current = object;
+        while let Node::Expression(Expression::Member { object, member, .. }) = current {
+            path.push(member);
+            current = object;
+        }
+
+        let name = match current {
+            Node::Literal(Literal::Identifier(name, _)) => name,
+            _ => return Err("Only members of variables and trigger rows can be assigned".to_string())
+        };
+
+        let variable = match ctx.get(name) {
+            Ok(variable) => variable,
+            Err(_) if path.len() == 1 => {
+                self.assign_trigger_row(name, member, value, ctx)?;
+                return Ok(None)
+            },
+            Err(error) => return Err(error)
+        };
+
+        let mut variable = variable.lock().unwrap_or_else(|e| e.into_inner());
+        let mut target = &mut *variable;
+        let (member, parents) = path.split_first().unwrap();
+        for parent in parents.iter().rev() {
+            target = target.get_member_mut(parent).ok_or(format!("Member '{}' not found in object", parent))?;
+        }
+        target.set_member(member, value)?;
+
+        Ok(None)
+    }
 }
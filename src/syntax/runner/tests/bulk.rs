@@ -0,0 +1,48 @@
+use crate::utils::test::TestDatabase;
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(16), unique; n: i64; }"#);
+    db
+}
+
+#[test]
+fn insert_many_rows() {
+    let db = items();
+
+    db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }, { name: "c" }]"#);
+    assert_eq!(db.query("query items select *"), "[[0, a, 1], [1, b, 2], [2, c, NULL]]");
+}
+
+#[test]
+fn sequence_continues_after_stored_and_explicit_values() {
+    let db = items();
+
+    db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }]"#);
+    db.query(r#"query items insert [{ name: "c", n: 3 }, { id: 10, name: "d", n: 4 }, { name: "e", n: 5 }]"#);
+    assert_eq!(db.query("query items select id, name"), "[[0, a], [1, b], [2, c], [10, d], [11, e]]");
+}
+
+#[test]
+fn duplicate_in_batch_writes_nothing() {
+    let db = items();
+
+    db.query(r#"query items insert name: "a" n: 1"#);
+    let error = db.error(r#"query items insert [{ name: "b", n: 1 }, { name: "b", n: 2 }]"#);
+    assert_eq!(error, "Value 'b' for column 'name' is not unique");
+
+    let error = db.error(r#"query items insert [{ name: "c", n: 1 }, { name: "a", n: 2 }]"#);
+    assert_eq!(error, "Value 'a' for column 'name' is not unique");
+    assert_eq!(db.query("query items select name"), "[[a]]");
+}
+
+#[test]
+fn large_batch() {
+    let db = items();
+
+    let rows = (0..2000).map(|i| format!(r#"{{ name: "n{}", n: {} }}"#, i, i)).collect::<Vec<_>>();
+    db.query(&format!("query items insert [{}]", rows.join(", ")));
+
+    assert_eq!(db.query("let ids = query items select id; len(ids)"), "2000");
+    assert_eq!(db.query(r#"query items select id where name == "n1999""#), "[[1999]]");
+}
@@ -0,0 +1,129 @@
+use crate::{utils::test::TestDatabase, database::{Database, Run}};
+
+/// Messages of the errors the type checker reports for 'script' and the offsets they point at
+fn errors(db: &TestDatabase, script: &str) -> Vec<(String, usize)> {
+    let statement = Database::prepare(script.to_string()).unwrap();
+    let database = db.database.read().unwrap();
+
+    match Database::check(&database, &statement) {
+        Ok(()) => vec![],
+        Err(e) => e.errors().iter().map(|error| (error.message().to_string(), error.token().index.start)).collect(),
+    }
+}
+
+/// Offset of the 'n'th (from 0) occurrence of 'needle' in 'script'
+fn offset(script: &str, needle: &str, n: usize) -> usize {
+    script.match_indices(needle).nth(n).unwrap().0
+}
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { name: fixed(16), unique; n: i64; }"#);
+    db
+}
+
+#[test]
+fn repeated_names_point_at_each_use() {
+    let db = TestDatabase::new();
+
+    let script = "let a = 1; a = missing + missing;";
+    assert_eq!(errors(&db, script), vec![
+        ("Variable 'missing' not found".to_string(), offset(script, "missing", 0)),
+        ("Variable 'missing' not found".to_string(), offset(script, "missing", 1)),
+    ]);
+}
+
+#[test]
+fn calls_point_at_their_name() {
+    let db = TestDatabase::new();
+
+    let script = r#"fn f(x: int) -> int { return x; }; f(f("a"));"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Function 'f' expects argument 'x' to be of type 'Int' but got 'String'".to_string(), offset(script, "f(", 2)),
+    ]);
+}
+
+#[test]
+fn statements_point_at_their_keyword() {
+    let db = TestDatabase::new();
+
+    let script = r#"fn f() -> int { return "a"; }; if 1 { let x = 1; }; break;"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Function 'f' must return a value of type 'Int' but returns 'String'".to_string(), offset(script, "return", 0)),
+        ("If condition must be a boolean, got 'Int'".to_string(), offset(script, "if", 0)),
+        ("Break outside of loop".to_string(), offset(script, "break", 0)),
+    ]);
+}
+
+#[test]
+fn columns_point_at_their_name() {
+    let db = items();
+
+    // 'colour' is also a variable, the errors point at the column names
+    let script = r#"let colour = "red"; query items insert [{ name: colour, colour: colour }] on conflict (colour) do nothing"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Column 'colour' not found in table 'items'".to_string(), offset(script, "colour", 2)),
+        ("Column 'colour' not found in table 'items'".to_string(), offset(script, "colour", 4)),
+    ]);
+
+    let script = r#"query items update n: 1, size: n where items.size == n"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Column 'size' not found in table 'items'".to_string(), offset(script, "size", 0)),
+        ("Column 'size' not found in table 'items'".to_string(), offset(script, "size", 1)),
+    ]);
+}
+
+#[test]
+fn tables_point_at_their_name() {
+    let db = items();
+
+    let script = "let rows = query items select name; query missing select name";
+    assert_eq!(errors(&db, script), vec![
+        ("Table 'missing' does not exist in database 'testdb'".to_string(), offset(script, "missing", 0)),
+    ]);
+}
+
+#[test]
+fn declarations_cannot_take_database_function_names() {
+    let db = TestDatabase::new();
+
+    let script = "fn len(x: int) -> int { return x; }; let round = 1;";
+    assert_eq!(errors(&db, script), vec![
+        ("Cannot declare 'len', the database has a function with the same name".to_string(), offset(script, "len", 0)),
+        ("Cannot declare 'round', the database has a function with the same name".to_string(), offset(script, "round", 0)),
+    ]);
+}
+
+#[test]
+fn operands_of_known_types_must_match_the_operator() {
+    let db = TestDatabase::new();
+
+    let script = r#"let s = "a"; let t = true; let x = s * 2; let y = t - 1; let z = [1] + 1; let o = { a: 1 } / 1;"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Cannot multiply 'String' and 'Int'".to_string(), offset(script, "*", 0)),
+        ("Cannot subtract 'Boolean' and 'Int'".to_string(), offset(script, "-", 0)),
+        ("Cannot add 'Array(Int)' and 'Int'".to_string(), offset(script, "+", 0)),
+        ("Cannot divide 'Object' and 'Int'".to_string(), offset(script, "/", 0)),
+    ]);
+
+    let script = r#"let f = fn(x: int) -> int { return x; }; let t = true; let a = f + 1; let b = 1 && t; let c = t || "a";"#;
+    assert_eq!(errors(&db, script), vec![
+        ("Cannot add 'Function([Int], Int)' and 'Int'".to_string(), offset(script, "+", 0)),
+        ("Cannot apply '&&' to 'Int' and 'Boolean'".to_string(), offset(script, "&&", 0)),
+        ("Cannot apply '||' to 'Boolean' and 'String'".to_string(), offset(script, "||", 0)),
+    ]);
+
+    // the script is rejected before the insert runs
+    db.query("create table items { n: i64; }");
+    let error = db.error(r#"query items insert n: 1; "a" * 2"#);
+    assert!(error.contains("Cannot multiply 'String' and 'Int'"), "{}", error);
+    assert_eq!(db.query("query items select n"), "[]");
+}
+
+#[test]
+fn operands_of_unknown_types_are_checked_when_the_script_runs() {
+    let db = TestDatabase::new();
+
+    let script = r#"let a = ["a", 1]; let x = a[0] * 2; let y = $1 && 1; let z = 1 + null; let s = "a" + "b"; let n = 1 + 2.5;"#;
+    assert_eq!(errors(&db, script), vec![]);
+}
@@ -0,0 +1,40 @@
+use crate::utils::test::TestDatabase;
+
+#[test]
+fn closures_see_later_assignments() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("let x = 1; let f = fn() { return x; }; x = 2; f()"), "2");
+}
+
+#[test]
+fn closure_assignments_are_seen_by_their_scope() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("let count = 0; let inc = fn() { count = count + 1; }; inc(); inc(); count"), "2");
+    assert_eq!(db.query("let total = 0; map([1, 2, 3], fn(x) { total = total + x; return x; }); total"), "6");
+}
+
+#[test]
+fn copies_of_a_closure_share_its_variables() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("let n = 0; let a = fn() { n = n + 1; return n; }; let b = a; a(); b()"), "2");
+}
+
+#[test]
+fn returned_closures_keep_their_own_variables() {
+    let db = TestDatabase::new();
+
+    let script = "fn counter() { let n = 0; return fn() { n = n + 1; return n; }; }; \
+        let a = counter(); let b = counter(); a(); a(); [a(), b()]";
+    assert_eq!(db.query(script), "[3, 1]");
+}
+
+#[test]
+fn module_variables_are_shared_with_the_importer() {
+    let db = TestDatabase::new();
+    let path = db.module("counter.db", "export let count = 0; export fn inc() { count = count + 1; };");
+
+    assert_eq!(db.query(&format!("import \"{}\"; inc(); inc(); count", path)), "2");
+}
@@ -0,0 +1,85 @@
+use crate::{utils::test::TestDatabase, auth::{User, Privilege, action::{TableAction, FunctionAction}}};
+
+fn cats() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table cats { id: i64, unique; parent: i64; name: fixed(16); }");
+    db.query(r#"query cats insert [{ id: 1, name: "root" }, { id: 2, parent: 1, name: "a" }, { id: 3, parent: 1, name: "b" }, { id: 4, parent: 2, name: "a1" }, { id: 5, parent: 4, name: "a1x" }]"#);
+    db
+}
+
+#[test]
+fn with_query() {
+    let db = cats();
+
+    assert_eq!(db.query("with tops as (query cats select id, name where parent == null) query tops select *"), "[[1, root]]");
+}
+
+#[test]
+fn chained_expressions() {
+    let db = cats();
+
+    let result = db.query("with a as (query cats select id where id > 2), b as (query a select id where id < 5) query b select *");
+    assert_eq!(result, "[[3], [4]]");
+}
+
+#[test]
+fn recursive_query() {
+    let db = cats();
+
+    let result = db.query("with recursive tree(id, name) as (query cats select id, name where id == 2 union all query cats select id, name join tree on cats.parent == id) query tree select *");
+    assert_eq!(result, "[[2, a], [4, a1], [5, a1x]]");
+
+    let result = db.query("with recursive tree as (query cats select id, name where id == 2 union query cats select cats.id, cats.name join tree on cats.parent == id) query tree select name order id desc");
+    assert_eq!(result, "[[a1x], [a1], [a]]");
+}
+
+#[test]
+fn recursive_union_stops_without_new_rows() {
+    let db = cats();
+
+    assert_eq!(db.query("with recursive n as (query cats select id where id == 1 union query n select id) query n select *"), "[[1]]");
+}
+
+#[test]
+fn recursive_union_all_is_limited() {
+    let db = cats();
+
+    let error = db.error("with recursive loop as (query cats select id where id == 1 union all query loop select id) query loop select *");
+    assert_eq!(error, "Recursive expression 'loop' exceeded 10000 iterations");
+}
+
+#[test]
+fn column_names_must_match() {
+    let db = cats();
+
+    let error = db.error("with a(x, y) as (query cats select id) query a select *");
+    assert_eq!(error, "Expression 'a' has 1 columns, but 2 column names were specified");
+}
+
+#[test]
+fn expressions_do_not_shadow_tables_of_policies() {
+    let db = TestDatabase::new();
+    db.query("create table docs { id: i64; owner: fixed(16); }");
+    db.query("create table allowed { name: fixed(16); }");
+    db.query("create table names { name: fixed(16); }");
+    db.query(r#"query docs insert [{ id: 1, owner: "bob" }, { id: 2, owner: "eve" }]"#);
+    db.query(r#"query allowed insert name: "bob""#);
+    db.query(r#"query names insert [{ name: "bob" }, { name: "eve" }]"#);
+    db.query("create function visible(o: string) -> bool { let rows = query allowed select name where name == o; return len(rows) > 0; }");
+    db.query(r#"create policy "own" for docs.select visible(owner)"#);
+
+    let mut bob = User::new("bob", "");
+    for table in ["docs", "allowed", "names"] {
+        bob.privileges.insert(Privilege::table(table, TableAction::Select));
+    }
+    bob.privileges.insert(Privilege::function("visible", FunctionAction::Execute));
+
+    let result = db.run_as(bob.clone(), "query docs select *").unwrap();
+    assert_eq!(result.to_string(), "[[1, bob]]");
+
+    // 'allowed' in the function of the policy is still the table
+    let result = db.run_as(bob.clone(), "with allowed as (query names select name) query docs select *").unwrap();
+    assert_eq!(result.to_string(), "[[1, bob]]");
+    let result = db.run_as(bob, "with allowed as (query names select name) query allowed select *").unwrap();
+    assert_eq!(result.to_string(), "[[bob], [eve]]");
+}
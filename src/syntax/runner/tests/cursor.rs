@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use crate::{utils::test::TestDatabase, database::{Database, Run}, basics::Value, file::data::LoadMode, auth::{User, Privilege, action::TableAction}};
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(16); owner: fixed(16); }"#);
+    db.query(r#"query items insert [{ name: "a", owner: "bob" }, { name: "b", owner: "eve" }, { name: "c", owner: "bob" }, { name: "d", owner: "bob" }, { name: "e", owner: "eve" }]"#);
+    db
+}
+
+fn fetch_all(db: &TestDatabase, input: &str, fetch_size: usize) -> Vec<String> {
+    let statement = Database::prepare(input.to_string()).unwrap();
+    let mut cursor = Database::cursor(db.database.clone(), &statement, &[], fetch_size, Rc::new(db.options())).unwrap();
+
+    let mut batches = vec![];
+    loop {
+        let rows = cursor.fetch().unwrap();
+        if rows.is_empty() { break }
+        batches.push(Value::Array(rows).to_string());
+    }
+    batches
+}
+
+fn use_disk(db: &TestDatabase) {
+    db.database.write().unwrap().get_table_mut("items").unwrap().data.load_mode = LoadMode::Disk;
+}
+
+fn bob() -> User {
+    let mut user = User::new("bob", "");
+    user.privileges.insert(Privilege::table("items", TableAction::Select));
+    user
+}
+
+#[test]
+fn fetch_in_batches() {
+    let db = items();
+
+    let batches = fetch_all(&db, "query items select id, name where id > 0 order id asc", 2);
+    assert_eq!(batches, ["[[1, b], [2, c]]", "[[3, d], [4, e]]"]);
+}
+
+#[test]
+fn fetch_with_offset_and_limit() {
+    let db = items();
+
+    let batches = fetch_all(&db, r#"query items select name where name != "b" offset 1 limit 3"#, 2);
+    assert_eq!(batches, ["[[c], [d]]", "[[e]]"]);
+}
+
+#[test]
+fn cursor_skips_rows_deleted_while_open() {
+    let db = items();
+
+    let statement = Database::prepare("query items select name".to_string()).unwrap();
+    let mut cursor = Database::cursor(db.database.clone(), &statement, &[], 2, Rc::new(db.options())).unwrap();
+    assert_eq!(Value::Array(cursor.fetch().unwrap()).to_string(), "[[a], [b]]");
+
+    db.query("query items delete where id == 2");
+    assert_eq!(Value::Array(cursor.fetch().unwrap()).to_string(), "[[d], [e]]");
+}
+
+#[test]
+fn cursor_must_be_a_select() {
+    let db = items();
+
+    let statement = Database::prepare(r#"query items insert name: "x""#.to_string()).unwrap();
+    let error = Database::cursor(db.database.clone(), &statement, &[], 2, Rc::new(db.options())).err().unwrap();
+    assert_eq!(error, "Cursor statement must be a single select query");
+}
+
+#[test]
+fn disk_table_is_filtered_while_fetching() {
+    let db = items();
+    use_disk(&db);
+
+    let batches = fetch_all(&db, "query items select * where id > 0 offset 1", 2);
+    assert_eq!(batches, ["[[2, c, bob], [3, d, bob]]", "[[4, e, eve]]"]);
+}
+
+#[test]
+fn disk_table_policies_see_the_current_row() {
+    let db = items();
+    db.query(r#"create policy "own" for items.select items.owner == "bob""#);
+    use_disk(&db);
+
+    // the policy reads the row as '<table>.<column>', including the first row
+    let result = db.run_as(bob(), "query items select name").unwrap();
+    assert_eq!(result.to_string(), "[[a], [c], [d]]");
+
+    let result = db.run_as(bob(), "query items select name where items.name != \"c\"").unwrap();
+    assert_eq!(result.to_string(), "[[a], [d]]");
+}
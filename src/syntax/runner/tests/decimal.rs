@@ -0,0 +1,69 @@
+use crate::utils::test::TestDatabase;
+
+fn round(db: &TestDatabase, function: &str, value: &str, precision: i64) -> String {
+    db.query(&format!("{}(decimal(\"{}\", 3), {})", function, value, precision))
+}
+
+#[test]
+fn halves_are_rounded_by_mode() {
+    let db = TestDatabase::new();
+
+    // half up rounds away from zero
+    assert_eq!(round(&db, "round", "2.5", 0), "3");
+    assert_eq!(round(&db, "round", "-2.5", 0), "-3");
+    assert_eq!(round(&db, "round", "2.449", 1), "2.4");
+
+    // half even rounds to the nearest even digit
+    assert_eq!(round(&db, "round_even", "2.5", 0), "2");
+    assert_eq!(round(&db, "round_even", "3.5", 0), "4");
+    assert_eq!(round(&db, "round_even", "-0.125", 2), "-0.12");
+    assert_eq!(round(&db, "round_even", "0.126", 2), "0.13");
+
+    // truncation drops the digits
+    assert_eq!(round(&db, "trunc", "2.999", 2), "2.99");
+    assert_eq!(round(&db, "trunc", "-2.999", 0), "-2");
+}
+
+#[test]
+fn floor_and_ceil_follow_the_sign() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("floor(decimal(\"2.1\", 1))"), "2");
+    assert_eq!(db.query("floor(decimal(\"-2.1\", 1))"), "-3");
+    assert_eq!(db.query("ceil(decimal(\"2.1\", 1))"), "3");
+    assert_eq!(db.query("ceil(decimal(\"-2.1\", 1))"), "-2");
+}
+
+#[test]
+fn rounding_to_a_larger_scale_pads_zeros() {
+    let db = TestDatabase::new();
+
+    assert_eq!(round(&db, "round", "1.5", 5), "1.50000");
+    assert_eq!(db.query("decimal(\"1.005\", 2)"), "1.01");
+    assert_eq!(db.query("decimal(0.1, 3)"), "0.100");
+    assert!(db.error("round(decimal(\"1\", 0), 39)").contains("between 0 and 38"));
+}
+
+#[test]
+fn columns_round_to_their_scale() {
+    let db = TestDatabase::new();
+    db.query("create table prices { id: i64; price: decimal(5, 2); }");
+
+    db.query("query prices insert id: 1 price: decimal(\"1.005\", 3)");
+    db.query("query prices insert id: 2 price: 0.125");
+    db.query("query prices insert id: 3 price: \"-0.125\"");
+    assert_eq!(db.query("query prices select price"), "[[1.01], [0.13], [-0.13]]");
+
+    // rounding up may push the value over the precision
+    let error = db.error("query prices insert id: 4 price: decimal(\"999.995\", 3)");
+    assert!(error.starts_with("Decimal 999.995 does not fit into decimal(5, 2)"), "{}", error);
+}
+
+#[test]
+fn division_rounds_the_last_digit() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("decimal(\"2\", 0) / decimal(\"3\", 0)"), "0.666667");
+    assert_eq!(db.query("decimal(\"1.00\", 2) / decimal(\"3\", 0)"), "0.33333333");
+    assert_eq!(db.query("decimal(\"0.10\", 2) + decimal(\"0.2\", 1)"), "0.30");
+}
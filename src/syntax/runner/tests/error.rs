@@ -0,0 +1,61 @@
+use crate::{utils::test::TestDatabase, auth::User};
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { name: fixed(16), unique; n: i64, required; }"#);
+    db.query(r#"query items insert name: "a" n: 1"#);
+    db
+}
+
+/// Kind of the error 'statement' raises, as seen by a 'catch' block
+fn kind(statement: &str) -> String {
+    format!("let kind = \"none\"; try {{ {} }} catch (e) {{ kind = e.kind; }}; kind", statement)
+}
+
+#[test]
+fn constraint_errors() {
+    let db = items();
+
+    assert_eq!(db.query(&kind(r#"query items insert name: "a" n: 2;"#)), "constraint");
+    assert_eq!(db.query(&kind(r#"query items insert [{ name: "b", n: 2 }, { name: "b", n: 3 }];"#)), "constraint");
+    assert_eq!(db.query(&kind(r#"query items insert name: "c" n: null;"#)), "constraint");
+}
+
+#[test]
+fn auth_errors() {
+    let db = items();
+
+    let result = db.run_as(User::new("bob", ""), &kind(r#"query items insert name: "b" n: 2;"#)).unwrap();
+    assert_eq!(result.to_string(), "auth");
+    let result = db.run_as(User::new("bob", ""), &kind("create table other { n: i64; };")).unwrap();
+    assert_eq!(result.to_string(), "auth");
+}
+
+#[test]
+fn type_errors() {
+    let db = items();
+
+    // values of unknown type are only checked when the script runs
+    assert_eq!(db.query(&kind(r#"fn f(x: int) -> int { return x; }; let a = [1, "a"]; f(a[1]);"#)), "type");
+    assert_eq!(db.query(&kind("let x = 5; x[0];")), "type");
+    assert_eq!(db.query(&kind(r#"let a = [1, "a"]; query items insert name: "b" n: a[1];"#)), "type");
+}
+
+#[test]
+fn not_found_errors() {
+    let db = items();
+
+    // names in functions are only looked up when they run
+    assert_eq!(db.query(&kind("fn f() { missing(); }; f();")), "not_found");
+    assert_eq!(db.query(&kind("fn f() { query missing select name; }; f();")), "not_found");
+}
+
+#[test]
+fn other_errors_are_runtime_errors() {
+    let db = items();
+
+    // the message mentions 'not found', the kind doesn't depend on it
+    assert_eq!(db.query(&kind("let o = { a: 1 }; o.b.c = 1;")), "runtime");
+    assert_eq!(db.query(&kind(r#"query items insert name: "b";"#)), "runtime");
+    assert_eq!(db.query(&kind(r#"throw "failed";"#)), "error");
+}
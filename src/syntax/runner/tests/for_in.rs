@@ -0,0 +1,55 @@
+use crate::utils::test::TestDatabase;
+
+/// Table with 600 rows, more than a loop over a query fetches at once
+fn numbers() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table numbers { n: i64; }");
+    let rows = (0..600).map(|n| format!("{{ n: {} }}", n)).collect::<Vec<_>>();
+    db.query(&format!("query numbers insert [{}]", rows.join(", ")));
+    db
+}
+
+#[test]
+fn iterates_over_every_row() {
+    let db = numbers();
+
+    assert_eq!(db.query("let total = 0; for row in query numbers select n { total = total + row[0]; }; total"), "179700");
+    assert_eq!(db.query("let last = 0; for (i, [n]) in query numbers select n { last = i; }; last"), "599");
+    assert_eq!(db.query("let total = 0; for { n } in query numbers select n as object { total = total + n; }; total"), "179700");
+    assert_eq!(db.query("let count = 0; for row in query numbers select n where n < 10 { count = count + 1; }; count"), "10");
+}
+
+#[test]
+fn break_stops_fetching() {
+    let db = numbers();
+
+    assert_eq!(db.query("let count = 0; for row in query numbers select n { count = count + 1; if count == 3 { break; } }; count"), "3");
+}
+
+#[test]
+fn rows_are_fetched_while_the_loop_runs() {
+    let db = numbers();
+
+    // rows deleted before their batch is fetched are skipped
+    let script = "let count = 0; for [n] in query numbers select n { \
+        if n == 0 { let deleted = query numbers delete where n >= 300; }; \
+        count = count + 1; \
+    }; count";
+    assert_eq!(db.query(script), "300");
+
+    // rows updated before their batch is fetched have their new values
+    let db = numbers();
+    let script = "let total = 0; for [n] in query numbers select n { \
+        if n == 0 { let updated = query numbers update n: 1 where n >= 0; }; \
+        total = total + n; \
+    }; total";
+    assert_eq!(db.query(script), ((0..256).sum::<i64>() + 344).to_string());
+}
+
+#[test]
+fn query_errors_stop_the_loop() {
+    let db = numbers();
+
+    let error = db.error("fn f() { for row in query missing select n { }; }; f()");
+    assert!(error.contains("Table 'missing' does not exist"), "{}", error);
+}
@@ -0,0 +1,96 @@
+use crate::{utils::test::TestDatabase, auth::{User, Privilege, action::{FunctionAction, TableAction}}};
+
+const HELPER: &str = "fn helper(x: int) -> int { return x * 2; }; helper(21)";
+
+#[test]
+fn script_functions_are_scoped_to_the_run() {
+    let mut db = TestDatabase::new();
+
+    // running a script again declares its functions again
+    assert_eq!(db.query(HELPER), "42");
+    assert_eq!(db.query(HELPER), "42");
+    assert!(!db.database.read().unwrap().functions.contains_key("helper"));
+
+    db.reload();
+    assert!(db.error("helper(1)").starts_with("Function 'helper' not found"));
+}
+
+#[test]
+fn script_functions_need_no_privileges() {
+    let db = TestDatabase::new();
+
+    let result = db.run_as(User::new("bob", ""), HELPER).unwrap();
+    assert_eq!(result.to_string(), "42");
+
+    let error = db.run_as(User::new("bob", ""), "create function helper() -> int { return 1; }").unwrap_err();
+    assert_eq!(error, "Can't create function, permission denied");
+}
+
+#[test]
+fn created_functions_are_persisted() {
+    let mut db = TestDatabase::new();
+    db.query("create function double(x: int) -> int { return x * 2; }");
+    assert_eq!(db.error("create function double(x: int) -> int { return x; }"), "Function 'double' already exists");
+
+    db.reload();
+    assert_eq!(db.query("double(3)"), "6");
+
+    // replacing a function is written to the schema as well
+    db.query("create or replace function double(x: int) -> int { return x + x + 1; }");
+    assert_eq!(db.query("double(3)"), "7");
+    db.reload();
+    assert_eq!(db.query("double(3)"), "7");
+}
+
+/// Table 'docs' with a policy which lets bob see his own rows through the function 'visible'
+fn docs() -> (TestDatabase, User) {
+    let db = TestDatabase::new();
+    db.query("create table docs { id: i64; owner: fixed(16); }");
+    db.query(r#"query docs insert [{ id: 1, owner: "bob" }, { id: 2, owner: "eve" }]"#);
+    db.query(r#"create function visible(o: string) -> bool { return o == "bob"; }"#);
+    db.query(r#"create policy "own" for docs.select visible(owner)"#);
+
+    let mut bob = User::new("bob", "");
+    bob.privileges.insert(Privilege::table("docs", TableAction::Select));
+    bob.privileges.insert(Privilege::function("visible", FunctionAction::Execute));
+    (db, bob)
+}
+
+#[test]
+fn scripts_cannot_replace_functions_of_policies() {
+    let (db, bob) = docs();
+    assert_eq!(db.run_as(bob.clone(), "query docs select *").unwrap().to_string(), "[[1, bob]]");
+
+    let error = db.run_as(bob.clone(), r#"fn visible(o: string) -> bool { return true; }; query docs select *"#).unwrap_err();
+    assert!(error.contains("Cannot declare 'visible', the database has a function with the same name"), "{}", error);
+    let error = db.run_as(bob.clone(), r#"let visible = fn(o: string) -> bool { return true; }; query docs select *"#).unwrap_err();
+    assert!(error.contains("Cannot declare 'visible', the database has a function with the same name"), "{}", error);
+
+    // names of builtins are taken as well
+    assert!(db.error("let len = 1;").contains("Cannot declare 'len'"));
+}
+
+#[test]
+fn policies_and_defaults_do_not_see_script_variables() {
+    let (db, bob) = docs();
+    db.query(r#"create policy "named" for docs.select owner == name"#);
+    db.query("create table notes { id: i64; text: fixed(16), default(note); }");
+
+    let error = db.run_as(bob, r#"let name = "eve"; query docs select *"#).unwrap_err();
+    assert!(error.contains("Variable 'name' not found"), "{}", error);
+    let error = db.error(r#"let note = "from script"; query notes insert id: 1"#);
+    assert!(error.contains("Variable 'note' not found"), "{}", error);
+}
+
+#[test]
+fn created_functions_need_the_execute_privilege() {
+    let db = TestDatabase::new();
+    db.query("create function secret() -> int { return 7; }");
+
+    let error = db.run_as(User::new("bob", ""), "secret()").unwrap_err();
+    assert_eq!(error, "unauthorized");
+
+    let mut bob = User::new("bob", "");
+    bob.privileges.insert(Privilege::function("secret", FunctionAction::Execute));
+    assert_eq!(db.run_as(bob, "secret()").unwrap().to_string(), "7");
+}
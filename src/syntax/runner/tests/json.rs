@@ -0,0 +1,56 @@
+use crate::{utils::test::TestDatabase, basics::Value};
+
+const DOCUMENT: &str = r#"{"user":{"id":7,"name":"ann","tags":["a","b"]},"order":{"limit":2.5,"select":null},"ok":true}"#;
+
+const SELECT: &str = "let rows = query docs select data where id == 1";
+
+fn documents() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table docs { id: i64; data: json(256); }");
+    db
+}
+
+#[test]
+fn text_is_stored_as_a_document() {
+    let mut db = documents();
+    db.run_with("query docs insert id: 1 data: $1", &[Value::Text(DOCUMENT.to_string())]).unwrap();
+
+    let round_trip = |db: &TestDatabase| db.query(&format!("{}; to_json(rows)", SELECT));
+    assert_eq!(db.query(&format!("{}; to_json(rows[0][0])", SELECT)), DOCUMENT);
+    assert_eq!(db.query(&format!("{}; rows[0][0].user.id", SELECT)), "7");
+
+    let before = round_trip(&db);
+    db.reload();
+    assert_eq!(round_trip(&db), before);
+
+    // updates parse text the same way
+    db.query(r#"query docs update data: "[1, 2]" where id == 1"#);
+    assert_eq!(db.query(&format!("{}; to_json(rows[0][0])", SELECT)), "[1,2]");
+
+    let error = db.error(r#"query docs insert id: 2 data: "not json""#);
+    assert!(error.starts_with("Invalid JSON for column 'data'"), "{}", error);
+}
+
+#[test]
+fn documents_round_trip_through_builtins() {
+    let db = TestDatabase::new();
+
+    let script = format!("to_json(parse_json({:?}))", DOCUMENT);
+    assert_eq!(db.query(&script), DOCUMENT);
+    assert_eq!(db.query(r#"to_json({ a: [1, "x", null], b: { c: 1.5 } })"#), r#"{"a":[1,"x",null],"b":{"c":1.5}}"#);
+}
+
+#[test]
+fn keywords_are_member_names() {
+    let db = documents();
+    db.run_with("query docs insert id: 1 data: $1", &[Value::Text(DOCUMENT.to_string())]).unwrap();
+    db.query(r#"query docs insert id: 2 data: "{\"order\": {\"limit\": 1}}""#);
+
+    assert_eq!(db.query("let doc = parse_json(\"{\\\"select\\\": {\\\"where\\\": 1}}\"); doc.select.where"), "1");
+    assert_eq!(db.query("query docs select id where data.order.limit > 2"), "[[1]]");
+    assert_eq!(db.query("query docs select id where data.order.select == null"), "[[1], [2]]");
+
+    // paths of json indexes may name keywords as well
+    db.query("create index docs_limit on docs(data.order.limit) json");
+    assert_eq!(db.query("query docs select id where data.order.limit == 1"), "[[2]]");
+}
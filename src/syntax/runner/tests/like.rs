@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use crate::{utils::test::TestDatabase, syntax::{runner::{Runner, operator::REGEX_CACHE_SIZE}, context::RunnerContext}};
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(32), unique; }"#);
+    db.query(r#"query items insert [{ name: "Apple pie" }, { name: "apple" }, { name: "100% juice" }, { name: "1000 juice" }, { name: "a_b" }, { name: "axb" }]"#);
+    db
+}
+
+#[test]
+fn like_wildcards() {
+    let db = items();
+
+    assert_eq!(db.query(r#"query items select name where name like "app%""#), "[[apple]]");
+    assert_eq!(db.query(r#"query items select name where name like "%juice""#), "[[100% juice], [1000 juice]]");
+    assert_eq!(db.query(r#"query items select name where name like "a_b""#), "[[a_b], [axb]]");
+    assert_eq!(db.query(r#"query items select name where name like "%p%e%""#), "[[Apple pie], [apple]]");
+}
+
+#[test]
+fn like_escapes() {
+    let db = items();
+
+    assert_eq!(db.query(r#"query items select name where name like "100\\%%""#), "[[100% juice]]");
+    assert_eq!(db.query(r#"query items select name where name like "a\\_b""#), "[[a_b]]");
+}
+
+#[test]
+fn ilike_ignores_case() {
+    let db = items();
+
+    assert_eq!(db.query(r#"query items select name where name ilike "APP%""#), "[[Apple pie], [apple]]");
+    assert_eq!(db.query(r#""Apple" ilike "apple""#), "true");
+}
+
+#[test]
+fn regex_match() {
+    let db = items();
+
+    assert_eq!(db.query(r#"query items select name where name =~ "^[0-9]+ ""#), "[[1000 juice]]");
+
+    let error = db.error(r#"query items select name where name =~ "(""#);
+    assert!(error.starts_with("Invalid regex pattern"), "{}", error);
+}
+
+#[test]
+fn regex_cache_is_bounded() {
+    let db = TestDatabase::new();
+    db.query("create table patterns { pattern: fixed(16); }");
+    let rows = (0..REGEX_CACHE_SIZE * 3).map(|i| format!(r#"{{ pattern: "^p{}$" }}"#, i)).collect::<Vec<_>>();
+    db.query(&format!("query patterns insert [{}]", rows.join(", ")));
+
+    // every row matches with its own pattern
+    let runner = Runner::new(db.database.clone());
+    let ctx = RunnerContext::new_ctx(Rc::new(db.options()));
+    let matched = runner.run_raw(r#"query patterns select pattern where "p1" =~ pattern"#, &ctx).unwrap().unwrap();
+
+    assert_eq!(matched.to_string(), "[[^p1$]]");
+    assert!(runner.regex_cache.borrow().len() <= REGEX_CACHE_SIZE);
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use crate::{utils::test::TestDatabase, database::RunLimits};
+
+fn run_limited(db: &TestDatabase, input: &str, limits: RunLimits) -> Result<String, String> {
+    db.run_with_options(input, db.options().with_limits(limits)).map(|value| value.to_string())
+}
+
+#[test]
+fn step_limit() {
+    let db = TestDatabase::new();
+    let limits = RunLimits { max_steps: Some(1000), ..Default::default() };
+
+    let error = run_limited(&db, "let i = 0; while i >= 0 { i = i + 1; }", limits.clone()).unwrap_err();
+    assert_eq!(error, "Run exceeded the maximum of 1000 evaluation steps");
+    assert_eq!(run_limited(&db, "let i = 0; while i < 10 { i = i + 1; }; i", limits).unwrap(), "10");
+}
+
+#[test]
+fn timeout() {
+    let db = TestDatabase::new();
+    let limits = RunLimits { timeout: Some(Duration::from_millis(100)), ..Default::default() };
+
+    let error = run_limited(&db, "for (let i = 0; i >= 0; i = i + 1) { let x = 1; }", limits).unwrap_err();
+    assert!(error.starts_with("Run exceeded the timeout of"), "{}", error);
+}
+
+#[test]
+fn cancel_from_another_thread() {
+    let db = TestDatabase::new();
+    let options = db.options();
+    let cancel = options.cancel_handle();
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        cancel.cancel();
+    });
+    let error = db.run_with_options("let i = 0; while i >= 0 { i = i + 1; }", options).unwrap_err();
+    canceller.join().unwrap();
+
+    assert_eq!(error, "Run was cancelled");
+}
+
+#[test]
+fn recursion_depth() {
+    let db = TestDatabase::new();
+    let limits = RunLimits { max_depth: Some(50), ..Default::default() };
+    let fact = |n: usize| {
+        let input = format!("fn fact(n: int) -> int {{ if n < 2 {{ return 1; }}; return n * fact(n - 1); }}; fact({})", n);
+        run_limited(&db, &input, limits.clone())
+    };
+
+    assert_eq!(fact(10).unwrap(), "3628800");
+    assert_eq!(fact(60).unwrap_err(), "Function 'fact' exceeded the maximum recursion depth of 50");
+}
+
+#[test]
+fn row_limit() {
+    let db = TestDatabase::new();
+    db.query("create table items { id: i64; }");
+    db.query("query items insert [{ id: 1 }, { id: 2 }, { id: 3 }]");
+    let limits = RunLimits { max_rows: Some(2), ..Default::default() };
+
+    assert_eq!(run_limited(&db, "query items select id limit 2", limits.clone()).unwrap(), "[[1], [2]]");
+    let error = run_limited(&db, "query items select id", limits).unwrap_err();
+    assert_eq!(error, "Query returned more than 2 rows");
+}
@@ -0,0 +1,24 @@
+mod upsert;
+mod bulk;
+mod returning;
+mod parameters;
+mod like;
+mod window;
+mod cte;
+mod cursor;
+mod limit;
+mod parallel;
+mod vector;
+mod trigger;
+mod function;
+mod native;
+mod types;
+mod decimal;
+mod time;
+mod object;
+mod json;
+mod checker;
+mod closure;
+mod error;
+mod for_in;
+mod module;
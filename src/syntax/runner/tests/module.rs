@@ -0,0 +1,51 @@
+use crate::utils::test::TestDatabase;
+
+/// Path a module is identified by in import cycle errors
+fn canonical(path: &str) -> String {
+    std::fs::canonicalize(path).unwrap().display().to_string()
+}
+
+#[test]
+fn module_importing_itself_is_a_cycle() {
+    let db = TestDatabase::new();
+    let a = db.module("a.db", "import \"a.db\"; export let a = 1;");
+
+    let error = db.error(&format!("import \"{}\"; a", a));
+    assert_eq!(error, format!("Import cycle: '{}' -> '{}'", canonical(&a), canonical(&a)));
+}
+
+#[test]
+fn modules_importing_each_other_are_a_cycle() {
+    let db = TestDatabase::new();
+    let a = db.module("a.db", "import \"b.db\"; export let a = 1;");
+    let b = db.module("b.db", "import \"c.db\"; export let b = 2;");
+    db.module("c.db", "import \"b.db\"; export let c = 3;");
+
+    // the cycle starts at the first module imported again
+    let c = a.replace("a.db", "c.db");
+    let error = db.error(&format!("import \"{}\"; a", a));
+    assert_eq!(error, format!("Import cycle: '{}' -> '{}' -> '{}'", canonical(&b), canonical(&c), canonical(&b)));
+}
+
+#[test]
+fn shared_imports_are_not_a_cycle() {
+    let db = TestDatabase::new();
+    let a = db.module("a.db", "import \"b.db\"; import \"c.db\"; export let a = b + c;");
+    db.module("b.db", "import \"d.db\"; export let b = d + 1;");
+    db.module("c.db", "import \"d.db\"; export let c = d + 2;");
+    db.module("d.db", "export let d = 10;");
+
+    assert_eq!(db.query(&format!("import \"{}\"; a", a)), "23");
+    assert_eq!(db.query(&format!("import \"{}\"; import \"{}\"; a", a, a)), "23");
+}
+
+#[test]
+fn caught_cycles_leave_no_module_open() {
+    let db = TestDatabase::new();
+    let a = db.module("a.db", "import \"b.db\"; export let a = 1;");
+    db.module("b.db", "import \"a.db\"; export let b = 2;");
+    let d = db.module("d.db", "export let d = 10;");
+
+    let script = format!("let kind = \"none\"; try {{ import \"{}\"; }} catch (e) {{ kind = e.kind; }}; import \"{}\"; [kind, d]", a, d);
+    assert_eq!(db.query(&script), "[runtime, 10]");
+}
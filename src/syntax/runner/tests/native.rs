@@ -0,0 +1,61 @@
+use crate::{utils::test::TestDatabase, function::Function, syntax::ast::{Type, Node}, basics::{Value, value::NumericValue}, auth::{User, Privilege, action::FunctionAction}};
+
+fn add_one() -> Function {
+    Function::native("add_one", vec![("x", Type::Int)], Type::Int, |_, args, _, _| match &args[0] {
+        Value::Numeric(NumericValue::IntI64(x)) => Ok(Some(Value::Numeric(NumericValue::IntI64(x + 1)))),
+        _ => Err("Expected an integer".to_string()),
+    })
+}
+
+/// Returns a string although it's declared to return an integer
+fn broken() -> Function {
+    Function::native("broken", vec![], Type::Int, |_, _, _, _| Ok(Some(Value::Text("one".to_string()))))
+}
+
+fn database() -> TestDatabase {
+    let db = TestDatabase::new();
+    let mut database = db.database.write().unwrap();
+    database.register_function(add_one()).unwrap();
+    database.register_function(broken()).unwrap();
+    drop(database);
+    db
+}
+
+#[test]
+fn native_functions_are_called_by_scripts() {
+    let db = database();
+
+    assert_eq!(db.query("add_one(41)"), "42");
+    assert_eq!(db.query("let f = add_one; f(1)"), "2");
+}
+
+#[test]
+fn native_functions_are_type_checked() {
+    let db = database();
+
+    assert!(db.error(r#"add_one("x")"#).starts_with("Function 'add_one' expects argument 'x' to be of type 'Int' but got 'String'"));
+    assert_eq!(db.error("broken()"), "Function 'broken' must return a value of type 'Int'");
+}
+
+#[test]
+fn registration_is_checked() {
+    let db = database();
+    let mut database = db.database.write().unwrap();
+
+    assert_eq!(database.register_function(add_one()).unwrap_err(), "Function 'add_one' already exists");
+
+    let custom = Function::custom("custom", &[], &Type::Void, &Node::Block(vec![]));
+    assert_eq!(database.register_function(custom).unwrap_err(), "Function 'custom' is not a native function");
+}
+
+#[test]
+fn restricted_native_functions_need_the_execute_privilege() {
+    let db = TestDatabase::new();
+    db.database.write().unwrap().register_function(add_one().restricted()).unwrap();
+
+    assert_eq!(db.run_as(User::new("bob", ""), "add_one(1)").unwrap_err(), "unauthorized");
+
+    let mut bob = User::new("bob", "");
+    bob.privileges.insert(Privilege::function("add_one", FunctionAction::Execute));
+    assert_eq!(db.run_as(bob, "add_one(1)").unwrap().to_string(), "2");
+}
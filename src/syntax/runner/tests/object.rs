@@ -0,0 +1,48 @@
+use crate::utils::test::TestDatabase;
+
+#[test]
+fn object_literals_keep_their_order() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("{ b: 1, a: \"x\", \"quoted key\": [1, 2] }"), "{b: 1, a: x, quoted key: [1, 2]}");
+    assert_eq!(db.query("let o = {}; o"), "{}");
+    assert_eq!(db.query("let o = { inner: { value: 1 + 2 } }; o"), "{inner: {value: 3}}");
+}
+
+#[test]
+fn members_are_read_and_assigned() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("let o = { a: { b: 2 } }; o.a.b"), "2");
+    assert_eq!(db.query("let o = { a: 1 }; o[\"a\"]"), "1");
+
+    // assigning a new member appends it, nested members are changed in place
+    assert_eq!(db.query("let o = { a: 1 }; o.b = 2; o.a = 3; o"), "{a: 3, b: 2}");
+    assert_eq!(db.query("let o = { a: { b: 1 } }; o.a.c = 2; o"), "{a: {b: 1, c: 2}}");
+
+    // missing members read as null, only assigning below them fails
+    assert_eq!(db.query("let o = { a: 1 }; o.b"), "NULL");
+    assert_eq!(db.error("let o = { a: 1 }; o.b.c = 1; o"), "Member 'b' not found in object");
+    assert_eq!(db.error("let o = 1; o.a = 1; o"), "Cannot set member 'a' of a value which is not an object");
+}
+
+#[test]
+fn functions_return_objects() {
+    let db = TestDatabase::new();
+
+    let script = "fn point(x: int, y: int) -> object { return { x: x, y: y }; }; point(1, 2).y";
+    assert_eq!(db.query(script), "2");
+}
+
+#[test]
+fn rows_are_selected_as_objects() {
+    let db = TestDatabase::new();
+    db.query("create table users { id: i64; name: fixed(16); }");
+    db.query("query users insert id: 1 name: \"alice\"");
+    db.query("query users insert id: 2 name: \"bob\"");
+
+    assert_eq!(db.query("query users select id, name as object"), "[{id: 1, name: alice}, {id: 2, name: bob}]");
+    assert_eq!(db.query("query users select name where id == 2 as object"), "[{name: bob}]");
+    assert_eq!(db.query("let users = query users select * as object; users[1].name"), "bob");
+    assert_eq!(db.query("query users select * where id == 3 as object"), "[]");
+}
@@ -0,0 +1,87 @@
+use crate::{utils::test::TestDatabase, basics::Value};
+
+use super::super::parallel::MIN_ROWS_PER_THREAD;
+
+const ROWS: usize = MIN_ROWS_PER_THREAD * 4;
+
+fn value(i: usize) -> usize {
+    (i * 7919) % 1000
+}
+
+/// Table with enough rows to be split across four threads
+fn numbers() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table numbers { id: i64; g: i64; v: i64; }");
+
+    let rows = (0..ROWS)
+        .map(|i| format!("{{ id: {}, g: {}, v: {} }}", i, i % 3, value(i)))
+        .collect::<Vec<_>>();
+    db.query(&format!("query numbers insert [{}]", rows.join(", ")));
+    db
+}
+
+fn run_on(db: &TestDatabase, input: &str, threads: usize) -> String {
+    let mut options = db.options();
+    options.threads = threads;
+    db.run_with_options(input, options).unwrap().to_string()
+}
+
+/// Runs 'input' on one and on four threads, returns the result after checking both are equal
+fn parallel(db: &TestDatabase, input: &str) -> String {
+    let sequential = run_on(db, input, 1);
+    let parallel = run_on(db, input, 4);
+    assert_eq!(sequential, parallel, "{}", input);
+    parallel
+}
+
+#[test]
+fn filter_and_projection() {
+    let db = numbers();
+
+    // workers see the variables used by the query
+    let result = parallel(&db, "let k = 3; let unused = 1; query numbers select id, v where ((v % 7) == 0) && (v > k)");
+    let expected = (0..ROWS)
+        .filter(|i| value(*i).is_multiple_of(7) && value(*i) > 3)
+        .map(|i| format!("[{}, {}]", i, value(i)))
+        .collect::<Vec<_>>();
+    assert_eq!(result, format!("[{}]", expected.join(", ")));
+}
+
+#[test]
+fn partition_aggregates() {
+    let db = numbers();
+
+    let result = parallel(&db, "query numbers select g, sum(v) over (partition by g), count() over (partition by g), min(v) over (partition by g), max(v) over (partition by g) limit 3");
+    let expected = (0..3)
+        .map(|g| {
+            let values = (0..ROWS).filter(|i| i % 3 == g).map(value).collect::<Vec<_>>();
+            let (min, max) = (values.iter().min().unwrap(), values.iter().max().unwrap());
+            format!("[{}, {}, {}, {}, {}]", g, values.iter().sum::<usize>(), values.len(), min, max)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(result, format!("[{}]", expected.join(", ")));
+}
+
+#[test]
+fn whole_table_aggregates() {
+    let db = numbers();
+
+    let result = parallel(&db, "query numbers select sum(v) over (), count() over () limit 1");
+    let total = (0..ROWS).map(value).sum::<usize>();
+    assert_eq!(result, format!("[[{}, {}]]", total, ROWS));
+
+    // ordered windows depend on the rows before them, they are evaluated on one thread
+    parallel(&db, "query numbers select id, sum(v) over (partition by g order by id) where id < 10");
+}
+
+#[test]
+fn results_are_values() {
+    let db = numbers();
+
+    let mut options = db.options();
+    options.threads = 4;
+    match db.run_with_options("query numbers select v where id == 5", options).unwrap() {
+        Value::Array(rows) => assert_eq!(rows.len(), 1),
+        value => panic!("Expected rows, got {}", value),
+    }
+}
@@ -0,0 +1,55 @@
+use std::rc::Rc;
+
+use crate::{utils::test::TestDatabase, basics::{Value, value::NumericValue}, database::{Database, Run}};
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(32), unique; }"#);
+    db
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+#[test]
+fn parameters_are_values() {
+    let db = items();
+
+    // quotes in parameters are never parsed as script
+    let name = text(r#"it's "quoted""#);
+    let inserted = db.run_with("query items insert name: $1 returning *", std::slice::from_ref(&name)).unwrap();
+    assert_eq!(inserted.to_string(), r#"[[0, it's "quoted"]]"#);
+
+    let selected = db.run_with("query items select id where name == $1", &[name]).unwrap();
+    assert_eq!(selected.to_string(), "[[0]]");
+}
+
+#[test]
+fn parameter_count_must_match() {
+    let db = items();
+
+    let error = db.run_with("query items select * where name == $2", &[text("a")]).unwrap_err();
+    assert_eq!(error, "Statement expects 2 parameters, got 1");
+
+    let error = db.run_with("query items select * where name == $1", &[]).unwrap_err();
+    assert_eq!(error, "Statement expects 1 parameters, got 0");
+}
+
+#[test]
+fn prepared_statement_runs_many_times() {
+    let db = items();
+
+    let statement = Database::prepare("query items insert name: $1 returning id".to_string()).unwrap();
+    assert_eq!(statement.parameters(), 1);
+
+    for name in ["a", "b", "c"] {
+        let options = Rc::new(db.options());
+        Database::execute(db.database.clone(), &statement, &[text(name)], options).unwrap();
+    }
+    assert_eq!(db.query("query items select *"), "[[0, a], [1, b], [2, c]]");
+
+    let statement = Database::prepare("query items select name where id == $1".to_string()).unwrap();
+    let result = Database::execute(db.database.clone(), &statement, &[Value::Numeric(NumericValue::IntI64(2))], Rc::new(db.options())).unwrap();
+    assert_eq!(result.data.to_string(), "[[c]]");
+}
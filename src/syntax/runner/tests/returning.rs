@@ -0,0 +1,42 @@
+use crate::utils::test::TestDatabase;
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(16), unique; n: i64; }"#);
+    db
+}
+
+#[test]
+fn insert_returning() {
+    let db = items();
+
+    assert_eq!(db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }] returning id"#), "[[0], [1]]");
+    assert_eq!(db.query(r#"query items insert name: "c" n: 5 returning *, n * 2"#), "[[2, c, 5, 10]]");
+}
+
+#[test]
+fn update_returning() {
+    let db = items();
+    db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }, { name: "c", n: 3 }]"#);
+
+    assert_eq!(db.query("query items update n: 10 where n > 1 returning id, items.n"), "[[1, 10], [2, 10]]");
+    assert_eq!(db.query("query items update n: 11 where n > 100"), "0");
+}
+
+#[test]
+fn delete_returning() {
+    let db = items();
+    db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }]"#);
+
+    assert_eq!(db.query("query items delete where id == 0 returning name"), "[[a]]");
+    assert_eq!(db.query("query items delete where id == 1"), "1");
+    assert_eq!(db.query("query items select *"), "[]");
+}
+
+#[test]
+fn upsert_returning() {
+    let db = items();
+    db.query(r#"query items insert name: "a" n: 1"#);
+
+    assert_eq!(db.query(r#"query items insert name: "a" n: 1 on conflict (name) do update set n: excluded.n + 100 returning id, n"#), "[[0, 101]]");
+}
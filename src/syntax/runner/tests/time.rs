@@ -0,0 +1,63 @@
+use crate::utils::test::TestDatabase;
+
+#[test]
+fn iso_strings_are_parsed() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("parse_date(\"2024-02-29\")"), "2024-02-29");
+    assert_eq!(db.query("parse_time(\"23:59:01.25\")"), "23:59:01.25");
+    assert_eq!(db.query("parse_timestamp(\"2024-03-01T10:30:00+02:00\")"), "2024-03-01T10:30:00+02:00");
+    assert_eq!(db.query("parse_timestamp(\"2024-03-01 10:30\")"), "2024-03-01T10:30:00Z");
+    assert_eq!(db.query("parse_interval(\"P1Y2M3W4DT5H6M7.5S\")"), "P1Y2M25DT5H6M7.5S");
+    assert_eq!(db.query("parse_interval(\"-P1D\")"), "P-1D");
+
+    assert!(db.error("parse_date(\"2023-02-29\")").starts_with("Invalid date '2023-02-29'"));
+    assert!(db.error("parse_time(\"24:00\")").starts_with("Invalid time '24:00'"));
+    assert!(db.error("parse_timestamp(\"2024-03-01T10:30+19:00\")").starts_with("Invalid timestamp"));
+    assert!(db.error("parse_interval(\"P1H\")").starts_with("Invalid interval 'P1H'"));
+}
+
+#[test]
+fn large_intervals_are_rejected() {
+    let db = TestDatabase::new();
+
+    for interval in ["PT9999999999999H", "PT99999999999999M", "P999999999Y", "P9999999999W", "PT9223372036854775807S"] {
+        let error = db.error(&format!("parse_interval(\"{}\")", interval));
+        assert!(error.starts_with(&format!("Invalid interval '{}'", interval)), "{}", error);
+    }
+
+    // components may not overflow together either
+    assert!(db.error("parse_interval(\"PT2562047000H2562047000H\")").starts_with("Invalid interval"));
+}
+
+#[test]
+fn timestamps_compare_by_instant() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("parse_timestamp(\"2024-01-01T10:00:00+02:00\") == parse_timestamp(\"2024-01-01T08:00:00Z\")"), "true");
+    assert_eq!(db.query("parse_timestamp(\"2024-01-01T10:00:00+02:00\") < parse_timestamp(\"2024-01-01T09:00:00Z\")"), "true");
+    assert_eq!(db.query("parse_date(\"2024-01-02\") > parse_timestamp(\"2024-01-01T23:00:00Z\")"), "true");
+    assert_eq!(db.query("parse_date(\"2024-01-01\") == parse_timestamp(\"2024-01-01T00:00:00Z\")"), "true");
+
+    // stored timestamps compare with parsed ones whatever their unit
+    db.query("create table events { id: i64; at: time(s); }");
+    db.query("query events insert id: 1 at: parse_timestamp(\"2024-01-01T08:00:00Z\")");
+    db.query("query events insert id: 2 at: parse_timestamp(\"2024-01-01T12:00:00Z\")");
+    assert_eq!(db.query("query events select id where at > parse_timestamp(\"2024-01-01T10:00:00+01:00\")"), "[[2]]");
+    assert_eq!(db.query("query events select id where at == parse_timestamp(\"2024-01-01T10:00:00+02:00\")"), "[[1]]");
+}
+
+#[test]
+fn intervals_compare_by_length() {
+    let db = TestDatabase::new();
+
+    assert_eq!(db.query("parse_interval(\"P1M\") == parse_interval(\"P30D\")"), "true");
+    assert_eq!(db.query("parse_interval(\"PT25H\") > parse_interval(\"P1D\")"), "true");
+    assert_eq!(db.query("parse_interval(\"P1DT-1H\") < parse_interval(\"PT24H\")"), "true");
+    assert_eq!(db.query("parse_interval(\"P1D\") == parse_interval(\"PT24H\")"), "true");
+    assert_eq!(db.query("parse_interval(\"P1D\") - parse_interval(\"PT24H\")"), "P1DT-24H");
+
+    // times of day only compare with each other
+    assert_eq!(db.query("parse_time(\"10:00\") < parse_time(\"10:00:01\")"), "true");
+    assert_eq!(db.query("parse_time(\"10:00\") == parse_interval(\"PT10H\")"), "false");
+}
@@ -0,0 +1,84 @@
+use crate::utils::test::TestDatabase;
+
+use super::super::trigger::MAX_TRIGGER_DEPTH;
+
+fn products() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table products { id: i64, unique; name: fixed(16); price: i64; }");
+    db.query("create table log { msg: fixed(64); }");
+    db.query("create function check_price() -> bool { if new.price < 0 { return false; }; if new.price > 100 { new.price = 100; }; return true; };");
+    db.query("create trigger check_insert before insert on products execute check_price");
+    db.query("create trigger check_update before update on products execute check_price");
+    db
+}
+
+#[test]
+fn before_trigger_modifies_the_row() {
+    let db = products();
+
+    db.query(r#"query products insert [{ id: 1, name: "a", price: 10 }, { id: 2, name: "b", price: 500 }]"#);
+    db.query("query products update price: 300 where id == 1");
+    assert_eq!(db.query("query products select id, price"), "[[1, 100], [2, 100]]");
+}
+
+#[test]
+fn before_trigger_aborts() {
+    let db = products();
+    db.query(r#"query products insert id: 1 name: "a" price: 10"#);
+
+    let error = db.error(r#"query products insert [{ id: 2, name: "b", price: 20 }, { id: 3, name: "c", price: 0 - 5 }]"#);
+    assert_eq!(error, "Trigger 'check_insert' aborted the insert on table 'products'");
+
+    let error = db.error("query products update price: 0 - 1 where id == 1");
+    assert_eq!(error, "Trigger 'check_update' aborted the update on table 'products'");
+
+    // none of the rows of an aborted statement are written
+    assert_eq!(db.query("query products select id, price"), "[[1, 10]]");
+}
+
+#[test]
+fn upsert_fires_update_triggers() {
+    let db = products();
+    db.query(r#"query products insert id: 1 name: "a" price: 10"#);
+
+    db.query(r#"query products insert id: 1 name: "a" price: 700 on conflict (id) do update set price: excluded.price"#);
+    assert_eq!(db.query("query products select id, price"), "[[1, 100]]");
+
+    let error = db.error(r#"query products insert id: 1 name: "a" price: 5 on conflict (id) do update set price: 0 - 5"#);
+    assert_eq!(error, "Trigger 'check_update' aborted the update on table 'products'");
+}
+
+#[test]
+fn after_trigger_cannot_abort() {
+    let db = products();
+    db.query(r#"create function log_delete() -> bool { let r = query log insert msg: format("deleted {}", [old.name]); return false; };"#);
+    db.query(r#"create function fail() { throw "after trigger failed"; };"#);
+    db.query("create trigger log_delete after delete on products execute log_delete");
+    db.query("create trigger fail after insert on products execute fail");
+
+    // the rows are already written when after triggers run
+    db.query(r#"query products insert [{ id: 1, name: "a", price: 10 }, { id: 2, name: "b", price: 20 }]"#);
+    db.query("query products delete where id == 1");
+    assert_eq!(db.query("query products select id"), "[[2]]");
+    assert_eq!(db.query("query log select msg"), "[[deleted a]]");
+}
+
+#[test]
+fn triggers_firing_themselves_are_capped() {
+    let db = TestDatabase::new();
+    db.query("create table items { n: i64; }");
+    db.query("create function again() { let r = query items insert n: new.n + 1; };");
+
+    db.query("create trigger again before insert on items execute again");
+    let error = db.error("query items insert n: 0");
+    assert_eq!(error, format!("Trigger 'again' exceeded the maximum nesting depth of {}", MAX_TRIGGER_DEPTH));
+    assert_eq!(db.query("query items select n"), "[]");
+
+    // after triggers cannot abort, the insert stops once the nesting is too deep
+    db.query("create table others { n: i64; }");
+    db.query("create function again_after() { let r = query others insert n: new.n + 1; };");
+    db.query("create trigger again_after after insert on others execute again_after");
+    db.query("query others insert n: 0");
+    let rows = db.query("query others select n");
+    assert!(rows.ends_with(&format!("[{}]]", MAX_TRIGGER_DEPTH)), "{}", rows);
+}
@@ -0,0 +1,76 @@
+use crate::{utils::test::TestDatabase, basics::{Value, value::{NumericValue, TimestampValue}}};
+
+const TYPED: &str = "fn signed(x: int) -> int { return x; }; \
+    fn unsigned(x: uint) -> uint { return x; }; \
+    fn float(x: float) -> float { return x; }; \
+    [signed($1), unsigned($2), float($3)]";
+
+#[test]
+fn narrow_numbers_are_widened() {
+    let db = TestDatabase::new();
+
+    let params = [
+        Value::Numeric(NumericValue::IntI8(-3)),
+        Value::Numeric(NumericValue::IntU16(7)),
+        Value::Numeric(NumericValue::Float32(1.5)),
+    ];
+    assert_eq!(db.run_with(TYPED, &params).unwrap().to_string(), "[-3, 7, 1.5]");
+
+    let params = [
+        Value::Numeric(NumericValue::IntI32(-70000)),
+        Value::Numeric(NumericValue::IntU8(255)),
+        Value::Numeric(NumericValue::Float64(2.25)),
+    ];
+    assert_eq!(db.run_with(TYPED, &params).unwrap().to_string(), "[-70000, 255, 2.25]");
+}
+
+#[test]
+fn widened_numbers_keep_their_kind() {
+    let db = TestDatabase::new();
+
+    let params = [
+        Value::Numeric(NumericValue::IntU32(1)),
+        Value::Numeric(NumericValue::IntU8(1)),
+        Value::Numeric(NumericValue::Float32(1.0)),
+    ];
+    let error = db.run_with(TYPED, &params).unwrap_err();
+    assert!(error.contains("UInt"), "{}", error);
+}
+
+#[test]
+fn every_value_has_a_type() {
+    let db = TestDatabase::new();
+
+    let values = [
+        Value::Timestamp(TimestampValue::Seconds(60)),
+        Value::Binary(vec![1, 2]),
+        Value::Enum("red".to_string()),
+        Value::UUID("00000000-0000-0000-0000-000000000000".to_string()),
+    ];
+    for value in values {
+        // untyped parameters and arrays accept values without a script type
+        let result = db.run_with("fn id(x) { return x; }; [id($1)]", std::slice::from_ref(&value)).unwrap();
+        assert_eq!(result, Value::Array(vec![value.clone()]));
+
+        let error = db.run_with("fn id(x: int) -> int { return x; }; id($1)", &[value]).unwrap_err();
+        assert!(error.contains("Any"), "{}", error);
+    }
+}
+
+#[test]
+fn mismatched_operands_are_type_errors() {
+    let db = TestDatabase::new();
+    db.query("create table users { name: fixed(16); age: i64; }");
+    db.query(r#"query users insert name: "a" age: 30"#);
+
+    // operands of unknown type are only checked when the script runs
+    assert_eq!(db.error(r#"let v = ["a", 1]; v[0] * 2"#), "Cannot multiply 'String' and 'Int'");
+    assert_eq!(db.error("let r = query users select name; r[0][0] * 2"), "Cannot multiply 'String' and 'Int'");
+    assert_eq!(db.error("let v = [[1], 1]; v[0] - 1"), "Cannot subtract 'Array(Int)' and 'Int'");
+    assert_eq!(db.error("let v = [{ a: 1 }, 1]; v[0] + 1"), "Cannot add 'Object' and 'Int'");
+    assert_eq!(db.error("let v = [fn(x: int) -> int { return x; }, 1]; v[0] / 1"), "Cannot divide 'Function([Int], Int)' and 'Int'");
+    assert_eq!(db.error("let v = [true, 1]; v[0] && v[1]"), "Cannot apply '&&' to 'Boolean' and 'Int'");
+
+    let result = db.query(r#"let kind = "none"; try { let v = ["a", 1]; v[0] % 2; } catch (e) { kind = e.kind; }; kind"#);
+    assert_eq!(result, "type");
+}
@@ -0,0 +1,42 @@
+use crate::utils::test::TestDatabase;
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table items { id: i64, unique, default(seq("items", "id")); name: fixed(16), unique; n: i64; }"#);
+    db.query(r#"query items insert [{ name: "a", n: 1 }, { name: "b", n: 2 }]"#);
+    db
+}
+
+#[test]
+fn conflict_without_clause_fails() {
+    let db = items();
+
+    let error = db.error(r#"query items insert name: "a" n: 5"#);
+    assert!(error.contains("not unique"), "{}", error);
+    assert_eq!(db.query("query items select name, n"), "[[a, 1], [b, 2]]");
+}
+
+#[test]
+fn do_nothing_skips_conflicting_rows() {
+    let db = items();
+
+    db.query(r#"query items insert [{ name: "a", n: 5 }, { name: "c", n: 3 }] on conflict (name) do nothing"#);
+    assert_eq!(db.query("query items select name, n"), "[[a, 1], [b, 2], [c, 3]]");
+}
+
+#[test]
+fn do_update_uses_excluded_values() {
+    let db = items();
+
+    db.query(r#"query items insert [{ name: "a", n: 5 }, { name: "d", n: 4 }] on conflict (name) do update set n: n + excluded.n"#);
+    assert_eq!(db.query("query items select name, n"), "[[a, 6], [b, 2], [d, 4]]");
+}
+
+#[test]
+fn do_update_same_row_twice_fails() {
+    let db = items();
+
+    let error = db.error(r#"query items insert [{ name: "a", n: 5 }, { name: "a", n: 2 }] on conflict (name) do update set n: excluded.n"#);
+    assert_eq!(error, "Insert on conflict cannot update the same row twice");
+    assert_eq!(db.query("query items select name, n"), "[[a, 1], [b, 2]]");
+}
@@ -0,0 +1,62 @@
+use crate::{utils::test::TestDatabase, auth::{User, Privilege, action::TableAction}};
+
+fn items() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query("create table items { id: i64, unique; owner: fixed(16); embedding: vector(3); }");
+    db.query(r#"query items insert [{ id: 1, owner: "eve", embedding: [1, 0, 0] }, { id: 2, owner: "bob", embedding: [0, 1, 0] }, { id: 3, owner: "eve", embedding: [0.9, 0.1, 0] }, { id: 4, owner: "bob", embedding: [0, 0, 0] }, { id: 5, owner: "bob" }]"#);
+    db
+}
+
+fn bob() -> User {
+    let mut user = User::new("bob", "");
+    user.privileges.insert(Privilege::table("items", TableAction::Select));
+    user
+}
+
+#[test]
+fn zero_vector_is_not_null() {
+    let mut db = items();
+
+    // the table is read back from its file
+    db.reload();
+    assert_eq!(db.query("query items select id, embedding where id > 3"), "[[4, [0, 0, 0]], [5, NULL]]");
+
+    db.query("query items update embedding: null where id == 4");
+    assert_eq!(db.query("query items select id where embedding == null"), "[[4], [5]]");
+}
+
+#[test]
+fn nearest_rows_from_index() {
+    let db = items();
+    db.query("create index items_ann on items(embedding) vector");
+
+    assert_eq!(db.query("query items select id order l2(embedding, [1, 0, 0])++ limit 2"), "[[1], [3]]");
+    assert_eq!(db.query("query items select id order dot(embedding, [0, 1, 0])-- limit 1"), "[[2]]");
+}
+
+#[test]
+fn nearest_rows_respect_policies() {
+    let db = TestDatabase::new();
+    db.query("create table items { id: i64, unique; owner: fixed(16); embedding: vector(3); }");
+
+    // eve's rows surround the query vector, bob's rows are far from it
+    let rows = (0..400)
+        .map(|i| {
+            let (owner, x, y) = match i % 40 {
+                0 => ("bob", 0.0, 5.0 + i as f64 / 400.0),
+                _ => ("eve", 1.0 + i as f64 / 400.0, (i % 7) as f64 / 10.0),
+            };
+            format!(r#"{{ id: {}, owner: "{}", embedding: [{}, {}, 0] }}"#, i, owner, x, y)
+        })
+        .collect::<Vec<_>>();
+    db.query(&format!("query items insert [{}]", rows.join(", ")));
+    db.query("create index items_ann on items(embedding) vector");
+    db.query(r#"create policy "own" for items.select owner == "bob""#);
+
+    let query = "query items select id order l2(embedding, [1, 0, 0])++ limit 2";
+    assert_eq!(db.query(query), "[[7], [14]]");
+
+    // bob gets the nearest of his own rows, not the visible part of eve's nearest rows
+    let result = db.run_as(bob(), query).unwrap();
+    assert_eq!(result.to_string(), "[[0], [40]]");
+}
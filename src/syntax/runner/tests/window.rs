@@ -0,0 +1,48 @@
+use crate::utils::test::TestDatabase;
+
+fn emps() -> TestDatabase {
+    let db = TestDatabase::new();
+    db.query(r#"create table emps { id: i64, unique, default(seq("emps", "id")); name: fixed(16); dept: fixed(16); salary: i64; }"#);
+    db.query(r#"query emps insert [{ name: "a", dept: "x", salary: 10 }, { name: "b", dept: "x", salary: 20 }, { name: "c", dept: "x", salary: 20 }, { name: "d", dept: "y", salary: 5 }, { name: "e", dept: "y", salary: 7 }]"#);
+    db
+}
+
+#[test]
+fn ranking_functions() {
+    let db = emps();
+
+    let result = db.query("query emps select name, row_number() over (partition by dept order by salary desc), rank() over (partition by dept order by salary desc), dense_rank() over (partition by dept order by salary desc)");
+    assert_eq!(result, "[[a, 3, 3, 2], [b, 1, 1, 1], [c, 2, 1, 1], [d, 2, 2, 2], [e, 1, 1, 1]]");
+}
+
+#[test]
+fn aggregates_over_frames() {
+    let db = emps();
+
+    // ordered windows sum up to the current row and its peers, unordered ones the whole partition
+    let result = db.query("query emps select name, sum(salary) over (partition by dept order by salary), sum(salary) over (partition by dept), count() over ()");
+    assert_eq!(result, "[[a, 10, 50, 5], [b, 50, 50, 5], [c, 50, 50, 5], [d, 5, 12, 5], [e, 12, 12, 5]]");
+}
+
+#[test]
+fn offset_and_value_functions() {
+    let db = emps();
+
+    let result = db.query(r#"query emps select name, lag(name) over (order by id), lead(name, 2, "none") over (order by id), first_value(name) over (partition by dept order by salary), max(salary) over (partition by dept)"#);
+    assert_eq!(result, "[[a, NULL, c, a, 20], [b, a, d, a, 20], [c, b, e, a, 20], [d, c, none, d, 7], [e, d, none, d, 7]]");
+}
+
+#[test]
+fn windows_see_filtered_rows_before_order_and_limit() {
+    let db = emps();
+
+    let result = db.query("query emps select name, row_number() over (order by salary) where salary > 5 order id desc limit 2");
+    assert_eq!(result, "[[e, 1], [c, 4]]");
+}
+
+#[test]
+fn unknown_window_function() {
+    let db = emps();
+
+    assert_eq!(db.error("query emps select name, foo() over ()"), "Unknown window function 'foo'");
+}
@@ -0,0 +1,130 @@
+use crate::{syntax::context::{Ctx, RunnerContextScope, TriggerRows}, basics::{Value, Row, Table, Trigger, trigger::TriggerTiming}, utils::log};
+
+use super::{Runner, RunnerResult};
+
+/// Maximum nesting of triggers, deeper triggers most likely fire themselves endlessly
+pub(super) const MAX_TRIGGER_DEPTH: usize = 16;
+
+impl Runner {
+    /// Runs 'triggers' for one row of a statement on 'table', 'old' is the row before an update
+    /// or delete and 'new' the row written by an insert or update. Returns the new row, which
+    /// before triggers may have modified with 'new.<column> = value'
+    ///
+    /// A before trigger aborts the statement by returning false or with an error. After triggers
+    /// run once the rows are written, they cannot abort it, their errors are only logged
+    pub(super) fn fire_triggers(&self, table: &Table, triggers: &[Trigger], old: Option<&Row>, new: Option<Row>, ctx: &Ctx) -> Result<Option<Row>, String> {
+        let first = match triggers.first() {
+            Some(trigger) => trigger,
+            None => return Ok(new)
+        };
+
+        let depth = *self.trigger_depth.borrow() + 1;
+        if depth > MAX_TRIGGER_DEPTH {
+            return Err(format!("Trigger '{}' exceeded the maximum nesting depth of {}", first.name, MAX_TRIGGER_DEPTH))
+        }
+
+        self.trigger_depth.replace(depth);
+        let result = self.run_triggers(table, triggers, old, new, ctx);
+        *self.trigger_depth.borrow_mut() -= 1;
+
+        result
+    }
+
+    fn run_triggers(&self, table: &Table, triggers: &[Trigger], old: Option<&Row>, new: Option<Row>, ctx: &Ctx) -> Result<Option<Row>, String> {
+        let timing = triggers[0].timing;
+
+        let ctx = &Ctx::bounded(ctx.clone());
+        ctx.set_trigger_rows(TriggerRows {
+            columns: table.get_column_map(&table.get_column_names()).unwrap(),
+            old: old.cloned(),
+            new,
+            modifiable: timing == TriggerTiming::Before,
+        });
+
+        for trigger in triggers {
+            match (timing, self.run_trigger(trigger, ctx)) {
+                (TriggerTiming::Before, Ok(Some(Value::Boolean(false)))) => {
+                    return Err(format!("Trigger '{}' aborted the {} on table '{}'", trigger.name, trigger.event.name(), table.name))
+                },
+                (TriggerTiming::Before, Err(e)) => return Err(e),
+                (TriggerTiming::After, Err(e)) => {
+                    log::error(format!("Trigger '{}' failed after the {} on table '{}': {}", trigger.name, trigger.event.name(), table.name, e));
+                },
+                _ => (),
+            }
+        }
+
+        let rows = ctx.take_trigger_rows().expect("Trigger rows should be set");
+        let mut new = match rows.new {
+            Some(new) if rows.modifiable => new,
+            new => return Ok(new)
+        };
+
+        // values assigned by the triggers have to be valid for their columns
+        for (i, column) in table.columns.iter().enumerate() {
+            let value = column.transform_value(new.get(i).unwrap_or(&Value::Null))?;
+            new.set(i, value);
+        }
+
+        Ok(Some(new))
+    }
+
+    fn run_trigger(&self, trigger: &Trigger, ctx: &Ctx) -> RunnerResult {
+        let database = self.database.read();
+        let function = database.functions.get(&trigger.function)
+            .ok_or(format!("Function '{}' of trigger '{}' not found", trigger.function, trigger.name))?;
+
+        if !function.params.is_empty() {
+            return Err(format!("Function '{}' of trigger '{}' must not take arguments", trigger.function, trigger.name))
+        }
+
+        self.execute_stored_function(function, vec![], ctx)
+    }
+
+    /// Evaluates 'old.<column>' or 'new.<column>' in a trigger function, returns None if 'name'
+    /// does not refer to a trigger row
+    pub(super) fn eval_trigger_row(&self, name: &str, column: &str, ctx: &Ctx) -> Option<RunnerResult> {
+        if name != "old" && name != "new" {
+            return None
+        }
+
+        let rows = ctx.trigger_rows()?.borrow();
+        let rows = rows.as_ref()?;
+
+        let row = match name {
+            "old" => rows.old.as_ref(),
+            _ => rows.new.as_ref(),
+        };
+
+        let result = match (row, rows.columns.get(column)) {
+            (Some(row), Some(&index)) => Ok(Some(row.get(index).cloned().unwrap_or(Value::Null))),
+            (None, _) => Err(format!("Row '{}' is not available in {} triggers", name, if name == "old" { "insert" } else { "delete" })),
+            (_, None) => Err(format!("Column '{}' not found in row '{}'", column, name)),
+        };
+
+        Some(result)
+    }
+
+    /// Assigns 'value' to 'new.<column>' in a before trigger function
+    pub(super) fn assign_trigger_row(&self, name: &str, column: &str, value: Value, ctx: &Ctx) -> Result<(), String> {
+        let cell = match ctx.trigger_rows() {
+            Some(cell) if name == "new" => cell,
+            Some(_) if name == "old" => return Err("Row 'old' cannot be modified".to_string()),
+            _ => return Err(format!("Cannot assign to '{}.{}', only trigger rows can be modified", name, column))
+        };
+
+        let mut rows = cell.borrow_mut();
+        let rows = rows.as_mut().expect("Trigger rows should be set");
+        if !rows.modifiable {
+            return Err("Row 'new' can only be modified in before triggers".to_string())
+        }
+
+        let index = *rows.columns.get(column).ok_or(format!("Column '{}' not found in row 'new'", column))?;
+        match rows.new.as_mut() {
+            Some(row) => row.set(index, value),
+            None => return Err("Row 'new' is not available in delete triggers".to_string())
+        }
+
+        Ok(())
+    }
+}
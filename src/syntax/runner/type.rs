@@ -1,26 +1,29 @@
 use crate::{basics::{Value, value::NumericValue}, syntax::ast::Type};
 
-use super::Runner;
+use super::{Runner, math::Widened};
 
 impl Runner {
     pub fn check_type(&self, type_: &Type, value: &Value) -> bool {
         match (type_, value) {
-            (Type::Int, Value::Numeric(NumericValue::IntI64(_))) => true,
-            (Type::UInt, Value::Numeric(NumericValue::IntU64(_))) => true,
-            (Type::Float, Value::Numeric(NumericValue::Float64(_))) => true,
+            (Type::Int | Type::UInt | Type::Float | Type::Decimal, Value::Numeric(number)) => numeric_type(number) == *type_,
             (Type::String, Value::Text(_)) => true,
             (Type::Boolean, Value::Boolean(_)) => true,
             (Type::Array(type_), Value::Array(values)) => values.iter().all(|value| self.check_type(type_, value)),
+            (Type::Object, Value::Object(_)) => true,
+            // functions are compared by their parameters, untyped parameters accept any type
+            (Type::Function(params, _), Value::Function(closure)) => {
+                params.len() == closure.function.params.len() &&
+                    params.iter().zip(&closure.function.params).all(|(type_, (_, param))| type_ == param || *param == Type::Any)
+            },
             (Type::Void, Value::Null) => true,
+            (Type::Any, _) => true,
             _ => false
         }
     }
 
     pub fn get_type(&self, value: &Value) -> Type {
         match value {
-            Value::Numeric(NumericValue::IntI64(_)) => Type::Int,
-            Value::Numeric(NumericValue::IntU64(_)) => Type::UInt,
-            Value::Numeric(NumericValue::Float64(_)) => Type::Float,
+            Value::Numeric(number) => numeric_type(number),
             Value::Text(_) => Type::String,
             Value::Boolean(_) => Type::Boolean,
             Value::Array(values) => {
@@ -34,9 +37,24 @@ impl Runner {
                     Type::Array(Box::new(Type::Void))
                 }
             },
+            Value::Object(_) => Type::Object,
+            Value::Function(closure) => Type::Function(
+                closure.function.params.iter().map(|(_, type_)| type_.clone()).collect(),
+                Box::new(closure.function.return_type.clone())
+            ),
             Value::Null => Type::Void,
-
-            _ => unimplemented!("get_type {:?}", value)
+            // scripts have no types of their own for these, they are passed around as is
+            Value::Timestamp(_) | Value::Binary(_) | Value::Enum(_) | Value::UUID(_) => Type::Any,
         }
     }
 }
+
+/// Script type of a number, narrower column types are widened like in arithmetic
+pub(super) fn numeric_type(value: &NumericValue) -> Type {
+    match Widened::from(value) {
+        Widened::Unsigned(_) => Type::UInt,
+        Widened::Signed(_) => Type::Int,
+        Widened::Float(_) => Type::Float,
+        Widened::Decimal(_) => Type::Decimal,
+    }
+}
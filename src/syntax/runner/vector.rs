@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::{syntax::ast::{Node, Literal, Expression, Operator, SelectQuery}, basics::Table, index::{IndexKind, Metric, to_vector}, auth::RlsAction};
+
+use super::{Runner, Ctx};
+
+impl Runner {
+    /// Returns the rows of 'table' which can be among the results of a select ordered by the
+    /// distance of an indexed vector column to a constant vector, e.g.
+    /// 'order l2(embedding, $q) asc limit 10'. Only those rows have to be sorted.
+    ///
+    /// Returns None when every row has to be considered, which is the case for queries with a
+    /// where clause, joins, window functions or without a limit, and for users with select
+    /// policies, the nearest rows may not be visible to them
+    pub(super) fn nearest_candidates(&self, select: &SelectQuery, table: &Table, ctx: &Ctx) -> Result<Option<HashSet<usize>>, String> {
+        let limit = match select.limit {
+            Some(limit) => limit,
+            None => return Ok(None)
+        };
+
+        let has_window = select.columns.iter().any(|node| matches!(node, Node::Expression(Expression::Window(_))));
+        if select.where_clause.is_some() || !select.joins.is_empty() || has_window {
+            return Ok(None)
+        }
+
+        if !table.police(ctx.cluster_user(), RlsAction::Select).is_empty() {
+            return Ok(None)
+        }
+
+        let (operator, name, arguments) = match select.order.as_deref() {
+            Some(Node::Expression(Expression::Unary { operator, right })) => match right.as_ref() {
+                Node::Expression(Expression::Call { name, arguments, .. }) if arguments.len() == 2 => (operator, name, arguments),
+                _ => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        // only the nearest rows are wanted, larger dot products are nearer
+        let metric = match Metric::from_name(name) {
+            Some(metric) => metric,
+            None => return Ok(None)
+        };
+        let nearest_first = match metric {
+            Metric::Dot => matches!(operator, Operator::Dec),
+            _ => matches!(operator, Operator::Inc),
+        };
+        if !nearest_first {
+            return Ok(None)
+        }
+
+        let column = match &arguments[0] {
+            Node::Literal(Literal::Identifier(column, _)) => column,
+            Node::Expression(Expression::Member { object, member, .. }) => match object.as_ref() {
+                Node::Literal(Literal::Identifier(name, _)) if *name == table.name => member,
+                _ => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        let index = match table.get_index(column, IndexKind::Vector) {
+            Some(index) => index.vector().expect("Index should be a vector index"),
+            None => return Ok(None)
+        };
+
+        // the query vector must not depend on the row, otherwise every row is considered
+        let query = match self.run(&arguments[1], ctx) {
+            Ok(Some(value)) => match to_vector(&value) {
+                Some(query) => query,
+                None => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        let amount = limit + select.offset.unwrap_or(0);
+        Ok(Some(index.search(&query, metric, amount)))
+    }
+}
@@ -0,0 +1,327 @@
+use std::{cmp::Ordering, collections::HashMap, thread};
+
+use crate::{syntax::{ast::{Window, Node}, context::{Ctx, RunnerContextFields}}, basics::{Value, Row, value::NumericValue}};
+
+use super::Runner;
+
+/// Values of one filtered row needed to evaluate a window function
+struct WindowRow {
+    /// Index of the joined row
+    index: usize,
+    partition: Vec<Value>,
+    order: Vec<Value>,
+    arguments: Vec<Value>,
+}
+
+impl Runner {
+    /// Evaluates a window function over the filtered joined rows ('row_indexes')
+    ///
+    /// Returns the value of each row, keyed by the joined row index
+    pub(super) fn eval_window(&self, window: &Window, joined_rows: &[Vec<*const Row>], row_indexes: &[usize], null_base_row: &Row, ctx: &Ctx) -> Result<HashMap<usize, Value>, String> {
+        let min_arguments = match window.function.as_str() {
+            "row_number" | "rank" | "dense_rank" | "count" => 0,
+            "lag" | "lead" | "first_value" | "last_value" | "sum" | "avg" | "min" | "max" => 1,
+            name => return Err(format!("Unknown window function '{}'", name))
+        };
+        if window.arguments.len() < min_arguments {
+            return Err(format!("Window function '{}' expects {} argument(s)", window.function, min_arguments))
+        }
+
+        let order_nodes = window.order.iter().map(|(node, _)| node).collect::<Vec<_>>();
+        let ascending = window.order.iter().map(|(_, ascending)| *ascending).collect::<Vec<_>>();
+
+        let mut rows = Vec::with_capacity(row_indexes.len());
+        let nodes = window.partition.iter().chain(order_nodes.iter().copied()).chain(window.arguments.iter()).collect::<Vec<_>>();
+        let threads = self.scan_threads(&nodes, row_indexes.len(), ctx);
+        if threads > 1 {
+            let values = self.eval_parallel(&nodes, joined_rows, row_indexes, threads, ctx)?;
+            for (&index, mut values) in row_indexes.iter().zip(values) {
+                let arguments = values.split_off(window.partition.len() + order_nodes.len());
+                let order = values.split_off(window.partition.len());
+                rows.push(WindowRow { index, partition: values, order, arguments })
+            }
+        } else {
+            for &index in row_indexes {
+                let joined_row = &joined_rows[index];
+                ctx.set_joined_row(joined_row);
+                let base_row = joined_row.first().copied().unwrap_or(std::ptr::null());
+                if base_row.is_null() {
+                    ctx.set_row(null_base_row);
+                } else {
+                    ctx.set_row(unsafe { &*base_row });
+                }
+
+                rows.push(WindowRow {
+                    index,
+                    partition: self.eval_window_values(window.partition.iter(), ctx)?,
+                    order: self.eval_window_values(order_nodes.iter().copied(), ctx)?,
+                    arguments: self.eval_window_values(window.arguments.iter(), ctx)?,
+                })
+            }
+        }
+
+        // aggregates over whole partitions are merged from partial aggregates of each thread
+        if threads > 1 && window.order.is_empty() && is_aggregate(&window.function) {
+            return self.eval_window_aggregates(&window.function, rows, threads)
+        }
+
+        // rows of a partition are next to each other, sorted by the window order
+        rows.sort_by(|a, b| {
+            compare_values(&a.partition, &b.partition, &[])
+                .then_with(|| compare_values(&a.order, &b.order, &ascending))
+        });
+
+        let mut result = HashMap::with_capacity(rows.len());
+        let mut start = 0;
+        while start < rows.len() {
+            let mut end = start + 1;
+            while end < rows.len() && compare_values(&rows[start].partition, &rows[end].partition, &[]) == Ordering::Equal {
+                end += 1;
+            }
+
+            self.eval_window_partition(window, &rows[start..end], &ascending, &mut result)?;
+            start = end;
+        }
+
+        Ok(result)
+    }
+
+    /// Evaluates an aggregate over whole partitions, 'rows' are split into one chunk per thread
+    /// and every thread aggregates the partitions of its chunk
+    fn eval_window_aggregates(&self, function: &str, rows: Vec<WindowRow>, threads: usize) -> Result<HashMap<usize, Value>, String> {
+        let row_count = rows.len();
+        let chunk_size = row_count.div_ceil(threads);
+        let mut chunks = vec![];
+        let mut rows = rows.into_iter();
+        loop {
+            let chunk = rows.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() { break }
+            chunks.push(chunk);
+        }
+
+        let database = self.database.arc();
+        let results = thread::scope(|scope| {
+            let workers = chunks.into_iter().map(|chunk| {
+                let database = database.clone();
+                scope.spawn(move || partial_aggregates(&Runner::new(database), function, chunk))
+            }).collect::<Vec<_>>();
+
+            workers.into_iter()
+                .map(|worker| worker.join().unwrap_or_else(|_| Err("Worker thread panicked".to_string())))
+                .collect::<Vec<_>>()
+        });
+
+        let mut partials = vec![];
+        for result in results {
+            partials.extend(result?);
+        }
+
+        // partial aggregates of the same partition are next to each other
+        partials.sort_by(|a, b| compare_values(&a.partition, &b.partition, &[]));
+        let mut merged: Vec<PartialAggregate> = Vec::with_capacity(partials.len());
+        for partial in partials {
+            match merged.last_mut() {
+                Some(last) if compare_values(&last.partition, &partial.partition, &[]) == Ordering::Equal => {
+                    last.aggregate.merge(self, partial.aggregate)?;
+                    last.indexes.extend(partial.indexes);
+                },
+                _ => merged.push(partial),
+            }
+        }
+
+        let mut result = HashMap::with_capacity(row_count);
+        for partial in merged {
+            let value = partial.aggregate.value(function);
+            for index in partial.indexes {
+                result.insert(index, value.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn eval_window_values<'n>(&self, nodes: impl Iterator<Item = &'n Node>, ctx: &Ctx) -> Result<Vec<Value>, String> {
+        nodes.map(|node| Ok(self.run(node, ctx)?.unwrap_or(Value::Null))).collect()
+    }
+
+    /// Evaluates the window function on one sorted partition
+    ///
+    /// Without 'order by' the frame is the whole partition, otherwise it ends with the last peer
+    /// (row with equal order values) of the current row
+    fn eval_window_partition(&self, window: &Window, rows: &[WindowRow], ascending: &[bool], result: &mut HashMap<usize, Value>) -> Result<(), String> {
+        // (start, end) of each group of peers
+        let mut peers = vec![];
+        let mut start = 0;
+        while start < rows.len() {
+            let mut end = start + 1;
+            while end < rows.len() && (window.order.is_empty() || compare_values(&rows[start].order, &rows[end].order, ascending) == Ordering::Equal) {
+                end += 1;
+            }
+            peers.push((start, end));
+            start = end;
+        }
+
+        let mut aggregate = WindowAggregate::default();
+        for (group, &(start, end)) in peers.iter().enumerate() {
+            for row in &rows[start..end] {
+                aggregate.add(self, &window.function, row)?;
+            }
+
+            for i in start..end {
+                let value = match window.function.as_str() {
+                    "row_number" => uint(i + 1),
+                    "rank" => uint(start + 1),
+                    "dense_rank" => uint(group + 1),
+                    "lag" | "lead" => {
+                        let offset = match rows[i].arguments.get(1) {
+                            Some(Value::Numeric(n)) if n.to_i128() >= 0 => n.to_i128() as usize,
+                            Some(_) => return Err(format!("Window function '{}' expects a non-negative offset", window.function)),
+                            None => 1,
+                        };
+                        let target = match window.function.as_str() {
+                            "lag" => i.checked_sub(offset),
+                            _ => i.checked_add(offset),
+                        };
+
+                        match target.and_then(|target| rows.get(target)) {
+                            Some(row) => row.arguments[0].clone(),
+                            None => rows[i].arguments.get(2).cloned().unwrap_or(Value::Null),
+                        }
+                    },
+                    "first_value" => rows[0].arguments[0].clone(),
+                    "last_value" => rows[end - 1].arguments[0].clone(),
+                    _ => aggregate.value(&window.function),
+                };
+
+                result.insert(rows[i].index, value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate of the rows of one partition which were evaluated by the same thread
+struct PartialAggregate {
+    partition: Vec<Value>,
+    aggregate: WindowAggregate,
+    /// Joined row indexes of the aggregated rows
+    indexes: Vec<usize>,
+}
+
+/// Aggregates the rows of each partition in 'rows'
+fn partial_aggregates(runner: &Runner, function: &str, mut rows: Vec<WindowRow>) -> Result<Vec<PartialAggregate>, String> {
+    rows.sort_by(|a, b| compare_values(&a.partition, &b.partition, &[]));
+
+    let mut partials: Vec<PartialAggregate> = vec![];
+    for row in rows {
+        let partial = match partials.last_mut() {
+            Some(last) if compare_values(&last.partition, &row.partition, &[]) == Ordering::Equal => last,
+            _ => {
+                partials.push(PartialAggregate { partition: row.partition.clone(), aggregate: WindowAggregate::default(), indexes: vec![] });
+                partials.last_mut().unwrap()
+            }
+        };
+
+        partial.aggregate.add(runner, function, &row)?;
+        partial.indexes.push(row.index);
+    }
+
+    Ok(partials)
+}
+
+fn is_aggregate(function: &str) -> bool {
+    matches!(function, "count" | "sum" | "avg" | "min" | "max")
+}
+
+/// Running aggregate of a window frame, null values are skipped
+#[derive(Default)]
+struct WindowAggregate {
+    count: usize,
+    sum: Option<Value>,
+    float_sum: f64,
+    min: Option<Value>,
+    max: Option<Value>,
+}
+
+impl WindowAggregate {
+    fn add(&mut self, runner: &Runner, function: &str, row: &WindowRow) -> Result<(), String> {
+        let value = match row.arguments.first() {
+            Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+            // count() without arguments counts rows
+            None => {
+                self.count += 1;
+                return Ok(())
+            }
+        };
+        self.count += 1;
+
+        match function {
+            "sum" | "avg" => {
+                let number = value.as_numeric().ok_or(format!("Window function '{}' expects numeric values", function))?;
+                self.float_sum += number.to_f64();
+                self.sum = match self.sum.take() {
+                    Some(sum) => runner.eval_add(&sum, value)?,
+                    None => Some(value.clone()),
+                };
+            },
+            "min" if self.min.as_ref().is_none_or(|min| value < min) => self.min = Some(value.clone()),
+            "max" if self.max.as_ref().is_none_or(|max| value > max) => self.max = Some(value.clone()),
+            _ => ()
+        }
+
+        Ok(())
+    }
+
+    /// Adds the rows aggregated by 'other'
+    fn merge(&mut self, runner: &Runner, other: WindowAggregate) -> Result<(), String> {
+        self.count += other.count;
+        self.float_sum += other.float_sum;
+        self.sum = match (self.sum.take(), other.sum) {
+            (Some(sum), Some(other)) => runner.eval_add(&sum, &other)?,
+            (sum, other) => sum.or(other),
+        };
+
+        if let Some(min) = other.min.filter(|min| self.min.as_ref().is_none_or(|current| min < current)) {
+            self.min = Some(min);
+        }
+        if let Some(max) = other.max.filter(|max| self.max.as_ref().is_none_or(|current| max > current)) {
+            self.max = Some(max);
+        }
+
+        Ok(())
+    }
+
+    fn value(&self, function: &str) -> Value {
+        match function {
+            "count" => uint(self.count),
+            "sum" => self.sum.clone().unwrap_or(Value::Null),
+            "avg" if self.count > 0 => Value::Numeric(NumericValue::Float64(self.float_sum / self.count as f64)),
+            "min" => self.min.clone().unwrap_or(Value::Null),
+            "max" => self.max.clone().unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+}
+
+fn uint(value: usize) -> Value {
+    Value::Numeric(NumericValue::IntU64(value as u64))
+}
+
+/// Compares values in order, 'ascending' flags reverse the ordering of descending values
+fn compare_values(a: &[Value], b: &[Value], ascending: &[bool]) -> Ordering {
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+        let ordering = match ascending.get(i) {
+            Some(false) => ordering.reverse(),
+            _ => ordering,
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering
+        }
+    }
+
+    Ordering::Equal
+}
@@ -1,8 +1,8 @@
 use std::borrow::Cow;
 
-use crate::{basics::{Value, Column, column::{ColumnType, NumericType, TextType, TimestampType}, value::{NumericValue}}, auth::RlsAction};
+use crate::{basics::{Value, Column, trigger::TriggerTiming, column::{ColumnType, NumericType, TextType, TimestampType}, value::{NumericValue}}, auth::RlsAction, index::IndexKind};
 
-use super::ast::{Node, SDL, CreateSDL, Literal, Number, Statement, Type, Expression, Operator, Query, SelectQuery, InsertQuery, DeleteQuery, UpdateQuery, GrantSDL};
+use super::ast::{Node, SDL, CreateSDL, Literal, Number, Statement, Type, Expression, Operator, Query, SelectQuery, InsertQuery, DeleteQuery, UpdateQuery, GrantSDL, OnConflict, ConflictAction, WithQuery, Pattern};
 
 /// Indentation helper
 fn spaces(indent: usize) -> String {
@@ -27,6 +27,41 @@ fn escapify(s: &str) -> String {
     }
 }
 
+/// Function declaration, 'fn name(parameters) -> type { ... }'
+fn function(name: &str, parameters: &[(String, Type)], return_type: &Type, block: &Node, indent: usize) -> Result<String, String> {
+    let mut schema = format!("fn {}(", name);
+
+    for (i, (name, data_type)) in parameters.iter().enumerate() {
+        schema.push_str(&format!("{}: {}", name, data_type.to_schema_string(0)?));
+        
+        if i < parameters.len() - 1 {
+            schema.push_str(", ");
+        }
+    }
+
+    schema.push_str(") ");
+    if return_type != &Type::Void {
+        schema.push_str(&format!("-> {} ", return_type.to_schema_string(0)?));
+    }
+    schema.push_str(block.to_schema_string(indent)?.trim());
+
+    Ok(schema)
+}
+
+/// Returning clause of a query, empty if there is none
+fn returning(returning: &Option<Vec<Node>>, indent: usize) -> Result<String, String> {
+    let returning = match returning {
+        Some(returning) => returning,
+        None => return Ok(String::new())
+    };
+
+    let columns = returning.iter()
+        .map(|node| Ok(node.to_schema_string(indent)?.trim().to_string()))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(format!(" returning {}", columns.join(", ")))
+}
+
 pub trait SchemaStringDeblock {
     fn deblock(&self) -> String;
 }
@@ -181,6 +216,25 @@ impl ToSchemaString for CreateSDL {
                 let superuser = if *is_superuser { " superuser" } else { "" };
                 format!("{}create user {}:{}{}", spaces, name, password, superuser)
             }
+            CreateSDL::Index { name, table, column, kind } => {
+                let (column, kind) = match kind {
+                    IndexKind::FullText => (column.clone(), "fulltext"),
+                    IndexKind::Vector => (column.clone(), "vector"),
+                    IndexKind::Json(path) => (std::iter::once(column).chain(path).cloned().collect::<Vec<_>>().join("."), "json"),
+                };
+                format!("{}create index {} on {}({}) {}", spaces, name, table, column, kind)
+            }
+            CreateSDL::Trigger { table, trigger } => {
+                let timing = match trigger.timing {
+                    TriggerTiming::Before => "before",
+                    TriggerTiming::After => "after",
+                };
+                format!("{}create trigger {} {} {} on {} execute {}", spaces, trigger.name, timing, trigger.event.name(), table, trigger.function)
+            }
+            CreateSDL::Function { name, parameters, return_type, block, replace, .. } => {
+                let replace = if *replace { "or replace " } else { "" };
+                format!("{}create {}{}", spaces, replace, function(name, parameters, return_type, block, indent)?)
+            }
         };
 
         Ok(s)
@@ -216,6 +270,7 @@ impl ToSchemaString for ColumnType {
                 NumericType::IntI64 => "i64".to_string(),
                 NumericType::Float32 => "f32".to_string(),
                 NumericType::Float64 => "f64".to_string(),
+                NumericType::Decimal(precision, scale) => format!("decimal({}, {})", precision, scale),
             },
             ColumnType::Text(text) => match text {
                 TextType::Char => "char".to_string(),
@@ -227,8 +282,14 @@ impl ToSchemaString for ColumnType {
                 TimestampType::Milliseconds => "time(ms)".to_string(),
                 TimestampType::Microseconds => "time(us)".to_string(),
                 TimestampType::Nanoseconds => "time(ns)".to_string(),
+                TimestampType::Zoned => "time(tz)".to_string(),
+                TimestampType::Date => "date".to_string(),
+                TimestampType::Time => "time".to_string(),
+                TimestampType::Interval => "interval".to_string(),
             },
             ColumnType::Boolean => "bool".to_string(),
+            ColumnType::Vector(dimension) => format!("vector({})", dimension),
+            ColumnType::Json(length) => format!("json({})", length),
             _ => Err("Unsupported column type for schema string".to_string())?,
         };
 
@@ -250,6 +311,7 @@ impl ToSchemaString for Value {
                 NumericValue::IntI64(n) => n.to_string(),
                 NumericValue::Float32(n) => n.to_string(),
                 NumericValue::Float64(n) => n.to_string(),
+                NumericValue::Decimal(n) => n.to_string(),
             },
             Value::Text(text) => format!("{:?}", text),
             Value::Timestamp(time) => match time {
@@ -267,6 +329,19 @@ impl ToSchemaString for Value {
                 s.push_str("]");
                 s
             },
+            Value::Object(entries) => {
+                let mut s = "{".to_string();
+
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    s.push_str(&format!(" {:?}: {}", key, value.to_schema_string(indent + 1)?.trim()));
+                    if i < entries.len() - 1 {
+                        s.push(',');
+                    }
+                }
+
+                s.push_str(" }");
+                s
+            },
             Value::Null => "null".to_string(),
 
             _ => Err("Unsupported value for schema string".to_string())?,
@@ -279,7 +354,7 @@ impl ToSchemaString for Value {
 impl ToSchemaString for Literal {
     fn to_schema_string(&self, indent: usize) -> Result<String, String> {
         let s = match self {
-            Literal::Identifier(id) => id.to_string(),
+            Literal::Identifier(id, _) => id.to_string(),
             Literal::Number(number) => match number {
                 Number::Int(n) => n.to_string(),
                 Number::UInt(n) => n.to_string(),
@@ -298,7 +373,21 @@ impl ToSchemaString for Literal {
                 s.push_str("]");
                 s
             }
+            Literal::Object(entries) => {
+                let mut s = "{".to_string();
+
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    s.push_str(&format!(" {:?}: {}", key, value.to_schema_string(indent + 1)?.trim()));
+                    if i < entries.len() - 1 {
+                        s.push(',');
+                    }
+                }
+
+                s.push_str(" }");
+                s
+            }
             Literal::Null => "null".to_string(),
+            Literal::Parameter(index) => format!("${}", index),
         };
 
         Ok(format!("{}{}", spaces(indent), s))
@@ -308,29 +397,13 @@ impl ToSchemaString for Literal {
 impl ToSchemaString for Statement {
     fn to_schema_string(&self, indent: usize) -> Result<String, String> {
         let s = match self {
-            Statement::Assignment { name, value } => format!("{} = {}", name, value.to_schema_string(indent)?.trim()),
+            Statement::Assignment { name, value, .. } => format!("{} = {}", name, value.to_schema_string(indent)?.trim()),
+            Statement::MemberAssignment { object, member, value } => format!("{}.{} = {}", object.to_schema_string(indent)?.trim(), member, value.to_schema_string(indent)?.trim()),
             Statement::Expression(expression) => return expression.to_schema_string(indent),
-            Statement::Function { name, parameters, return_type, block } => {
-                let mut schema = format!("fn {}(", name);
-
-                for (i, (name, data_type)) in parameters.iter().enumerate() {
-                    schema.push_str(&format!("{}: {}", name, data_type.to_schema_string(0)?));
-                    
-                    if i < parameters.len() - 1 {
-                        schema.push_str(", ");
-                    }
-                }
-
-                schema.push_str(") ");
-                if return_type != &Type::Void {
-                    schema.push_str(&format!("-> {} ", return_type.to_schema_string(0)?));
-                }
-                schema.push_str(&block.to_schema_string(indent)?.trim());
-                schema
-            },
-            Statement::Let { name, value } => format!("let {} = {}", name, value.to_schema_string(indent)?.trim()),
-            Statement::Return(value) => format!("return {}", value.to_schema_string(indent)?.trim()),
-            Statement::If { condition, then_block, else_block } => {
+            Statement::Function { name, parameters, return_type, block, .. } => function(name, parameters, return_type, block, indent)?,
+            Statement::Let { name, value, .. } => format!("let {} = {}", name, value.to_schema_string(indent)?.trim()),
+            Statement::Return { value, .. } => format!("return {}", value.to_schema_string(indent)?.trim()),
+            Statement::If { condition, then_block, else_block, .. } => {
                 let mut schema = format!("if {} ", condition.to_schema_string(indent)?.trim());
 
                 schema.push_str(&then_block.to_schema_string(indent)?.trim());
@@ -341,18 +414,39 @@ impl ToSchemaString for Statement {
 
                 schema
             },
-            Statement::While { condition, block } => 
+            Statement::While { condition, block, .. } => 
                 format!("while {} {}", condition.to_schema_string(indent)?.trim(), block.to_schema_string(indent)?.trim()),
-            Statement::For { initializer, condition, action, block } =>
+            Statement::For { initializer, condition, action, block, .. } =>
                 format!("for ({}; {}; {}) {}", 
                     initializer.to_schema_string(indent)?.trim(),
                     condition.to_schema_string(indent)?.trim(),
                     action.to_schema_string(indent)?.trim(),
                     block.to_schema_string(indent)?.trim(),
                 ),
+            Statement::ForIn { index, pattern, iterable, block, .. } => {
+                let pattern = match pattern {
+                    Pattern::Identifier(name) => name.clone(),
+                    Pattern::Array(names) => format!("[{}]", names.join(", ")),
+                    Pattern::Object(names) => format!("{{{}}}", names.join(", ")),
+                };
+                let pattern = match index {
+                    Some(index) => format!("({}, {})", index, pattern),
+                    None => pattern,
+                };
+
+                format!("for {} in {} {}", pattern, iterable.to_schema_string(indent)?.trim(), block.to_schema_string(indent)?.trim())
+            },
             Statement::Loop { block } => format!("loop {}", block.to_schema_string(indent)?.trim()),
-            Statement::Break => "break".to_string(),
-            Statement::Continue => "continue".to_string(),
+            Statement::Break { .. } => "break".to_string(),
+            Statement::Continue { .. } => "continue".to_string(),
+            Statement::Throw { value, .. } => format!("throw {}", value.to_schema_string(indent)?.trim()),
+            Statement::Import { path, .. } => format!("import {:?}", path),
+            Statement::Export { declaration: node, .. } => format!("export {}", node.to_schema_string(indent)?.trim()),
+            Statement::Try { block, name, catch_block, .. } => format!("try {} catch ({}) {}",
+                block.to_schema_string(indent)?.trim(),
+                name,
+                catch_block.to_schema_string(indent)?.trim(),
+            ),
         };
 
         Ok(format!("{}{}", spaces(indent), s))
@@ -365,10 +459,20 @@ impl ToSchemaString for Type {
             Type::Int => "int".to_string(),
             Type::UInt => "uint".to_string(),
             Type::Float => "float".to_string(),
+            Type::Decimal => "decimal".to_string(),
             Type::String => "string".to_string(),
             Type::Boolean => "bool".to_string(),
             Type::Pointer(t) => format!("*{}", t.to_schema_string(0)?),
-            Type::Array(t) => format!("[]{}", t.to_schema_string(0)?),
+            Type::Array(t) => format!("[{}]", t.to_schema_string(0)?),
+            Type::Object => "object".to_string(),
+            Type::Any => "any".to_string(),
+            Type::Function(params, return_type) => {
+                let params = params.iter().map(|t| t.to_schema_string(0)).collect::<Result<Vec<_>, String>>()?;
+                match return_type.as_ref() {
+                    Type::Void => format!("fn({})", params.join(", ")),
+                    return_type => format!("fn({}) -> {}", params.join(", "), return_type.to_schema_string(0)?),
+                }
+            },
             _ => Err("Unsupported type for schema string".to_string())?,
         };
 
@@ -379,7 +483,7 @@ impl ToSchemaString for Type {
 impl ToSchemaString for Expression {
     fn to_schema_string(&self, indent: usize) -> Result<String, String> {
         let s = match self {
-            Expression::Binary { left, operator, right } => 
+            Expression::Binary { left, operator, right, .. } => 
                 format!("{} {} {}",
                     left.to_schema_string(indent)?.trim(),
                     operator.to_schema_string(0)?,
@@ -387,7 +491,7 @@ impl ToSchemaString for Expression {
                 ),
             Expression::Unary { operator, right } => 
                 format!("{}{}", right.to_schema_string(indent)?.trim(), operator.to_schema_string(0)?),
-            Expression::Call { name, arguments } => {
+            Expression::Call { name, arguments, .. } => {
                 let mut schema = format!("{}(", name);
 
                 for (i, arg) in arguments.iter().enumerate() {
@@ -404,10 +508,47 @@ impl ToSchemaString for Expression {
             Expression::Index { object, index } => {
                 format!("{}[{}]", object.to_schema_string(indent)?.trim(), index.to_schema_string(indent)?.trim())
             },
-            Expression::Member { object, member } => {
+            Expression::Member { object, member, .. } => {
                 format!("{}.{}", object.to_schema_string(indent)?.trim(), escapify(member))
             },
             Expression::Literal(literal) => literal.to_schema_string(indent)?,
+            Expression::Window(window) => {
+                let call = Expression::Call { name: window.function.clone(), arguments: window.arguments.clone(), token: 0 };
+                let mut schema = format!("{} over (", call.to_schema_string(0)?);
+
+                if !window.partition.is_empty() {
+                    let partition = window.partition.iter()
+                        .map(|node| Ok(node.to_schema_string(indent)?.trim().to_string()))
+                        .collect::<Result<Vec<_>, String>>()?;
+                    schema.push_str(&format!("partition by {}", partition.join(", ")));
+                }
+
+                if !window.order.is_empty() {
+                    let order = window.order.iter()
+                        .map(|(node, ascending)| {
+                            let direction = if *ascending { "asc" } else { "desc" };
+                            Ok(format!("{} {}", node.to_schema_string(indent)?.trim(), direction))
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    if !window.partition.is_empty() { schema.push(' ') }
+                    schema.push_str(&format!("order by {}", order.join(", ")));
+                }
+
+                schema.push(')');
+                schema
+            },
+            Expression::Lambda { parameters, return_type, block, .. } => {
+                let parameters = parameters.iter()
+                    .map(|(name, data_type)| Ok(format!("{}: {}", name, data_type.to_schema_string(0)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+
+                let mut schema = format!("fn({}) ", parameters.join(", "));
+                if return_type != &Type::Void {
+                    schema.push_str(&format!("-> {} ", return_type.to_schema_string(0)?));
+                }
+                schema.push_str(block.to_schema_string(indent)?.trim());
+                schema
+            },
 
             _ => Err("Unsupported expression for schema string".to_string())?,
         };
@@ -431,6 +572,9 @@ impl ToSchemaString for Operator {
             Operator::Le => "<=".to_string(),
             Operator::Gt => ">".to_string(),
             Operator::Ge => ">=".to_string(),
+            Operator::Like => "like".to_string(),
+            Operator::ILike => "ilike".to_string(),
+            Operator::Match => "=~".to_string(),
 
             Operator::And => "&&".to_string(),
             Operator::Or => "||".to_string(),
@@ -468,10 +612,44 @@ impl ToSchemaString for Query {
              Query::Insert(insert) => insert.to_schema_string(indent),
              Query::Update(update) => update.to_schema_string(indent),
              Query::Delete(delete) => delete.to_schema_string(indent),
+             Query::With(with) => with.to_schema_string(indent),
          }
     }
 }
 
+impl ToSchemaString for WithQuery {
+    fn to_schema_string(&self, indent: usize) -> Result<String, String> {
+        let mut schema = format!("{}with ", spaces(indent));
+        if self.recursive {
+            schema.push_str("recursive ");
+        }
+
+        let mut ctes = vec![];
+        for cte in &self.ctes {
+            let mut s = escapify(&cte.name);
+            if !cte.columns.is_empty() {
+                let columns = cte.columns.iter().map(|c| escapify(c)).collect::<Vec<_>>();
+                s.push_str(&format!("({})", columns.join(", ")));
+            }
+
+            s.push_str(&format!(" as ({}", cte.query.to_schema_string(0)?));
+            if let Some(union) = &cte.union {
+                s.push_str(if union.all { " union all " } else { " union " });
+                s.push_str(&union.query.to_schema_string(0)?);
+            }
+            s.push(')');
+
+            ctes.push(s);
+        }
+
+        schema.push_str(&ctes.join(", "));
+        schema.push(' ');
+        schema.push_str(&self.query.to_schema_string(0)?);
+
+        Ok(schema)
+    }
+}
+
 impl ToSchemaString for SelectQuery {
     fn to_schema_string(&self, indent: usize) -> Result<String, String> {
         let mut schema = format!("{}query ", spaces(indent));
@@ -517,7 +695,11 @@ impl ToSchemaString for SelectQuery {
         }
 
         if let Some(exclude) = &self.exclude {
-            schema.push_str(&format!(" exclude {}", exclude.join(", ")))
+            schema.push_str(&format!(" exclude {}", exclude.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ")))
+        }
+
+        if self.as_objects {
+            schema.push_str(" as object")
         }
 
         Ok(schema)
@@ -530,8 +712,50 @@ impl ToSchemaString for InsertQuery {
         schema.push_str(&self.table);
         schema.push_str(" insert");
 
-        for (key, val) in &self.key_values {
-            schema.push_str(&format!(" {}:{}", escapify(key), val.to_schema_string(indent)?.trim()));
+        if let [row] = self.rows.as_slice() {
+            for (key, _, val) in row {
+                schema.push_str(&format!(" {}:{}", escapify(key), val.to_schema_string(indent)?.trim()));
+            }
+        } else {
+            let mut rows = vec![];
+            for row in &self.rows {
+                let mut key_values = vec![];
+                for (key, _, val) in row {
+                    key_values.push(format!("{}: {}", escapify(key), val.to_schema_string(indent)?.trim()));
+                }
+                rows.push(format!("{{ {} }}", key_values.join(", ")));
+            }
+            schema.push_str(&format!(" [{}]", rows.join(", ")));
+        }
+
+        if let Some(on_conflict) = &self.on_conflict {
+            schema.push_str(&on_conflict.to_schema_string(indent)?);
+        }
+
+        schema.push_str(&returning(&self.returning, indent)?);
+
+        Ok(schema)
+    }
+}
+
+impl ToSchemaString for OnConflict {
+    fn to_schema_string(&self, indent: usize) -> Result<String, String> {
+        let mut schema = " on conflict".to_string();
+
+        if !self.columns.is_empty() {
+            let columns = self.columns.iter().map(|(c, _)| escapify(c)).collect::<Vec<_>>();
+            schema.push_str(&format!(" ({})", columns.join(", ")));
+        }
+
+        match &self.action {
+            ConflictAction::Nothing => schema.push_str(" do nothing"),
+            ConflictAction::Update(key_values) => {
+                schema.push_str(" do update set");
+
+                for (key, _, val) in key_values {
+                    schema.push_str(&format!(" {}:{}", escapify(key), val.to_schema_string(indent)?.trim()));
+                }
+            }
         }
 
         Ok(schema)
@@ -544,7 +768,7 @@ impl ToSchemaString for UpdateQuery {
         schema.push_str(&self.table);
         schema.push_str(" update");
 
-        for (key, val) in &self.key_values {
+        for (key, _, val) in &self.key_values {
             schema.push_str(&format!(" {}:{}", escapify(key), val.to_schema_string(indent)?.trim()));
         }
 
@@ -552,6 +776,8 @@ impl ToSchemaString for UpdateQuery {
             schema.push_str(&format!(" where {}", where_clause.to_schema_string(indent)?.trim()));
         }
 
+        schema.push_str(&returning(&self.returning, indent)?);
+
         Ok(schema)
     }
 }
@@ -566,6 +792,8 @@ impl ToSchemaString for DeleteQuery {
             schema.push_str(&format!(" where {}", where_clause.to_schema_string(indent)?.trim()));
         }
 
+        schema.push_str(&returning(&self.returning, indent)?);
+
         Ok(schema)
     }
 }
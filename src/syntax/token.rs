@@ -59,6 +59,10 @@ pub enum QueryKeyword {
     Limit,
     Offset,
     Exclude,
+    Returning,
+
+    With,
+    Union,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -92,6 +96,8 @@ pub enum Literal {
     Int(String),
     Float(String),
     String(String),
+    /// Positional '$n' placeholder, bound to a value when the script is run
+    Parameter(usize),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -111,6 +117,12 @@ pub enum Keyword {
     Null,
     On,
     To,
+    Try,
+    Catch,
+    Throw,
+    In,
+    Import,
+    Export,
 }
 
 impl Keyword {
@@ -173,6 +185,9 @@ pub enum Operator {
     BitwiseXor,
     BitwiseLeftShift,
     BitwiseRightShift,
+    Like,
+    ILike,
+    Match,
 }
 
 impl Operator {
@@ -204,6 +219,9 @@ impl Operator {
             Operator::GreaterThanOrEqual => ast::Operator::Ge,
             Operator::LessThan => ast::Operator::Lt,
             Operator::LessThanOrEqual => ast::Operator::Le,
+            Operator::Like => ast::Operator::Like,
+            Operator::ILike => ast::Operator::ILike,
+            Operator::Match => ast::Operator::Match,
              
             // Logical
             Operator::And => ast::Operator::And,
@@ -230,6 +248,7 @@ impl Operator {
     pub fn is_comparison(&self) -> bool {
         match self {
             Operator::Equal | Operator::NotEqual | Operator::GreaterThan | Operator::GreaterThanOrEqual | Operator::LessThan | Operator::LessThanOrEqual => true,
+            Operator::Like | Operator::ILike | Operator::Match => true,
             _ => false
         }
     }
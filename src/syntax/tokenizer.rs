@@ -3,19 +3,23 @@ use std::ops::RangeBounds;
 use super::token::{Token, TokenKind, Symbol, Operator, Literal, Keyword, QueryKeyword, SDLKeyword};
 
 pub struct Tokenizer {
-    input: String,
+    /// Characters of the input, indexed by 'position'
+    input: Vec<char>,
     position: usize,
     token_start: usize,
     line: usize,
+    /// Whether the previous token is a '.', words after it are member names and never keywords
+    member: bool,
 }
 
 impl Tokenizer {
     pub fn new(input: String) -> Tokenizer {
         Tokenizer {
-            input,
+            input: input.chars().collect(),
             position: 0,
             token_start: 0,
             line: 0,
+            member: false,
         }
     }
 
@@ -30,6 +34,7 @@ impl Tokenizer {
         loop {
             let token = self.token()?;
             if token.kind == TokenKind::EOF { break; }
+            self.member = token.kind == TokenKind::Symbol(Symbol::Period);
             tokens.push(token);
         }
 
@@ -38,12 +43,12 @@ impl Tokenizer {
 
     /// Get current character
     fn current(&self) -> Option<char> {
-        self.input.chars().nth(self.position)
+        self.input.get(self.position).copied()
     }
 
     /// Get next character
     fn next(&self) -> Option<char> {
-        self.input.chars().nth(self.position + 1)
+        self.input.get(self.position + 1).copied()
     }
  
     /// Advance position
@@ -101,6 +106,7 @@ impl Tokenizer {
             '0'..='9' => self.number(),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier_or_keyword(),
             '"' | '\'' => self.string(),
+            '$' => self.parameter(),
             _ => self.symbol_or_operator(),
         }
     }
@@ -178,6 +184,7 @@ impl Tokenizer {
                 ('!', _) => Some(Operator::Not),
 
                 ('=', Some('=')) => adv_ret(self, Some(Operator::Equal)),
+                ('=', Some('~')) => adv_ret(self, Some(Operator::Match)),
                 ('=', _) => Some(Operator::Assign),
 
                 ('+', Some('+')) => adv_ret(self, Some(Operator::Increment)),
@@ -193,6 +200,7 @@ impl Tokenizer {
                 ('*', _) => Some(Operator::Multiply),
 
                 ('/', Some('=')) => adv_ret(self, Some(Operator::DivideAssign)),
+                ('/', _) => Some(Operator::Divide),
 
                 ('%', Some('=')) => adv_ret(self, Some(Operator::ModulusAssign)),
                 ('%', _) => Some(Operator::Modulus),
@@ -209,6 +217,23 @@ impl Tokenizer {
         Err(self.error("unexpected character", "symbol or operator"))
     }
 
+    /// Parse '$n' parameter token, 'n' starts at 1
+    fn parameter(&mut self) -> Result<Token, String> {
+        self.mark_start();
+        self.advance();
+
+        let mut value = String::new();
+        while let Some(current @ '0'..='9') = self.current() {
+            value.push(current);
+            self.advance();
+        }
+
+        match value.parse::<usize>() {
+            Ok(index) if index > 0 => self.ok_token(TokenKind::Literal(Literal::Parameter(index))),
+            _ => Err(self.error("invalid parameter", "'$' followed by a number greater than 0"))
+        }
+    }
+
     /// Parse string token
     fn string(&mut self) -> Result<Token, String> {
         let mut value = String::new();
@@ -305,6 +330,18 @@ impl Tokenizer {
             Err(self.error("unexpected character", "identifier or keyword"))?
         }
 
+        // 'data.user.select' reads the member 'select'
+        if self.member {
+            return self.ok_token(TokenKind::Identifier(value))
+        }
+
+        // pattern operators are words, but they behave like any other binary operator
+        match value.as_str() {
+            "like" => return self.ok_token(TokenKind::Operator(Operator::Like)),
+            "ilike" => return self.ok_token(TokenKind::Operator(Operator::ILike)),
+            _ => ()
+        }
+
         let keyword = match value.as_str() {
             "if" => Some(Keyword::If),
             "else" => Some(Keyword::Else),
@@ -321,6 +358,12 @@ impl Tokenizer {
             "null" => Some(Keyword::Null),
             "on" => Some(Keyword::On),
             "to" => Some(Keyword::To),
+            "try" => Some(Keyword::Try),
+            "catch" => Some(Keyword::Catch),
+            "throw" => Some(Keyword::Throw),
+            "in" => Some(Keyword::In),
+            "import" => Some(Keyword::Import),
+            "export" => Some(Keyword::Export),
             _ => None
         };
 
@@ -359,6 +402,10 @@ impl Tokenizer {
             "limit" => QueryKeyword::Limit,
             "offset" => QueryKeyword::Offset,
             "exclude" => QueryKeyword::Exclude,
+            "returning" => QueryKeyword::Returning,
+
+            "with" => QueryKeyword::With,
+            "union" => QueryKeyword::Union,
     
             _ => return None
         };
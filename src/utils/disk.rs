@@ -1,4 +1,4 @@
-use std::fs;
+use std::{fs, io::Write};
 
 use crate::utils::log;
 
@@ -43,6 +43,20 @@ pub fn write_file(path: &str, content: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Appends content to file 'path', the file is created if it doesn't exist
+pub fn append_file(path: &str, content: &str) -> Result<(), String> {
+    let result = fs::OpenOptions::new().append(true).create(true).open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+
+    if let Err(e) = result {
+        let err_msg = format!("failed to append to file {}\n{}", path, e);
+        log::error(&err_msg);
+        return Err(err_msg);
+    }
+
+    Ok(())
+}
+
 pub fn copy_file(from: &str, to: &str) -> Result<(), String> {
     let result = fs::copy(from, to);
 
@@ -1,6 +1,8 @@
 pub mod disk;
 pub mod log;
 pub mod args;
+#[cfg(test)]
+pub mod test;
 
 pub fn is_valid_name(name: &str) -> bool {
     if name.is_empty() ||
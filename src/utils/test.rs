@@ -0,0 +1,100 @@
+use std::{rc::Rc, sync::{Arc, RwLock, atomic::{AtomicUsize, Ordering}}};
+
+use crate::{database::{Database, DatabaseBuilder, Run, RunOptions}, cluster::{Cluster, ClusterSettings}, basics::Value, auth::User};
+
+static DATABASES: AtomicUsize = AtomicUsize::new(0);
+
+/// Database in its own temporary directory for tests, the directory is removed when it's dropped
+pub struct TestDatabase {
+    pub database: Arc<RwLock<Database>>,
+    root_dir: String,
+}
+
+impl TestDatabase {
+    pub fn new() -> Self {
+        let id = DATABASES.fetch_add(1, Ordering::Relaxed);
+        let root_dir = std::env::temp_dir()
+            .join(format!("drumnbase_test_{}_{}", std::process::id(), id))
+            .to_string_lossy()
+            .to_string();
+        let _ = std::fs::remove_dir_all(&root_dir);
+
+        let database = DatabaseBuilder::new("testdb", &root_dir).create().unwrap();
+        Self { database: Arc::new(RwLock::new(database)), root_dir }
+    }
+
+    /// Options of the root cluster user
+    pub fn options(&self) -> RunOptions {
+        let settings = ClusterSettings::new("testdb", &self.root_dir);
+        Cluster::root_run_options(self.database.clone(), &settings)
+    }
+
+    /// Options of 'user', who is not a superuser unless set on it
+    pub fn user_options(&self, user: User) -> RunOptions {
+        let user = Rc::new(user);
+        let mut options = self.options();
+        options.cluster_user = user.clone();
+        options.auth_user = user;
+        options
+    }
+
+    pub fn run_as(&self, user: User, input: &str) -> Result<Value, String> {
+        self.run_with_options(input, self.user_options(user))
+    }
+
+    pub fn run_with_options(&self, input: &str, options: RunOptions) -> Result<Value, String> {
+        Database::run(self.database.clone(), input.to_string(), Rc::new(options))
+            .map(|result| result.data)
+    }
+
+    pub fn run(&self, input: &str) -> Result<Value, String> {
+        self.run_with_options(input, self.options())
+    }
+
+    /// Runs 'input' as root with 'params' bound to its placeholders
+    pub fn run_with(&self, input: &str, params: &[Value]) -> Result<Value, String> {
+        Database::run_with(self.database.clone(), input.to_string(), params, Rc::new(self.options()))
+            .map(|result| result.data)
+    }
+
+    /// Runs 'input' as root, returns its result formatted with 'Display'
+    pub fn query(&self, input: &str) -> String {
+        match self.run(input) {
+            Ok(value) => value.to_string(),
+            Err(e) => panic!("Script failed: {}\n{}", e, input),
+        }
+    }
+
+    /// Runs 'input' as root, returns the error it failed with
+    pub fn error(&self, input: &str) -> String {
+        match self.run(input) {
+            Ok(value) => panic!("Script didn't fail, returned {}\n{}", value, input),
+            Err(e) => e,
+        }
+    }
+
+    /// Writes a module file 'name' to the directory of the database, returns its path
+    pub fn module(&self, name: &str, source: &str) -> String {
+        let path = std::path::Path::new(&self.root_dir).join(name);
+        std::fs::write(&path, source).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    /// Loads the database from disk again, like after a restart
+    pub fn reload(&mut self) {
+        let database = DatabaseBuilder::new("testdb", &self.root_dir).load().unwrap();
+        self.database = Arc::new(RwLock::new(database));
+    }
+}
+
+impl Default for TestDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root_dir);
+    }
+}